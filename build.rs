@@ -0,0 +1,26 @@
+//! Generates the shell completions and the man page from the command line definitions when
+//! HERMES_GEN_DIR is set, for packaging: `HERMES_GEN_DIR=dist cargo build --release`.
+
+use std::{env, fs, io, path::PathBuf};
+
+use clap::CommandFactory;
+use clap_complete::Shell;
+
+#[path = "src/cli.rs"]
+mod cli;
+
+fn main() -> io::Result<()> {
+    println!("cargo:rerun-if-changed=src/cli.rs");
+    println!("cargo:rerun-if-env-changed=HERMES_GEN_DIR");
+    let Some(dir) = env::var_os("HERMES_GEN_DIR").map(PathBuf::from) else {
+        return Ok(());
+    };
+    fs::create_dir_all(&dir)?;
+    let mut command = cli::Cli::command();
+    for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+        clap_complete::generate_to(shell, &mut command, "hermes", &dir)?;
+    }
+    let mut man_page = Vec::new();
+    clap_mangen::Man::new(command).render(&mut man_page)?;
+    fs::write(dir.join("hermes.1"), man_page)
+}
@@ -1,17 +1,71 @@
 use ratatui::style;
 use std::collections::HashMap;
 use std::fmt::{self};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
 use std::slice::Iter;
+use std::str::FromStr;
+
+use crate::assertions::Assertion;
+use crate::auth::Auth;
+use crate::changelog::{Change, CHANGELOG_LIMIT};
+use crate::folders::Folder;
+use crate::json_tree;
+use crate::pagination::Cursor;
+use crate::runner::{Hooks, Precondition, Scope};
+use crate::secrets::Masker;
+use crate::signing::BodySigning;
+use crate::source;
+use crate::storage::FileSnapshot;
+use crate::{cookies::CookieJar, etags::ETags};
 
 /// Collection represents a collection of Routes and/or nested Collections with Environments.
 #[derive(Debug, Clone)]
 pub struct Collection {
-    identifier: String,
     name: String,
     requests: Vec<Request>,
     enable_environment: bool,
     active_environment: String,
     environments: HashMap<String, HashMap<String, String>>,
+    /// The Accept preset new requests in this collection start with.
+    default_accept: AcceptPreset,
+    /// The environment keys whose values must be masked whenever they are shown.
+    secret_keys: Vec<String>,
+    /// Path to a PEM file with an extra CA certificate to trust, for APIs behind a private CA.
+    ca_certificate: Option<String>,
+    /// The address family hosts are resolved to, unless a request picks its own.
+    ip_family: IpFamily,
+    /// How requests reach their host, unless a request picks its own route.
+    proxy: ProxyRoute,
+    /// The before and after hooks of the collection and its folders.
+    hooks: HashMap<Scope, Hooks>,
+    /// Endpoints that must answer before the collection is run.
+    preconditions: Vec<Precondition>,
+    /// The folders that should not be used anymore, by path, with why.
+    deprecated_folders: HashMap<String, String>,
+    /// Variables shared by every environment, used when the environment does not define them.
+    variables: HashMap<String, String>,
+    /// Headers sent with every request that does not set them itself.
+    default_headers: HashMap<String, String>,
+    /// The folder the collection was read from. None for collections that only live in memory.
+    root: Option<PathBuf>,
+    /// The folder or hermes file given to load the collection, so its files can be checked again.
+    loaded_from: Option<PathBuf>,
+    /// Whether its folder can't be written, e.g. a checked out repository on CI or a read-only
+    /// mount. Changes are then kept in memory only.
+    read_only: bool,
+    /// The hermes files that were parsed to build the collection.
+    loaded_files: Vec<PathBuf>,
+    /// What the hermes files looked like when they were loaded or last saved, so saving one does
+    /// not overwrite what was changed outside hermes since.
+    snapshots: HashMap<PathBuf, FileSnapshot>,
+    /// What is most likely a mistake in the hermes files but did not keep the collection from
+    /// loading, rendered like the problems of the loader.
+    warnings: Vec<String>,
+    /// The cookies set by the responses to its requests, sent back with the requests after them.
+    cookies: CookieJar,
+    /// The ETags of the resources its requests read, sent back in the `If-Match` of updates.
+    etags: ETags,
 }
 
 impl Collection {
@@ -19,12 +73,22 @@ impl Collection {
         self.requests.push(route);
     }
 
+    /// Removes the request at the given index. The hooks that run it are left as they are.
+    pub fn remove_request(&mut self, index: usize) -> Option<Request> {
+        (index < self.requests.len()).then(|| self.requests.remove(index))
+    }
+
+    /// Gets a mutable reference to the request at the given index.
+    pub fn get_request_mut(&mut self, index: usize) -> Option<&mut Request> {
+        self.requests.get_mut(index)
+    }
+
     pub fn get_request_count(&self) -> usize {
         self.requests.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        return self.requests.is_empty();
+        self.requests.is_empty()
     }
 
     pub fn set_name(&mut self, name: String) {
@@ -35,12 +99,13 @@ impl Collection {
         self.name.clone()
     }
 
-    pub fn set_identifier(&mut self, identifier: String) {
-        self.identifier = identifier;
+    pub fn set_default_accept(&mut self, preset: AcceptPreset) {
+        self.default_accept = preset;
     }
 
-    pub fn identifier(&self) -> String {
-        self.identifier.clone()
+    /// Gets the Accept preset that new requests in this collection should use by default.
+    pub fn default_accept(&self) -> AcceptPreset {
+        self.default_accept
     }
 
     pub fn new_environment(&mut self, environment_name: String) {
@@ -51,14 +116,242 @@ impl Collection {
         self.active_environment = enviroment_name;
     }
 
+    pub fn active_environment_name(&self) -> &str {
+        &self.active_environment
+    }
+
+    /// Gets the names of every environment, sorted.
+    pub fn environment_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.environments.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    pub fn is_environment_enabled(&self) -> bool {
+        self.enable_environment
+    }
+
     pub fn add_environment_entry(&mut self, key: String, value: String) {
         if let Some(env) = self.environments.get_mut(&self.active_environment) {
             env.insert(key, value);
         }
     }
 
-    pub fn get_active_environment(&mut self) -> Option<&mut HashMap<String, String>> {
-        self.environments.get_mut(&self.active_environment)
+    /// Gets the key-value pairs of the active environment.
+    /// None when the environment is turned off.
+    pub fn active_environment(&self) -> Option<&HashMap<String, String>> {
+        if !self.enable_environment {
+            return None;
+        }
+        self.environments.get(&self.active_environment)
+    }
+
+    /// Gets the key-value pairs of an environment by its name, active or not.
+    pub fn environment(&self, environment_name: &str) -> Option<&HashMap<String, String>> {
+        self.environments.get(environment_name)
+    }
+
+    pub fn set_root(&mut self, root: PathBuf) {
+        self.root = Some(root);
+    }
+
+    pub fn root(&self) -> Option<&PathBuf> {
+        self.root.as_ref()
+    }
+
+    pub fn set_loaded_from(&mut self, path: PathBuf) {
+        self.loaded_from = Some(path);
+    }
+
+    pub fn loaded_from(&self) -> Option<&PathBuf> {
+        self.loaded_from.as_ref()
+    }
+
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Adds a file the collection was built from, along with what it looked like then. A file
+    /// without a snapshot is snapshotted when it is first saved.
+    pub fn add_loaded_file(&mut self, file: PathBuf, snapshot: Option<FileSnapshot>) {
+        if let Some(snapshot) = snapshot {
+            self.set_snapshot(snapshot);
+        }
+        self.loaded_files.push(file);
+    }
+
+    pub fn loaded_files(&self) -> &[PathBuf] {
+        &self.loaded_files
+    }
+
+    pub fn snapshot(&self, file: &Path) -> Option<&FileSnapshot> {
+        self.snapshots.get(file)
+    }
+
+    /// Keeps what a file of the collection looks like once it was saved.
+    pub fn set_snapshot(&mut self, snapshot: FileSnapshot) {
+        self.snapshots
+            .insert(snapshot.path().to_path_buf(), snapshot);
+    }
+
+    pub fn add_warning(&mut self, warning: String) {
+        self.warnings.push(warning);
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn cookies(&self) -> &CookieJar {
+        &self.cookies
+    }
+
+    pub fn cookies_mut(&mut self) -> &mut CookieJar {
+        &mut self.cookies
+    }
+
+    pub fn etags(&self) -> &ETags {
+        &self.etags
+    }
+
+    pub fn etags_mut(&mut self) -> &mut ETags {
+        &mut self.etags
+    }
+
+    /// Renames the request at the given index and updates every reference to its old name, such as
+    /// hooks. Nothing changes when the new name is invalid. Returns how many references were
+    /// updated.
+    pub fn rename_request(&mut self, index: usize, name: String) -> Result<usize, String> {
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err(String::from("the name of a request can't be empty"));
+        }
+        let Some(request) = self.requests.get(index) else {
+            return Err(String::from("there is no request to rename"));
+        };
+        let old_name = request.get_name();
+        if old_name == name {
+            return Ok(0);
+        }
+        if self.requests.iter().any(|r| r.get_name() == name) {
+            return Err(format!("a request named {} already exists", name));
+        }
+        self.requests[index].set_name(name.clone());
+        Ok(self
+            .hooks
+            .values_mut()
+            .map(|hooks| hooks.rename(&old_name, &name))
+            .sum())
+    }
+
+    pub fn add_variable(&mut self, key: String, value: String) {
+        self.variables.insert(key, value);
+    }
+
+    /// Gets the collection variables, which are looked up after the active environment.
+    pub fn variables(&self) -> &HashMap<String, String> {
+        &self.variables
+    }
+
+    pub fn add_default_header(&mut self, name: String, value: String) {
+        self.default_headers.insert(name, value);
+    }
+
+    pub fn default_headers(&self) -> &HashMap<String, String> {
+        &self.default_headers
+    }
+
+    pub fn set_ca_certificate(&mut self, path: Option<String>) {
+        self.ca_certificate = path;
+    }
+
+    pub fn ca_certificate(&self) -> Option<&str> {
+        self.ca_certificate.as_deref()
+    }
+
+    pub fn set_ip_family(&mut self, family: IpFamily) {
+        self.ip_family = family;
+    }
+
+    pub fn ip_family(&self) -> IpFamily {
+        self.ip_family
+    }
+
+    pub fn set_proxy(&mut self, proxy: ProxyRoute) {
+        self.proxy = proxy;
+    }
+
+    pub fn proxy(&self) -> &ProxyRoute {
+        &self.proxy
+    }
+
+    /// Marks the environment key as secret, its value will be masked in every environment.
+    pub fn mark_secret(&mut self, key: String) {
+        if !self.secret_keys.contains(&key) {
+            self.secret_keys.push(key);
+        }
+    }
+
+    pub fn is_secret(&self, key: &str) -> bool {
+        self.secret_keys.iter().any(|k| k == key)
+    }
+
+    pub fn secret_keys(&self) -> &[String] {
+        &self.secret_keys
+    }
+
+    /// Builds a Masker that knows about the secret values of all the environments and the
+    /// collection variables.
+    pub fn masker(&self) -> Masker {
+        Masker::new(
+            self.environments
+                .values()
+                .flat_map(|env| env.iter())
+                .chain(self.variables.iter())
+                .filter(|(key, _)| self.is_secret(key))
+                .map(|(_, value)| value.clone()),
+        )
+    }
+
+    /// Gets the hooks of the scope to add more of them.
+    pub fn hooks_mut(&mut self, scope: Scope) -> &mut Hooks {
+        self.hooks.entry(scope).or_default()
+    }
+
+    pub fn hooks(&self) -> &HashMap<Scope, Hooks> {
+        &self.hooks
+    }
+
+    pub fn add_precondition(&mut self, precondition: Precondition) {
+        self.preconditions.push(precondition);
+    }
+
+    pub fn preconditions(&self) -> &[Precondition] {
+        &self.preconditions
+    }
+
+    /// Marks every request in the folder, and in the folders inside it, as deprecated.
+    pub fn deprecate_folder(&mut self, folder: String, message: String) {
+        self.deprecated_folders.insert(folder, message);
+    }
+
+    /// Gets why the request should not be used anymore, from the request itself or else from the
+    /// closest folder it is in that is deprecated. None when it is not deprecated.
+    pub fn deprecation<'a>(&'a self, request: &'a Request) -> Option<&'a str> {
+        if let Some(message) = request.get_deprecated() {
+            return Some(message);
+        }
+        let mut folder = request.get_folder();
+        loop {
+            if let Some(message) = self.deprecated_folders.get(folder) {
+                return Some(message);
+            }
+            folder = folder.rsplit_once('/')?.0;
+        }
     }
 
     pub fn enable_active_environment(&mut self) {
@@ -69,6 +362,11 @@ impl Collection {
         self.enable_environment = false;
     }
 
+    /// Gets the requests grouped by the folders they were found in.
+    pub fn tree(&self) -> Folder {
+        Folder::build(self)
+    }
+
     pub fn iter(&self) -> Iter<'_, Request> {
         self.requests.iter()
     }
@@ -92,10 +390,27 @@ impl Default for Collection {
         Collection {
             name: String::from("Untitled Collection"),
             requests: vec![],
-            identifier: String::new(),
             enable_environment: false,
             active_environment: String::new(),
             environments: HashMap::new(),
+            default_accept: AcceptPreset::default(),
+            secret_keys: Vec::new(),
+            ca_certificate: None,
+            ip_family: IpFamily::default(),
+            proxy: ProxyRoute::default(),
+            hooks: HashMap::new(),
+            preconditions: Vec::new(),
+            deprecated_folders: HashMap::new(),
+            variables: HashMap::new(),
+            default_headers: HashMap::new(),
+            root: None,
+            loaded_from: None,
+            read_only: false,
+            loaded_files: Vec::new(),
+            snapshots: HashMap::new(),
+            warnings: Vec::new(),
+            cookies: CookieJar::default(),
+            etags: ETags::default(),
         }
     }
 }
@@ -105,12 +420,67 @@ impl Default for Collection {
 #[derive(Debug, Clone)]
 pub struct Request {
     name: String,
+    /// The identifier written after `as` in the source, kept so writing the request back does
+    /// not rename its blocks. None when the request block had none.
+    identifier: Option<String>,
     method: HttpMethod,
     url: String,
     body: Option<String>,
     body_type: Option<HttpBody>,
+    /// The fields of form bodies, in order, sent instead of the body. Disabled fields are kept but
+    /// not sent.
+    form: Vec<FormField>,
     /// a list of key-value pairs for the headers.
     headers: HashMap<String, String>,
+    /// The query parameters added to the url, in order. Disabled ones are kept but not sent.
+    queries: Vec<QueryParam>,
+    /// The bearer token, basic credentials or API key from the auth block of the request.
+    auth: Option<Auth>,
+    /// The viewer defined in the settings block of the request. The viewer is picked based on the
+    /// response content type when none is defined.
+    viewer: Option<ResponseViewer>,
+    /// The address family defined in the settings block of the request, overriding the one of the
+    /// collection.
+    ip_family: Option<IpFamily>,
+    /// The proxy defined in the settings block of the request, overriding the one of the
+    /// collection.
+    proxy: Option<ProxyRoute>,
+    /// How many times the request is sent again when the server could not be reached or was
+    /// unavailable.
+    retries: u32,
+    /// Whether an Idempotency-Key header is generated every time the request is sent, unless it
+    /// sets one itself.
+    auto_idempotency_key: bool,
+    /// How the body is signed or encrypted before it is sent, from the settings block.
+    body_signing: Option<BodySigning>,
+    /// Where to find the cursor of the next page in the response, for APIs paginated without Link
+    /// headers.
+    next_cursor: Option<Cursor>,
+    /// Variables asked for every time the request is sent, e.g. an id that changes on every call.
+    prompts: Vec<PromptVariable>,
+    /// Variables set from the response every time it comes in, e.g. a token to use afterwards.
+    captures: Vec<CaptureRule>,
+    /// The environment the request always uses, whatever the active one of the collection is.
+    environment: Option<String>,
+    /// The assertions from the tests block of the request.
+    assertions: Vec<Assertion>,
+    /// A snippet every response is compared to, to show how it drifted.
+    expected: Option<String>,
+    /// The folder the request was found in, relative to the collection root. Empty for the root.
+    folder: String,
+    /// The hermes file the request was loaded from. None for requests made in the TUI.
+    file: Option<PathBuf>,
+    /// What the request is for, searched by the finder.
+    description: Option<String>,
+    /// The request exactly as it goes on the wire, sent instead of the method, url, headers and
+    /// body in lab mode. The url still picks the host to connect to.
+    raw: Option<String>,
+    /// The person or team responsible for the request.
+    owner: Option<String>,
+    /// Why the request should not be used anymore, None when it is not deprecated.
+    deprecated: Option<String>,
+    /// The latest changes to the request, oldest first.
+    changelog: Vec<Change>,
 }
 
 impl Request {
@@ -124,11 +494,34 @@ impl Request {
     ) -> Self {
         Self {
             name,
+            identifier: None,
             method,
             url,
             body,
             body_type,
             headers,
+            form: Vec::new(),
+            queries: Vec::new(),
+            auth: None,
+            viewer: None,
+            ip_family: None,
+            proxy: None,
+            retries: 0,
+            auto_idempotency_key: false,
+            body_signing: None,
+            next_cursor: None,
+            prompts: Vec::new(),
+            captures: Vec::new(),
+            environment: None,
+            assertions: Vec::new(),
+            expected: None,
+            folder: String::new(),
+            file: None,
+            description: None,
+            raw: None,
+            owner: None,
+            deprecated: None,
+            changelog: Vec::new(),
         }
     }
 
@@ -137,19 +530,429 @@ impl Request {
         self.name.clone()
     }
 
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub fn set_identifier(&mut self, identifier: Option<String>) {
+        self.identifier = identifier;
+    }
+
+    /// Gets the identifier the request is written with: the one given in the source, or else one
+    /// made from the name, so writing the same request always gives the same source.
+    pub fn get_identifier(&self) -> String {
+        match &self.identifier {
+            Some(identifier) => identifier.clone(),
+            None => source::identifier_of(&self.name),
+        }
+    }
+
     /// Gets the http method of the request.
     pub fn get_method(&self) -> HttpMethod {
         self.method
     }
 
+    pub fn set_method(&mut self, method: HttpMethod) {
+        self.method = method;
+    }
+
     /// Gets a clone of the url of the request.
     pub fn get_url(&self) -> String {
         self.url.clone()
     }
+
+    pub fn set_url(&mut self, url: String) {
+        self.url = url;
+    }
+
+    /// Gets the raw body of the request, if any.
+    pub fn get_body(&self) -> Option<&str> {
+        self.body.as_deref()
+    }
+
+    pub fn set_body(&mut self, body: Option<String>) {
+        self.body = body;
+    }
+
+    pub fn get_body_type(&self) -> Option<HttpBody> {
+        self.body_type
+    }
+
+    pub fn set_body_type(&mut self, body_type: Option<HttpBody>) {
+        self.body_type = body_type;
+    }
+
+    /// Gets the key-value pairs for the headers of the request.
+    pub fn get_headers(&self) -> &HashMap<String, String> {
+        &self.headers
+    }
+
+    pub fn set_headers(&mut self, headers: HashMap<String, String>) {
+        self.headers = headers;
+    }
+
+    pub fn get_form(&self) -> &[FormField] {
+        &self.form
+    }
+
+    pub fn set_form(&mut self, form: Vec<FormField>) {
+        self.form = form;
+    }
+
+    pub fn get_queries(&self) -> &[QueryParam] {
+        &self.queries
+    }
+
+    pub fn set_queries(&mut self, queries: Vec<QueryParam>) {
+        self.queries = queries;
+    }
+
+    pub fn set_viewer(&mut self, viewer: Option<ResponseViewer>) {
+        self.viewer = viewer;
+    }
+
+    /// Gets the viewer that should be used to display a response with the given content type.
+    /// The viewer from the request settings always wins over the detected one.
+    pub fn get_viewer(&self, content_type: &str) -> ResponseViewer {
+        self.viewer
+            .unwrap_or_else(|| ResponseViewer::from_content_type(content_type))
+    }
+
+    pub fn set_ip_family(&mut self, family: Option<IpFamily>) {
+        self.ip_family = family;
+    }
+
+    /// Gets the address family to resolve the host to, falling back to the one of the collection.
+    pub fn get_ip_family(&self, collection_family: IpFamily) -> IpFamily {
+        self.ip_family.unwrap_or(collection_family)
+    }
+
+    pub fn set_proxy(&mut self, proxy: Option<ProxyRoute>) {
+        self.proxy = proxy;
+    }
+
+    /// Gets how the request reaches its host, falling back to the route of the collection.
+    pub fn get_proxy(&self, collection_proxy: &ProxyRoute) -> ProxyRoute {
+        self.proxy
+            .clone()
+            .unwrap_or_else(|| collection_proxy.clone())
+    }
+
+    pub fn set_retries(&mut self, retries: u32) {
+        self.retries = retries;
+    }
+
+    pub fn get_retries(&self) -> u32 {
+        self.retries
+    }
+
+    pub fn set_auto_idempotency_key(&mut self, auto: bool) {
+        self.auto_idempotency_key = auto;
+    }
+
+    pub fn has_auto_idempotency_key(&self) -> bool {
+        self.auto_idempotency_key
+    }
+
+    pub fn set_auth(&mut self, auth: Option<Auth>) {
+        self.auth = auth;
+    }
+
+    pub fn get_auth(&self) -> Option<&Auth> {
+        self.auth.as_ref()
+    }
+
+    pub fn set_body_signing(&mut self, signing: Option<BodySigning>) {
+        self.body_signing = signing;
+    }
+
+    pub fn get_body_signing(&self) -> Option<&BodySigning> {
+        self.body_signing.as_ref()
+    }
+
+    pub fn set_next_cursor(&mut self, cursor: Option<Cursor>) {
+        self.next_cursor = cursor;
+    }
+
+    pub fn get_next_cursor(&self) -> Option<&Cursor> {
+        self.next_cursor.as_ref()
+    }
+
+    pub fn set_prompts(&mut self, prompts: Vec<PromptVariable>) {
+        self.prompts = prompts;
+    }
+
+    pub fn get_prompts(&self) -> &[PromptVariable] {
+        &self.prompts
+    }
+
+    pub fn set_captures(&mut self, captures: Vec<CaptureRule>) {
+        self.captures = captures;
+    }
+
+    pub fn add_capture(&mut self, capture: CaptureRule) {
+        self.captures.push(capture);
+    }
+
+    pub fn get_captures(&self) -> &[CaptureRule] {
+        &self.captures
+    }
+
+    /// Pins the request to an environment, None makes it follow the active environment again.
+    pub fn pin_environment(&mut self, environment_name: Option<String>) {
+        self.environment = environment_name;
+    }
+
+    pub fn get_pinned_environment(&self) -> Option<&str> {
+        self.environment.as_deref()
+    }
+
+    pub fn set_assertions(&mut self, assertions: Vec<Assertion>) {
+        self.assertions = assertions;
+    }
+
+    pub fn add_assertion(&mut self, assertion: Assertion) {
+        self.assertions.push(assertion);
+    }
+
+    pub fn get_assertions(&self) -> &[Assertion] {
+        &self.assertions
+    }
+
+    pub fn set_folder(&mut self, folder: String) {
+        self.folder = folder;
+    }
+
+    pub fn get_folder(&self) -> &str {
+        &self.folder
+    }
+
+    pub fn set_file(&mut self, file: PathBuf) {
+        self.file = Some(file);
+    }
+
+    pub fn get_file(&self) -> Option<&PathBuf> {
+        self.file.as_ref()
+    }
+
+    pub fn set_raw(&mut self, raw: Option<String>) {
+        self.raw = raw;
+    }
+
+    pub fn get_raw(&self) -> Option<&str> {
+        self.raw.as_deref()
+    }
+
+    pub fn set_description(&mut self, description: Option<String>) {
+        self.description = description;
+    }
+
+    pub fn get_description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn set_owner(&mut self, owner: Option<String>) {
+        self.owner = owner;
+    }
+
+    pub fn get_owner(&self) -> Option<&str> {
+        self.owner.as_deref()
+    }
+
+    pub fn set_deprecated(&mut self, message: Option<String>) {
+        self.deprecated = message;
+    }
+
+    /// Gets why the request itself is deprecated. See Collection::deprecation to also account for
+    /// the folders it is in.
+    pub fn get_deprecated(&self) -> Option<&str> {
+        self.deprecated.as_deref()
+    }
+
+    /// Adds a change to the changelog, dropping the oldest ones past CHANGELOG_LIMIT.
+    pub fn add_change(&mut self, change: Change) {
+        self.changelog.push(change);
+        if self.changelog.len() > CHANGELOG_LIMIT {
+            self.changelog
+                .drain(..self.changelog.len() - CHANGELOG_LIMIT);
+        }
+    }
+
+    pub fn set_changelog(&mut self, changelog: Vec<Change>) {
+        self.changelog = changelog;
+    }
+
+    pub fn get_changelog(&self) -> &[Change] {
+        &self.changelog
+    }
+
+    pub fn set_expected(&mut self, expected: Option<String>) {
+        self.expected = expected;
+    }
+
+    pub fn get_expected(&self) -> Option<&str> {
+        self.expected.as_deref()
+    }
+
+    /// Gets the latest change, which tells who last touched the request and when.
+    pub fn last_change(&self) -> Option<&Change> {
+        self.changelog.last()
+    }
+}
+
+/// PromptVariable is a variable whose value is typed in every time the request is sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptVariable {
+    name: String,
+    /// What is shown to the user when asking for the value.
+    label: String,
+}
+
+impl PromptVariable {
+    /// Reads the value of a `prompt` field, the name of the variable followed by an optional label,
+    /// e.g. `user_id User ID`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        let (name, label) = value.split_once(char::is_whitespace).unwrap_or((value, ""));
+        if name.is_empty() {
+            return Err(String::from("prompt is missing the variable name"));
+        }
+        let label = match label.trim() {
+            "" => name,
+            label => label,
+        };
+        Ok(Self {
+            name: name.to_string(),
+            label: label.to_string(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+/// QueryParam is a parameter added to the query string of the url of a request. Disabled
+/// parameters stay with the request so they can be turned back on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryParam {
+    key: String,
+    value: String,
+    enabled: bool,
+}
+
+impl QueryParam {
+    pub fn new(key: String, value: String, enabled: bool) -> Self {
+        Self {
+            key,
+            value,
+            enabled,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+/// FormField is a field of a form-urlencoded or multipart-form body. In multipart forms, values
+/// starting with `@` are the path of a file to send.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormField {
+    name: String,
+    value: String,
+    enabled: bool,
+}
+
+impl FormField {
+    pub fn new(name: String, value: String, enabled: bool) -> Self {
+        Self {
+            name,
+            value,
+            enabled,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+/// CaptureRule sets a variable to a node of the JSON body of every response to the request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureRule {
+    name: String,
+    /// The JSONPath of the node, e.g. `$.data.token`.
+    path: String,
+}
+
+impl CaptureRule {
+    pub fn new(name: String, path: String) -> Self {
+        Self { name, path }
+    }
+
+    /// Reads the value of a `capture` field, the name of the variable followed by the JSONPath,
+    /// e.g. `TOKEN $.data.token`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        match value.split_once(char::is_whitespace) {
+            Some((name, path)) if path.trim().starts_with('$') => Ok(Self {
+                name: name.to_string(),
+                path: path.trim().to_string(),
+            }),
+            _ => Err(format!(
+                "capture `{}` must be a variable name followed by a JSONPath",
+                value
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Gets the value of the variable from a response body. Strings are used as they are, any
+    /// other node as JSON.
+    pub fn extract(&self, body: &str) -> Result<String, String> {
+        let body: serde_json::Value = serde_json::from_str(body)
+            .map_err(|_| format!("can't capture {}, the body is not JSON", self.name))?;
+        match json_tree::lookup(&body, &self.path) {
+            Some(serde_json::Value::String(text)) => Ok(text.clone()),
+            Some(value) => Ok(value.to_string()),
+            None => Err(format!(
+                "can't capture {}, {} is not in the body",
+                self.name, self.path
+            )),
+        }
+    }
 }
 
 /// HttpMethod is the method that a Request should use to call the API.
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub enum HttpMethod {
     #[default]
     Get,
@@ -161,18 +964,18 @@ pub enum HttpMethod {
 }
 
 impl HttpMethod {
-    pub fn to_str(&self) -> &str {
+    pub fn to_str(self) -> &'static str {
         match self {
             HttpMethod::Get => "GET",
             HttpMethod::Post => "POST",
             HttpMethod::Put => "PUT",
             HttpMethod::Patch => "PATCH",
             HttpMethod::Delete => "DELETE",
-            HttpMethod::Option => "OPTION",
+            HttpMethod::Option => "OPTIONS",
         }
     }
 
-    pub fn color(&self) -> style::Color {
+    pub fn color(self) -> style::Color {
         match self {
             HttpMethod::Get => style::Color::Green,
             HttpMethod::Post => style::Color::Yellow,
@@ -182,6 +985,12 @@ impl HttpMethod {
             HttpMethod::Option => style::Color::LightCyan,
         }
     }
+
+    /// Checks if sending the method twice has the same effect as sending it once, so it is safe
+    /// to retry.
+    pub fn is_idempotent(self) -> bool {
+        !matches!(self, HttpMethod::Post | HttpMethod::Patch)
+    }
 }
 
 impl fmt::Display for HttpMethod {
@@ -192,15 +1001,421 @@ impl fmt::Display for HttpMethod {
             HttpMethod::Patch => "PATCH",
             HttpMethod::Put => "PUT",
             HttpMethod::Delete => "DELETE",
-            HttpMethod::Option => "OPTION",
+            HttpMethod::Option => "OPTIONS",
         };
         write!(f, "{}", method)
     }
 }
 
+impl FromStr for HttpMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "GET" => Ok(HttpMethod::Get),
+            "POST" => Ok(HttpMethod::Post),
+            "PATCH" => Ok(HttpMethod::Patch),
+            "PUT" => Ok(HttpMethod::Put),
+            "DELETE" => Ok(HttpMethod::Delete),
+            // files written before OPTIONS was spelled right
+            "OPTIONS" | "OPTION" => Ok(HttpMethod::Option),
+            _ => Err(format!("unknown http method '{}'", s)),
+        }
+    }
+}
+
 /// HttpBody is the type of body that is being sent in the Request.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum HttpBody {
     Json,
+    Text,
     FormUrlEncoded,
+    MultipartForm,
+}
+
+impl HttpBody {
+    /// Gets the type of a body block from its sub-type, e.g. `json` for `body.json`.
+    pub fn from_sub_type(sub_type: &str) -> Option<Self> {
+        match sub_type {
+            "json" => Some(HttpBody::Json),
+            "text" => Some(HttpBody::Text),
+            "form-urlencoded" => Some(HttpBody::FormUrlEncoded),
+            "multipart-form" => Some(HttpBody::MultipartForm),
+            _ => None,
+        }
+    }
+
+    pub fn sub_type(self) -> &'static str {
+        match self {
+            HttpBody::Json => "json",
+            HttpBody::Text => "text",
+            HttpBody::FormUrlEncoded => "form-urlencoded",
+            HttpBody::MultipartForm => "multipart-form",
+        }
+    }
+
+    /// Checks if the body is made of fields rather than written as a value.
+    pub fn is_form(self) -> bool {
+        matches!(self, HttpBody::FormUrlEncoded | HttpBody::MultipartForm)
+    }
+
+    /// Gets the Content-Type sent with a body of this type when the request does not set one.
+    /// Multipart forms get theirs with the boundary when they are sent.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            HttpBody::Json => "application/json",
+            HttpBody::Text => "text/plain",
+            HttpBody::FormUrlEncoded => "application/x-www-form-urlencoded",
+            HttpBody::MultipartForm => "multipart/form-data",
+        }
+    }
+}
+
+/// AcceptPreset is a commonly used value for the Accept header so that it does not have to be
+/// typed by hand for every request.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum AcceptPreset {
+    #[default]
+    Any,
+    Json,
+    Xml,
+    Html,
+}
+
+impl AcceptPreset {
+    /// All the available presets, in the order they are shown in the TUI.
+    pub const ALL: [AcceptPreset; 4] = [
+        AcceptPreset::Any,
+        AcceptPreset::Json,
+        AcceptPreset::Xml,
+        AcceptPreset::Html,
+    ];
+
+    /// Gets the value that should be sent in the Accept header.
+    pub fn header_value(self) -> &'static str {
+        match self {
+            AcceptPreset::Any => "*/*",
+            AcceptPreset::Json => "application/json",
+            AcceptPreset::Xml => "application/xml",
+            AcceptPreset::Html => "text/html",
+        }
+    }
+}
+
+impl fmt::Display for AcceptPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let preset = match self {
+            AcceptPreset::Any => "Any",
+            AcceptPreset::Json => "JSON",
+            AcceptPreset::Xml => "XML",
+            AcceptPreset::Html => "HTML",
+        };
+        write!(f, "{}", preset)
+    }
+}
+
+impl FromStr for AcceptPreset {
+    type Err = String;
+
+    /// Parses the value used by the `accept` field in a collection block.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "any" => Ok(AcceptPreset::Any),
+            "json" => Ok(AcceptPreset::Json),
+            "xml" => Ok(AcceptPreset::Xml),
+            "html" => Ok(AcceptPreset::Html),
+            _ => Err(format!("unknown accept preset '{}'", s)),
+        }
+    }
+}
+
+/// ResponseViewer is how the body of a response is rendered in the TUI.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ResponseViewer {
+    Json,
+    Table,
+    #[default]
+    Raw,
+    Hex,
+    Image,
+}
+
+impl ResponseViewer {
+    /// Picks a viewer based on the value of a Content-Type header. Parameters such as the charset
+    /// are ignored.
+    pub fn from_content_type(content_type: &str) -> Self {
+        let mime = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        match mime.as_str() {
+            "text/csv" | "text/tab-separated-values" => ResponseViewer::Table,
+            "application/octet-stream" => ResponseViewer::Hex,
+            _ if mime == "application/json" || mime.ends_with("+json") => ResponseViewer::Json,
+            _ if mime.starts_with("image/") => ResponseViewer::Image,
+            _ => ResponseViewer::Raw,
+        }
+    }
+}
+
+impl fmt::Display for ResponseViewer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let viewer = match self {
+            ResponseViewer::Json => "json",
+            ResponseViewer::Table => "table",
+            ResponseViewer::Raw => "raw",
+            ResponseViewer::Hex => "hex",
+            ResponseViewer::Image => "image",
+        };
+        write!(f, "{}", viewer)
+    }
+}
+
+impl FromStr for ResponseViewer {
+    type Err = String;
+
+    /// Parses the value used by the `viewer` field in a settings block.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(ResponseViewer::Json),
+            "table" => Ok(ResponseViewer::Table),
+            "raw" => Ok(ResponseViewer::Raw),
+            "hex" => Ok(ResponseViewer::Hex),
+            "image" => Ok(ResponseViewer::Image),
+            _ => Err(format!("unknown response viewer '{}'", s)),
+        }
+    }
+}
+
+/// IpFamily forces hosts to be resolved to IPv4 or IPv6 addresses, to debug dual-stack issues.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum IpFamily {
+    #[default]
+    Any,
+    V4,
+    V6,
+}
+
+impl IpFamily {
+    /// Checks if the address belongs to the family.
+    pub fn allows(self, address: &IpAddr) -> bool {
+        match self {
+            IpFamily::Any => true,
+            IpFamily::V4 => address.is_ipv4(),
+            IpFamily::V6 => address.is_ipv6(),
+        }
+    }
+
+    /// Gets the local address to bind the client to. Binding to the unspecified address of a
+    /// family makes the client only connect to addresses of that family.
+    pub fn local_address(self) -> Option<IpAddr> {
+        match self {
+            IpFamily::Any => None,
+            IpFamily::V4 => Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            IpFamily::V6 => Some(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+        }
+    }
+}
+
+impl fmt::Display for IpFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let family = match self {
+            IpFamily::Any => "any",
+            IpFamily::V4 => "ipv4",
+            IpFamily::V6 => "ipv6",
+        };
+        write!(f, "{}", family)
+    }
+}
+
+/// ProxyRoute is how requests reach their host, e.g. a health check that must hit the origin
+/// directly while everything else goes through the corporate proxy.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum ProxyRoute {
+    /// Through the proxy of the system, from the `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY`
+    /// environment variables.
+    #[default]
+    System,
+    /// Straight to the host, ignoring any proxy.
+    Direct,
+    /// Through the proxy at the url.
+    Via(String),
+}
+
+impl fmt::Display for ProxyRoute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProxyRoute::System => write!(f, "system"),
+            ProxyRoute::Direct => write!(f, "direct"),
+            ProxyRoute::Via(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+impl FromStr for ProxyRoute {
+    type Err = String;
+
+    /// Parses the value used by the `proxy` field in a collection or settings block.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            route if route.eq_ignore_ascii_case("system") => Ok(ProxyRoute::System),
+            route if route.eq_ignore_ascii_case("direct") => Ok(ProxyRoute::Direct),
+            url if url.starts_with("http://") || url.starts_with("https://") => {
+                Ok(ProxyRoute::Via(url.to_string()))
+            }
+            _ => Err(format!(
+                "unknown proxy '{}', expected system, direct or an http(s) url",
+                s
+            )),
+        }
+    }
+}
+
+impl FromStr for IpFamily {
+    type Err = String;
+
+    /// Parses the value used by the `ip-family` field in a collection or settings block.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "any" => Ok(IpFamily::Any),
+            "ipv4" | "4" => Ok(IpFamily::V4),
+            "ipv6" | "6" => Ok(IpFamily::V6),
+            _ => Err(format!("unknown ip family '{}'", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_accept_presets() {
+        let preset: AcceptPreset = "JSON".parse().unwrap();
+        assert_eq!(preset.header_value(), "application/json");
+        assert_eq!(preset.to_string(), "JSON");
+        assert!("yaml".parse::<AcceptPreset>().is_err());
+        assert_eq!(Collection::default().default_accept(), AcceptPreset::Any);
+    }
+
+    #[test]
+    fn should_pick_the_viewer_of_responses() {
+        let test_cases = [
+            ("application/json; charset=utf-8", ResponseViewer::Json),
+            ("application/problem+json", ResponseViewer::Json),
+            ("text/csv", ResponseViewer::Table),
+            ("text/tab-separated-values", ResponseViewer::Table),
+            ("application/octet-stream", ResponseViewer::Hex),
+            ("image/png", ResponseViewer::Image),
+            ("text/html", ResponseViewer::Raw),
+        ];
+        for (content_type, viewer) in test_cases {
+            assert_eq!(ResponseViewer::from_content_type(content_type), viewer);
+        }
+
+        // the viewer set on the request wins over the content type
+        let mut request = Request::new(
+            String::from("export"),
+            HttpMethod::Get,
+            String::from("/export"),
+            None,
+            None,
+            HashMap::new(),
+        );
+        assert_eq!(request.get_viewer("text/csv"), ResponseViewer::Table);
+        request.set_viewer(Some("RAW".parse().unwrap()));
+        assert_eq!(request.get_viewer("text/csv"), ResponseViewer::Raw);
+        assert!("pdf".parse::<ResponseViewer>().is_err());
+    }
+
+    #[test]
+    fn should_parse_prompt_variables() {
+        let prompt = PromptVariable::parse("user_id User ID").unwrap();
+        assert_eq!((prompt.name(), prompt.label()), ("user_id", "User ID"));
+        let prompt = PromptVariable::parse("otp").unwrap();
+        assert_eq!(prompt.label(), "otp");
+        assert!(PromptVariable::parse("  ").is_err());
+    }
+
+    #[test]
+    fn should_rename_requests() {
+        let mut collection = Collection::default();
+        for name in ["list", "get"] {
+            collection.add_request(Request::new(
+                String::from(name),
+                HttpMethod::Get,
+                String::from("/users"),
+                None,
+                None,
+                HashMap::new(),
+            ));
+        }
+
+        assert_eq!(collection.rename_request(0, String::from("  all  ")), Ok(0));
+        assert_eq!(collection.iter().next().unwrap().get_name(), "all");
+        assert_eq!(
+            collection.rename_request(0, String::from("get")),
+            Err(String::from("a request named get already exists"))
+        );
+        assert!(collection.rename_request(0, String::from(" ")).is_err());
+        assert!(collection.rename_request(2, String::from("other")).is_err());
+    }
+
+    #[test]
+    fn should_only_resolve_the_active_environment_when_it_is_on() {
+        let mut collection = Collection::default();
+        collection.new_environment(String::from("local"));
+        collection.set_active_environment(String::from("local"));
+        collection.add_environment_entry(String::from("BASE"), String::from("http://localhost"));
+        collection.enable_active_environment();
+        assert_eq!(
+            collection
+                .active_environment()
+                .and_then(|environment| environment.get("BASE"))
+                .map(String::as_str),
+            Some("http://localhost")
+        );
+
+        collection.disable_active_environment();
+        assert_eq!(collection.active_environment(), None);
+        assert!(collection.environment("local").is_some());
+    }
+
+    #[test]
+    fn should_inherit_the_deprecation_of_folders() {
+        let mut collection = Collection::default();
+        collection.deprecate_folder(String::from("billing"), String::from("use billing-v2"));
+        let mut invoices = Request::new(
+            String::from("invoices"),
+            HttpMethod::Get,
+            String::from("/invoices"),
+            None,
+            None,
+            HashMap::new(),
+        );
+        let users = invoices.clone();
+        invoices.set_folder(String::from("billing/invoices"));
+        assert_eq!(collection.deprecation(&invoices), Some("use billing-v2"));
+        invoices.set_deprecated(Some(String::from("use list-invoices")));
+        assert_eq!(collection.deprecation(&invoices), Some("use list-invoices"));
+        assert_eq!(collection.deprecation(&users), None);
+    }
+
+    #[test]
+    fn should_capture_values_of_the_body() {
+        let capture = CaptureRule::parse("TOKEN $.data.token").unwrap();
+        assert_eq!(
+            capture.extract(r#"{"data": {"token": "abc"}}"#),
+            Ok(String::from("abc"))
+        );
+        assert_eq!(
+            capture.extract(r#"{"data": {}}"#),
+            Err(String::from(
+                "can't capture TOKEN, $.data.token is not in the body"
+            ))
+        );
+        assert!(CaptureRule::parse("TOKEN data.token").is_err());
+    }
 }
@@ -0,0 +1,403 @@
+//! Assertions from the `tests` block of a request. Besides the status and body, they can check the
+//! shape of the headers, the cookies that were set, the redirects that were followed and the
+//! negotiated protocol, which matters when testing an API for security.
+
+use std::{fmt, net::SocketAddr};
+
+use serde_json::Value;
+
+use crate::json_tree;
+
+/// Everything about a response that assertions can look at.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ResponseFacts {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// The urls of every redirect that was followed, in order, ending with the final url.
+    pub redirects: Vec<String>,
+    /// The negotiated protocol, e.g. `HTTP/1.1` or `HTTP/2.0`.
+    pub protocol: String,
+    pub body: String,
+    /// The address of the server the response came from.
+    pub remote_addr: Option<SocketAddr>,
+}
+
+impl ResponseFacts {
+    /// Describes the address the response came from for the response meta, e.g.
+    /// `IPv6 [::1]:8080`.
+    pub fn remote(&self) -> Option<String> {
+        self.remote_addr.map(|address| {
+            let family = if address.is_ipv6() { "IPv6" } else { "IPv4" };
+            format!("{} {}", family, address)
+        })
+    }
+
+    /// Gets every cookie set by the response.
+    pub fn cookies(&self) -> Vec<Cookie> {
+        self.headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("set-cookie"))
+            .filter_map(|(_, value)| Cookie::parse(value))
+            .collect()
+    }
+
+    /// Gets the value of the first header with the name, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A cookie from a `Set-Cookie` header. Only the parts that are worth asserting on are kept.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Cookie {
+    name: String,
+    http_only: bool,
+    secure: bool,
+    same_site: Option<String>,
+}
+
+impl Cookie {
+    pub fn parse(header: &str) -> Option<Self> {
+        let mut parts = header.split(';').map(str::trim);
+        let (name, _) = parts.next()?.split_once('=')?;
+        if name.is_empty() {
+            return None;
+        }
+        let mut cookie = Cookie {
+            name: name.to_string(),
+            ..Cookie::default()
+        };
+        for attribute in parts {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            match key.to_ascii_lowercase().as_str() {
+                "httponly" => cookie.http_only = true,
+                "secure" => cookie.secure = true,
+                "samesite" => cookie.same_site = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        Some(cookie)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Checks a flag such as `HttpOnly`, `Secure` or `SameSite=Strict`.
+    fn has_flag(&self, flag: &str) -> bool {
+        let (key, value) = flag.split_once('=').unwrap_or((flag, ""));
+        match key.to_ascii_lowercase().as_str() {
+            "httponly" => self.http_only,
+            "secure" => self.secure,
+            "samesite" => self
+                .same_site
+                .as_ref()
+                .is_some_and(|same_site| value.is_empty() || same_site.eq_ignore_ascii_case(value)),
+            _ => false,
+        }
+    }
+}
+
+/// A comparison against a number, written as `5`, `< 5`, `<= 5`, `> 5` or `>= 5`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal(usize),
+    Less(usize),
+    LessOrEqual(usize),
+    Greater(usize),
+    GreaterOrEqual(usize),
+}
+
+impl Comparison {
+    fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        let (constructor, number): (fn(usize) -> Comparison, &str) =
+            if let Some(number) = value.strip_prefix("<=") {
+                (Comparison::LessOrEqual, number)
+            } else if let Some(number) = value.strip_prefix(">=") {
+                (Comparison::GreaterOrEqual, number)
+            } else if let Some(number) = value.strip_prefix('<') {
+                (Comparison::Less, number)
+            } else if let Some(number) = value.strip_prefix('>') {
+                (Comparison::Greater, number)
+            } else {
+                (Comparison::Equal, value.strip_prefix("==").unwrap_or(value))
+            };
+        number
+            .trim()
+            .parse()
+            .map(constructor)
+            .map_err(|_| format!("expected a number to compare with, got `{}`", value))
+    }
+
+    fn matches(self, actual: usize) -> bool {
+        match self {
+            Comparison::Equal(expected) => actual == expected,
+            Comparison::Less(expected) => actual < expected,
+            Comparison::LessOrEqual(expected) => actual <= expected,
+            Comparison::Greater(expected) => actual > expected,
+            Comparison::GreaterOrEqual(expected) => actual >= expected,
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Comparison::Equal(n) => write!(f, "{}", n),
+            Comparison::Less(n) => write!(f, "< {}", n),
+            Comparison::LessOrEqual(n) => write!(f, "<= {}", n),
+            Comparison::Greater(n) => write!(f, "> {}", n),
+            Comparison::GreaterOrEqual(n) => write!(f, ">= {}", n),
+        }
+    }
+}
+
+/// A single assertion, one per field of a `tests` block.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Assertion {
+    Status(u16),
+    BodyContains(String),
+    /// A header must be present. When a value is given, the header must have it.
+    Header {
+        name: String,
+        value: Option<String>,
+    },
+    HeaderCount(Comparison),
+    /// A cookie must be set with all the flags.
+    Cookie {
+        name: String,
+        flags: Vec<String>,
+    },
+    RedirectCount(Comparison),
+    /// The final url after following every redirect.
+    RedirectTo(String),
+    Protocol(String),
+    /// A node of a JSON body must exist. When a value is given, the node must have it.
+    Json {
+        path: String,
+        value: Option<Value>,
+    },
+}
+
+impl Assertion {
+    /// Builds an assertion from a field and its value in a `tests` block.
+    pub fn parse(field: &str, value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        match field {
+            "status" => value
+                .parse()
+                .map(Assertion::Status)
+                .map_err(|_| format!("expected a status code, got `{}`", value)),
+            "body-contains" => Ok(Assertion::BodyContains(value.to_string())),
+            "header" => {
+                let (name, expected) = match value.split_once(':') {
+                    Some((name, expected)) => (name, Some(expected.trim().to_string())),
+                    None => (value, None),
+                };
+                Ok(Assertion::Header {
+                    name: name.trim().to_string(),
+                    value: expected,
+                })
+            }
+            "header-count" => Comparison::parse(value).map(Assertion::HeaderCount),
+            "cookie" => {
+                let mut words = value.split_whitespace();
+                let name = words
+                    .next()
+                    .ok_or_else(|| String::from("expected the name of the cookie"))?;
+                Ok(Assertion::Cookie {
+                    name: name.to_string(),
+                    flags: words.map(String::from).collect(),
+                })
+            }
+            "redirects" => Comparison::parse(value).map(Assertion::RedirectCount),
+            "redirect-to" => Ok(Assertion::RedirectTo(value.to_string())),
+            "protocol" => Ok(Assertion::Protocol(value.to_string())),
+            "json" => {
+                let (path, expected) = match value.split_once("==") {
+                    Some((path, expected)) => {
+                        let expected = expected.trim();
+                        // anything that is not JSON is compared as a string
+                        let expected = serde_json::from_str(expected)
+                            .unwrap_or_else(|_| Value::String(expected.to_string()));
+                        (path.trim(), Some(expected))
+                    }
+                    None => (value, None),
+                };
+                if !path.starts_with('$') {
+                    return Err(format!(
+                        "expected a JSONPath starting with $, got `{}`",
+                        path
+                    ));
+                }
+                Ok(Assertion::Json {
+                    path: path.to_string(),
+                    value: expected,
+                })
+            }
+            _ => Err(format!("unknown assertion `{}`", field)),
+        }
+    }
+
+    /// Gets the field and the value the assertion is written as in a `tests` block.
+    pub fn to_field(&self) -> (&'static str, String) {
+        match self {
+            Assertion::Status(status) => ("status", status.to_string()),
+            Assertion::BodyContains(text) => ("body-contains", text.clone()),
+            Assertion::Header { name, value } => match value {
+                Some(value) => ("header", format!("{}: {}", name, value)),
+                None => ("header", name.clone()),
+            },
+            Assertion::HeaderCount(comparison) => ("header-count", comparison.to_string()),
+            Assertion::Cookie { name, flags } => {
+                let mut words = vec![name.as_str()];
+                words.extend(flags.iter().map(String::as_str));
+                ("cookie", words.join(" "))
+            }
+            Assertion::RedirectCount(comparison) => ("redirects", comparison.to_string()),
+            Assertion::RedirectTo(url) => ("redirect-to", url.clone()),
+            Assertion::Protocol(protocol) => ("protocol", protocol.clone()),
+            Assertion::Json { path, value } => match value {
+                Some(value) => ("json", format!("{} == {}", path, value)),
+                None => ("json", path.clone()),
+            },
+        }
+    }
+
+    /// Checks the assertion against a response. The error explains what did not match.
+    pub fn check(&self, response: &ResponseFacts) -> Result<(), String> {
+        match self {
+            Assertion::Status(expected) if response.status != *expected => Err(format!(
+                "expected status {}, got {}",
+                expected, response.status
+            )),
+            Assertion::BodyContains(text) if !response.body.contains(text.as_str()) => {
+                Err(format!("expected the body to contain `{}`", text))
+            }
+            Assertion::Header { name, value } => match (response.header(name), value) {
+                (None, _) => Err(format!("expected header {}", name)),
+                (Some(actual), Some(expected)) if actual != expected => Err(format!(
+                    "expected header {} to be `{}`, got `{}`",
+                    name, expected, actual
+                )),
+                _ => Ok(()),
+            },
+            Assertion::HeaderCount(comparison) if !comparison.matches(response.headers.len()) => {
+                Err(format!(
+                    "expected {} headers, got {}",
+                    comparison,
+                    response.headers.len()
+                ))
+            }
+            Assertion::Cookie { name, flags } => {
+                let cookies = response.cookies();
+                let cookie = cookies
+                    .iter()
+                    .find(|cookie| cookie.name() == name)
+                    .ok_or_else(|| format!("expected cookie {} to be set", name))?;
+                let missing: Vec<&str> = flags
+                    .iter()
+                    .filter(|flag| !cookie.has_flag(flag))
+                    .map(String::as_str)
+                    .collect();
+                if missing.is_empty() {
+                    Ok(())
+                } else {
+                    Err(format!("cookie {} is missing {}", name, missing.join(", ")))
+                }
+            }
+            Assertion::RedirectCount(comparison) => {
+                // the last url is where the request ended, not a redirect
+                let count = response.redirects.len().saturating_sub(1);
+                if comparison.matches(count) {
+                    Ok(())
+                } else {
+                    Err(format!("expected {} redirects, got {}", comparison, count))
+                }
+            }
+            Assertion::RedirectTo(url) => match response.redirects.last() {
+                Some(last) if last == url => Ok(()),
+                last => Err(format!(
+                    "expected to end at {}, ended at {}",
+                    url,
+                    last.map(String::as_str).unwrap_or("the original url")
+                )),
+            },
+            Assertion::Json { path, value } => {
+                let body: Value = serde_json::from_str(&response.body)
+                    .map_err(|_| String::from("expected a JSON body"))?;
+                match (json_tree::lookup(&body, path), value) {
+                    (None, _) => Err(format!("expected {} in the body", path)),
+                    (Some(actual), Some(expected)) if actual != expected => Err(format!(
+                        "expected {} to be {}, got {}",
+                        path, expected, actual
+                    )),
+                    _ => Ok(()),
+                }
+            }
+            Assertion::Protocol(protocol) if !response.protocol.eq_ignore_ascii_case(protocol) => {
+                Err(format!(
+                    "expected protocol {}, got {}",
+                    protocol, response.protocol
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_check_cookie_assertions() {
+        let response = ResponseFacts {
+            status: 200,
+            headers: vec![(
+                String::from("Set-Cookie"),
+                String::from("session=abc; Path=/; HttpOnly; Secure; SameSite=Strict"),
+            )],
+            ..ResponseFacts::default()
+        };
+        let test_cases = vec![
+            ("session HttpOnly Secure SameSite=Strict", true),
+            ("session SameSite", true),
+            ("session SameSite=Lax", false),
+            ("tracking", false),
+        ];
+        for (value, expected) in test_cases {
+            let assertion = Assertion::parse("cookie", value).unwrap();
+            assert_eq!(assertion.check(&response).is_ok(), expected, "{}", value);
+        }
+    }
+
+    #[test]
+    fn should_check_redirects_and_protocol() {
+        let response = ResponseFacts {
+            status: 200,
+            redirects: vec![
+                String::from("http://example.com/old"),
+                String::from("https://example.com/new"),
+            ],
+            protocol: String::from("HTTP/2.0"),
+            ..ResponseFacts::default()
+        };
+        let test_cases = vec![
+            (("redirects", "1"), true),
+            (("redirects", "> 1"), false),
+            (("redirect-to", "https://example.com/new"), true),
+            (("protocol", "http/2.0"), true),
+            (("header-count", "0"), true),
+        ];
+        for ((field, value), expected) in test_cases {
+            let assertion = Assertion::parse(field, value).unwrap();
+            assert_eq!(assertion.check(&response).is_ok(), expected, "{}", field);
+        }
+        assert!(Assertion::parse("header-count", "many").is_err());
+    }
+}
@@ -0,0 +1,122 @@
+//! The blocks of a hermes file as they are read, printed by `hermes ast` so linters, generators
+//! and other tools can use hermes files without a parser of their own. `--json` prints the tree
+//! with where every block and field is, lines and columns counting from 0 like the diagnostics.
+
+use std::{fs, path::Path};
+
+use serde::Serialize;
+
+use crate::source::{self, Diagnostic, SourceBlock, SourceValue};
+
+#[derive(Debug, Serialize)]
+struct Ast<'a> {
+    blocks: &'a [SourceBlock],
+    diagnostics: &'a [Diagnostic],
+}
+
+/// Runs `hermes ast`, returning what to print and whether the file has errors. Files with errors
+/// are read as far as possible, warnings are printed but don't count as problems.
+pub fn run(file: &Path, json: bool) -> Result<(String, bool), String> {
+    let contents =
+        fs::read_to_string(file).map_err(|err| format!("reading {}: {}", file.display(), err))?;
+    let (blocks, diagnostics) = source::read(&contents);
+    let printed = if json {
+        serde_json::to_string_pretty(&Ast {
+            blocks: &blocks,
+            diagnostics: &diagnostics,
+        })
+        .map_err(|err| err.to_string())?
+    } else {
+        let mut printed = String::new();
+        for block in &blocks {
+            outline(block, 0, &mut printed);
+        }
+        for diagnostic in &diagnostics {
+            let path = file.display().to_string();
+            printed.push_str(&format!("{}\n", diagnostic.render(&path, &contents)));
+        }
+        printed
+    };
+    Ok((printed, diagnostics.iter().any(Diagnostic::is_error)))
+}
+
+/// Writes the block as an indented outline, e.g. `request as login (line 3)` followed by its
+/// fields. Disabled fields are marked with `0`, and inline blocks without a type are only their
+/// fields.
+fn outline(block: &SourceBlock, depth: usize, out: &mut String) {
+    let mut depth = depth;
+    if !block.block_type().is_empty() {
+        let mut header = block.block_type().to_string();
+        if !block.sub_type().is_empty() {
+            header = format!("{}.{}", header, block.sub_type());
+        }
+        if !block.identifier().is_empty() {
+            header = format!("{} as {}", header, block.identifier());
+        }
+        out.push_str(&format!(
+            "{}{} (line {})\n",
+            "  ".repeat(depth),
+            header,
+            block.line() + 1
+        ));
+        depth += 1;
+    }
+    let indent = "  ".repeat(depth);
+    for (name, enabled, value) in block.toggled_entries() {
+        let state = if enabled { "" } else { " 0" };
+        match value {
+            SourceValue::Text(text) => {
+                out.push_str(&format!("{}{}{} `{}`\n", indent, name, state, text));
+            }
+            SourceValue::Reference(identifier) => {
+                out.push_str(&format!("{}{}{} -> {}\n", indent, name, state, identifier));
+            }
+            SourceValue::Block(inline) => {
+                out.push_str(&format!("{}{}{}\n", indent, name, state));
+                outline(inline, depth + 1, out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::external;
+
+    #[test]
+    fn should_print_the_tree_of_a_file() {
+        let path = external::temp_path("ast", "hermes");
+        fs::write(
+            &path,
+            "request as login {\n    url `/login`\n    headers 0 auth\n    queries {\n        page `1`\n    }\n    body body.json {\n        value `{}`\n    }\n}\n\nheaders {\n",
+        )
+        .unwrap();
+
+        let (printed, has_problems) = run(&path, true).unwrap();
+        assert!(has_problems);
+        let ast: Value = serde_json::from_str(&printed).unwrap();
+        let login = &ast["blocks"][0];
+        assert_eq!(login["type"], "request");
+        assert_eq!(login["identifier"], "login");
+        assert_eq!(login["end_line"], 9);
+        assert_eq!(login["fields"][0]["span"]["line"], 1);
+        assert_eq!(login["fields"][0]["span"]["start"], 4);
+        assert_eq!(
+            login["fields"][1]["value"],
+            serde_json::json!({"kind": "reference", "value": "auth"})
+        );
+        assert_eq!(login["fields"][3]["value"]["value"]["sub_type"], "json");
+        assert_eq!(ast["diagnostics"][0]["line"], 11);
+
+        let (printed, _) = run(&path, false).unwrap();
+        assert!(printed.starts_with(
+            "request as login (line 1)\n  url `/login`\n  headers 0 -> auth\n  queries\n    page `1`\n  body\n    body.json (line 7)\n"
+        ));
+        assert!(printed.contains("error: the headers block is never closed, add a `}`\n"));
+        assert!(printed.ends_with("12 | headers {\n   | ^^^^^^^\n   = expected `}`\n"));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,260 @@
+//! How a request proves who is sending it, from its `auth` block: a bearer token, basic
+//! credentials or an API key sent in a header or in the query string. The values are written like
+//! the rest of the request, so a `{{VARIABLE}}` keeps the secret in the environment and masked.
+
+use std::{fmt, str::FromStr};
+
+use crate::credentials::Credential;
+
+/// The sub types of auth blocks along with their fields, in the order they are written.
+pub const AUTH_TYPES: [(&str, &[&str]); 3] = [
+    ("bearer", &["token"]),
+    ("basic", &["username", "password"]),
+    ("api-key", &["name", "value", "in"]),
+];
+
+/// Where an API key is sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum KeyLocation {
+    #[default]
+    Header,
+    Query,
+}
+
+impl fmt::Display for KeyLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyLocation::Header => write!(f, "header"),
+            KeyLocation::Query => write!(f, "query"),
+        }
+    }
+}
+
+impl FromStr for KeyLocation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "header" => Ok(KeyLocation::Header),
+            "query" => Ok(KeyLocation::Query),
+            _ => Err(format!(
+                "an API key is sent in the `header` or the `query`, not in `{}`",
+                s
+            )),
+        }
+    }
+}
+
+/// The auth of a request, as written in its auth block, variables included.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Auth {
+    /// Sent as `Authorization: Bearer <token>`.
+    Bearer { token: String },
+    /// Sent as `Authorization: Basic <base64 of username:password>`.
+    Basic { username: String, password: String },
+    /// Sent as the header, or the query parameter, with the name.
+    ApiKey {
+        name: String,
+        value: String,
+        location: KeyLocation,
+    },
+}
+
+impl Auth {
+    /// Gets the sub type of the auth block, e.g. `bearer` for `auth.bearer`.
+    pub fn sub_type(&self) -> &'static str {
+        match self {
+            Auth::Bearer { .. } => "bearer",
+            Auth::Basic { .. } => "basic",
+            Auth::ApiKey { .. } => "api-key",
+        }
+    }
+
+    /// Gets the fields of the auth block, in the order they are written.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Auth::Bearer { token } => vec![("token", token.clone())],
+            Auth::Basic { username, password } => {
+                vec![
+                    ("username", username.clone()),
+                    ("password", password.clone()),
+                ]
+            }
+            Auth::ApiKey {
+                name,
+                value,
+                location,
+            } => vec![
+                ("name", name.clone()),
+                ("value", value.clone()),
+                ("in", location.to_string()),
+            ],
+        }
+    }
+
+    /// Reads the auth from the sub type of its block and its fields. Missing fields are empty,
+    /// except the name of an API key, and API keys are sent in a header unless `in` says otherwise.
+    pub fn from_fields(
+        sub_type: &str,
+        field: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, String> {
+        let text = |name: &str| field(name).unwrap_or_default();
+        match sub_type {
+            "bearer" => Ok(Auth::Bearer {
+                token: text("token"),
+            }),
+            "basic" => Ok(Auth::Basic {
+                username: text("username"),
+                password: text("password"),
+            }),
+            "api-key" => {
+                let name = text("name");
+                if name.trim().is_empty() {
+                    return Err(String::from("the api-key auth needs the name of the key"));
+                }
+                Ok(Auth::ApiKey {
+                    name,
+                    value: text("value"),
+                    location: field("in")
+                        .map(|location| location.parse())
+                        .transpose()?
+                        .unwrap_or_default(),
+                })
+            }
+            "" => Err(String::from(
+                "the auth block needs a type, e.g. auth.bearer, auth.basic or auth.api-key",
+            )),
+            sub_type => Err(format!(
+                "unknown type of auth `{}`, expected bearer, basic or api-key",
+                sub_type
+            )),
+        }
+    }
+
+    /// Gets the auth with every value passed through resolve, e.g. to replace its variables.
+    pub fn resolve<E>(
+        &self,
+        mut resolve: impl FnMut(&str) -> Result<String, E>,
+    ) -> Result<Self, E> {
+        Ok(match self {
+            Auth::Bearer { token } => Auth::Bearer {
+                token: resolve(token)?,
+            },
+            Auth::Basic { username, password } => Auth::Basic {
+                username: resolve(username)?,
+                password: resolve(password)?,
+            },
+            Auth::ApiKey {
+                name,
+                value,
+                location,
+            } => Auth::ApiKey {
+                name: resolve(name)?,
+                value: resolve(value)?,
+                location: *location,
+            },
+        })
+    }
+
+    /// Gets the header sending the auth, None for API keys sent in the query.
+    pub fn header(&self) -> Option<(String, String)> {
+        match self {
+            Auth::Bearer { token } => {
+                Some((String::from("Authorization"), format!("Bearer {}", token)))
+            }
+            Auth::Basic { username, password } => {
+                let credential = Credential {
+                    username: username.clone(),
+                    password: password.clone(),
+                };
+                Some((String::from("Authorization"), credential.authorization()))
+            }
+            Auth::ApiKey {
+                name,
+                value,
+                location: KeyLocation::Header,
+            } => Some((name.clone(), value.clone())),
+            Auth::ApiKey { .. } => None,
+        }
+    }
+
+    /// Gets the query parameter sending the auth, only for API keys sent in the query.
+    pub fn query(&self) -> Option<(String, String)> {
+        match self {
+            Auth::ApiKey {
+                name,
+                value,
+                location: KeyLocation::Query,
+            } => Some((name.clone(), value.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Auth::Bearer { token } => write!(f, "bearer {}", token),
+            Auth::Basic { username, .. } => write!(f, "basic as {}", username),
+            Auth::ApiKey { name, location, .. } => {
+                write!(f, "api key {} in the {}", name, location)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn should_send_every_kind_of_auth() {
+        let fields = HashMap::from([("token", "{{TOKEN}}")]);
+        let bearer = Auth::from_fields("bearer", |name| {
+            fields.get(name).map(|value| value.to_string())
+        })
+        .unwrap();
+        let resolved = bearer
+            .resolve(|text| Ok::<_, ()>(text.replace("{{TOKEN}}", "abc")))
+            .unwrap();
+        assert_eq!(
+            resolved.header(),
+            Some((String::from("Authorization"), String::from("Bearer abc")))
+        );
+        assert_eq!(bearer.fields(), [("token", String::from("{{TOKEN}}"))]);
+
+        let fields = HashMap::from([("username", "Aladdin"), ("password", "open sesame")]);
+        let basic = Auth::from_fields("basic", |name| {
+            fields.get(name).map(|value| value.to_string())
+        })
+        .unwrap();
+        assert_eq!(
+            basic.header().unwrap().1,
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+
+        let fields = HashMap::from([("name", "api_key"), ("value", "secret"), ("in", "query")]);
+        let query = Auth::from_fields("api-key", |name| {
+            fields.get(name).map(|value| value.to_string())
+        })
+        .unwrap();
+        assert_eq!(query.header(), None);
+        assert_eq!(
+            query.query(),
+            Some((String::from("api_key"), String::from("secret")))
+        );
+        let header = Auth::from_fields("api-key", |name| {
+            (name == "name").then(|| String::from("X-Api-Key"))
+        })
+        .unwrap();
+        assert_eq!(header.header().unwrap().0, "X-Api-Key");
+        assert_eq!(header.to_string(), "api key X-Api-Key in the header");
+
+        assert!(Auth::from_fields("api-key", |_| None).is_err());
+        assert!(Auth::from_fields("digest", |_| None).is_err());
+        assert!(Auth::from_fields("", |_| None).is_err());
+        assert!("cookie".parse::<KeyLocation>().is_err());
+    }
+}
@@ -0,0 +1,147 @@
+//! Background tasks, such as sending requests, run on a tokio runtime so the TUI keeps drawing
+//! while they are in flight. Finished tasks report back through a channel that the update loop
+//! drains between events. Cancelled tasks are no longer waited for, and stop early where their
+//! work checks its Cancel.
+
+use std::{
+    collections::HashSet,
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tokio::{
+    runtime::{self, Runtime},
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+};
+
+/// The frames of the spinner shown while tasks are in flight.
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+/// How long each frame of the spinner is shown for, in milliseconds.
+const SPINNER_FRAME_MILLIS: u128 = 80;
+
+/// Asks work running in the background to stop early. The work checks it where it can stop,
+/// e.g. before reading the body of a response.
+#[derive(Debug, Default, Clone)]
+pub struct Cancel(Arc<AtomicBool>);
+
+impl Cancel {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug)]
+pub struct Background<T> {
+    runtime: Runtime,
+    sender: UnboundedSender<(u64, T)>,
+    receiver: UnboundedReceiver<(u64, T)>,
+    /// The ids of the tasks that did not report back yet, and were not cancelled.
+    in_flight: HashSet<u64>,
+    next_task: u64,
+}
+
+impl<T: Send + 'static> Background<T> {
+    pub fn new() -> io::Result<Self> {
+        let runtime = runtime::Builder::new_multi_thread()
+            .thread_name("hermes-background")
+            .build()?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Ok(Self {
+            runtime,
+            sender,
+            receiver,
+            in_flight: HashSet::new(),
+            next_task: 0,
+        })
+    }
+
+    /// Runs blocking work, like a call through the blocking http client, off the render loop. Its
+    /// result is handed out by try_next once it is done. Returns the id of the task, to cancel it
+    /// with.
+    pub fn spawn_blocking(&mut self, work: impl FnOnce() -> T + Send + 'static) -> u64 {
+        let sender = self.sender.clone();
+        let task = self.next_task;
+        self.next_task += 1;
+        self.in_flight.insert(task);
+        self.runtime.spawn_blocking(move || {
+            // the receiver only goes away when the app does
+            let _ = sender.send((task, work()));
+        });
+        task
+    }
+
+    /// Stops waiting for the task, its result is dropped whenever it comes in. Returns false when
+    /// it already reported back.
+    pub fn cancel(&mut self, task: u64) -> bool {
+        self.in_flight.remove(&task)
+    }
+
+    /// Takes the result of a finished task without waiting. Returns None when no task finished
+    /// since the last call.
+    pub fn try_next(&mut self) -> Option<T> {
+        loop {
+            let (task, result) = self.receiver.try_recv().ok()?;
+            if self.in_flight.remove(&task) {
+                return Some(result);
+            }
+        }
+    }
+
+    /// The number of tasks that did not report back yet.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+}
+
+/// Gets the frame of the spinner to show now, so it turns on every redraw without keeping state.
+pub fn spinner() -> char {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default();
+    SPINNER[(millis / SPINNER_FRAME_MILLIS) as usize % SPINNER.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn should_report_finished_tasks() {
+        let mut background = Background::new().unwrap();
+        background.spawn_blocking(|| {
+            thread::sleep(Duration::from_millis(20));
+            1
+        });
+        background.spawn_blocking(|| 2);
+        let cancelled = background.spawn_blocking(|| 3);
+        assert_eq!(background.in_flight(), 3);
+        assert!(background.cancel(cancelled));
+        assert!(!background.cancel(cancelled));
+        assert_eq!(background.in_flight(), 2);
+
+        let mut results = Vec::new();
+        while results.len() < 2 {
+            match background.try_next() {
+                Some(result) => results.push(result),
+                None => thread::sleep(Duration::from_millis(1)),
+            }
+        }
+        results.sort();
+        assert_eq!(results, vec![1, 2]);
+        assert_eq!(background.in_flight(), 0);
+        // the cancelled task finished too, but is never handed out
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(background.try_next(), None);
+    }
+}
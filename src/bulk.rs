@@ -0,0 +1,155 @@
+//! Bulk edits of the requests marked in the sidebar: one header or query parameter set on all of
+//! them at once, e.g. `X-Tenant: {{tenant}}` on every request of a large collection. They are
+//! written like overrides and previewed request by request before they are applied.
+
+use crate::{
+    api::{QueryParam, Request},
+    overrides::Override,
+};
+
+/// What a bulk edit does to a request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// The request did not have the header or query parameter.
+    Added,
+    /// The request had it with this other value.
+    Replaced(String),
+    /// The request already had it with the same value.
+    Unchanged,
+}
+
+/// Reads a bulk edit written like an override, `Header: value` or `?key=value`. Requests have no
+/// variables of their own, so variables can't be set in bulk.
+pub fn parse(input: &str) -> Result<Override, String> {
+    match input.parse()? {
+        Override::Variable { .. } => Err(String::from(
+            "requests have no variables of their own, expected `Header: value` or `?key=value`",
+        )),
+        with => Ok(with),
+    }
+}
+
+/// Tells what the bulk edit would do to the request.
+pub fn preview(with: &Override, request: &Request) -> Change {
+    let (current, value) = match with {
+        Override::Variable { .. } => return Change::Unchanged,
+        Override::Header { name, value } => (
+            request
+                .get_headers()
+                .iter()
+                .find(|(header, _)| header.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone()),
+            value,
+        ),
+        Override::Query { key, value } => (
+            request
+                .get_queries()
+                .iter()
+                .find(|query| query.key() == key)
+                // applying enables a disabled one, even with the same value
+                .map(|query| match query.is_enabled() {
+                    true => query.value().to_string(),
+                    false => format!("{} (disabled)", query.value()),
+                }),
+            value,
+        ),
+    };
+    match current {
+        None => Change::Added,
+        Some(current) if &current == value => Change::Unchanged,
+        Some(current) => Change::Replaced(current),
+    }
+}
+
+/// Sets the header or query parameter on the request, replacing the ones with the same name. A
+/// replaced query parameter keeps its place among the others and is enabled.
+pub fn apply(with: &Override, request: &mut Request) {
+    match with {
+        Override::Variable { .. } => {}
+        Override::Header { name, value } => {
+            let mut headers = request.get_headers().clone();
+            headers.retain(|header, _| !header.eq_ignore_ascii_case(name));
+            headers.insert(name.clone(), value.clone());
+            request.set_headers(headers);
+        }
+        Override::Query { key, value } => {
+            let mut queries = Vec::new();
+            let mut replaced = false;
+            for query in request.get_queries() {
+                if query.key() != key {
+                    queries.push(query.clone());
+                } else if !replaced {
+                    queries.push(QueryParam::new(key.clone(), value.clone(), true));
+                    replaced = true;
+                }
+            }
+            if !replaced {
+                queries.push(QueryParam::new(key.clone(), value.clone(), true));
+            }
+            request.set_queries(queries);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::api::HttpMethod;
+
+    #[test]
+    fn should_set_a_header_or_query_parameter_on_many_requests() {
+        let mut request = Request::new(
+            String::from("list users"),
+            HttpMethod::Get,
+            String::from("{{BASE_URL}}/users"),
+            None,
+            None,
+            HashMap::from([(String::from("x-tenant"), String::from("acme"))]),
+        );
+        request.set_queries(vec![
+            QueryParam::new(String::from("page"), String::from("1"), true),
+            QueryParam::new(String::from("tenant"), String::from("acme"), false),
+            QueryParam::new(String::from("sort"), String::from("name"), true),
+        ]);
+        assert!(parse("tenant=acme").is_err());
+
+        let header = parse("X-Tenant: {{tenant}}").unwrap();
+        assert_eq!(
+            preview(&header, &request),
+            Change::Replaced(String::from("acme"))
+        );
+        apply(&header, &mut request);
+        assert_eq!(
+            request.get_headers(),
+            &HashMap::from([(String::from("X-Tenant"), String::from("{{tenant}}"))])
+        );
+        assert_eq!(preview(&header, &request), Change::Unchanged);
+
+        let query = parse("?tenant=acme").unwrap();
+        assert_eq!(
+            preview(&query, &request),
+            Change::Replaced(String::from("acme (disabled)"))
+        );
+        let query = parse("?tenant={{tenant}}").unwrap();
+        apply(&query, &mut request);
+        let queries: Vec<_> = request
+            .get_queries()
+            .iter()
+            .map(|query| (query.key(), query.value(), query.is_enabled()))
+            .collect();
+        assert_eq!(
+            queries,
+            [
+                ("page", "1", true),
+                ("tenant", "{{tenant}}", true),
+                ("sort", "name", true),
+            ]
+        );
+        assert_eq!(
+            preview(&parse("?limit=10").unwrap(), &request),
+            Change::Added
+        );
+    }
+}
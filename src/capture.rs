@@ -0,0 +1,223 @@
+//! Guardrails for capturing response bodies. Bodies up to the configured size are kept in memory,
+//! bigger ones are streamed to a temp file so an accidental multi-GB response can't take all the
+//! memory. Only the head and tail of a spilled body are kept around for the viewer. The temp file
+//! is removed once the last response holding it is dropped.
+
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{charset, external};
+
+/// The number of bytes kept from each end of a body that was written to a temp file.
+const PREVIEW_BYTES: usize = 8 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CapturedBody {
+    InMemory(Vec<u8>),
+    Spilled {
+        file: Arc<SpillFile>,
+        size: u64,
+        head: Vec<u8>,
+        tail: Vec<u8>,
+    },
+}
+
+/// A temp file holding a spilled body, removed when dropped.
+#[derive(Debug, PartialEq)]
+pub struct SpillFile(PathBuf);
+
+impl SpillFile {
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+impl CapturedBody {
+    /// Reads the whole body, moving it to a temp file once it grows past max_bytes.
+    pub fn capture(mut reader: impl Read, max_bytes: u64) -> io::Result<Self> {
+        let mut body = Vec::new();
+        (&mut reader).take(max_bytes + 1).read_to_end(&mut body)?;
+        if body.len() as u64 <= max_bytes {
+            return Ok(CapturedBody::InMemory(body));
+        }
+
+        let spill = SpillFile(external::temp_path("body", "txt"));
+        let mut file = File::create(spill.path())?;
+        let mut head = Vec::new();
+        let mut tail = Vec::new();
+        let mut size = 0;
+        let mut chunk = body;
+        let mut buffer = [0u8; 64 * 1024];
+        while !chunk.is_empty() {
+            file.write_all(&chunk)?;
+            size += chunk.len() as u64;
+            if head.len() < PREVIEW_BYTES {
+                let missing = (PREVIEW_BYTES - head.len()).min(chunk.len());
+                head.extend_from_slice(&chunk[..missing]);
+            }
+            tail.extend_from_slice(&chunk);
+            if tail.len() > PREVIEW_BYTES {
+                tail.drain(..tail.len() - PREVIEW_BYTES);
+            }
+            let read = reader.read(&mut buffer)?;
+            chunk = buffer[..read].to_vec();
+        }
+        Ok(CapturedBody::Spilled {
+            file: Arc::new(spill),
+            size,
+            head,
+            tail,
+        })
+    }
+
+    /// Gets the text to show in the viewer, decoded with the charset of the response. Spilled
+    /// bodies only show their head and tail.
+    pub fn preview(&self, content_type: Option<&str>) -> String {
+        let decode = |bytes: &[u8]| charset::decode(bytes, content_type).text().to_string();
+        match self {
+            CapturedBody::InMemory(body) => decode(body),
+            CapturedBody::Spilled {
+                file,
+                size,
+                head,
+                tail,
+            } => format!(
+                "{}\n\n... {} bytes in total, the full body is in {} until the response is closed ...\n\n{}",
+                decode(head),
+                size,
+                file.path().display(),
+                decode(tail)
+            ),
+        }
+    }
+
+    /// Gets the bytes of the body as a hex dump, sixteen to a line after their offset and followed by
+    /// the printable ones. Spilled bodies only show their head and tail.
+    pub fn hex_dump(&self) -> String {
+        match self {
+            CapturedBody::InMemory(body) => hex_lines(body, 0),
+            CapturedBody::Spilled {
+                file,
+                size,
+                head,
+                tail,
+            } => format!(
+                "{}\n\n... {} bytes in total, the full body is in {} until the response is closed ...\n\n{}",
+                hex_lines(head, 0),
+                size,
+                file.path().display(),
+                hex_lines(tail, *size as usize - tail.len())
+            ),
+        }
+    }
+
+    /// Gets the first bytes of the body, enough to tell what kind of file it is.
+    pub fn head(&self) -> &[u8] {
+        match self {
+            CapturedBody::InMemory(body) => &body[..body.len().min(PREVIEW_BYTES)],
+            CapturedBody::Spilled { head, .. } => head,
+        }
+    }
+
+    /// Gets the size of the whole body in bytes.
+    pub fn size(&self) -> u64 {
+        match self {
+            CapturedBody::InMemory(body) => body.len() as u64,
+            CapturedBody::Spilled { size, .. } => *size,
+        }
+    }
+
+    /// Gets the temp file holding the whole body, when it was too big to keep in memory.
+    pub fn spilled_path(&self) -> Option<&Path> {
+        match self {
+            CapturedBody::InMemory(_) => None,
+            CapturedBody::Spilled { file, .. } => Some(file.path()),
+        }
+    }
+}
+
+/// Writes the bytes as hex dump lines, counting their offsets from start.
+fn hex_lines(bytes: &[u8], start: usize) -> String {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let printable: String = chunk
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!(
+                "{:08x}  {:<47}  |{}|",
+                start + index * 16,
+                hex.join(" "),
+                printable
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_keep_small_bodies_in_memory() {
+        let body = CapturedBody::capture(&b"hello"[..], 5).unwrap();
+        assert_eq!(body, CapturedBody::InMemory(b"hello".to_vec()));
+        assert_eq!(body.spilled_path(), None);
+    }
+
+    #[test]
+    fn should_dump_bodies_as_hex() {
+        let body = CapturedBody::InMemory(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR more".to_vec());
+        assert_eq!(
+            body.hex_dump(),
+            "00000000  89 50 4e 47 0d 0a 1a 0a 00 00 00 0d 49 48 44 52  |.PNG........IHDR|\n\
+             00000010  20 6d 6f 72 65                                   | more|"
+        );
+        assert_eq!(body.head().len(), 21);
+    }
+
+    #[test]
+    fn should_spill_big_bodies_to_a_file() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let body = CapturedBody::capture(&data[..], 1000).unwrap();
+        let path = body.spilled_path().unwrap().to_path_buf();
+        match &body {
+            CapturedBody::Spilled {
+                size, head, tail, ..
+            } => {
+                assert_eq!(*size, data.len() as u64);
+                assert_eq!(*head, data[..PREVIEW_BYTES]);
+                assert_eq!(*tail, data[data.len() - PREVIEW_BYTES..]);
+                assert_eq!(fs::read(&path).unwrap(), data);
+            }
+            _ => panic!("expected the body to be spilled"),
+        }
+
+        // the file stays until the last copy of the body is gone
+        let copy = body.clone();
+        drop(body);
+        assert!(path.exists());
+        drop(copy);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,185 @@
+//! Who changed a request and when, for large collections shared by a team. Requests keep a short
+//! changelog that is written along with them, and a change is recorded whenever a request is
+//! edited from hermes, so nobody has to keep it up to date by hand.
+
+use std::{fmt, process::Command};
+
+use chrono::{DateTime, SecondsFormat, SubsecRound, Utc};
+
+use crate::api::Request;
+
+/// How many changes a request keeps, older ones are dropped.
+pub const CHANGELOG_LIMIT: usize = 20;
+
+/// A change to a request, written as `<time> <author>: <summary>` in the `changelog` field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    at: DateTime<Utc>,
+    author: String,
+    summary: String,
+}
+
+impl Change {
+    pub fn new(at: DateTime<Utc>, author: String, summary: String) -> Self {
+        Self {
+            at,
+            author,
+            summary,
+        }
+    }
+
+    /// Reads a change written as `2024-05-01T10:00:00Z Jane Doe: changed the url`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (at, rest) = value.trim().split_once(' ').unwrap_or((value.trim(), ""));
+        let at = DateTime::parse_from_rfc3339(at)
+            .map_err(|_| format!("changelog entry `{}` must start with a time", value))?;
+        let (author, summary) = rest.split_once(':').unwrap_or((rest, ""));
+        if author.trim().is_empty() {
+            return Err(format!("changelog entry `{}` is missing the author", value));
+        }
+        Ok(Self {
+            at: at.with_timezone(&Utc),
+            author: author.trim().to_string(),
+            summary: summary.trim().to_string(),
+        })
+    }
+
+    pub fn at(&self) -> DateTime<Utc> {
+        self.at
+    }
+
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    pub fn summary(&self) -> &str {
+        &self.summary
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}: {}",
+            self.at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.author,
+            self.summary
+        )
+    }
+}
+
+/// Describes what changed between two versions of a request, e.g. `changed the url and headers`.
+/// None when nothing that gets sent changed.
+pub fn describe(before: &Request, after: &Request) -> Option<String> {
+    let mut changed = Vec::new();
+    if before.get_name() != after.get_name() {
+        changed.push("name");
+    }
+    if before.get_method() != after.get_method() {
+        changed.push("method");
+    }
+    if before.get_url() != after.get_url() {
+        changed.push("url");
+    }
+    if before.get_headers() != after.get_headers() {
+        changed.push("headers");
+    }
+    if before.get_auth() != after.get_auth() {
+        changed.push("auth");
+    }
+    if before.get_body() != after.get_body()
+        || before.get_body_type() != after.get_body_type()
+        || before.get_form() != after.get_form()
+    {
+        changed.push("body");
+    }
+    if before.get_assertions() != after.get_assertions() {
+        changed.push("tests");
+    }
+    if before.get_captures() != after.get_captures() {
+        changed.push("captures");
+    }
+    if before.get_expected() != after.get_expected() {
+        changed.push("expected result");
+    }
+    if before.get_description() != after.get_description() {
+        changed.push("description");
+    }
+    if before.get_queries() != after.get_queries() {
+        changed.push("queries");
+    }
+    if before.get_raw() != after.get_raw() {
+        changed.push("raw");
+    }
+    if before.get_owner() != after.get_owner() {
+        changed.push("owner");
+    }
+    let (last, rest) = changed.split_last()?;
+    Some(match rest {
+        [] => format!("changed the {}", last),
+        rest => format!("changed the {} and {}", rest.join(", "), last),
+    })
+}
+
+/// Adds a change by the author to the request when it differs from how it was before.
+pub fn record(before: &Request, after: &mut Request, author: &str) {
+    if let Some(summary) = describe(before, after) {
+        after.add_change(Change::new(
+            Utc::now().trunc_subsecs(0),
+            author.to_string(),
+            summary,
+        ));
+    }
+}
+
+/// Gets who changes are recorded as: the author from the settings, the name from the git config
+/// or the user of the system, in that order.
+pub fn author(configured: Option<&str>) -> String {
+    if let Some(author) = configured.filter(|author| !author.trim().is_empty()) {
+        return author.trim().to_string();
+    }
+    Command::new("git")
+        .args(["config", "user.name"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_else(|| String::from("unknown"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::api::HttpMethod;
+
+    #[test]
+    fn should_record_what_changed() {
+        let before = Request::new(
+            String::from("Login"),
+            HttpMethod::Post,
+            String::from("/login"),
+            None,
+            None,
+            HashMap::new(),
+        );
+        let mut after = before.clone();
+        record(&before, &mut after, "alice");
+        assert!(after.get_changelog().is_empty());
+
+        after.set_url(String::from("/v2/login"));
+        after.set_body(Some(String::from("{}")));
+        after.set_owner(Some(String::from("auth-team")));
+        record(&before, &mut after, "Alice Doe");
+        let change = after.last_change().unwrap();
+        assert_eq!(change.author(), "Alice Doe");
+        assert_eq!(change.summary(), "changed the url, body and owner");
+        assert_eq!(Change::parse(&change.to_string()).as_ref(), Ok(change));
+        assert!(Change::parse("yesterday alice changed it").is_err());
+    }
+}
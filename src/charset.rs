@@ -0,0 +1,138 @@
+//! Decoding of response bodies. The charset is taken from a byte order mark first, then from the
+//! Content-Type header, then from the body itself, e.g. a `<meta charset>` or an XML declaration,
+//! so Latin-1 or Shift-JIS APIs render correctly instead of being read as UTF-8.
+
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+
+/// How many bytes of the body are searched for a declared charset.
+const SNIFF_BYTES: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decoded {
+    text: String,
+    /// Whether some bytes were not valid in the charset and were replaced.
+    had_errors: bool,
+}
+
+impl Decoded {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn had_errors(&self) -> bool {
+        self.had_errors
+    }
+}
+
+/// Decodes the body, removing the byte order mark if there is one. Bodies without any hint of
+/// their charset are read as UTF-8 when they are valid UTF-8 and as Windows-1252 otherwise, which
+/// is what browsers do for Latin-1.
+pub fn decode(body: &[u8], content_type: Option<&str>) -> Decoded {
+    let encoding = Encoding::for_bom(body)
+        .map(|(encoding, _)| encoding)
+        .or_else(|| content_type.and_then(charset_of).and_then(label))
+        .or_else(|| declared_charset(body))
+        .unwrap_or(if std::str::from_utf8(body).is_ok() {
+            UTF_8
+        } else {
+            WINDOWS_1252
+        });
+    // decode sniffs the BOM again and strips it
+    let (text, _, had_errors) = encoding.decode(body);
+    Decoded {
+        text: text.into_owned(),
+        had_errors,
+    }
+}
+
+/// Guesses the mime type of a body that came without a Content-Type from its first bytes.
+pub fn sniff_mime(body: &[u8]) -> &'static str {
+    const SIGNATURES: [(&[u8], &str); 6] = [
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF8", "image/gif"),
+        (b"RIFF", "image/webp"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+    ];
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(magic, _)| body.starts_with(magic)) {
+        return mime;
+    }
+    let text = decode(&body[..body.len().min(SNIFF_BYTES)], None);
+    let start = text.text().trim_start();
+    if start.starts_with('{') || start.starts_with('[') {
+        "application/json"
+    } else if start.starts_with("<?xml") {
+        "application/xml"
+    } else if start.starts_with('<') {
+        "text/html"
+    } else if text.had_errors() || text.text().contains('\0') {
+        "application/octet-stream"
+    } else {
+        "text/plain"
+    }
+}
+
+/// Gets the charset parameter of a Content-Type header value.
+fn charset_of(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+fn label(charset: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(charset.as_bytes())
+}
+
+/// Finds the charset declared inside the body, like `<meta charset="...">` or
+/// `<?xml version="1.0" encoding="..."?>`.
+fn declared_charset(body: &[u8]) -> Option<&'static Encoding> {
+    // declarations are ASCII, so a lossy read of the start of the body is enough to find them
+    let start = String::from_utf8_lossy(&body[..body.len().min(SNIFF_BYTES)]).to_lowercase();
+    ["charset=", "encoding="].iter().find_map(|key| {
+        let value = &start[start.find(key)? + key.len()..];
+        let value = value.trim_start_matches(['"', '\'']);
+        let end = value
+            .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '-' || ch == '_'))
+            .unwrap_or(value.len());
+        label(&value[..end])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_decode_with_the_declared_charset() {
+        let test_cases: Vec<(&[u8], Option<&str>, &str)> = vec![
+            (b"caf\xe9", Some("text/plain; charset=ISO-8859-1"), "café"),
+            (
+                b"\x93\xfa\x96\x7b",
+                Some("text/plain; charset=\"Shift_JIS\""),
+                "日本",
+            ),
+            (
+                b"\xef\xbb\xbfhello",
+                Some("text/plain; charset=latin1"),
+                "hello",
+            ),
+            (
+                b"<meta charset=\"shift_jis\">\x93\xfa",
+                None,
+                "<meta charset=\"shift_jis\">日",
+            ),
+            (b"caf\xc3\xa9", None, "café"),
+            (b"caf\xe9", None, "café"),
+        ];
+        for (body, content_type, text) in test_cases {
+            let decoded = decode(body, content_type);
+            assert_eq!(decoded.text(), text);
+        }
+        assert_eq!(sniff_mime(b"\x89PNG\r\n\x1a\n...."), "image/png");
+        assert_eq!(sniff_mime(b"  {\"a\": 1}"), "application/json");
+    }
+}
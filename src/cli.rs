@@ -0,0 +1,164 @@
+//! The command line of hermes. Running it without a command opens the TUI. This file is also
+//! included by the build script to generate the shell completions and the man page, so it must
+//! not use anything else from the crate.
+
+use std::path::PathBuf;
+
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "hermes",
+    version,
+    about = "A light-weight API client in the terminal with VIM keymaps.",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    /// The collections to open in the TUI, as folders or single .hermes files.
+    pub collections: Vec<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run every request of a collection, or only one, and check its tests, for scripts and CI.
+    #[command(after_help = RUN_EXIT_CODES)]
+    Run(RunArgs),
+    /// Import a request shared from hermes, given as a share link or the path to a share file, or
+    /// a collection exported from Postman (v2.1) or Insomnia, or the folder of a Bruno collection.
+    Import {
+        source: String,
+        /// The folder to write the request or the files of the collection to.
+        #[arg(long, default_value = ".")]
+        into: PathBuf,
+        /// Print the files a collection would be written to, without writing them.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print completion candidates of a collection as JSON, for shell and editor integrations.
+    Complete(CompleteArgs),
+    /// Print the blocks and fields of a hermes file as they are read, along with its problems.
+    Ast {
+        file: PathBuf,
+        /// Print the tree as JSON, with where every block and field is, for other tools.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print how often every request of a collection was sent from the TUI and when it last
+    /// succeeded as JSON, the least used first, to find the requests nobody uses anymore.
+    Usage {
+        /// The folder of the collection.
+        collection: PathBuf,
+    },
+    /// Print the completion script of hermes for a shell.
+    Completions { shell: Shell },
+    /// Print the man page of hermes.
+    Man,
+}
+
+/// Kept in sync with runner::Exit, this file can't use the rest of the crate.
+const RUN_EXIT_CODES: &str = "Exit codes:
+  0  every test passed
+  1  some tests failed
+  2  the command line is wrong
+  3  the collection has problems or a request could not be built
+  4  a precondition or a request could not reach the server";
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    /// The folder of the collection.
+    pub collection: PathBuf,
+    /// Only run this request, given by its identifier or its name, with the hooks around it.
+    pub request: Option<String>,
+    /// The environment to use instead of the one picked by the collection.
+    #[arg(long, short)]
+    pub env: Option<String>,
+    /// Only print the summary of the run.
+    #[arg(long, short, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Also print the headers and the body of every response.
+    #[arg(long, short)]
+    pub verbose: bool,
+    /// What to do with requests that are deprecated.
+    #[arg(long, value_enum, default_value_t = DeprecatedPolicy::Warn)]
+    pub deprecated: DeprecatedPolicy,
+    /// Print every request as it would be sent, variables resolved, without sending anything.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DeprecatedPolicy {
+    /// Send them and print why they are deprecated.
+    Warn,
+    /// Don't send them.
+    Skip,
+    /// Don't send them and count them as failed.
+    Fail,
+}
+
+#[derive(Debug, Args)]
+#[group(skip)]
+#[command(group(ArgGroup::new("candidates").required(true).args(["keywords", "variables"])))]
+pub struct CompleteArgs {
+    /// Block types, plus the blocks and requests of the collection that can be referenced.
+    #[arg(long)]
+    pub keywords: bool,
+    /// The variables of every environment and of the collection, plus the built-ins.
+    #[arg(long, requires = "collection")]
+    pub variables: bool,
+    /// The folder of the collection.
+    pub collection: Option<PathBuf>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn should_require_what_to_complete() {
+        assert!(Cli::try_parse_from(["hermes", "complete", "./api"]).is_err());
+        assert!(Cli::try_parse_from(["hermes", "complete", "--variables"]).is_err());
+        assert!(Cli::try_parse_from(["hermes", "complete", "./api", "--keywords"]).is_ok());
+    }
+
+    #[test]
+    fn should_parse_the_output_of_runs() {
+        let cli = Cli::try_parse_from(["hermes", "run", "./api", "login", "-e", "staging", "-q"])
+            .unwrap();
+        let Some(Command::Run(args)) = cli.command else {
+            panic!("expected the run command");
+        };
+        assert_eq!(args.collection, PathBuf::from("./api"));
+        assert_eq!(args.request.as_deref(), Some("login"));
+        assert_eq!(args.env.as_deref(), Some("staging"));
+        assert!(args.quiet && !args.verbose);
+        assert!(Cli::try_parse_from(["hermes", "run", "./api", "--quiet", "--verbose"]).is_err());
+    }
+
+    #[test]
+    fn should_take_the_collections_to_open() {
+        let cli = Cli::try_parse_from(["hermes", "./billing", "./scratch.hermes"]).unwrap();
+        assert_eq!(
+            cli.collections,
+            [
+                PathBuf::from("./billing"),
+                PathBuf::from("./scratch.hermes")
+            ]
+        );
+        assert!(cli.command.is_none());
+    }
+
+    #[test]
+    fn should_import_into_the_current_directory() {
+        let cli = Cli::try_parse_from(["hermes", "import", "export.json", "--dry-run"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Import { into, dry_run: true, .. }) if into == Path::new(".")
+        ));
+    }
+}
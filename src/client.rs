@@ -0,0 +1,898 @@
+//! Sending requests. The variables of a request are resolved into an Outgoing request, which is
+//! sent here with a blocking reqwest client. The response keeps everything the viewer and the
+//! tests need: the status, headers, redirects, protocol, remote address and the captured body.
+
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    io::{self, Read},
+    net::SocketAddr,
+    path::Path,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use ratatui::style::Color;
+use reqwest::{
+    blocking::Client,
+    header::{HeaderMap, HeaderName, HeaderValue},
+    redirect, Certificate, Method, Proxy, Url,
+};
+
+use crate::{
+    api::{HttpBody, HttpMethod, IpFamily, ProxyRoute, Request},
+    assertions::ResponseFacts,
+    auth::Auth,
+    background::Cancel,
+    capture::CapturedBody,
+    diagnostics, lab,
+    network::NetworkSimulation,
+    signing,
+    tunnel::{Route, SshTunnel},
+    variables::{ResolveError, Resolver, Scopes},
+};
+
+/// How many redirects are followed before giving up.
+const MAX_REDIRECTS: usize = 10;
+/// The characters escaped in the fields of form-urlencoded bodies.
+const FORM_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'*');
+/// How long to wait before the first retry. Every retry after it waits twice as long.
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+/// The longest a retry waits, whatever the Retry-After header of the server asks for.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+/// How often a retry waiting for its turn checks whether the send was cancelled.
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+/// The statuses that mean the server may answer if asked again.
+const RETRIED_STATUSES: [u16; 4] = [429, 502, 503, 504];
+/// The header servers use to recognize a request that was sent again.
+pub const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+
+/// A request with every variable resolved, ready to be sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outgoing {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    /// The raw request sent instead of the rest in lab mode, see lab.
+    pub raw: Option<String>,
+    /// The fields of a multipart form, sent instead of the body. Values starting with `@` are the
+    /// path of a file to send.
+    pub form: Vec<(String, String)>,
+}
+
+impl Outgoing {
+    /// Writes the request the way it would be sent, as the request line, the headers and the body.
+    /// Raw requests are written as they are, and multipart forms as one `name=value` line per field.
+    pub fn render(&self) -> String {
+        if let Some(raw) = &self.raw {
+            return raw.clone();
+        }
+        let mut rendered = format!("{} {}\n", self.method, self.url);
+        for (name, value) in &self.headers {
+            rendered.push_str(&format!("{}: {}\n", name, value));
+        }
+        if let Some(body) = &self.body {
+            rendered.push_str(&format!("\n{}\n", body));
+        } else if !self.form.is_empty() {
+            rendered.push('\n');
+            for (name, value) in &self.form {
+                rendered.push_str(&format!("{}={}\n", name, value));
+            }
+        }
+        rendered
+    }
+}
+
+/// How requests are sent, from the collection and the settings.
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    pub ip_family: IpFamily,
+    pub proxy: ProxyRoute,
+    /// Path to a PEM file with an extra CA certificate to trust.
+    pub ca_certificate: Option<String>,
+    /// Bodies bigger than this are written to a temp file, see capture.
+    pub max_body_bytes: u64,
+    /// The simulated network conditions, None to send requests as they are.
+    pub network: Option<NetworkSimulation>,
+    /// Whether raw requests can be sent.
+    pub lab_mode: bool,
+    /// How many times a request is sent again when it could not connect or the server was
+    /// unavailable, see RETRIED_STATUSES.
+    pub retries: u32,
+    /// Stops the send as soon as it can, dropping the connection.
+    pub cancel: Cancel,
+    /// The ssh tunnel of the environment, opened by the first request to the host it forwards to.
+    pub tunnel: Option<Arc<Mutex<SshTunnel>>>,
+    /// How long a request may take, None for the default of the http client.
+    pub timeout: Option<Duration>,
+}
+
+/// A try at sending a request, kept when the request was retried.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attempt {
+    /// The status of the response, None when none came in.
+    pub status: Option<u16>,
+    /// Why the attempt was retried, or how the last one ended.
+    pub outcome: String,
+    pub elapsed: Duration,
+    pub sent_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    facts: ResponseFacts,
+    body: CapturedBody,
+    elapsed: Duration,
+    /// Every attempt at sending the request, the response being the last one. Empty when it was
+    /// sent once.
+    attempts: Vec<Attempt>,
+}
+
+impl Response {
+    /// Gets the status, headers and the rest of what tests check, with the body decoded.
+    pub fn facts(&self) -> &ResponseFacts {
+        &self.facts
+    }
+
+    pub fn body(&self) -> &CapturedBody {
+        &self.body
+    }
+
+    pub fn attempts(&self) -> &[Attempt] {
+        &self.attempts
+    }
+
+    /// Gets how long the response took to come back, the last attempt only when it was retried.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Gets the color of the status, green for success up to red for server errors.
+    pub fn status_color(&self) -> Color {
+        match self.facts.status {
+            200..=299 => Color::LightGreen,
+            300..=399 => Color::LightCyan,
+            400..=499 => Color::LightYellow,
+            _ => Color::LightRed,
+        }
+    }
+
+    /// Describes the response in a single line, e.g. `200 OK in 87 ms`, or
+    /// `200 OK in 87 ms after 3 attempts` when it was retried.
+    pub fn summary(&self) -> String {
+        let reason = reqwest::StatusCode::from_u16(self.facts.status)
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or_default();
+        let mut summary = format!(
+            "{} {} in {} ms",
+            self.facts.status,
+            reason,
+            self.elapsed.as_millis()
+        );
+        if !self.attempts.is_empty() {
+            summary.push_str(&format!(" after {} attempts", self.attempts.len()));
+        }
+        summary
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SendError {
+    /// The server could not be reached, worth running the connection diagnostics for.
+    Connection {
+        url: String,
+        message: String,
+    },
+    Failed(String),
+    /// Every attempt failed, the last one with the error.
+    Retried {
+        attempts: Vec<Attempt>,
+        last: Box<SendError>,
+    },
+    Cancelled,
+}
+
+impl SendError {
+    /// Gets every attempt at sending the request. Empty when it was sent once.
+    pub fn attempts(&self) -> &[Attempt] {
+        match self {
+            SendError::Retried { attempts, .. } => attempts,
+            _ => &[],
+        }
+    }
+
+    /// Gets the url of the server that could not be reached, when that is why sending failed.
+    pub fn unreachable_url(&self) -> Option<&str> {
+        match self {
+            SendError::Connection { url, .. } => Some(url),
+            SendError::Retried { last, .. } => last.unreachable_url(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Connection { url, message } => {
+                write!(f, "could not connect to {}: {}", url, message)
+            }
+            SendError::Failed(message) => write!(f, "{}", message),
+            SendError::Retried { attempts, last } => {
+                write!(f, "{} (after {} attempts)", last, attempts.len())
+            }
+            SendError::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+/// Resolves the variables in the url, headers and body of the request. The auth of the request,
+/// the default headers of the collection, a Content-Type matching the type of the body and the
+/// generated Idempotency-Key are added when the request does not set them.
+pub fn prepare(
+    request: &Request,
+    default_headers: &HashMap<String, String>,
+    scopes: &Scopes,
+    resolver: &mut Resolver,
+) -> Result<Outgoing, ResolveError> {
+    let mut resolve = |text: &str| resolver.interpolate(text, scopes);
+    let mut url = resolve(&request.get_url())?;
+    let auth = request
+        .get_auth()
+        .map(|auth| auth.resolve(&mut resolve))
+        .transpose()?;
+    let mut queries = request
+        .get_queries()
+        .iter()
+        .filter(|query| query.is_enabled())
+        .map(|query| Ok((resolve(query.key())?, resolve(query.value())?)))
+        .collect::<Result<Vec<_>, ResolveError>>()?;
+    if let Some((key, value)) = auth.as_ref().and_then(Auth::query) {
+        if !queries.iter().any(|(set, _)| *set == key) {
+            queries.push((key, value));
+        }
+    }
+    // urls that don't parse are sent as they are, so sending reports what is wrong with them
+    if let (false, Ok(mut parsed)) = (queries.is_empty(), Url::parse(&url)) {
+        parsed.query_pairs_mut().extend_pairs(queries);
+        url = parsed.to_string();
+    }
+    let mut headers = request
+        .get_headers()
+        .iter()
+        .map(|(name, value)| Ok((name.clone(), resolve(value)?)))
+        .collect::<Result<Vec<_>, ResolveError>>()?;
+    // the auth of the request wins over the default headers, but not over its own headers
+    if let Some((name, value)) = auth.as_ref().and_then(Auth::header) {
+        if !headers
+            .iter()
+            .any(|(set, _)| set.eq_ignore_ascii_case(&name))
+        {
+            headers.push((name, value));
+        }
+    }
+    for (name, value) in default_headers {
+        if !headers
+            .iter()
+            .any(|(set, _)| set.eq_ignore_ascii_case(name))
+        {
+            headers.push((name.clone(), resolve(value)?));
+        }
+    }
+    if request.has_auto_idempotency_key()
+        && !headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case(IDEMPOTENCY_KEY))
+    {
+        // generated once per send, so every retry of it carries the same key
+        headers.push((
+            String::from(IDEMPOTENCY_KEY),
+            uuid::Uuid::new_v4().to_string(),
+        ));
+    }
+    headers.sort();
+    let fields = request
+        .get_form()
+        .iter()
+        .filter(|field| field.is_enabled())
+        .map(|field| Ok((resolve(field.name())?, resolve(field.value())?)))
+        .collect::<Result<Vec<_>, ResolveError>>()?;
+    let mut body = request.get_body().map(&mut resolve).transpose()?;
+    let mut form = Vec::new();
+    match request.get_body_type() {
+        Some(HttpBody::FormUrlEncoded) => body = Some(encode_form(&fields)),
+        Some(HttpBody::MultipartForm) => form = fields,
+        _ => {}
+    }
+    let raw = request.get_raw().map(&mut resolve).transpose()?;
+    let signing = request.get_body_signing();
+    if let (Some(signing), Some(unsigned)) = (signing, &body) {
+        let key = resolve(signing.key())?;
+        body = Some(
+            signing::apply(signing.method(), &key, unsigned)
+                .map_err(ResolveError::SigningFailed)?,
+        );
+    }
+    // multipart forms get their Content-Type when they are sent, along with the boundary
+    let content_type = match (signing, request.get_body_type()) {
+        (Some(signing), _) => Some(signing.method().content_type()),
+        (None, body_type) => body_type.map(HttpBody::content_type),
+    };
+    if let (Some(_), Some(content_type)) = (&body, content_type) {
+        if !headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        {
+            headers.push((String::from("Content-Type"), content_type.to_string()));
+        }
+    }
+    Ok(Outgoing {
+        method: request.get_method(),
+        url,
+        headers,
+        body,
+        raw,
+        form,
+    })
+}
+
+/// Encodes the fields of a form-urlencoded body, e.g. `name=Hermes&tags=a%2Cb`.
+fn encode_form(fields: &[(String, String)]) -> String {
+    fields
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "{}={}",
+                utf8_percent_encode(name, FORM_ENCODE_SET),
+                utf8_percent_encode(value, FORM_ENCODE_SET)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Writes the fields of a multipart form, reading the files of the ones starting with `@`.
+/// Returns the Content-Type with the boundary along with the body.
+fn encode_multipart(fields: &[(String, String)]) -> Result<(String, Vec<u8>), SendError> {
+    let boundary = format!("hermes-{}", uuid::Uuid::new_v4().simple());
+    let mut body = Vec::new();
+    for (name, value) in fields {
+        let name = name.replace('"', "%22");
+        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+        match value.strip_prefix('@') {
+            Some(path) => {
+                let contents = fs::read(path).map_err(|err| {
+                    SendError::Failed(format!("reading the file of {}: {}: {}", name, path, err))
+                })?;
+                let file_name = Path::new(path)
+                    .file_name()
+                    .map(|file_name| file_name.to_string_lossy().replace('"', "%22"))
+                    .unwrap_or_default();
+                body.extend_from_slice(
+                    format!(
+                        "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+                        name, file_name
+                    )
+                    .as_bytes(),
+                );
+                body.extend_from_slice(&contents);
+            }
+            None => {
+                body.extend_from_slice(
+                    format!(
+                        "Content-Disposition: form-data; name=\"{}\"\r\n\r\n{}",
+                        name, value
+                    )
+                    .as_bytes(),
+                );
+            }
+        }
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    Ok((format!("multipart/form-data; boundary={}", boundary), body))
+}
+
+/// Warns about retrying a request that may not be safe to send twice, i.e. one whose method is
+/// not idempotent and that has no Idempotency-Key header for the server to recognize it by.
+pub fn retry_warning(request: &Outgoing, retries: u32) -> Option<String> {
+    let keyed = request
+        .headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case(IDEMPOTENCY_KEY));
+    (retries > 0 && !request.method.is_idempotent() && !keyed).then(|| {
+        format!(
+            "retrying {} without an {} header may apply it more than once",
+            request.method, IDEMPOTENCY_KEY
+        )
+    })
+}
+
+/// Sends the request and reads the whole response. Requests that could not connect or got one of
+/// RETRIED_STATUSES are sent again up to `retries` times, waiting longer every time, and keep
+/// every attempt. A cancelled send stops before the next attempt.
+pub fn send(request: &Outgoing, options: &Options) -> Result<Response, SendError> {
+    let mut attempts = Vec::new();
+    loop {
+        if options.cancel.is_cancelled() {
+            return Err(SendError::Cancelled);
+        }
+        let sent_at = Utc::now().timestamp();
+        let started = Instant::now();
+        let result = send_once(request, options);
+        let retry = attempts.len() < options.retries as usize;
+        let delay = match &result {
+            Ok(response) if retry && RETRIED_STATUSES.contains(&response.facts.status) => Some(
+                retry_delay(attempts.len(), response.facts.header("retry-after")),
+            ),
+            Err(SendError::Connection { .. }) if retry => Some(retry_delay(attempts.len(), None)),
+            _ => None,
+        };
+        if delay.is_none() && attempts.is_empty() {
+            return result;
+        }
+        attempts.push(Attempt {
+            status: result.as_ref().ok().map(|response| response.facts.status),
+            outcome: match &result {
+                Ok(response) => response.summary(),
+                Err(err) => err.to_string(),
+            },
+            elapsed: started.elapsed(),
+            sent_at,
+        });
+        let Some(delay) = delay else {
+            return match result {
+                Ok(response) => Ok(Response {
+                    attempts,
+                    ..response
+                }),
+                Err(last) => Err(SendError::Retried {
+                    attempts,
+                    last: Box::new(last),
+                }),
+            };
+        };
+        wait_unless_cancelled(delay, &options.cancel);
+    }
+}
+
+/// Waits for the delay, or until the send is cancelled.
+fn wait_unless_cancelled(delay: Duration, cancel: &Cancel) {
+    let started = Instant::now();
+    while !cancel.is_cancelled() {
+        let Some(left) = delay.checked_sub(started.elapsed()) else {
+            return;
+        };
+        thread::sleep(left.min(CANCEL_CHECK_INTERVAL));
+    }
+}
+
+/// Reads the body of a response until the send is cancelled, which drops the connection.
+struct UntilCancelled<'a, R> {
+    reader: R,
+    cancel: &'a Cancel,
+}
+
+impl<R: Read> Read for UntilCancelled<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.cancel.is_cancelled() {
+            return Err(io::Error::other("cancelled"));
+        }
+        self.reader.read(buf)
+    }
+}
+
+/// Gets how long to wait before sending a request again after the attempts so far, honoring the
+/// delay in seconds of a Retry-After header.
+fn retry_delay(attempts: usize, retry_after: Option<&str>) -> Duration {
+    retry_after
+        .and_then(|seconds| seconds.trim().parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or_else(|| RETRY_DELAY * 2u32.saturating_pow(attempts as u32))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// Sends the request once and reads the whole response.
+fn send_once(request: &Outgoing, options: &Options) -> Result<Response, SendError> {
+    let route = tunnel_route(&request.url, options)?;
+    if let Some(raw) = &request.raw {
+        return send_raw(&request.url, raw, route.map(|route| route.forward), options);
+    }
+    let redirects = Arc::new(Mutex::new(Vec::new()));
+    let client = build_client(options, route, Arc::clone(&redirects))?;
+
+    let method = Method::from_bytes(request.method.to_str().as_bytes())
+        .map_err(|err| SendError::Failed(err.to_string()))?;
+    let mut headers = HeaderMap::new();
+    for (name, value) in &request.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|err| SendError::Failed(format!("header {}: {}", name, err)))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|err| SendError::Failed(format!("header {}: {}", name, err)))?;
+        headers.append(name, value);
+    }
+    let multipart = if request.form.is_empty() {
+        None
+    } else {
+        let (content_type, body) = encode_multipart(&request.form)?;
+        // the boundary in the Content-Type has to be the one the body uses
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            HeaderValue::from_str(&content_type)
+                .map_err(|err| SendError::Failed(err.to_string()))?,
+        );
+        Some(body)
+    };
+    let mut builder = client.request(method, &request.url).headers(headers);
+    if let Some(body) = multipart {
+        builder = builder.body(body);
+    } else if let Some(body) = &request.body {
+        builder = builder.body(body.clone());
+    }
+
+    if let Some(network) = &options.network {
+        thread::sleep(network.latency());
+        if network.should_fail() {
+            return Err(SendError::Failed(String::from(
+                "request dropped by the network simulation",
+            )));
+        }
+    }
+    if options.cancel.is_cancelled() {
+        return Err(SendError::Cancelled);
+    }
+    let started = Instant::now();
+    let response = builder.send().map_err(|err| {
+        if diagnostics::is_connection_error(&err) {
+            SendError::Connection {
+                url: request.url.clone(),
+                message: err.to_string(),
+            }
+        } else {
+            SendError::Failed(err.to_string())
+        }
+    })?;
+
+    let status = response.status().as_u16();
+    let protocol = format!("{:?}", response.version());
+    let remote_addr = response.remote_addr();
+    let headers: Vec<(String, String)> = response
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).to_string(),
+            )
+        })
+        .collect();
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from);
+    let reader = UntilCancelled {
+        reader: response,
+        cancel: &options.cancel,
+    };
+    let body = CapturedBody::capture(reader, options.max_body_bytes).map_err(|err| {
+        if options.cancel.is_cancelled() {
+            SendError::Cancelled
+        } else {
+            SendError::Failed(format!("reading the body: {}", err))
+        }
+    })?;
+    if let Some(network) = &options.network {
+        thread::sleep(network.transfer_time(body.size() as usize));
+    }
+    let elapsed = started.elapsed();
+
+    let redirects = redirects.lock().map(|r| r.clone()).unwrap_or_default();
+    Ok(Response {
+        facts: ResponseFacts {
+            status,
+            headers,
+            redirects,
+            protocol,
+            body: body.preview(content_type.as_deref()),
+            remote_addr,
+        },
+        body,
+        elapsed,
+        attempts: Vec::new(),
+    })
+}
+
+/// Gets where the request goes through the ssh tunnel, when the url points at the host the tunnel
+/// forwards to. The tunnel is opened the first time it is needed.
+fn tunnel_route(url: &str, options: &Options) -> Result<Option<Route>, SendError> {
+    let Some(tunnel) = &options.tunnel else {
+        return Ok(None);
+    };
+    let parsed = Url::parse(url).map_err(|err| SendError::Failed(format!("{}: {}", url, err)))?;
+    let mut tunnel = tunnel
+        .lock()
+        .map_err(|_| SendError::Failed(String::from("the ssh tunnel is not usable")))?;
+    tunnel.route(&parsed).map_err(|err| SendError::Connection {
+        url: url.to_string(),
+        message: format!("the ssh tunnel did not open: {}", err),
+    })
+}
+
+/// Sends a raw request as it was written, see lab.
+fn send_raw(
+    url: &str,
+    raw: &str,
+    address: Option<SocketAddr>,
+    options: &Options,
+) -> Result<Response, SendError> {
+    if !options.lab_mode {
+        return Err(SendError::Failed(String::from(
+            "raw requests are only sent with lab_mode on in the settings",
+        )));
+    }
+    let bytes = lab::encode(raw).map_err(SendError::Failed)?;
+    let started = Instant::now();
+    let response = lab::send(
+        url,
+        &bytes,
+        address,
+        options.ip_family,
+        options.ca_certificate.as_deref(),
+    )?;
+    let body = CapturedBody::capture(response.body.as_slice(), options.max_body_bytes)
+        .map_err(|err| SendError::Failed(format!("reading the body: {}", err)))?;
+    let content_type = response
+        .headers
+        .iter()
+        .find(|(name, _)| name == "content-type")
+        .map(|(_, value)| value.as_str());
+    Ok(Response {
+        facts: ResponseFacts {
+            status: response.status,
+            body: body.preview(content_type),
+            headers: response.headers,
+            redirects: Vec::new(),
+            protocol: response.protocol,
+            remote_addr: Some(response.remote_addr),
+        },
+        body,
+        elapsed: started.elapsed(),
+        attempts: Vec::new(),
+    })
+}
+
+/// Builds a client for the options. Every redirect followed is pushed to redirects. Requests to the
+/// host the ssh tunnel forwards to go through its relay.
+fn build_client(
+    options: &Options,
+    route: Option<Route>,
+    redirects: Arc<Mutex<Vec<String>>>,
+) -> Result<Client, SendError> {
+    let policy = redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() > MAX_REDIRECTS {
+            return attempt.error("too many redirects");
+        }
+        if let Ok(mut redirects) = redirects.lock() {
+            redirects.push(attempt.url().to_string());
+        }
+        attempt.follow()
+    });
+    let mut builder = Client::builder()
+        .redirect(policy)
+        .local_address(options.ip_family.local_address());
+    builder = match &options.proxy {
+        ProxyRoute::System => builder,
+        ProxyRoute::Direct => builder.no_proxy(),
+        ProxyRoute::Via(url) => builder.proxy(
+            Proxy::all(url).map_err(|err| SendError::Failed(format!("proxy {}: {}", url, err)))?,
+        ),
+    };
+    if let (Some(route), Some(tunnel)) = (route, &options.tunnel) {
+        let tunnel = Arc::clone(tunnel);
+        let relay = format!("http://{}", route.proxy);
+        builder = builder.proxy(Proxy::custom(move |url| {
+            let through = tunnel.lock().is_ok_and(|tunnel| tunnel.matches(url));
+            through.then(|| relay.clone())
+        }));
+    }
+    if let Some(timeout) = options.timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(path) = &options.ca_certificate {
+        let pem = fs::read(path).map_err(|err| {
+            SendError::Failed(format!("reading CA certificate {}: {}", path, err))
+        })?;
+        let certificate = Certificate::from_pem(&pem)
+            .map_err(|err| SendError::Failed(format!("CA certificate {}: {}", path, err)))?;
+        builder = builder.add_root_certificate(certificate);
+    }
+    builder
+        .build()
+        .map_err(|err| SendError::Failed(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::{Ipv4Addr, TcpListener},
+    };
+
+    use super::*;
+
+    #[test]
+    fn should_send_requests_and_follow_redirects() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let replies = [
+                format!(
+                    "HTTP/1.1 302 Found\r\nLocation: http://127.0.0.1:{}/new\r\nContent-Length: 0\r\n\r\n",
+                    port
+                ),
+                String::from("HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=latin1\r\nContent-Length: 4\r\nX-Id: 7\r\n\r\ncaf\u{e9}"),
+            ];
+            for reply in replies {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buffer = [0; 1024];
+                let _ = stream.read(&mut buffer).unwrap();
+                // write é as a single latin1 byte
+                let bytes: Vec<u8> = reply.chars().map(|ch| ch as u8).collect();
+                stream.write_all(&bytes).unwrap();
+            }
+        });
+
+        let request = Outgoing {
+            method: HttpMethod::Get,
+            url: format!("http://127.0.0.1:{}/old", port),
+            headers: vec![(String::from("Accept"), String::from("text/plain"))],
+            body: None,
+            raw: None,
+            form: Vec::new(),
+        };
+        let options = Options {
+            max_body_bytes: 1024,
+            ..Options::default()
+        };
+        let response = send(&request, &options).unwrap();
+        server.join().unwrap();
+
+        let facts = response.facts();
+        assert_eq!(facts.status, 200);
+        assert_eq!(facts.body, "café");
+        assert_eq!(facts.redirects, [format!("http://127.0.0.1:{}/new", port)]);
+        assert_eq!(facts.protocol, "HTTP/1.1");
+        assert!(facts
+            .headers
+            .contains(&(String::from("x-id"), String::from("7"))));
+        assert!(response.summary().starts_with("200 OK in"));
+    }
+
+    #[test]
+    fn should_send_through_the_proxy_of_the_request() {
+        let proxy = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = proxy.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = proxy.accept().unwrap();
+            let mut buffer = [0; 1024];
+            let read = stream.read(&mut buffer).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8_lossy(&buffer[..read]).into_owned()
+        });
+
+        let request = Outgoing {
+            method: HttpMethod::Get,
+            url: String::from("http://origin.invalid/health"),
+            headers: Vec::new(),
+            body: None,
+            raw: None,
+            form: Vec::new(),
+        };
+        let options = Options {
+            proxy: format!("http://127.0.0.1:{}", port).parse().unwrap(),
+            ..Options::default()
+        };
+        let response = send(&request, &options).unwrap();
+        assert_eq!(response.facts().status, 204);
+        assert!(server
+            .join()
+            .unwrap()
+            .starts_with("GET http://origin.invalid/health HTTP/1.1"));
+        assert_eq!("direct".parse(), Ok(ProxyRoute::Direct));
+        assert!("socks5://127.0.0.1:1080".parse::<ProxyRoute>().is_err());
+    }
+
+    #[test]
+    fn should_encode_form_bodies() {
+        let fields = vec![
+            (String::from("name"), String::from("Hermes Bot")),
+            (String::from("tags"), String::from("a,b")),
+        ];
+        assert_eq!(encode_form(&fields), "name=Hermes%20Bot&tags=a%2Cb");
+
+        let file = std::env::temp_dir().join(format!("hermes-{}.txt", uuid::Uuid::new_v4()));
+        fs::write(&file, "file contents").unwrap();
+        let fields = vec![
+            (String::from("title"), String::from("Report")),
+            (String::from("file"), format!("@{}", file.display())),
+        ];
+        let (content_type, body) = encode_multipart(&fields).unwrap();
+        fs::remove_file(&file).unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .unwrap();
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.starts_with(&format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nReport\r\n",
+            boundary
+        )));
+        assert!(body.contains("filename=\"hermes-"));
+        assert!(body.contains("\r\n\r\nfile contents\r\n"));
+        assert!(body.ends_with(&format!("--{}--\r\n", boundary)));
+        assert!(encode_multipart(&[(String::from("file"), String::from("@/missing"))]).is_err());
+    }
+
+    #[test]
+    fn should_retry_unavailable_servers() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let replies = [
+                "HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nContent-Length: 0\r\n\r\n",
+                "HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n",
+            ];
+            for reply in replies {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buffer = [0; 1024];
+                let _ = stream.read(&mut buffer).unwrap();
+                stream.write_all(reply.as_bytes()).unwrap();
+            }
+        });
+
+        let mut request = Outgoing {
+            method: HttpMethod::Post,
+            url: format!("http://127.0.0.1:{}/orders", port),
+            headers: Vec::new(),
+            body: None,
+            raw: None,
+            form: Vec::new(),
+        };
+        assert!(retry_warning(&request, 2).is_some());
+        assert_eq!(retry_warning(&request, 0), None);
+        request
+            .headers
+            .push((String::from("idempotency-key"), String::from("abc")));
+        assert_eq!(retry_warning(&request, 2), None);
+
+        let options = Options {
+            retries: 2,
+            max_body_bytes: 1024,
+            ..Options::default()
+        };
+        let response = send(&request, &options).unwrap();
+        server.join().unwrap();
+        assert_eq!(response.facts().status, 201);
+        let statuses: Vec<_> = response.attempts().iter().map(|a| a.status).collect();
+        assert_eq!(statuses, [Some(503), Some(201)]);
+        assert!(response.summary().ends_with("after 2 attempts"));
+
+        // a cancelled send does not go out again
+        options.cancel.cancel();
+        assert!(matches!(
+            send(&request, &options),
+            Err(SendError::Cancelled)
+        ));
+    }
+}
@@ -0,0 +1,229 @@
+//! Completion candidates for shell and editor integrations, printed as JSON by `hermes complete`.
+//! `--keywords` gives the block types along with the blocks and requests of the collection that can
+//! be referenced, `--variables` gives the names that can be used in `{{NAME}}`.
+
+use std::{collections::BTreeMap, fs, io::BufReader, path::Path};
+
+use serde::Serialize;
+
+use crate::{
+    cli::CompleteArgs,
+    faker, loader,
+    parser::{self, ScanSettings},
+    source::{self, SourceBlock, SourceValue},
+    syntax::BLOCK_TYPES,
+    variables,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Block,
+    Keyword,
+    Reference,
+    Request,
+    Variable,
+    Builtin,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Candidate {
+    label: String,
+    kind: Kind,
+    /// Where the candidate comes from, e.g. the environment that defines a variable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+impl Candidate {
+    fn new(label: impl Into<String>, kind: Kind, detail: Option<String>) -> Self {
+        Self {
+            label: label.into(),
+            kind,
+            detail,
+        }
+    }
+}
+
+/// Runs `hermes complete`, returning the JSON to print.
+pub fn run(args: &CompleteArgs) -> Result<String, String> {
+    let blocks = match &args.collection {
+        Some(collection) => read_collection(collection)?,
+        None => Vec::new(),
+    };
+    let candidates = match &args.collection {
+        Some(collection) if args.variables => variables(&blocks, collection),
+        _ => keywords(&blocks),
+    };
+    serde_json::to_string(&candidates).map_err(|err| err.to_string())
+}
+
+/// Reads the blocks of every hermes file in the collection. Files with problems are read as far as
+/// possible, completions are most needed while a file is being written.
+fn read_collection(dir: &Path) -> Result<Vec<SourceBlock>, String> {
+    if !dir.is_dir() {
+        return Err(format!("{} is not a collection folder", dir.display()));
+    }
+    let scan = parser::get_hermes_files(&dir.to_string_lossy(), &ScanSettings::default());
+    Ok(scan
+        .files
+        .iter()
+        .filter_map(|file| fs::File::open(file).ok())
+        .filter_map(|file| source::read_from(BufReader::new(file)).ok())
+        .flat_map(|(blocks, _)| blocks)
+        .collect())
+}
+
+/// Gets the block types, the `as` keyword, the identifiers of the blocks and the request names.
+pub fn keywords(blocks: &[SourceBlock]) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = BLOCK_TYPES
+        .iter()
+        .map(|block_type| Candidate::new(*block_type, Kind::Block, None))
+        .collect();
+    candidates.push(Candidate::new("as", Kind::Keyword, None));
+    for block in blocks.iter().filter(|block| !block.identifier().is_empty()) {
+        candidates.push(Candidate::new(
+            block.identifier(),
+            Kind::Reference,
+            Some(block.block_type().to_string()),
+        ));
+    }
+    for block in blocks
+        .iter()
+        .filter(|block| block.block_type() == "request")
+    {
+        if let Some(name) = block.text("name") {
+            candidates.push(Candidate::new(name, Kind::Request, None));
+        }
+    }
+    candidates
+}
+
+/// Gets the variables of every environment and of the collection, followed by the built-ins.
+/// Environments in dotenv files are read relative to the collection folder.
+pub fn variables(blocks: &[SourceBlock], dir: &Path) -> Vec<Candidate> {
+    // keeps one candidate per name, listing every environment that defines it
+    let mut defined: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut define = |name: &str, origin: &str| {
+        let origins = defined.entry(name.to_string()).or_default();
+        if !origins.iter().any(|known| known == origin) {
+            origins.push(origin.to_string());
+        }
+    };
+    for block in blocks {
+        match block.block_type() {
+            "environment" => {
+                let origin = match block.identifier() {
+                    "" => "environment",
+                    identifier => identifier,
+                };
+                for (name, _) in block.entries() {
+                    define(name, origin);
+                }
+            }
+            "collection" => {
+                for (field, value) in block.entries() {
+                    match (field, value) {
+                        ("variables", SourceValue::Block(variables)) => {
+                            for (name, _) in variables.entries() {
+                                define(name, "collection");
+                            }
+                        }
+                        ("environment", SourceValue::Block(environment)) => {
+                            for (name, _) in environment.entries() {
+                                define(name, "environment");
+                            }
+                        }
+                        ("environment", SourceValue::Text(path)) => {
+                            let contents = fs::read_to_string(dir.join(path)).unwrap_or_default();
+                            for (name, _) in loader::dotenv(&contents) {
+                                define(&name, path);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut candidates: Vec<Candidate> = defined
+        .into_iter()
+        .map(|(name, origins)| Candidate::new(name, Kind::Variable, Some(origins.join(", "))))
+        .collect();
+    for built_in in variables::BUILT_INS {
+        if built_in.starts_with(faker::PREFIX) {
+            candidates.extend(faker::GENERATORS.iter().map(|generator| {
+                Candidate::new(
+                    format!("{}{}", faker::PREFIX, generator),
+                    Kind::Builtin,
+                    None,
+                )
+            }));
+        } else {
+            candidates.push(Candidate::new(built_in, Kind::Builtin, None));
+        }
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external;
+
+    #[test]
+    fn should_list_variables_of_the_collection() {
+        let dir = external::temp_path("complete", "d");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("collection.hermes"),
+            "collection {\n    environment 1 `.env`\n    variables 1 {\n        PAGE_SIZE 1 `20`\n    }\n}\n\nenvironment as staging {\n    API_TOKEN 1 `a`\n    HOST 1 `b`\n}\n\nrequest as login {\n    name `Login`\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join(".env"),
+            "# local\nHOST=localhost\nexport PORT=8080\n",
+        )
+        .unwrap();
+
+        let json = run(&CompleteArgs {
+            keywords: false,
+            variables: true,
+            collection: Some(dir.clone()),
+        })
+        .unwrap();
+        let candidates: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        let variables: Vec<(&str, &str)> = candidates
+            .iter()
+            .filter(|candidate| candidate["kind"] == "variable")
+            .map(|candidate| {
+                (
+                    candidate["label"].as_str().unwrap(),
+                    candidate["detail"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            variables,
+            [
+                ("API_TOKEN", "staging"),
+                ("HOST", ".env, staging"),
+                ("PAGE_SIZE", "collection"),
+                ("PORT", ".env"),
+            ]
+        );
+        assert!(candidates.iter().any(|c| c["label"] == "$faker.email"));
+
+        let json = run(&CompleteArgs {
+            keywords: true,
+            variables: false,
+            collection: Some(dir.clone()),
+        })
+        .unwrap();
+        assert!(json.contains(r#"{"label":"login","kind":"reference","detail":"request"}"#));
+        assert!(json.contains(r#"{"label":"Login","kind":"request"}"#));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -63,14 +63,18 @@ impl Input {
     }
 
     pub fn enter_character(&mut self, character: char) {
-        match self.input_mode {
-            InputMode::Insert => {
-                self.input.insert(self.cursor_index, character);
-                self.move_cursor_right();
-            }
-            // ignore all other modes
-            _ => {}
-        };
+        // ignore all other modes
+        if let InputMode::Insert = self.input_mode {
+            self.insert_at_cursor(character);
+        }
+    }
+
+    /// Inserts the whole string at the cursor, as if it was typed in insert mode. Inputs are
+    /// prefilled with it before they are focused, so it inserts in any mode.
+    pub fn insert_string(&mut self, string: &str) {
+        for character in string.chars() {
+            self.insert_at_cursor(character);
+        }
     }
 
     pub fn delete_character(&mut self) {
@@ -99,10 +103,7 @@ impl Input {
     }
 
     pub fn get_cursor_index_u16(&self) -> u16 {
-        match u16::try_from(self.cursor_index) {
-            Ok(v) => v,
-            Err(_) => 0,
-        }
+        u16::try_from(self.cursor_index).unwrap_or_default()
     }
 
     /// Reset the states of the input widget
@@ -112,6 +113,17 @@ impl Input {
         self.cursor_index = 0;
     }
 
+    /// Inserts the character at the cursor, which counts characters, not bytes.
+    fn insert_at_cursor(&mut self, character: char) {
+        let offset = self
+            .input
+            .char_indices()
+            .nth(self.cursor_index)
+            .map_or(self.input.len(), |(offset, _)| offset);
+        self.input.insert(offset, character);
+        self.move_cursor_right();
+    }
+
     /// Moves the cursors by the right.
     fn move_cursor_right(&mut self) {
         let new_cursor_index = self.cursor_index.saturating_add(1);
@@ -209,6 +221,15 @@ impl<T: Clone> List<T> {
     }
 }
 
+impl<T: Clone + PartialEq> List<T> {
+    /// Selects the given item if it is in the List. The selection is left untouched otherwise.
+    pub fn select(&mut self, item: &T) {
+        if let Some(index) = self.items.iter().position(|i| i == item) {
+            self.selected_index = index;
+        }
+    }
+}
+
 impl<T: ToString + Clone> Widget for List<T> {
     fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer) {
         Paragraph::new(self.items[self.selected_index].to_string())
@@ -244,3 +265,535 @@ impl<T: Default + Clone> Default for List<T> {
         }
     }
 }
+
+/// Which side of a pair is being edited in the KeyValueEditor.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum PairField {
+    #[default]
+    Key,
+    Value,
+}
+
+/// KeyValueEditor is a list of key-value pairs, such as headers, that can be added, edited and
+/// removed. Use j/k to move, `a` to add, `e` to edit and `d` to delete a pair. While editing,
+/// <tab> switches between the key and the value, <enter> keeps the changes and <esc> drops them.
+/// Toggleable editors also turn pairs on and off with <space>.
+#[derive(Debug, Default, Clone, Setters)]
+pub struct KeyValueEditor {
+    /// The pairs along with whether they are enabled.
+    #[setters(skip)]
+    pairs: Vec<(String, String, bool)>,
+    #[setters(skip)]
+    selected_index: usize,
+    /// The pair being edited along with which side of it, None when only moving around.
+    #[setters(skip)]
+    editing: Option<(Input, Input, PairField)>,
+    /// Whether the pair being edited was just added, so dropping the changes removes it.
+    #[setters(skip)]
+    editing_new: bool,
+    #[setters(skip)]
+    is_focused: bool,
+    #[setters(into)]
+    title: String,
+    /// Whether pairs can be disabled without removing them, such as query parameters.
+    toggleable: bool,
+}
+
+impl KeyValueEditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the pairs, sorted by key so they are always listed the same way.
+    pub fn set_pairs(&mut self, pairs: impl IntoIterator<Item = (String, String)>) {
+        let mut pairs: Vec<_> = pairs.into_iter().collect();
+        pairs.sort();
+        self.set_toggled_pairs(pairs.into_iter().map(|(key, value)| (key, value, true)));
+    }
+
+    /// Replaces the pairs along with whether they are enabled, keeping their order.
+    pub fn set_toggled_pairs(&mut self, pairs: impl IntoIterator<Item = (String, String, bool)>) {
+        self.pairs = pairs.into_iter().collect();
+        self.selected_index = 0;
+        self.editing = None;
+    }
+
+    /// Gets the enabled pairs with a key. Pairs being edited are not included until they are kept.
+    pub fn get_pairs(&self) -> Vec<(String, String)> {
+        self.get_toggled_pairs()
+            .into_iter()
+            .filter(|(_, _, enabled)| *enabled)
+            .map(|(key, value, _)| (key, value))
+            .collect()
+    }
+
+    /// Gets the pairs with a key along with whether they are enabled, in order.
+    pub fn get_toggled_pairs(&self) -> Vec<(String, String, bool)> {
+        self.pairs
+            .iter()
+            .filter(|(key, _, _)| !key.trim().is_empty())
+            .map(|(key, value, enabled)| (key.trim().to_string(), value.clone(), *enabled))
+            .collect()
+    }
+
+    pub fn is_toggleable(&self) -> bool {
+        self.toggleable
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.editing.is_some()
+    }
+
+    pub fn set_focus(&mut self, state: bool) {
+        self.is_focused = state;
+    }
+
+    /// Removes every pair and stops editing.
+    pub fn reset(&mut self) {
+        self.pairs.clear();
+        self.selected_index = 0;
+        self.editing = None;
+        self.is_focused = false;
+    }
+
+    /// Handles a key, returns false when the key is not used by the editor so the caller can use
+    /// it, e.g. <tab> to move to the next field of a form.
+    pub fn handle_key(&mut self, key: &ratatui::crossterm::event::KeyEvent) -> bool {
+        use ratatui::crossterm::event::KeyCode;
+        if let Some((key_input, value_input, field)) = &mut self.editing {
+            let input = match field {
+                PairField::Key => &mut *key_input,
+                PairField::Value => &mut *value_input,
+            };
+            match key.code {
+                KeyCode::Char(ch) => input.enter_character(ch),
+                KeyCode::Backspace => input.delete_character(),
+                KeyCode::Tab | KeyCode::BackTab => {
+                    input.enable_normal_mode();
+                    *field = match field {
+                        PairField::Key => PairField::Value,
+                        PairField::Value => PairField::Key,
+                    };
+                    match field {
+                        PairField::Key => key_input.enable_insert_mode(),
+                        PairField::Value => value_input.enable_insert_mode(),
+                    }
+                }
+                KeyCode::Enter => {
+                    let (key, value) = (key_input.get_string(), value_input.get_string());
+                    self.editing = None;
+                    if key.trim().is_empty() {
+                        self.pairs.remove(self.selected_index);
+                    } else {
+                        let enabled = self.pairs[self.selected_index].2;
+                        self.pairs[self.selected_index] = (key, value, enabled);
+                    }
+                    self.clamp_selection();
+                }
+                KeyCode::Esc => {
+                    self.editing = None;
+                    if self.editing_new {
+                        self.pairs.remove(self.selected_index);
+                    }
+                    self.clamp_selection();
+                }
+                _ => {}
+            }
+            return true;
+        }
+        match key.code {
+            KeyCode::Char('j') if !self.pairs.is_empty() => {
+                self.selected_index = (self.selected_index + 1) % self.pairs.len();
+            }
+            KeyCode::Char('k') if !self.pairs.is_empty() => {
+                self.selected_index =
+                    (self.selected_index + self.pairs.len() - 1) % self.pairs.len();
+            }
+            KeyCode::Char('a') => {
+                self.pairs.push((String::new(), String::new(), true));
+                self.selected_index = self.pairs.len() - 1;
+                self.start_editing(true);
+            }
+            KeyCode::Char('e') if !self.pairs.is_empty() => self.start_editing(false),
+            KeyCode::Char('d') if !self.pairs.is_empty() => {
+                self.pairs.remove(self.selected_index);
+                self.clamp_selection();
+            }
+            KeyCode::Char(' ') if self.toggleable && !self.pairs.is_empty() => {
+                let enabled = &mut self.pairs[self.selected_index].2;
+                *enabled = !*enabled;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Gets where the cursor goes while a pair is edited in the area the editor is rendered in.
+    pub fn cursor_position(&self, area: ratatui::prelude::Rect) -> Option<(u16, u16)> {
+        let (key_input, value_input, field) = self.editing.as_ref()?;
+        let row = u16::try_from(self.selected_index - self.first_visible(area.height)).ok()?;
+        let left = area.x + 1 + self.toggle_width();
+        let x = match field {
+            PairField::Key => left + key_input.get_cursor_index_u16(),
+            PairField::Value => {
+                let key_width = u16::try_from(key_input.get_string().chars().count()).ok()?;
+                left + 2 + key_width + value_input.get_cursor_index_u16()
+            }
+        };
+        Some((x, area.y + 1 + row))
+    }
+
+    /// Gets the width of the checkbox shown before every pair of toggleable editors.
+    fn toggle_width(&self) -> u16 {
+        if self.toggleable {
+            4
+        } else {
+            0
+        }
+    }
+
+    fn start_editing(&mut self, is_new: bool) {
+        let (key, value, _) = &self.pairs[self.selected_index];
+        let mut key_input = Input::new();
+        key_input.enable_insert_mode();
+        key_input.insert_string(key);
+        let mut value_input = Input::new();
+        value_input.enable_insert_mode();
+        value_input.insert_string(value);
+        let field = if is_new {
+            value_input.enable_normal_mode();
+            PairField::Key
+        } else {
+            key_input.enable_normal_mode();
+            PairField::Value
+        };
+        self.editing = Some((key_input, value_input, field));
+        self.editing_new = is_new;
+    }
+
+    fn clamp_selection(&mut self) {
+        self.selected_index = self.selected_index.min(self.pairs.len().saturating_sub(1));
+    }
+
+    /// Gets the first pair shown so the selected one is always in view.
+    fn first_visible(&self, height: u16) -> usize {
+        let rows = usize::from(height.saturating_sub(2)).max(1);
+        (self.selected_index + 1).saturating_sub(rows)
+    }
+}
+
+impl Widget for KeyValueEditor {
+    fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer) {
+        use ratatui::{
+            style::{Color, Modifier, Style},
+            text::{Line, Span},
+        };
+        let key_style = Style::new().fg(Color::LightBlue);
+        let focus_style = Style::new().fg(Color::Yellow);
+        let mut lines: Vec<Line> = self
+            .pairs
+            .iter()
+            .enumerate()
+            .skip(self.first_visible(area.height))
+            .map(|(index, (key, value, enabled))| {
+                let selected = self.is_focused && index == self.selected_index;
+                let mut line = match &self.editing {
+                    Some((key_input, value_input, field)) if index == self.selected_index => {
+                        let (key_style, value_style) = match field {
+                            PairField::Key => (focus_style, Style::default()),
+                            PairField::Value => (key_style, focus_style),
+                        };
+                        Line::from(vec![
+                            Span::styled(key_input.get_string(), key_style),
+                            Span::raw(": "),
+                            Span::styled(value_input.get_string(), value_style),
+                        ])
+                    }
+                    _ => {
+                        let line = Line::from(vec![
+                            Span::styled(key.clone(), key_style),
+                            Span::raw(": "),
+                            Span::raw(value.clone()),
+                        ]);
+                        let line = if *enabled {
+                            line
+                        } else {
+                            line.patch_style(Style::new().add_modifier(Modifier::CROSSED_OUT))
+                        };
+                        if selected {
+                            line.style(Style::new().bg(Color::DarkGray))
+                        } else {
+                            line
+                        }
+                    }
+                };
+                if self.toggleable {
+                    let checkbox = if *enabled { "[x] " } else { "[ ] " };
+                    line.spans.insert(0, Span::raw(checkbox));
+                }
+                line
+            })
+            .collect();
+        if self.pairs.is_empty() && self.is_focused {
+            lines.push(Line::styled(
+                "Press a to add.",
+                Style::new().fg(Color::DarkGray),
+            ));
+        }
+        Paragraph::new(lines)
+            .block(
+                ratatui::widgets::Block::bordered()
+                    .title(self.title)
+                    .border_style(if self.is_focused {
+                        focus_style
+                    } else {
+                        Style::default()
+                    }),
+            )
+            .render(area, buf);
+    }
+}
+
+/// The spaces inserted by <tab> in a TextArea.
+const INDENT: &str = "    ";
+
+/// TextArea is a multi-line text editor, e.g. for request bodies. The arrow keys move the cursor,
+/// <home> and <end> go to the start and end of the line and the view scrolls to keep the cursor in
+/// sight.
+#[derive(Debug, Default, Clone, Setters)]
+pub struct TextArea {
+    #[setters(skip)]
+    lines: Vec<String>,
+    /// The line of the cursor.
+    #[setters(skip)]
+    row: usize,
+    /// The column of the cursor, in characters.
+    #[setters(skip)]
+    column: usize,
+    /// The first line shown, moved while rendering to keep the cursor in view.
+    #[setters(skip)]
+    scroll: std::cell::Cell<usize>,
+    #[setters(into)]
+    title: String,
+}
+
+impl TextArea {
+    pub fn new(text: &str) -> Self {
+        Self {
+            lines: text.split('\n').map(String::from).collect(),
+            ..Self::default()
+        }
+    }
+
+    pub fn get_text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Gets the line of the cursor.
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    /// Edits the text or moves the cursor. Returns whether the key was used.
+    pub fn handle_key(&mut self, key: &ratatui::crossterm::event::KeyEvent) -> bool {
+        use ratatui::crossterm::event::{KeyCode, KeyModifiers};
+        let line_length = self.lines[self.row].chars().count();
+        match key.code {
+            KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.insert(&ch.to_string())
+            }
+            KeyCode::Tab => self.insert(INDENT),
+            KeyCode::Enter => {
+                let index = self.byte_index();
+                let rest = self.lines[self.row].split_off(index);
+                self.row += 1;
+                self.column = 0;
+                self.lines.insert(self.row, rest);
+            }
+            KeyCode::Backspace if self.column > 0 => {
+                self.column -= 1;
+                let index = self.byte_index();
+                self.lines[self.row].remove(index);
+            }
+            KeyCode::Backspace if self.row > 0 => {
+                let line = self.lines.remove(self.row);
+                self.row -= 1;
+                self.column = self.lines[self.row].chars().count();
+                self.lines[self.row].push_str(&line);
+            }
+            KeyCode::Delete if self.column < line_length => {
+                let index = self.byte_index();
+                self.lines[self.row].remove(index);
+            }
+            KeyCode::Delete if self.row + 1 < self.lines.len() => {
+                let line = self.lines.remove(self.row + 1);
+                self.lines[self.row].push_str(&line);
+            }
+            KeyCode::Left if self.column > 0 => self.column -= 1,
+            KeyCode::Right if self.column < line_length => self.column += 1,
+            KeyCode::Up if self.row > 0 => {
+                self.row -= 1;
+                self.clamp_column();
+            }
+            KeyCode::Down if self.row + 1 < self.lines.len() => {
+                self.row += 1;
+                self.clamp_column();
+            }
+            KeyCode::Home => self.column = 0,
+            KeyCode::End => self.column = line_length,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Gets where the cursor is on screen when the text area is rendered in the area.
+    pub fn cursor_position(&self, area: ratatui::prelude::Rect) -> (u16, u16) {
+        let scroll = self.scroll_to_cursor(area.height);
+        let x = area.x + 1 + u16::try_from(self.column).unwrap_or(u16::MAX);
+        let y = area.y + 1 + u16::try_from(self.row - scroll).unwrap_or(u16::MAX);
+        (
+            x.min(area.right().saturating_sub(2)),
+            y.min(area.bottom().saturating_sub(2)),
+        )
+    }
+
+    /// Keeps the cursor within the lines shown in an area of the height, borders included, and
+    /// returns the first line to show.
+    pub fn scroll_to_cursor(&self, height: u16) -> usize {
+        let visible = usize::from(height.saturating_sub(2)).max(1);
+        let scroll = self.scroll.get();
+        if self.row < scroll {
+            self.scroll.set(self.row);
+        } else if self.row >= scroll + visible {
+            self.scroll.set(self.row + 1 - visible);
+        }
+        self.scroll.get()
+    }
+
+    fn insert(&mut self, text: &str) {
+        let index = self.byte_index();
+        self.lines[self.row].insert_str(index, text);
+        self.column += text.chars().count();
+    }
+
+    fn byte_index(&self) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices()
+            .nth(self.column)
+            .map_or(line.len(), |(index, _)| index)
+    }
+
+    fn clamp_column(&mut self) {
+        self.column = self.column.min(self.lines[self.row].chars().count());
+    }
+}
+
+impl Widget for &TextArea {
+    fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer) {
+        let lines: Vec<ratatui::text::Line> = self
+            .lines
+            .iter()
+            .skip(self.scroll_to_cursor(area.height))
+            .map(|line| ratatui::text::Line::raw(line.as_str()))
+            .collect();
+        Paragraph::new(lines)
+            .block(ratatui::widgets::Block::bordered().title(self.title.as_str()))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::*;
+
+    fn press(editor: &mut KeyValueEditor, keys: &str) {
+        for ch in keys.chars() {
+            let code = match ch {
+                '\t' => KeyCode::Tab,
+                '\n' => KeyCode::Enter,
+                '\x1b' => KeyCode::Esc,
+                '\x08' => KeyCode::Backspace,
+                ch => KeyCode::Char(ch),
+            };
+            editor.handle_key(&KeyEvent::new(code, KeyModifiers::NONE));
+        }
+    }
+
+    #[test]
+    fn should_prefill_inputs_in_any_mode() {
+        let mut input = Input::new();
+        input.insert_string("/users");
+        assert_eq!(input.get_string(), "/users");
+        // typed characters still need insert mode
+        input.enter_character('s');
+        assert_eq!(input.get_string(), "/users");
+        input.enable_insert_mode();
+        input.enter_character('/');
+        assert_eq!(input.get_string(), "/users/");
+    }
+
+    #[test]
+    fn should_insert_after_characters_of_several_bytes() {
+        let mut input = Input::new();
+        input.insert_string("créer");
+        input.enable_insert_mode();
+        input.enter_character('é');
+        input.insert_string(" ünë");
+        assert_eq!(input.get_string(), "créeré ünë");
+        input.delete_character();
+        assert_eq!(input.get_string(), "créeré ün");
+    }
+
+    #[test]
+    fn should_add_edit_and_remove_pairs() {
+        let mut editor = KeyValueEditor::new();
+        editor.set_pairs([(String::from("Accept"), String::from("*/*"))]);
+        press(&mut editor, "aX-Id\t42\n");
+        press(&mut editor, "aX-Gone\x1b");
+        assert_eq!(
+            editor.get_pairs(),
+            [
+                (String::from("Accept"), String::from("*/*")),
+                (String::from("X-Id"), String::from("42")),
+            ]
+        );
+
+        press(&mut editor, "ke\x08\x08\x08json\n");
+        assert!(!editor.is_editing());
+        press(&mut editor, "jd");
+        assert_eq!(
+            editor.get_pairs(),
+            [(String::from("Accept"), String::from("json"))]
+        );
+        assert!(!editor.handle_key(&KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn should_edit_text_across_lines() {
+        let mut text_area = TextArea::new("{\n}");
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        text_area.handle_key(&key(KeyCode::End));
+        text_area.handle_key(&key(KeyCode::Enter));
+        text_area.handle_key(&key(KeyCode::Tab));
+        for ch in "\"a\": 1".chars() {
+            text_area.handle_key(&key(KeyCode::Char(ch)));
+        }
+        assert_eq!(text_area.get_text(), "{\n    \"a\": 1\n}");
+
+        text_area.handle_key(&key(KeyCode::Down));
+        text_area.handle_key(&key(KeyCode::Home));
+        text_area.handle_key(&key(KeyCode::Backspace));
+        assert_eq!(text_area.get_text(), "{\n    \"a\": 1}");
+        assert_eq!(
+            text_area.cursor_position(ratatui::prelude::Rect::new(0, 0, 20, 3)),
+            (11, 1)
+        );
+        assert!(!text_area.handle_key(&key(KeyCode::Esc)));
+    }
+}
@@ -0,0 +1,323 @@
+//! The cookies set by responses, sent back with the requests after them like a browser would, so
+//! logging in once keeps the session for the rest of the collection. Every collection has its own
+//! jar, which only lives while Hermes runs. Pinned cookies stay as they are whatever the server
+//! sends, to hold on to a session while testing what the server does with it.
+
+use chrono::DateTime;
+use reqwest::Url;
+
+use crate::client::Outgoing;
+
+/// A cookie kept in the jar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredCookie {
+    name: String,
+    value: String,
+    /// The host the cookie is sent to, lowercase and without a leading dot.
+    domain: String,
+    /// Whether only the host itself gets the cookie, i.e. it was set without a Domain.
+    host_only: bool,
+    path: String,
+    secure: bool,
+    http_only: bool,
+    /// When the cookie expires, in seconds since epoch. None for cookies of the session.
+    expires: Option<i64>,
+    pinned: bool,
+}
+
+impl StoredCookie {
+    /// Reads a Set-Cookie header the host sent for a request to the path. None when it is not a
+    /// cookie or the host may not set it.
+    fn parse(header: &str, host: &str, request_path: &str, now: i64) -> Option<Self> {
+        let mut parts = header.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        if name.trim().is_empty() {
+            return None;
+        }
+        let mut cookie = StoredCookie {
+            name: name.trim().to_string(),
+            value: value.trim().to_string(),
+            domain: host.to_string(),
+            host_only: true,
+            path: default_path(request_path),
+            secure: false,
+            http_only: false,
+            expires: None,
+            pinned: false,
+        };
+        let mut max_age = None;
+        for attribute in parts {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "domain" if !value.is_empty() => {
+                    let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                    if !domain_matches(host, &domain) {
+                        return None;
+                    }
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "max-age" => max_age = value.parse::<i64>().ok(),
+                "expires" => {
+                    cookie.expires = DateTime::parse_from_rfc2822(value)
+                        .ok()
+                        .map(|expires| expires.timestamp());
+                }
+                _ => {}
+            }
+        }
+        // Max-Age wins over Expires
+        if let Some(max_age) = max_age {
+            cookie.expires = Some(now.saturating_add(max_age));
+        }
+        Some(cookie)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Describes where the cookie is sent and how, e.g. `example.com/ secure, http-only`.
+    pub fn scope(&self) -> String {
+        let mut flags = Vec::new();
+        if self.secure {
+            flags.push("secure");
+        }
+        if self.http_only {
+            flags.push("http-only");
+        }
+        if self.expires.is_none() {
+            flags.push("session");
+        }
+        if self.pinned {
+            flags.push("pinned");
+        }
+        let domain = if self.host_only {
+            self.domain.clone()
+        } else {
+            format!(".{}", self.domain)
+        };
+        format!("{}{} {}", domain, self.path, flags.join(", "))
+    }
+
+    fn is_expired(&self, now: i64) -> bool {
+        !self.pinned && self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    fn is_same(&self, other: &StoredCookie) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+
+    /// Whether the cookie goes with a request to the host and path.
+    fn matches(&self, host: &str, path: &str, https: bool) -> bool {
+        let domain = if self.host_only {
+            host == self.domain
+        } else {
+            domain_matches(host, &self.domain)
+        };
+        // browsers send secure cookies to localhost over plain http too
+        let secure = !self.secure || https || host == "localhost" || host == "127.0.0.1";
+        domain && secure && path_matches(path, &self.path)
+    }
+}
+
+/// Gets the path a cookie is sent to when it does not say, the folder of the request path.
+fn default_path(path: &str) -> String {
+    match path.rfind('/') {
+        Some(0) | None => String::from("/"),
+        Some(end) => path[..end].to_string(),
+    }
+}
+
+/// Whether the host is the domain or one of its subdomains.
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// Whether a request to the path gets the cookies of the cookie path, e.g. `/api/users` gets the
+/// ones of `/api` but not the ones of `/ap`.
+fn path_matches(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || path[cookie_path.len()..].starts_with('/'))
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct CookieJar {
+    cookies: Vec<StoredCookie>,
+}
+
+impl CookieJar {
+    /// Keeps the cookies the response to a request to the url sets, replacing the ones with the
+    /// same name, domain and path. Cookies set to expire are removed, unless they are pinned.
+    pub fn store(&mut self, url: &str, headers: &[(String, String)], now: i64) {
+        let Some((host, path, _)) = split_url(url) else {
+            return;
+        };
+        let set = headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("set-cookie"))
+            .filter_map(|(_, value)| StoredCookie::parse(value, &host, &path, now));
+        for cookie in set {
+            match self.cookies.iter().position(|kept| kept.is_same(&cookie)) {
+                Some(position) if self.cookies[position].pinned => {}
+                Some(position) => self.cookies[position] = cookie,
+                None => self.cookies.push(cookie),
+            }
+        }
+        self.cookies.retain(|cookie| !cookie.is_expired(now));
+    }
+
+    /// Gets the value of the Cookie header of a request to the url, None when no cookie goes
+    /// with it. Cookies with longer paths go first.
+    pub fn header_for(&self, url: &str, now: i64) -> Option<String> {
+        let (host, path, https) = split_url(url)?;
+        let mut cookies: Vec<_> = self
+            .cookies
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now) && cookie.matches(&host, &path, https))
+            .collect();
+        if cookies.is_empty() {
+            return None;
+        }
+        cookies.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        let pairs: Vec<_> = cookies
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect();
+        Some(pairs.join("; "))
+    }
+
+    /// Adds the cookies of the jar to the request, unless it sets a Cookie header itself. Raw
+    /// requests are sent as they are written.
+    pub fn add_to(&self, outgoing: &mut Outgoing, now: i64) {
+        let sets_cookies = outgoing
+            .headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("cookie"));
+        if sets_cookies || outgoing.raw.is_some() {
+            return;
+        }
+        if let Some(header) = self.header_for(&outgoing.url, now) {
+            outgoing.headers.push((String::from("Cookie"), header));
+        }
+    }
+
+    pub fn cookies(&self) -> &[StoredCookie] {
+        &self.cookies
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<StoredCookie> {
+        (index < self.cookies.len()).then(|| self.cookies.remove(index))
+    }
+
+    /// Pins the cookie at the index, or unpins it. Returns whether it is pinned now.
+    pub fn toggle_pin(&mut self, index: usize) -> Option<bool> {
+        let cookie = self.cookies.get_mut(index)?;
+        cookie.pinned = !cookie.pinned;
+        Some(cookie.pinned)
+    }
+}
+
+/// Gets the host, path and whether it is https of a url.
+fn split_url(url: &str) -> Option<(String, String, bool)> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?.to_ascii_lowercase();
+    Some((host, url.path().to_string(), url.scheme() == "https"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_cookie(value: &str) -> Vec<(String, String)> {
+        vec![(String::from("Set-Cookie"), String::from(value))]
+    }
+
+    #[test]
+    fn should_send_back_the_cookies_responses_set() {
+        let mut jar = CookieJar::default();
+        jar.store(
+            "https://api.example.com/auth/login",
+            &[
+                (
+                    String::from("set-cookie"),
+                    String::from("session=abc; Path=/; Secure; HttpOnly"),
+                ),
+                (String::from("Set-Cookie"), String::from("step=2")),
+                (
+                    String::from("Set-Cookie"),
+                    String::from("shared=1; Domain=.example.com; Path=/; Max-Age=60"),
+                ),
+                (
+                    String::from("Set-Cookie"),
+                    String::from("stolen=1; Domain=other.com"),
+                ),
+            ],
+            0,
+        );
+        assert_eq!(jar.cookies().len(), 3);
+        assert_eq!(
+            jar.header_for("https://api.example.com/auth/refresh", 0),
+            Some(String::from("step=2; session=abc; shared=1"))
+        );
+        assert_eq!(
+            jar.header_for("http://api.example.com/users", 0),
+            Some(String::from("shared=1"))
+        );
+        assert_eq!(
+            jar.header_for("https://www.example.com/", 0),
+            Some(String::from("shared=1"))
+        );
+        assert_eq!(jar.header_for("https://www.example.com/", 60), None);
+        assert_eq!(
+            jar.cookies()[0].scope(),
+            "api.example.com/ secure, http-only, session"
+        );
+
+        // pinned cookies are kept whatever the server sends
+        assert_eq!(jar.toggle_pin(0), Some(true));
+        jar.store(
+            "https://api.example.com/logout",
+            &set_cookie("session=; Path=/; Max-Age=0"),
+            1,
+        );
+        assert_eq!(jar.cookies()[0].value(), "abc");
+        jar.toggle_pin(0);
+        jar.store(
+            "https://api.example.com/logout",
+            &set_cookie("session=; Path=/; Expires=Thu, 01 Jan 1970 00:00:00 GMT"),
+            1,
+        );
+        assert!(jar
+            .cookies()
+            .iter()
+            .all(|cookie| cookie.name() != "session"));
+
+        let mut outgoing = Outgoing {
+            method: crate::api::HttpMethod::Get,
+            url: String::from("https://api.example.com/auth/me"),
+            headers: Vec::new(),
+            body: None,
+            raw: None,
+            form: Vec::new(),
+        };
+        jar.add_to(&mut outgoing, 1);
+        assert_eq!(
+            outgoing.headers,
+            [(String::from("Cookie"), String::from("step=2; shared=1"))]
+        );
+        jar.add_to(&mut outgoing, 1);
+        assert_eq!(outgoing.headers.len(), 1);
+        assert!(jar.remove(0).is_some());
+        assert_eq!(jar.remove(5), None);
+    }
+}
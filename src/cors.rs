@@ -0,0 +1,374 @@
+//! CORS preflights, the OPTIONS request browsers send before a cross-origin request that is not
+//! simple, and whether the answer lets the request through. The preflight asks for the method of
+//! the request and the headers browsers would not send without asking, and the checks explain
+//! which `Access-Control-*` header of the answer blocks the request, the way a browser reads them.
+
+use crate::{api::HttpMethod, assertions::ResponseFacts, client::Outgoing};
+
+/// The Origin preflights are sent from when the settings don't say.
+pub const DEFAULT_ORIGIN: &str = "http://localhost:3000";
+
+/// The methods browsers send without asking first.
+const SIMPLE_METHODS: [&str; 3] = ["GET", "HEAD", "POST"];
+
+/// The headers browsers send without asking first. Content-Type too, but only with the types
+/// forms send.
+const SAFELISTED_HEADERS: [&str; 3] = ["accept", "accept-language", "content-language"];
+
+const SAFELISTED_CONTENT_TYPES: [&str; 3] = [
+    "application/x-www-form-urlencoded",
+    "multipart/form-data",
+    "text/plain",
+];
+
+/// The headers browsers set themselves, scripts can't set them so preflights never ask for them.
+const BROWSER_HEADERS: [&str; 9] = [
+    "accept-charset",
+    "accept-encoding",
+    "connection",
+    "content-length",
+    "cookie",
+    "host",
+    "origin",
+    "referer",
+    "user-agent",
+];
+
+/// What a preflight asks the server for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preflight {
+    origin: String,
+    /// The method of the request, sent in Access-Control-Request-Method.
+    method: String,
+    /// The headers of the request that need to be allowed, lowercase and sorted, sent in
+    /// Access-Control-Request-Headers.
+    headers: Vec<String>,
+    /// Whether the request is sent with credentials, which a wildcard does not allow.
+    credentials: bool,
+}
+
+impl Preflight {
+    /// Makes a preflight from the origin asking for the method and the comma separated headers.
+    pub fn new(origin: &str, method: &str, headers: &str, credentials: bool) -> Self {
+        let mut headers: Vec<String> = headers
+            .split(',')
+            .map(|header| header.trim().to_ascii_lowercase())
+            .filter(|header| !header.is_empty())
+            .collect();
+        headers.sort();
+        headers.dedup();
+        Self {
+            origin: origin.trim().to_string(),
+            method: method.trim().to_string(),
+            headers,
+            credentials,
+        }
+    }
+
+    /// Gets the preflight a browser would send from the origin before the request. Requests with
+    /// cookies are sent with credentials.
+    pub fn for_request(outgoing: &Outgoing, origin: &str) -> Self {
+        let headers: Vec<&str> = outgoing
+            .headers
+            .iter()
+            .filter(|(name, value)| !is_safelisted(name, value))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        let credentials = outgoing
+            .headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("cookie"));
+        Self::new(
+            origin,
+            outgoing.method.to_str(),
+            &headers.join(", "),
+            credentials,
+        )
+    }
+
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Gets the headers the preflight asks for, comma separated.
+    pub fn headers(&self) -> String {
+        self.headers.join(", ")
+    }
+
+    pub fn has_credentials(&self) -> bool {
+        self.credentials
+    }
+
+    /// Gets the OPTIONS request to send to the url of the request.
+    pub fn outgoing(&self, url: &str) -> Outgoing {
+        let mut headers = vec![
+            (String::from("Origin"), self.origin.clone()),
+            (
+                String::from("Access-Control-Request-Method"),
+                self.method.clone(),
+            ),
+        ];
+        if !self.headers.is_empty() {
+            headers.push((
+                String::from("Access-Control-Request-Headers"),
+                self.headers(),
+            ));
+        }
+        Outgoing {
+            method: HttpMethod::Option,
+            url: url.to_string(),
+            headers,
+            body: None,
+            raw: None,
+            form: Vec::new(),
+        }
+    }
+
+    /// Checks the answer to the preflight lets the request through, one check per thing a
+    /// browser looks at.
+    pub fn check(&self, facts: &ResponseFacts) -> Vec<Check> {
+        // a wildcard allows anything, except for requests with credentials
+        let wildcard = |values: &[String]| !self.credentials && values.iter().any(|v| v == "*");
+        let mut checks = vec![Check {
+            name: "status",
+            passed: (200..300).contains(&facts.status),
+            detail: format!("the preflight got a {}", facts.status),
+        }];
+
+        let origin = facts
+            .header("access-control-allow-origin")
+            .map(str::trim)
+            .unwrap_or_default();
+        let (passed, detail) = match origin {
+            "" => (false, String::from("no Access-Control-Allow-Origin")),
+            "*" if self.credentials => (
+                false,
+                String::from("`*` does not allow requests with credentials"),
+            ),
+            "*" => (true, String::from("every origin is allowed")),
+            origin if origin == self.origin => (true, format!("{} is allowed", origin)),
+            origin => (false, format!("only {} is allowed", origin)),
+        };
+        checks.push(Check {
+            name: "origin",
+            passed,
+            detail,
+        });
+
+        if self.credentials {
+            let passed = facts
+                .header("access-control-allow-credentials")
+                .is_some_and(|allowed| allowed.trim() == "true");
+            checks.push(Check {
+                name: "credentials",
+                passed,
+                detail: String::from(if passed {
+                    "cookies are allowed"
+                } else {
+                    "Access-Control-Allow-Credentials is not `true`"
+                }),
+            });
+        }
+
+        let methods = list(facts.header("access-control-allow-methods"));
+        let (passed, detail) = if SIMPLE_METHODS.contains(&self.method.to_uppercase().as_str()) {
+            (true, format!("{} needs no asking", self.method))
+        } else if wildcard(&methods)
+            || methods
+                .iter()
+                .any(|method| method.eq_ignore_ascii_case(&self.method))
+        {
+            (true, format!("{} is allowed", self.method))
+        } else {
+            (false, format!("{} is not allowed", self.method))
+        };
+        checks.push(Check {
+            name: "method",
+            passed,
+            detail,
+        });
+
+        let allowed = list(facts.header("access-control-allow-headers"));
+        // the wildcard never covers Authorization
+        let missing: Vec<&str> = self
+            .headers
+            .iter()
+            .filter(|header| {
+                !(wildcard(&allowed) && *header != "authorization"
+                    || allowed
+                        .iter()
+                        .any(|allowed| allowed.eq_ignore_ascii_case(header)))
+            })
+            .map(String::as_str)
+            .collect();
+        let detail = match (self.headers.is_empty(), missing.as_slice()) {
+            (true, _) => String::from("no header needs asking"),
+            (false, []) => format!("{} allowed", self.headers()),
+            (false, [header]) => format!("{} is not allowed", header),
+            (false, missing) => format!("{} are not allowed", missing.join(", ")),
+        };
+        checks.push(Check {
+            name: "headers",
+            passed: missing.is_empty(),
+            detail,
+        });
+        checks
+    }
+}
+
+/// Something a browser checks in the answer to a preflight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    name: &'static str,
+    passed: bool,
+    /// Why it passed or failed, e.g. `x-trace-id is not allowed`.
+    detail: String,
+}
+
+impl Check {
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    pub fn passed(&self) -> bool {
+        self.passed
+    }
+
+    pub fn detail(&self) -> &str {
+        &self.detail
+    }
+}
+
+/// Gets the `Access-Control-*` headers of the answer, the ones browsers read.
+pub fn response_headers(facts: &ResponseFacts) -> impl Iterator<Item = &(String, String)> {
+    facts
+        .headers
+        .iter()
+        .filter(|(name, _)| name.len() > 15 && name[..15].eq_ignore_ascii_case("access-control-"))
+}
+
+fn is_safelisted(name: &str, value: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    if name == "content-type" {
+        let media_type = value.split(';').next().unwrap_or_default().trim();
+        return SAFELISTED_CONTENT_TYPES
+            .iter()
+            .any(|safelisted| media_type.eq_ignore_ascii_case(safelisted));
+    }
+    SAFELISTED_HEADERS.contains(&name.as_str()) || BROWSER_HEADERS.contains(&name.as_str())
+}
+
+/// Reads a comma separated header, e.g. Access-Control-Allow-Methods.
+fn list(header: Option<&str>) -> Vec<String> {
+    header
+        .unwrap_or_default()
+        .split(',')
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(headers: &[(&str, &str)]) -> ResponseFacts {
+        ResponseFacts {
+            status: 204,
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn failed(checks: &[Check]) -> Vec<(&str, &str)> {
+        checks
+            .iter()
+            .filter(|check| !check.passed())
+            .map(|check| (check.name(), check.detail()))
+            .collect()
+    }
+
+    #[test]
+    fn should_check_the_preflight_against_the_request() {
+        let request = Outgoing {
+            method: HttpMethod::Put,
+            url: String::from("https://api.example.com/users/7"),
+            headers: vec![
+                (
+                    String::from("Content-Type"),
+                    String::from("application/json"),
+                ),
+                (String::from("Accept"), String::from("application/json")),
+                (String::from("Authorization"), String::from("Bearer abc")),
+                (String::from("X-Trace-Id"), String::from("1")),
+                (String::from("User-Agent"), String::from("hermes")),
+            ],
+            body: None,
+            raw: None,
+            form: Vec::new(),
+        };
+        let preflight = Preflight::for_request(&request, "https://app.example.com");
+        assert_eq!(
+            preflight.headers(),
+            "authorization, content-type, x-trace-id"
+        );
+        assert!(!preflight.has_credentials());
+        let outgoing = preflight.outgoing(&request.url);
+        assert_eq!(outgoing.method, HttpMethod::Option);
+        assert_eq!(
+            outgoing.headers[2],
+            (
+                String::from("Access-Control-Request-Headers"),
+                String::from("authorization, content-type, x-trace-id")
+            )
+        );
+
+        let checks = preflight.check(&answer(&[
+            ("Access-Control-Allow-Origin", "*"),
+            ("Access-Control-Allow-Methods", "GET, PUT"),
+            ("Access-Control-Allow-Headers", "*"),
+        ]));
+        assert_eq!(
+            failed(&checks),
+            [("headers", "authorization is not allowed")]
+        );
+
+        let checks = preflight.check(&answer(&[
+            ("Access-Control-Allow-Origin", "https://admin.example.com"),
+            ("Access-Control-Allow-Headers", "Content-Type"),
+        ]));
+        assert_eq!(
+            failed(&checks),
+            [
+                ("origin", "only https://admin.example.com is allowed"),
+                ("method", "PUT is not allowed"),
+                ("headers", "authorization, x-trace-id are not allowed"),
+            ]
+        );
+
+        let with_cookies = Preflight::new("https://app.example.com", "GET", "", true);
+        let answered = answer(&[
+            ("Access-Control-Allow-Origin", "*"),
+            ("access-control-max-age", "600"),
+            ("Vary", "Origin"),
+        ]);
+        assert_eq!(
+            failed(&with_cookies.check(&answered)),
+            [
+                ("origin", "`*` does not allow requests with credentials"),
+                (
+                    "credentials",
+                    "Access-Control-Allow-Credentials is not `true`"
+                ),
+            ]
+        );
+        assert_eq!(response_headers(&answered).count(), 2);
+    }
+}
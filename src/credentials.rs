@@ -0,0 +1,149 @@
+//! Basic auth credentials saved by host. When a request gets a 401 with a `Basic` challenge, the
+//! credentials of its host are offered to send it again. They are kept in `credentials.json` in
+//! the config directory, readable only by the user, and their passwords are masked like secrets.
+
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::{settings, storage};
+
+/// The file in the config directory where credentials are saved.
+const CREDENTIALS_FILE: &str = "credentials.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Credential {
+    pub username: String,
+    pub password: String,
+}
+
+impl Credential {
+    /// Gets the value of the Authorization header sending the credential.
+    pub fn authorization(&self) -> String {
+        let encoded = STANDARD.encode(format!("{}:{}", self.username, self.password));
+        format!("Basic {}", encoded)
+    }
+}
+
+/// Credentials by host, with the port when it is not the default one, e.g. `localhost:8080`.
+#[derive(Debug, Default, Clone)]
+pub struct Credentials {
+    credentials: BTreeMap<String, Credential>,
+}
+
+impl Credentials {
+    /// Loads the saved credentials. Missing or unreadable files have none.
+    pub fn load() -> Self {
+        let credentials = credentials_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        Self { credentials }
+    }
+
+    pub fn get(&self, host: &str) -> Option<&Credential> {
+        self.credentials.get(host)
+    }
+
+    /// Gets every saved password, to mask them.
+    pub fn passwords(&self) -> impl Iterator<Item = &str> {
+        self.credentials
+            .values()
+            .map(|credential| credential.password.as_str())
+    }
+
+    /// Saves the credential of the host, replacing the one it had.
+    pub fn save(&mut self, host: String, credential: Credential) -> io::Result<()> {
+        self.credentials.insert(host, credential);
+        let Some(path) = credentials_path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = serde_json::to_string_pretty(&self.credentials)?;
+        storage::overwrite(&path, &contents).map_err(|err| io::Error::other(err.to_string()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+}
+
+fn credentials_path() -> Option<PathBuf> {
+    Some(settings::config_dir()?.join(CREDENTIALS_FILE))
+}
+
+/// Gets the host credentials are saved under for the url, with the port when it is not the
+/// default one of the scheme.
+pub fn host_of(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
+
+/// Gets the realm of a `Basic` challenge in the `WWW-Authenticate` headers of a response, empty
+/// when the challenge has none. None when the server does not ask for Basic auth.
+pub fn basic_challenge(headers: &[(String, String)]) -> Option<String> {
+    headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("www-authenticate"))
+        .find_map(|(_, value)| {
+            let (scheme, params) = value.trim().split_once(' ').unwrap_or((value.trim(), ""));
+            if !scheme.eq_ignore_ascii_case("basic") {
+                return None;
+            }
+            let realm = params
+                .split(',')
+                .filter_map(|param| param.split_once('='))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case("realm"))
+                .map(|(_, realm)| realm.trim().trim_matches('"').to_string());
+            Some(realm.unwrap_or_default())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_answer_basic_challenges() {
+        let headers = vec![
+            (String::from("content-type"), String::from("text/plain")),
+            (
+                String::from("www-authenticate"),
+                String::from("Basic realm=\"staging\", charset=\"UTF-8\""),
+            ),
+        ];
+        assert_eq!(basic_challenge(&headers), Some(String::from("staging")));
+        let bearer = vec![(
+            String::from("WWW-Authenticate"),
+            String::from("Bearer realm=\"api\""),
+        )];
+        assert_eq!(basic_challenge(&bearer), None);
+
+        assert_eq!(
+            host_of("http://localhost:8080/users"),
+            Some(String::from("localhost:8080"))
+        );
+        assert_eq!(
+            host_of("https://api.example.com:443/users"),
+            Some(String::from("api.example.com"))
+        );
+        let credential = Credential {
+            username: String::from("Aladdin"),
+            password: String::from("open sesame"),
+        };
+        assert_eq!(
+            credential.authorization(),
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+    }
+}
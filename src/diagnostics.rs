@@ -0,0 +1,251 @@
+//! Connection troubleshooting. When a request can't connect, the DNS resolution, TCP connect and
+//! TLS handshake are run one by one so the panel can tell which stage failed instead of showing a
+//! bare error string.
+
+use std::{
+    fmt,
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph, Widget, Wrap},
+};
+use reqwest::Url;
+
+use crate::api::IpFamily;
+
+/// How long each address gets to accept the TCP connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    Dns,
+    Tcp,
+    Tls,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::Dns => write!(f, "DNS resolution"),
+            Stage::Tcp => write!(f, "TCP connect"),
+            Stage::Tls => write!(f, "TLS handshake"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// The stage worked, with what it found, e.g. the resolved addresses.
+    Passed(String),
+    Failed(String),
+    /// The stage was not run because an earlier one failed.
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageReport {
+    stage: Stage,
+    outcome: Outcome,
+    elapsed: Duration,
+}
+
+impl StageReport {
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    pub fn outcome(&self) -> &Outcome {
+        &self.outcome
+    }
+}
+
+/// Checks if the error of a request is worth diagnosing, i.e. the request never got a response.
+pub fn is_connection_error(err: &reqwest::Error) -> bool {
+    err.is_connect() || err.is_timeout()
+}
+
+/// Runs every stage of connecting to the url in order, only using addresses of the family. Stages
+/// after a failed one are skipped and the TLS handshake is only run for https urls.
+pub fn diagnose(url: &str, family: IpFamily) -> Result<Vec<StageReport>, String> {
+    let url = Url::parse(url).map_err(|err| format!("invalid url {}: {}", url, err))?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| format!("{} has no host", url))?
+        .to_string();
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| format!("{} has no port", url))?;
+    let tls = url.scheme() == "https";
+
+    let mut reports = Vec::new();
+    let started = Instant::now();
+    let addresses: Result<Vec<SocketAddr>, String> = (host.as_str(), port)
+        .to_socket_addrs()
+        .map(|addresses| {
+            addresses
+                .filter(|address| family.allows(&address.ip()))
+                .collect()
+        })
+        .map_err(|err| err.to_string());
+    let addresses = match addresses {
+        Ok(addresses) if !addresses.is_empty() => {
+            let found: Vec<String> = addresses.iter().map(|a| a.ip().to_string()).collect();
+            reports.push(report(
+                Stage::Dns,
+                Outcome::Passed(found.join(", ")),
+                started,
+            ));
+            addresses
+        }
+        Ok(_) => {
+            let outcome = Outcome::Failed(match family {
+                IpFamily::Any => format!("{} has no addresses", host),
+                _ => format!("{} has no {} addresses", host, family),
+            });
+            reports.push(report(Stage::Dns, outcome, started));
+            return Ok(skip_from(reports, Stage::Tcp, tls));
+        }
+        Err(err) => {
+            reports.push(report(Stage::Dns, Outcome::Failed(err), started));
+            return Ok(skip_from(reports, Stage::Tcp, tls));
+        }
+    };
+
+    let started = Instant::now();
+    let mut last_error = String::new();
+    let mut stream = None;
+    for address in &addresses {
+        match TcpStream::connect_timeout(address, CONNECT_TIMEOUT) {
+            Ok(connected) => {
+                stream = Some((connected, address));
+                break;
+            }
+            Err(err) => last_error = format!("{}: {}", address, err),
+        }
+    }
+    let Some((stream, address)) = stream else {
+        reports.push(report(Stage::Tcp, Outcome::Failed(last_error), started));
+        return Ok(skip_from(reports, Stage::Tls, tls));
+    };
+    let outcome = Outcome::Passed(format!("connected to {}", address));
+    reports.push(report(Stage::Tcp, outcome, started));
+
+    if tls {
+        let started = Instant::now();
+        let outcome = match native_tls::TlsConnector::new() {
+            Ok(connector) => match connector.connect(&host, stream) {
+                Ok(_) => Outcome::Passed(String::from("certificate accepted")),
+                Err(err) => Outcome::Failed(err.to_string()),
+            },
+            Err(err) => Outcome::Failed(err.to_string()),
+        };
+        reports.push(report(Stage::Tls, outcome, started));
+    }
+    Ok(reports)
+}
+
+fn report(stage: Stage, outcome: Outcome, started: Instant) -> StageReport {
+    StageReport {
+        stage,
+        outcome,
+        elapsed: started.elapsed(),
+    }
+}
+
+/// Marks the stages from `from` on as skipped.
+fn skip_from(mut reports: Vec<StageReport>, from: Stage, tls: bool) -> Vec<StageReport> {
+    let remaining: &[Stage] = match from {
+        Stage::Dns => &[Stage::Dns, Stage::Tcp, Stage::Tls],
+        Stage::Tcp => &[Stage::Tcp, Stage::Tls],
+        Stage::Tls => &[Stage::Tls],
+    };
+    for stage in remaining {
+        if *stage != Stage::Tls || tls {
+            reports.push(StageReport {
+                stage: *stage,
+                outcome: Outcome::Skipped,
+                elapsed: Duration::ZERO,
+            });
+        }
+    }
+    reports
+}
+
+/// A panel listing every stage of the connection and how it went.
+pub struct DiagnosticsPanel<'a> {
+    url: &'a str,
+    reports: &'a [StageReport],
+}
+
+impl<'a> DiagnosticsPanel<'a> {
+    pub fn new(url: &'a str, reports: &'a [StageReport]) -> Self {
+        Self { url, reports }
+    }
+}
+
+impl Widget for DiagnosticsPanel<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let lines: Vec<Line> = self
+            .reports
+            .iter()
+            .map(|report| {
+                let (mark, color, detail) = match report.outcome() {
+                    Outcome::Passed(detail) => ("ok  ", Color::LightGreen, detail.as_str()),
+                    Outcome::Failed(err) => ("fail", Color::LightRed, err.as_str()),
+                    Outcome::Skipped => ("skip", Color::DarkGray, ""),
+                };
+                Line::from(vec![
+                    Span::from(mark).style(Style::new().fg(color)),
+                    Span::from(format!(
+                        " {} ({} ms) {}",
+                        report.stage(),
+                        report.elapsed.as_millis(),
+                        detail
+                    )),
+                ])
+            })
+            .collect();
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title(format!("Connecting to {}", self.url)))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, TcpListener};
+
+    use super::*;
+
+    #[test]
+    fn should_report_the_stage_that_failed() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let url = format!("http://127.0.0.1:{}/health", port);
+        let reports = diagnose(&url, IpFamily::Any).unwrap();
+        assert_eq!(reports.len(), 2);
+        assert!(matches!(reports[1].outcome(), Outcome::Passed(_)));
+        let reports = diagnose(&url, IpFamily::V6).unwrap();
+        assert_eq!(
+            reports[0].outcome(),
+            &Outcome::Failed(String::from("127.0.0.1 has no ipv6 addresses"))
+        );
+        assert_eq!(reports[1].outcome(), &Outcome::Skipped);
+
+        drop(listener);
+        let reports =
+            diagnose(&format!("https://127.0.0.1:{}/health", port), IpFamily::V4).unwrap();
+        assert_eq!(reports[1].stage(), Stage::Tcp);
+        assert!(matches!(reports[1].outcome(), Outcome::Failed(_)));
+        assert_eq!(reports[2].outcome(), &Outcome::Skipped);
+    }
+}
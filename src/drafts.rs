@@ -0,0 +1,126 @@
+//! Drafts of edits that are not part of the collection yet, such as a request being written in
+//! the new request popup. The draft is saved to the data directory while typing so it can be
+//! recovered after a crash or an accidental quit, and removed once the edit is done or discarded.
+
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{history, storage};
+
+/// The file in the data directory where the draft is saved.
+const DRAFT_FILE: &str = "draft.json";
+/// How often a draft that keeps changing is saved.
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A request being written in the new request popup.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestDraft {
+    pub name: String,
+    pub method: String,
+    pub url: String,
+    pub accept: String,
+    /// Headers added on top of the accept preset. Drafts from before headers could be added have
+    /// none.
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+}
+
+impl RequestDraft {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_empty() && self.url.is_empty()
+    }
+}
+
+/// Saves a draft at most every AUTOSAVE_INTERVAL and keeps track of the last saved one.
+#[derive(Debug, Default)]
+pub struct Drafts {
+    /// Where the draft is saved. None keeps drafts in memory only.
+    path: Option<PathBuf>,
+    last_saved: Option<(Instant, RequestDraft)>,
+}
+
+impl Drafts {
+    pub fn new() -> Self {
+        Self {
+            path: history::data_dir().map(|dir| dir.join(DRAFT_FILE)),
+            last_saved: None,
+        }
+    }
+
+    /// Gets the draft left behind by a previous session, if any.
+    pub fn recover(&self) -> Option<RequestDraft> {
+        let contents = fs::read_to_string(self.path.as_ref()?).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Saves the draft if it changed and the last save was long enough ago.
+    pub fn autosave(&mut self, draft: &RequestDraft) -> io::Result<()> {
+        if let Some((saved_at, saved)) = &self.last_saved {
+            if saved == draft || saved_at.elapsed() < AUTOSAVE_INTERVAL {
+                return Ok(());
+            }
+        }
+        self.save(draft)
+    }
+
+    /// Saves the draft right away, e.g. before quitting.
+    pub fn save(&mut self, draft: &RequestDraft) -> io::Result<()> {
+        if draft.is_empty() {
+            return self.discard();
+        }
+        if let Some(path) = self.path.as_deref() {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let contents = serde_json::to_string_pretty(draft)?;
+            storage::overwrite(path, &contents).map_err(|err| io::Error::other(err.to_string()))?;
+        }
+        self.last_saved = Some((Instant::now(), draft.clone()));
+        Ok(())
+    }
+
+    /// Removes the draft once the edit is done or was thrown away.
+    pub fn discard(&mut self) -> io::Result<()> {
+        self.last_saved = None;
+        match self.path.as_deref().map(fs::remove_file) {
+            Some(Err(err)) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external;
+
+    #[test]
+    fn should_save_and_recover_drafts() {
+        let mut drafts = Drafts {
+            path: Some(external::temp_path("draft", "json")),
+            last_saved: None,
+        };
+        let mut draft = RequestDraft {
+            name: String::from("create user"),
+            method: String::from("POST"),
+            ..RequestDraft::default()
+        };
+        drafts.autosave(&draft).unwrap();
+        assert_eq!(drafts.recover(), Some(draft.clone()));
+
+        // changes right after a save wait for the next interval
+        draft.url = String::from("http://localhost/users");
+        drafts.autosave(&draft).unwrap();
+        assert_ne!(drafts.recover(), Some(draft.clone()));
+        drafts.save(&draft).unwrap();
+        assert_eq!(drafts.recover(), Some(draft));
+
+        drafts.discard().unwrap();
+        assert_eq!(drafts.recover(), None);
+    }
+}
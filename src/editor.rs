@@ -0,0 +1,166 @@
+//! An editable buffer for hermes source inside the TUI. The source is read again on every change
+//! and the problems found are underlined in place, so mistakes show up while typing instead of
+//! when the source is applied.
+
+use ratatui::{
+    buffer::Buffer,
+    crossterm::event::KeyEvent,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Widget},
+};
+
+use crate::{
+    components::TextArea,
+    source::{self, Diagnostic, Severity},
+};
+
+#[derive(Debug, Default, Clone)]
+pub struct SourceEditor {
+    text: TextArea,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl SourceEditor {
+    pub fn new(contents: &str) -> Self {
+        let mut editor = Self {
+            text: TextArea::new(contents.trim_end_matches('\n')),
+            diagnostics: Vec::new(),
+        };
+        editor.validate();
+        editor
+    }
+
+    pub fn contents(&self) -> String {
+        self.text.get_text() + "\n"
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Gets the first problem on the line of the cursor.
+    pub fn diagnostic_at_cursor(&self) -> Option<&Diagnostic> {
+        self.diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.line() == self.text.row())
+    }
+
+    /// Gets where the cursor is on screen when the editor is rendered in the area.
+    pub fn cursor_position(&self, area: Rect) -> (u16, u16) {
+        self.text.cursor_position(area)
+    }
+
+    /// Edits the buffer or moves the cursor. Returns whether the key was used.
+    pub fn handle_key(&mut self, key: &KeyEvent) -> bool {
+        let used = self.text.handle_key(key);
+        if used {
+            self.validate();
+        }
+        used
+    }
+
+    fn validate(&mut self) {
+        self.diagnostics = source::read(&self.contents()).1;
+    }
+}
+
+/// Gets the color problems of the severity are shown in.
+pub fn severity_color(severity: Severity) -> Color {
+    match severity {
+        Severity::Error => Color::LightRed,
+        Severity::Warning => Color::LightYellow,
+    }
+}
+
+impl Widget for &SourceEditor {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let lines: Vec<Line> = self
+            .text
+            .lines()
+            .iter()
+            .enumerate()
+            .skip(self.text.scroll_to_cursor(area.height))
+            .map(|(index, line)| {
+                let chars: Vec<char> = line.chars().collect();
+                // errors are underlined over the warnings on the same characters
+                let mut problems: Vec<Option<Severity>> = vec![None; chars.len()];
+                for diagnostic in self.diagnostics.iter().filter(|d| d.line() == index) {
+                    let end = diagnostic.end().min(chars.len());
+                    for problem in problems.iter_mut().take(end).skip(diagnostic.start()) {
+                        if *problem != Some(Severity::Error) {
+                            *problem = Some(diagnostic.severity());
+                        }
+                    }
+                }
+                // group the characters into spans that share the same style
+                let mut spans: Vec<Span> = Vec::new();
+                let mut start = 0;
+                for end in 1..=chars.len() {
+                    if end == chars.len() || problems[end] != problems[start] {
+                        let text: String = chars[start..end].iter().collect();
+                        spans.push(match problems[start] {
+                            Some(severity) => Span::styled(
+                                text,
+                                Style::new()
+                                    .fg(severity_color(severity))
+                                    .add_modifier(Modifier::UNDERLINED),
+                            ),
+                            None => Span::raw(text),
+                        });
+                        start = end;
+                    }
+                }
+                Line::from(spans)
+            })
+            .collect();
+        let errors = self.diagnostics.iter().filter(|d| d.is_error()).count();
+        let warnings = self.diagnostics.len() - errors;
+        let mut title = String::from("Source");
+        for (count, what) in [(errors, "problem"), (warnings, "warning")] {
+            match count {
+                0 => {}
+                1 => title.push_str(&format!(", 1 {}", what)),
+                count => title.push_str(&format!(", {} {}s", count, what)),
+            }
+        }
+        Paragraph::new(lines)
+            .block(Block::bordered().title(title))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::crossterm::event::{KeyCode, KeyModifiers};
+
+    use super::*;
+
+    #[test]
+    fn should_revalidate_on_every_keystroke() {
+        let mut editor = SourceEditor::new("request {\n    url `http://localhost`\n}");
+        assert!(editor.diagnostics().is_empty());
+
+        editor.handle_key(&KeyEvent::new(KeyCode::Down, KeyModifiers::NONE));
+        editor.handle_key(&KeyEvent::new(KeyCode::End, KeyModifiers::NONE));
+        editor.handle_key(&KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        assert_eq!(
+            editor.diagnostic_at_cursor().map(Diagnostic::message),
+            Some("this value is never closed, add a ` after it")
+        );
+
+        editor.handle_key(&KeyEvent::new(KeyCode::Char('`'), KeyModifiers::NONE));
+        editor.handle_key(&KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        editor.handle_key(&KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE));
+        editor.handle_key(&KeyEvent::new(KeyCode::Char('é'), KeyModifiers::NONE));
+        assert_eq!(
+            editor.contents(),
+            "request {\n    url `http://localhost`\n    é\n}\n"
+        );
+        assert_eq!(
+            editor.diagnostics()[0].message(),
+            "the field é has no value"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! The ETags of the resources a collection has seen, to test APIs using optimistic locking. A GET
+//! keeps the ETag of the resource and a PUT or PATCH to it afterwards gets an `If-Match` with it,
+//! so updates chained after a read go through, and the ETag in the response to the update is kept
+//! for the next one. Requests writing their own `If-Match` are sent as written, e.g. to check a
+//! stale ETag gets a `412`.
+
+use std::collections::HashMap;
+
+use reqwest::Url;
+
+use crate::{api::HttpMethod, assertions::ResponseFacts, client::Outgoing};
+
+#[derive(Debug, Default, Clone)]
+pub struct ETags {
+    /// The ETag of every resource by its url, without the query.
+    tags: HashMap<String, String>,
+}
+
+impl ETags {
+    /// Keeps the ETag of the response to a request to the url, for successful GETs, PUTs and
+    /// PATCHes. Successful DELETEs forget the resource. Weak ETags are not kept, `If-Match` never
+    /// matches them.
+    pub fn store(&mut self, method: HttpMethod, url: &str, facts: &ResponseFacts) {
+        let Some(resource) = resource(url) else {
+            return;
+        };
+        if !(200..300).contains(&facts.status) {
+            return;
+        }
+        match method {
+            HttpMethod::Get | HttpMethod::Put | HttpMethod::Patch => {
+                if let Some(tag) = facts
+                    .header("etag")
+                    .map(str::trim)
+                    .filter(|tag| tag.starts_with('"'))
+                {
+                    self.tags.insert(resource, tag.to_string());
+                }
+            }
+            HttpMethod::Delete => {
+                self.tags.remove(&resource);
+            }
+            _ => {}
+        }
+    }
+
+    /// Gets the ETag kept for the resource at the url.
+    pub fn get(&self, url: &str) -> Option<&str> {
+        self.tags.get(&resource(url)?).map(String::as_str)
+    }
+
+    /// Adds an `If-Match` with the ETag of the resource to PUTs and PATCHes, unless the request
+    /// sets a precondition itself. Raw requests are sent as they are written.
+    pub fn add_to(&self, outgoing: &mut Outgoing) {
+        if !matches!(outgoing.method, HttpMethod::Put | HttpMethod::Patch) || outgoing.raw.is_some()
+        {
+            return;
+        }
+        let has_precondition = outgoing.headers.iter().any(|(name, _)| {
+            name.eq_ignore_ascii_case("if-match") || name.eq_ignore_ascii_case("if-none-match")
+        });
+        if has_precondition {
+            return;
+        }
+        if let Some(tag) = self.get(&outgoing.url) {
+            outgoing
+                .headers
+                .push((String::from("If-Match"), tag.to_string()));
+        }
+    }
+}
+
+/// Gets the url of the resource at the url, the same for `/users/7`, `/users/7/` and
+/// `/users/7?fields=name`.
+fn resource(url: &str) -> Option<String> {
+    let mut url = Url::parse(url).ok()?;
+    url.set_query(None);
+    url.set_fragment(None);
+    let resource = url.to_string();
+    Some(match resource.strip_suffix('/') {
+        Some(trimmed) if url.path() != "/" => trimmed.to_string(),
+        _ => resource,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, etag: &str) -> ResponseFacts {
+        ResponseFacts {
+            status,
+            headers: vec![(String::from("ETag"), String::from(etag))],
+            ..Default::default()
+        }
+    }
+
+    fn outgoing(method: HttpMethod, headers: Vec<(String, String)>) -> Outgoing {
+        Outgoing {
+            method,
+            url: String::from("https://api.example.com/users/7?notify=true"),
+            headers,
+            body: None,
+            raw: None,
+            form: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn should_match_updates_to_the_etag_read_before() {
+        let mut etags = ETags::default();
+        etags.store(
+            HttpMethod::Get,
+            "https://api.example.com/users/7/",
+            &response(200, "\"v1\""),
+        );
+        etags.store(
+            HttpMethod::Get,
+            "https://api.example.com/users",
+            &response(200, "W/\"list\""),
+        );
+        etags.store(
+            HttpMethod::Get,
+            "https://api.example.com/teams/1",
+            &response(404, "\"gone\""),
+        );
+        assert_eq!(etags.get("https://api.example.com/users"), None);
+        assert_eq!(etags.get("https://api.example.com/teams/1"), None);
+
+        let mut put = outgoing(HttpMethod::Put, Vec::new());
+        etags.add_to(&mut put);
+        assert_eq!(
+            put.headers,
+            [(String::from("If-Match"), String::from("\"v1\""))]
+        );
+        let mut get = outgoing(HttpMethod::Get, Vec::new());
+        etags.add_to(&mut get);
+        assert!(get.headers.is_empty());
+        let stale = vec![(String::from("if-match"), String::from("\"v0\""))];
+        let mut patch = outgoing(HttpMethod::Patch, stale.clone());
+        etags.add_to(&mut patch);
+        assert_eq!(patch.headers, stale);
+
+        // the update answers with the ETag of the new version
+        etags.store(
+            HttpMethod::Patch,
+            "https://api.example.com/users/7",
+            &response(200, "\"v2\""),
+        );
+        assert_eq!(etags.get("https://api.example.com/users/7"), Some("\"v2\""));
+        etags.store(
+            HttpMethod::Put,
+            "https://api.example.com/users/7",
+            &response(412, "\"v3\""),
+        );
+        assert_eq!(etags.get("https://api.example.com/users/7"), Some("\"v2\""));
+        etags.store(
+            HttpMethod::Delete,
+            "https://api.example.com/users/7",
+            &response(204, ""),
+        );
+        assert_eq!(etags.get("https://api.example.com/users/7"), None);
+    }
+}
@@ -0,0 +1,144 @@
+//! Expected results of requests: a snippet of the response kept with the request, such as the
+//! fields of a JSON body other services rely on. Every response is compared to it and what drifted
+//! is shown, a lightweight contract test that needs no assertions. A JSON snippet matches a JSON
+//! body that has every key and item of the snippet with the same values, whatever else it has.
+//! Any other snippet matches a body that has every one of its lines.
+
+use serde_json::Value;
+
+use crate::json_tree;
+
+/// Gets the snippet to expect from a response body: JSON pretty printed so it reads well in
+/// hermes files, anything else as it is.
+pub fn record(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|json| serde_json::to_string_pretty(&json).ok())
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+/// Describes every way the body drifted from the expected snippet. Empty when it matches.
+pub fn drift(expected: &str, body: &str) -> Vec<String> {
+    let mut drift = Vec::new();
+    match parse_both(expected, body) {
+        Some((expected, actual)) => drift_json("$", &expected, Some(&actual), &mut drift),
+        None => drift.extend(
+            expected
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !body.contains(line))
+                .map(|line| format!("missing line: {}", line)),
+        ),
+    }
+    drift
+}
+
+/// Gets the expected snippet and the body to show their differences. A JSON body is cut down to
+/// the keys and items of the snippet, so only what drifted shows up.
+pub fn compared(expected: &str, body: &str) -> (String, String) {
+    match parse_both(expected, body) {
+        Some((expected, actual)) => (
+            serde_json::to_string_pretty(&expected).unwrap_or_default(),
+            serde_json::to_string_pretty(&project(&expected, &actual)).unwrap_or_default(),
+        ),
+        None => (expected.to_string(), body.to_string()),
+    }
+}
+
+/// Reads the snippet and the body as JSON, None unless both are.
+fn parse_both(expected: &str, body: &str) -> Option<(Value, Value)> {
+    Some((
+        serde_json::from_str(expected).ok()?,
+        serde_json::from_str(body).ok()?,
+    ))
+}
+
+fn drift_json(path: &str, expected: &Value, actual: Option<&Value>, drift: &mut Vec<String>) {
+    match (expected, actual) {
+        (_, None) => drift.push(format!("{}: missing", path)),
+        (Value::Object(expected), Some(Value::Object(actual))) => {
+            for (key, value) in expected {
+                let path = json_tree::child_path(path, key);
+                drift_json(&path, value, actual.get(key), drift);
+            }
+        }
+        (Value::Array(expected), Some(Value::Array(actual))) => {
+            for (index, value) in expected.iter().enumerate() {
+                let path = format!("{}[{}]", path, index);
+                drift_json(&path, value, actual.get(index), drift);
+            }
+        }
+        (expected, Some(actual)) if expected != actual => drift.push(format!(
+            "{}: expected {}, got {}",
+            path,
+            describe(expected),
+            describe(actual)
+        )),
+        _ => {}
+    }
+}
+
+/// Describes a value for drift, containers by their type since they can be long.
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Object(_) => String::from("an object"),
+        Value::Array(_) => String::from("an array"),
+        value => value.to_string(),
+    }
+}
+
+/// Cuts the actual value down to the keys and items of the expected one.
+fn project(expected: &Value, actual: &Value) -> Value {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => Value::Object(
+            expected
+                .iter()
+                .filter_map(|(key, value)| Some((key.clone(), project(value, actual.get(key)?))))
+                .collect(),
+        ),
+        (Value::Array(expected), Value::Array(actual)) => Value::Array(
+            expected
+                .iter()
+                .zip(actual)
+                .map(|(expected, actual)| project(expected, actual))
+                .collect(),
+        ),
+        _ => actual.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_show_how_the_response_drifted() {
+        let body = r#"{"id": 1, "name": "Grace", "tags": ["a"], "team": {"id": 2, "size": 5}}"#;
+        let expected =
+            record(r#"{"name": "Ada", "tags": ["a", "b"], "team": {"id": 2}, "role": "x"}"#);
+        assert_eq!(
+            drift(&expected, body),
+            [
+                "$.name: expected \"Ada\", got \"Grace\"",
+                "$.role: missing",
+                "$.tags[1]: missing",
+            ]
+        );
+        assert!(drift(&record(body), body).is_empty());
+        assert_eq!(
+            drift(r#"{"team": []}"#, body),
+            ["$.team: expected an array, got an object"]
+        );
+
+        let (old, new) = compared(r#"{"team": {"id": 3}}"#, body);
+        assert_eq!(old, "{\n  \"team\": {\n    \"id\": 3\n  }\n}");
+        assert_eq!(new, "{\n  \"team\": {\n    \"id\": 2\n  }\n}");
+
+        let html = "<html>\n  <title>Users</title>\n</html>";
+        assert!(drift("  <title>Users</title>", html).is_empty());
+        assert_eq!(
+            drift("<title>Teams</title>\n<html>", html),
+            ["missing line: <title>Teams</title>"]
+        );
+    }
+}
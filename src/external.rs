@@ -0,0 +1,164 @@
+//! Running external programs such as $EDITOR and $PAGER, with the TUI suspended while they own
+//! the terminal.
+
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::tui;
+
+/// Gets a unique path in the temp directory. The extension helps editors pick the right syntax.
+pub fn temp_path(name: &str, extension: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    env::temp_dir().join(format!(
+        "hermes-{}-{}-{}.{}",
+        name,
+        std::process::id(),
+        nanos,
+        extension
+    ))
+}
+
+/// Gets the editor of the user, $VISUAL then $EDITOR, or vi if neither is set. Either can have
+/// arguments, e.g. `code --wait`.
+fn editor() -> Command {
+    configured_command(env::var("VISUAL").ok().as_deref(), "EDITOR", "vi")
+}
+
+/// Runs the command until it exits, with an error naming the program when it can't be started.
+fn run(command: &mut Command) -> io::Result<ExitStatus> {
+    command.status().map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!(
+                "could not start {}: {}",
+                command.get_program().to_string_lossy(),
+                err
+            ),
+        )
+    })
+}
+
+/// Lets the user edit the text in $EDITOR (vi if not set) and returns the text once the editor
+/// exits. The text is returned unchanged if the editor exits with an error, e.g. `:cq` in vim.
+pub fn edit_text(terminal: &mut tui::Tui, text: &str, extension: &str) -> io::Result<String> {
+    let path = temp_path("edit", extension);
+    fs::write(&path, text)?;
+    let status = tui::suspend(terminal, || run(editor().arg(&path)));
+    let edited = match status {
+        Ok(status) if status.success() => fs::read_to_string(&path),
+        Ok(_) => Ok(text.to_string()),
+        Err(err) => Err(err),
+    };
+    fs::remove_file(&path)?;
+    edited
+}
+
+/// Opens the file in $EDITOR (vi if not set) with the cursor on the line, passed as `+line` which
+/// vi, vim, nano, emacs and most terminal editors understand.
+pub fn edit_file_at(terminal: &mut tui::Tui, path: &Path, line: Option<usize>) -> io::Result<()> {
+    let mut command = editor();
+    if let Some(line) = line {
+        command.arg(format!("+{}", line));
+    }
+    tui::suspend(terminal, || run(command.arg(path)).map(|_| ()))
+}
+
+/// Builds a command from a configured command line such as `bat -l json --paging always`. Falls
+/// back to the value of the environment variable, then to the default program.
+fn configured_command(configured: Option<&str>, variable: &str, default: &str) -> Command {
+    let command_line = configured
+        .map(String::from)
+        .or_else(|| env::var(variable).ok())
+        .filter(|c| !c.trim().is_empty())
+        .unwrap_or_else(|| String::from(default));
+    let mut parts = command_line.split_whitespace();
+    let mut command = Command::new(parts.next().unwrap_or(default));
+    command.args(parts);
+    command
+}
+
+/// Pipes the content into the configured pager (e.g. bat, jless), $PAGER or less.
+pub fn view_in_pager(
+    terminal: &mut tui::Tui,
+    pager: Option<&str>,
+    content: &str,
+) -> io::Result<()> {
+    let mut command = configured_command(pager, "PAGER", "less");
+    tui::suspend(terminal, || {
+        let mut child = command.stdin(Stdio::piped()).spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // the pager may quit before reading everything, that is not an error
+            let _ = stdin.write_all(content.as_bytes());
+        }
+        child.wait().map(|_| ())
+    })
+}
+
+/// Opens a file in the configured pager, $PAGER or less, for content too big to pipe from memory.
+pub fn open_in_pager(terminal: &mut tui::Tui, pager: Option<&str>, path: &Path) -> io::Result<()> {
+    let mut command = configured_command(pager, "PAGER", "less");
+    tui::suspend(terminal, || command.arg(path).status().map(|_| ()))
+}
+
+/// Shows the difference between two texts with the configured diff tool (e.g. delta), which is
+/// given the paths of the two texts. Without a diff tool, `diff -u` is piped into the pager.
+pub fn view_diff(
+    terminal: &mut tui::Tui,
+    diff_tool: Option<&str>,
+    pager: Option<&str>,
+    old: &str,
+    new: &str,
+) -> io::Result<()> {
+    let old_path = temp_path("old", "txt");
+    let new_path = temp_path("new", "txt");
+    fs::write(&old_path, old)?;
+    fs::write(&new_path, new)?;
+    let result = match diff_tool {
+        Some(tool) => {
+            let mut command = configured_command(Some(tool), "", "diff");
+            tui::suspend(terminal, || {
+                command.arg(&old_path).arg(&new_path).status().map(|_| ())
+            })
+        }
+        None => {
+            let diff = Command::new("diff")
+                .arg("-u")
+                .arg(&old_path)
+                .arg(&new_path)
+                .output()?;
+            view_in_pager(terminal, pager, &String::from_utf8_lossy(&diff.stdout))
+        }
+    };
+    fs::remove_file(&old_path)?;
+    fs::remove_file(&new_path)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_split_commands_into_program_and_arguments() {
+        let command = configured_command(Some("code --wait"), "EDITOR", "vi");
+        assert_eq!(command.get_program(), "code");
+        assert_eq!(command.get_args().collect::<Vec<_>>(), ["--wait"]);
+    }
+
+    #[test]
+    fn should_name_programs_that_do_not_start() {
+        let mut command = configured_command(Some("hermes-no-such-editor --wait"), "EDITOR", "vi");
+        let err = run(&mut command).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("could not start hermes-no-such-editor: "));
+    }
+}
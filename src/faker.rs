@@ -0,0 +1,136 @@
+//! Generators of realistic test data, used as `{{$faker.name}}` style variables. Some generators
+//! take an argument after a space, e.g. `{{$faker.lorem 30}}` for 30 words.
+
+use fake::{
+    faker::{
+        address::en::{CityName, CountryName, StreetName, ZipCode},
+        company::en::CompanyName,
+        internet::en::{IPv4, SafeEmail, Username},
+        lorem::en::{Paragraph, Sentence, Words},
+        name::en::{FirstName, LastName, Name},
+        phone_number::en::PhoneNumber,
+    },
+    Fake,
+};
+
+/// The prefix of the variables that are generated by the faker.
+pub const PREFIX: &str = "$faker.";
+
+/// The name of every generator, shown when an unknown one is used.
+pub const GENERATORS: [&str; 16] = [
+    "name",
+    "first_name",
+    "last_name",
+    "email",
+    "username",
+    "phone",
+    "company",
+    "street",
+    "city",
+    "country",
+    "zip",
+    "ipv4",
+    "lorem",
+    "sentence",
+    "paragraph",
+    "number",
+];
+
+/// Number of words generated by `lorem` when no count is given.
+const DEFAULT_LOREM_WORDS: usize = 10;
+/// The most words `lorem` generates, so a typo in the count can't exhaust the memory.
+const MAX_LOREM_WORDS: usize = 10_000;
+/// The biggest number generated by `number` when no maximum is given.
+const DEFAULT_MAX_NUMBER: u64 = 1000;
+
+/// Generates a value for a variable name such as `$faker.lorem 30`. Returns None when the name is
+/// not a faker variable at all.
+pub fn generate(name: &str) -> Option<Result<String, String>> {
+    let expression = name.strip_prefix(PREFIX)?;
+    let (generator, argument) = match expression.split_once(char::is_whitespace) {
+        Some((generator, argument)) => (generator, Some(argument.trim())),
+        None => (expression, None),
+    };
+    Some(run(generator, argument))
+}
+
+fn run(generator: &str, argument: Option<&str>) -> Result<String, String> {
+    let value = match generator {
+        "name" => Name().fake(),
+        "first_name" => FirstName().fake(),
+        "last_name" => LastName().fake(),
+        "email" => SafeEmail().fake(),
+        "username" => Username().fake(),
+        "phone" => PhoneNumber().fake(),
+        "company" => CompanyName().fake(),
+        "street" => StreetName().fake(),
+        "city" => CityName().fake(),
+        "country" => CountryName().fake(),
+        "zip" => ZipCode().fake(),
+        "ipv4" => IPv4().fake(),
+        "lorem" => {
+            let count = parse_argument(argument, DEFAULT_LOREM_WORDS)?;
+            let end = count
+                .checked_add(1)
+                .filter(|_| count <= MAX_LOREM_WORDS)
+                .ok_or_else(|| format!("lorem generates at most {} words", MAX_LOREM_WORDS))?;
+            Words(count..end).fake::<Vec<String>>().join(" ")
+        }
+        "sentence" => Sentence(4..12).fake(),
+        "paragraph" => Paragraph(3..6).fake(),
+        "number" => {
+            let max = parse_argument(argument, DEFAULT_MAX_NUMBER)?;
+            (0..=max).fake::<u64>().to_string()
+        }
+        _ => {
+            return Err(format!(
+                "unknown faker generator `{}`, expected one of {}",
+                generator,
+                GENERATORS.join(", ")
+            ))
+        }
+    };
+    Ok(value)
+}
+
+fn parse_argument<T: std::str::FromStr>(argument: Option<&str>, default: T) -> Result<T, String> {
+    match argument {
+        Some(argument) => argument
+            .parse()
+            .map_err(|_| format!("expected a number, got `{}`", argument)),
+        None => Ok(default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_generate_fake_values() {
+        assert_eq!(generate("HOST"), None);
+        assert!(generate("$faker.email").unwrap().unwrap().contains('@'));
+        assert_eq!(
+            generate("$faker.lorem 30")
+                .unwrap()
+                .unwrap()
+                .split_whitespace()
+                .count(),
+            30
+        );
+        assert!(
+            generate("$faker.number 5")
+                .unwrap()
+                .unwrap()
+                .parse::<u64>()
+                .unwrap()
+                <= 5
+        );
+        assert!(generate("$faker.lorem many").unwrap().is_err());
+        assert_eq!(
+            generate("$faker.lorem 18446744073709551615"),
+            Some(Err(String::from("lorem generates at most 10000 words")))
+        );
+        assert!(generate("$faker.unicorn").unwrap().is_err());
+    }
+}
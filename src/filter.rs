@@ -0,0 +1,228 @@
+//! Filters narrowing a JSON response down to the parts of interest, typed in the response pane.
+//! They are written as a JSONPath, e.g. `$.users[*].email` or `$..id`, or in a small subset of jq,
+//! e.g. `.users[] | .email` or `.items | length`. A filter that can match several nodes gets them
+//! in an array, even when only one matched.
+
+use serde_json::Value;
+
+/// A step of a filter, applied to every node the steps before it matched.
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    /// `.key` or `['key']`.
+    Key(String),
+    /// `[0]`, counting from the end when negative.
+    Index(i64),
+    /// `[*]`, `.*` or jq's `[]`: every item of an array or value of an object.
+    Each,
+    /// `..key`: the key in the node and every node under it.
+    Descend(String),
+    /// jq's `length` of arrays, objects and strings.
+    Length,
+    /// jq's `keys` of objects, sorted.
+    Keys,
+}
+
+/// Applies the filter to the body. Fails when the body is not JSON, the filter can't be read, or
+/// a filter picking a single node matches none.
+pub fn apply(filter: &str, body: &str) -> Result<Value, String> {
+    let steps = parse(filter)?;
+    let root: Value =
+        serde_json::from_str(body).map_err(|_| String::from("the body is not JSON"))?;
+    let mut nodes = vec![root];
+    for step in &steps {
+        nodes = nodes
+            .into_iter()
+            .flat_map(|node| apply_step(step, node))
+            .collect();
+    }
+    let many = steps
+        .iter()
+        .any(|step| matches!(step, Step::Each | Step::Descend(_)));
+    if many {
+        return Ok(Value::Array(nodes));
+    }
+    nodes
+        .pop()
+        .ok_or_else(|| format!("nothing matches {}", filter.trim()))
+}
+
+fn apply_step(step: &Step, node: Value) -> Vec<Value> {
+    match (step, node) {
+        (Step::Key(key), Value::Object(mut object)) => object.remove(key).into_iter().collect(),
+        (Step::Index(index), Value::Array(mut items)) => {
+            let index = if *index < 0 {
+                items.len().checked_sub(index.unsigned_abs() as usize)
+            } else {
+                Some(*index as usize)
+            };
+            match index.filter(|index| *index < items.len()) {
+                Some(index) => vec![items.swap_remove(index)],
+                None => Vec::new(),
+            }
+        }
+        (Step::Each, Value::Array(items)) => items,
+        (Step::Each, Value::Object(object)) => object.into_iter().map(|(_, value)| value).collect(),
+        (Step::Descend(key), node) => {
+            let mut found = Vec::new();
+            descend(key, &node, &mut found);
+            found
+        }
+        (Step::Length, Value::Array(items)) => vec![Value::from(items.len())],
+        (Step::Length, Value::Object(object)) => vec![Value::from(object.len())],
+        (Step::Length, Value::String(text)) => vec![Value::from(text.chars().count())],
+        (Step::Keys, Value::Object(object)) => {
+            vec![Value::from(object.keys().cloned().collect::<Vec<_>>())]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Collects the values of the key in the node and every node under it, outermost first.
+fn descend(key: &str, node: &Value, found: &mut Vec<Value>) {
+    match node {
+        Value::Object(object) => {
+            if let Some(value) = object.get(key) {
+                found.push(value.clone());
+            }
+            object.values().for_each(|value| descend(key, value, found));
+        }
+        Value::Array(items) => items.iter().for_each(|item| descend(key, item, found)),
+        _ => {}
+    }
+}
+
+/// Reads the filter, a JSONPath or jq path, optionally piped into more paths or into `length`
+/// or `keys`.
+fn parse(filter: &str) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    for part in split_pipes(filter) {
+        let part = part.trim();
+        match part {
+            "" => return Err(String::from("a `|` needs a filter on both sides")),
+            "length" => steps.push(Step::Length),
+            "keys" => steps.push(Step::Keys),
+            _ => parse_path(part, &mut steps)?,
+        }
+    }
+    Ok(steps)
+}
+
+fn parse_path(path: &str, steps: &mut Vec<Step>) -> Result<(), String> {
+    let mut rest = match path.strip_prefix('$') {
+        Some(rest) => rest,
+        None if path.starts_with('.') => path,
+        None => return Err(format!("`{}` is not a path, start it with $ or .", path)),
+    };
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("..") {
+            let (key, after) = split_key(after);
+            if key.is_empty() {
+                return Err(String::from("`..` needs a key after it, e.g. `$..id`"));
+            }
+            steps.push(Step::Descend(key.to_string()));
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix(".*") {
+            steps.push(Step::Each);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('.') {
+            // jq's identity `.` and `.[0]` have no key
+            let (key, after) = split_key(after);
+            if !key.is_empty() {
+                steps.push(Step::Key(key.to_string()));
+            }
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let (inside, after) = after
+                .split_once(']')
+                .ok_or_else(|| String::from("a `[` is not closed"))?;
+            let inside = inside.trim();
+            steps.push(match inside {
+                "" | "*" => Step::Each,
+                _ if inside.len() >= 2
+                    && (inside.starts_with('\'') && inside.ends_with('\'')
+                        || inside.starts_with('"') && inside.ends_with('"')) =>
+                {
+                    Step::Key(inside[1..inside.len() - 1].to_string())
+                }
+                _ => Step::Index(inside.parse().map_err(|_| {
+                    format!("`[{}]` is not an index, filters are not supported", inside)
+                })?),
+            });
+            rest = after;
+        } else {
+            return Err(format!("unexpected `{}`", rest));
+        }
+    }
+    Ok(())
+}
+
+/// Splits the key at the start of the path from the steps after it.
+fn split_key(path: &str) -> (&str, &str) {
+    let end = path.find(['.', '[']).unwrap_or(path.len());
+    (path[..end].trim(), &path[end..])
+}
+
+/// Splits the filter on the pipes that are not in brackets.
+fn split_pipes(filter: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, ch) in filter.char_indices() {
+        match ch {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            '|' if depth == 0 => {
+                parts.push(&filter[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&filter[start..]);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn should_filter_with_jsonpath_and_jq() {
+        let body = r#"{
+            "total": 3,
+            "users": [
+                {"id": 1, "name": "Ada", "team": {"id": 10}},
+                {"id": 2, "name": "Grace"},
+                {"id": 3, "name": "Linus", "tags": ["a", "b"]}
+            ]
+        }"#;
+        let filtered = |filter| apply(filter, body);
+        assert_eq!(filtered("$.total"), Ok(json!(3)));
+        assert_eq!(filtered(".users[-1].tags"), Ok(json!(["a", "b"])));
+        assert_eq!(
+            filtered("$.users[*].name"),
+            Ok(json!(["Ada", "Grace", "Linus"]))
+        );
+        assert_eq!(filtered(".users[] | .name"), filtered("$.users[*].name"));
+        assert_eq!(filtered("$.users[0]['name']"), Ok(json!("Ada")));
+        assert_eq!(filtered("$..id"), Ok(json!([1, 10, 2, 3])));
+        assert_eq!(filtered(".users | length"), Ok(json!(3)));
+        assert_eq!(filtered(".users[1] | keys"), Ok(json!(["id", "name"])));
+        assert_eq!(filtered("."), Ok(serde_json::from_str(body).unwrap()));
+        assert_eq!(filtered("$.users[*].tags[0]"), Ok(json!(["a"])));
+
+        assert_eq!(
+            filtered("$.users[5]"),
+            Err(String::from("nothing matches $.users[5]"))
+        );
+        assert!(filtered("$.users[?(@.id > 1)]").is_err());
+        assert!(filtered("users").is_err());
+        assert!(filtered(".users |").is_err());
+        assert_eq!(
+            apply(".users", "<html>"),
+            Err(String::from("the body is not JSON"))
+        );
+    }
+}
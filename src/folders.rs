@@ -0,0 +1,197 @@
+//! The requests of a collection grouped by the folders they were found in, so the side area can
+//! show them as a tree whose folders are collapsed to hide what they hold. Requests are still
+//! stored flat in the collection and referred to by their index there.
+
+use std::collections::HashSet;
+
+use crate::api::Collection;
+
+/// A folder of the collection with the folders and requests right inside it.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Folder {
+    name: String,
+    /// The path of the folder relative to the collection root, e.g. `users/admin`. Empty for the
+    /// root.
+    path: String,
+    folders: Vec<Folder>,
+    /// The indexes of the requests in the collection.
+    requests: Vec<usize>,
+}
+
+/// A folder or a request as shown in the side area, one per row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Row {
+    Folder {
+        path: String,
+        name: String,
+        depth: usize,
+        collapsed: bool,
+        /// How many requests the folder holds, including the ones in the folders inside it.
+        requests: usize,
+    },
+    Request {
+        index: usize,
+        depth: usize,
+    },
+}
+
+impl Row {
+    pub fn depth(&self) -> usize {
+        match self {
+            Row::Folder { depth, .. } | Row::Request { depth, .. } => *depth,
+        }
+    }
+}
+
+impl Folder {
+    /// Builds the root folder of the collection. Folders keep the order their first request was
+    /// loaded in.
+    pub fn build(collection: &Collection) -> Self {
+        let mut root = Self::default();
+        for (index, request) in collection.iter().enumerate() {
+            let mut folder = &mut root;
+            for name in request
+                .get_folder()
+                .split('/')
+                .filter(|name| !name.is_empty())
+            {
+                folder = folder.child(name);
+            }
+            folder.requests.push(index);
+        }
+        root
+    }
+
+    /// Gets the folder with the name right inside this one, adding it when it is missing.
+    fn child(&mut self, name: &str) -> &mut Folder {
+        let position = match self.folders.iter().position(|folder| folder.name == name) {
+            Some(position) => position,
+            None => {
+                let path = if self.path.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}/{}", self.path, name)
+                };
+                self.folders.push(Folder {
+                    name: name.to_string(),
+                    path,
+                    ..Folder::default()
+                });
+                self.folders.len() - 1
+            }
+        };
+        &mut self.folders[position]
+    }
+
+    /// Counts the requests in the folder and in every folder inside it.
+    pub fn request_count(&self) -> usize {
+        self.requests.len()
+            + self
+                .folders
+                .iter()
+                .map(Folder::request_count)
+                .sum::<usize>()
+    }
+
+    /// Gets the rows shown for what the folder holds, folders first, leaving out what is inside
+    /// the collapsed folders, which are given by path.
+    pub fn rows(&self, collapsed: &HashSet<String>) -> Vec<Row> {
+        let mut rows = Vec::new();
+        self.push_rows(collapsed, 0, &mut rows);
+        rows
+    }
+
+    fn push_rows(&self, collapsed: &HashSet<String>, depth: usize, rows: &mut Vec<Row>) {
+        for folder in &self.folders {
+            let is_collapsed = collapsed.contains(&folder.path);
+            rows.push(Row::Folder {
+                path: folder.path.clone(),
+                name: folder.name.clone(),
+                depth,
+                collapsed: is_collapsed,
+                requests: folder.request_count(),
+            });
+            if !is_collapsed {
+                folder.push_rows(collapsed, depth + 1, rows);
+            }
+        }
+        rows.extend(
+            self.requests
+                .iter()
+                .map(|&index| Row::Request { index, depth }),
+        );
+    }
+}
+
+/// Gets the paths of the folders the folder is in, closest first, e.g. `a/b` then `a` for
+/// `a/b/c`. The folder itself is left out.
+pub fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+    let mut rest = path;
+    std::iter::from_fn(move || {
+        let (parent, _) = rest.rsplit_once('/')?;
+        rest = parent;
+        Some(parent)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::api::{HttpMethod, Request};
+
+    #[test]
+    fn should_group_requests_by_folder() {
+        let mut collection = Collection::default();
+        for (name, folder) in [
+            ("Health", ""),
+            ("List users", "users"),
+            ("Promote", "users/admin"),
+            ("Login", "auth"),
+            ("Delete user", "users"),
+        ] {
+            let mut request = Request::new(
+                String::from(name),
+                HttpMethod::Get,
+                String::new(),
+                None,
+                None,
+                HashMap::new(),
+            );
+            request.set_folder(String::from(folder));
+            collection.add_request(request);
+        }
+        let root = Folder::build(&collection);
+        assert_eq!(root.requests, [0]);
+        let users = &root.folders[0];
+        assert_eq!(
+            (users.name.as_str(), &users.requests[..]),
+            ("users", &[1, 4][..])
+        );
+        assert_eq!(users.folders[0].path, "users/admin");
+        assert_eq!(users.request_count(), 3);
+
+        let rows = root.rows(&HashSet::new());
+        assert_eq!(rows.len(), 8);
+        assert_eq!(rows[1].depth(), 1);
+        assert_eq!(rows[2], Row::Request { index: 2, depth: 2 });
+
+        let rows = root.rows(&HashSet::from([String::from("users")]));
+        assert_eq!(
+            rows[0],
+            Row::Folder {
+                path: String::from("users"),
+                name: String::from("users"),
+                depth: 0,
+                collapsed: true,
+                requests: 3,
+            }
+        );
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[3], Row::Request { index: 0, depth: 0 });
+
+        let found: Vec<_> = ancestors("a/b/c").collect();
+        assert_eq!(found, ["a/b", "a"]);
+    }
+}
@@ -0,0 +1,435 @@
+//! HAR 1.2 exports of the requests sent since hermes started, to share them with teammates or to
+//! open them in the network panel of browser devtools. Requests that got no response are exported
+//! with a status of 0 and the error in `_error`, like browsers do. The export goes through the
+//! masker of the collection and the redaction of the settings, so neither secrets nor what is
+//! redacted end up in the file.
+
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat};
+use serde::Serialize;
+
+use crate::{
+    client::{Outgoing, Response, SendError},
+    redaction::Redaction,
+    secrets::Masker,
+};
+
+/// How many requests a session keeps, the oldest ones are dropped first.
+const MAX_EXCHANGES: usize = 1000;
+
+/// A request sent in the session and what came back.
+#[derive(Debug, Clone)]
+struct Exchange {
+    /// When the request was sent, in seconds since epoch.
+    sent_at: i64,
+    outgoing: Outgoing,
+    /// The response, or why none came back.
+    received: Result<Received, String>,
+}
+
+/// What is exported of a response.
+#[derive(Debug, Clone)]
+struct Received {
+    status: u16,
+    protocol: String,
+    headers: Vec<(String, String)>,
+    body: String,
+    size: u64,
+    elapsed: Duration,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Session {
+    /// Oldest first.
+    exchanges: Vec<Exchange>,
+}
+
+impl Session {
+    /// Keeps the request and its response, or the error it got, for the export.
+    pub fn record(
+        &mut self,
+        outgoing: &Outgoing,
+        response: &Result<Response, SendError>,
+        sent_at: i64,
+    ) {
+        let received = match response {
+            Ok(response) => Ok(Received {
+                status: response.facts().status,
+                protocol: response.facts().protocol.clone(),
+                headers: response.facts().headers.clone(),
+                body: response.facts().body.clone(),
+                size: response.body().size(),
+                elapsed: response.elapsed(),
+            }),
+            Err(err) => Err(err.to_string()),
+        };
+        self.push(outgoing.clone(), received, sent_at);
+    }
+
+    fn push(&mut self, outgoing: Outgoing, received: Result<Received, String>, sent_at: i64) {
+        self.exchanges.push(Exchange {
+            sent_at,
+            outgoing,
+            received,
+        });
+        if self.exchanges.len() > MAX_EXCHANGES {
+            self.exchanges.remove(0);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.exchanges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exchanges.is_empty()
+    }
+
+    /// Exports the session as a HAR file, masked and redacted.
+    pub fn export(&self, masker: &Masker, redaction: &Redaction) -> serde_json::Result<String> {
+        let clean = |text: &str| redaction.redact(&masker.mask(text));
+        let headers = |headers: &[(String, String)]| -> Vec<Pair> {
+            headers
+                .iter()
+                .map(|(name, value)| Pair {
+                    name: name.clone(),
+                    value: redaction.redact_header(name, &masker.mask(value)),
+                })
+                .collect()
+        };
+        let entries = self
+            .exchanges
+            .iter()
+            .map(|exchange| {
+                let outgoing = &exchange.outgoing;
+                let url = clean(&outgoing.url);
+                let content_type = outgoing
+                    .headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                    .map(|(_, value)| value.clone());
+                let post_data = match (&outgoing.body, outgoing.form.is_empty()) {
+                    (Some(body), _) => Some(PostData {
+                        mime_type: content_type.unwrap_or_default(),
+                        text: clean(body),
+                        params: Vec::new(),
+                    }),
+                    (None, false) => Some(PostData {
+                        mime_type: content_type
+                            .unwrap_or_else(|| String::from("multipart/form-data")),
+                        text: String::new(),
+                        params: outgoing
+                            .form
+                            .iter()
+                            .map(|(name, value)| match value.strip_prefix('@') {
+                                Some(path) => Param {
+                                    name: name.clone(),
+                                    value: None,
+                                    file_name: Some(path.to_string()),
+                                },
+                                None => Param {
+                                    name: name.clone(),
+                                    value: Some(clean(value)),
+                                    file_name: None,
+                                },
+                            })
+                            .collect(),
+                    }),
+                    (None, true) => None,
+                };
+                let body_size = outgoing.body.as_ref().map_or(0, |body| body.len() as i64);
+                let (response, time, http_version) = match &exchange.received {
+                    Ok(received) => {
+                        let time = received.elapsed.as_millis() as u64;
+                        let mime_type = received
+                            .headers
+                            .iter()
+                            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                            .map(|(_, value)| value.clone())
+                            .unwrap_or_default();
+                        let response = HarResponse {
+                            status: received.status,
+                            status_text: reqwest::StatusCode::from_u16(received.status)
+                                .ok()
+                                .and_then(|status| status.canonical_reason())
+                                .unwrap_or_default()
+                                .to_string(),
+                            http_version: received.protocol.clone(),
+                            cookies: Vec::new(),
+                            headers: headers(&received.headers),
+                            content: Content {
+                                size: received.size,
+                                mime_type,
+                                text: clean(&received.body),
+                            },
+                            redirect_url: String::new(),
+                            headers_size: -1,
+                            body_size: received.size as i64,
+                            error: None,
+                        };
+                        (response, time, received.protocol.clone())
+                    }
+                    Err(err) => {
+                        let response = HarResponse {
+                            status: 0,
+                            status_text: String::new(),
+                            http_version: String::new(),
+                            cookies: Vec::new(),
+                            headers: Vec::new(),
+                            content: Content {
+                                size: 0,
+                                mime_type: String::from("x-unknown"),
+                                text: String::new(),
+                            },
+                            redirect_url: String::new(),
+                            headers_size: -1,
+                            body_size: -1,
+                            error: Some(clean(err)),
+                        };
+                        (response, 0, String::from("HTTP/1.1"))
+                    }
+                };
+                Entry {
+                    started_date_time: DateTime::from_timestamp(exchange.sent_at, 0)
+                        .unwrap_or_default()
+                        .to_rfc3339_opts(SecondsFormat::Millis, true),
+                    time,
+                    request: HarRequest {
+                        method: outgoing.method.to_string(),
+                        query_string: query_string(&url),
+                        url,
+                        http_version,
+                        cookies: Vec::new(),
+                        headers: headers(&outgoing.headers),
+                        post_data,
+                        headers_size: -1,
+                        body_size,
+                    },
+                    response,
+                    cache: Cache {},
+                    timings: Timings {
+                        send: 0,
+                        wait: time,
+                        receive: 0,
+                    },
+                }
+            })
+            .collect();
+        serde_json::to_string_pretty(&Har {
+            log: Log {
+                version: "1.2",
+                creator: Creator {
+                    name: "hermes",
+                    version: env!("CARGO_PKG_VERSION"),
+                },
+                entries,
+            },
+        })
+    }
+}
+
+/// Gets the query parameters of the url, decoded.
+fn query_string(url: &str) -> Vec<Pair> {
+    reqwest::Url::parse(url)
+        .map(|url| {
+            url.query_pairs()
+                .map(|(name, value)| Pair {
+                    name: name.into_owned(),
+                    value: value.into_owned(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Serialize)]
+struct Har {
+    log: Log,
+}
+
+#[derive(Debug, Serialize)]
+struct Log {
+    version: &'static str,
+    creator: Creator,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize)]
+struct Creator {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    started_date_time: String,
+    /// How long the request took, in milliseconds.
+    time: u64,
+    request: HarRequest,
+    response: HarResponse,
+    cache: Cache,
+    timings: Timings,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarRequest {
+    method: String,
+    url: String,
+    http_version: String,
+    cookies: Vec<Pair>,
+    headers: Vec<Pair>,
+    query_string: Vec<Pair>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_data: Option<PostData>,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PostData {
+    mime_type: String,
+    text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    params: Vec<Param>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Param {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarResponse {
+    status: u16,
+    status_text: String,
+    http_version: String,
+    cookies: Vec<Pair>,
+    headers: Vec<Pair>,
+    content: Content,
+    #[serde(rename = "redirectURL")]
+    redirect_url: String,
+    headers_size: i64,
+    body_size: i64,
+    /// Why no response came back, the way browsers export failed requests.
+    #[serde(rename = "_error", skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Content {
+    size: u64,
+    mime_type: String,
+    text: String,
+}
+
+/// Nothing is known about the cache.
+#[derive(Debug, Serialize)]
+struct Cache {}
+
+/// Only the wait is known, the time the response took.
+#[derive(Debug, Serialize)]
+struct Timings {
+    send: u64,
+    wait: u64,
+    receive: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct Pair {
+    name: String,
+    value: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::api::HttpMethod;
+
+    #[test]
+    fn should_export_the_session_as_har() {
+        let mut session = Session::default();
+        let outgoing = Outgoing {
+            method: HttpMethod::Post,
+            url: String::from("http://localhost/users?page=2&token=abc"),
+            headers: vec![
+                (String::from("Authorization"), String::from("Bearer abc")),
+                (
+                    String::from("Content-Type"),
+                    String::from("application/json"),
+                ),
+            ],
+            body: Some(String::from(r#"{"password": "hunter2"}"#)),
+            raw: None,
+            form: Vec::new(),
+        };
+        let received = Received {
+            status: 201,
+            protocol: String::from("HTTP/1.1"),
+            headers: vec![(
+                String::from("Content-Type"),
+                String::from("application/json"),
+            )],
+            body: String::from(r#"{"id": 1}"#),
+            size: 9,
+            elapsed: Duration::from_millis(87),
+        };
+        session.push(outgoing.clone(), Ok(received), 0);
+        let upload = Outgoing {
+            body: None,
+            headers: Vec::new(),
+            form: vec![(String::from("photo"), String::from("@/tmp/rex.png"))],
+            ..outgoing
+        };
+        session.push(upload, Err(String::from("connection refused")), 60);
+        assert_eq!(session.len(), 2);
+
+        let masker = Masker::new([String::from("hunter2")]);
+        let redaction: Redaction =
+            serde_json::from_str(r#"{"patterns": ["token=([^&]+)"]}"#).unwrap();
+        let har: Value =
+            serde_json::from_str(&session.export(&masker, &redaction).unwrap()).unwrap();
+        assert_eq!(har["log"]["version"], "1.2");
+        let entries = har["log"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let created = &entries[0];
+        assert_eq!(created["startedDateTime"], "1970-01-01T00:00:00.000Z");
+        assert_eq!(created["time"], 87);
+        assert_eq!(
+            created["request"]["url"],
+            "http://localhost/users?page=2&token=[redacted]"
+        );
+        assert_eq!(
+            created["request"]["queryString"],
+            json!([{"name": "page", "value": "2"}, {"name": "token", "value": "[redacted]"}])
+        );
+        assert_eq!(created["request"]["headers"][0]["value"], "[redacted]");
+        assert!(!created["request"]["postData"]["text"]
+            .as_str()
+            .unwrap()
+            .contains("hunter2"));
+        assert_eq!(created["response"]["statusText"], "Created");
+        assert_eq!(created["response"]["content"]["text"], r#"{"id": 1}"#);
+
+        let failed = &entries[1];
+        assert_eq!(failed["response"]["status"], 0);
+        assert_eq!(failed["response"]["_error"], "connection refused");
+        assert_eq!(
+            failed["request"]["postData"]["params"],
+            json!([{"name": "photo", "fileName": "/tmp/rex.png"}])
+        );
+    }
+}
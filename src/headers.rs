@@ -0,0 +1,280 @@
+//! Helpers for structured header values. Headers like Cache-Control, Content-Disposition, Link and
+//! the rate limit headers pack several values into one line, they are split into their parts with
+//! a short explanation of each so they can be read at a glance in the header detail popup.
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph, Widget, Wrap},
+};
+
+/// A part of a header value and what it means.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderPart {
+    value: String,
+    explanation: String,
+}
+
+impl HeaderPart {
+    fn new(value: impl Into<String>, explanation: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            explanation: explanation.into(),
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn explanation(&self) -> &str {
+        &self.explanation
+    }
+}
+
+/// A link from a Link header, e.g. `<https://api.example.com/users?page=2>; rel="next"`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    url: String,
+    params: Vec<(String, String)>,
+}
+
+impl Link {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Gets the value of a parameter of the link, such as `rel`.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Checks if the link has the relation, a link can have several like `rel="next last"`.
+    pub fn has_rel(&self, rel: &str) -> bool {
+        self.param("rel")
+            .is_some_and(|rels| rels.split_whitespace().any(|r| r.eq_ignore_ascii_case(rel)))
+    }
+}
+
+/// Reads every link of a Link header value.
+pub fn parse_links(value: &str) -> Vec<Link> {
+    let mut links = Vec::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let url = rest[start + 1..start + end].to_string();
+        rest = &rest[start + end + 1..];
+        // the parameters go until the comma before the next link
+        let params_end = rest.find('<').unwrap_or(rest.len());
+        let params = split_params(rest[..params_end].trim_end().trim_end_matches(','));
+        rest = &rest[params_end..];
+        links.push(Link { url, params });
+    }
+    links
+}
+
+/// Splits the header into its parts with an explanation of each. Returns None for headers that
+/// are not structured.
+pub fn explain(name: &str, value: &str) -> Option<Vec<HeaderPart>> {
+    let name = name.to_lowercase();
+    let parts = match name.as_str() {
+        "cache-control" => value
+            .split(',')
+            .map(str::trim)
+            .filter(|directive| !directive.is_empty())
+            .map(|directive| HeaderPart::new(directive, explain_cache_directive(directive)))
+            .collect(),
+        "content-disposition" => {
+            let (kind, params) = value.split_once(';').unwrap_or((value, ""));
+            let mut parts = vec![HeaderPart::new(
+                kind.trim(),
+                match kind.trim().to_lowercase().as_str() {
+                    "inline" => "shown in the browser",
+                    "attachment" => "downloaded as a file",
+                    "form-data" => "a field of a multipart form",
+                    _ => "unknown disposition",
+                },
+            )];
+            for (key, param) in split_params(params) {
+                let explanation = match key.to_lowercase().as_str() {
+                    "filename" => "name of the file",
+                    "filename*" => "name of the file, with its encoding",
+                    "name" => "name of the form field",
+                    _ => "",
+                };
+                parts.push(HeaderPart::new(format!("{}={}", key, param), explanation));
+            }
+            parts
+        }
+        "link" => parse_links(value)
+            .into_iter()
+            .map(|link| {
+                let explanation = match link.param("rel") {
+                    Some(rel) => format!("{} page", rel),
+                    None => String::from("link without a relation"),
+                };
+                HeaderPart::new(link.url, explanation)
+            })
+            .collect(),
+        "retry-after" => vec![HeaderPart::new(
+            value.trim(),
+            match value.trim().parse::<u64>() {
+                Ok(seconds) => format!("retry in {}", duration(seconds)),
+                Err(_) => String::from("retry after this date"),
+            },
+        )],
+        _ => {
+            let limit = name
+                .strip_prefix("x-")
+                .unwrap_or(&name)
+                .strip_prefix("ratelimit-")
+                .or_else(|| name.strip_prefix("x-rate-limit-"))?;
+            let explanation = match limit {
+                "limit" => String::from("requests allowed in the window"),
+                "remaining" => String::from("requests left in the window"),
+                "used" => String::from("requests made in the window"),
+                "reset" => match value.trim().parse::<u64>() {
+                    // big values are epoch timestamps, small ones are seconds from now
+                    Ok(seconds) if seconds > 1_000_000_000 => {
+                        chrono::DateTime::from_timestamp(seconds as i64, 0)
+                            .map(|time| format!("window resets at {}", time.format("%H:%M:%S UTC")))
+                            .unwrap_or_default()
+                    }
+                    Ok(seconds) => format!("window resets in {}", duration(seconds)),
+                    Err(_) => String::from("when the window resets"),
+                },
+                "policy" => String::from("requests allowed per window, in seconds"),
+                _ => return None,
+            };
+            vec![HeaderPart::new(value.trim(), explanation)]
+        }
+    };
+    Some(parts)
+}
+
+fn explain_cache_directive(directive: &str) -> String {
+    let (name, value) = directive.split_once('=').unwrap_or((directive, ""));
+    let seconds = value.trim_matches('"').parse::<u64>().ok().map(duration);
+    let seconds = seconds.as_deref().unwrap_or(value);
+    match name.trim().to_lowercase().as_str() {
+        "max-age" => format!("fresh for {}", seconds),
+        "s-maxage" => format!("fresh for {} in shared caches", seconds),
+        "stale-while-revalidate" => {
+            format!("may be served stale for {} while revalidating", seconds)
+        }
+        "stale-if-error" => format!("may be served stale for {} on errors", seconds),
+        "no-cache" => String::from("must be revalidated before every use"),
+        "no-store" => String::from("must not be stored at all"),
+        "no-transform" => String::from("must not be modified by proxies"),
+        "must-revalidate" => String::from("must not be used stale"),
+        "proxy-revalidate" => String::from("shared caches must not use it stale"),
+        "public" => String::from("may be stored by shared caches"),
+        "private" => String::from("only the browser may store it"),
+        "immutable" => String::from("will not change while fresh"),
+        _ => String::from("unknown directive"),
+    }
+}
+
+/// Splits `; key=value; key="value"` parameters.
+fn split_params(params: &str) -> Vec<(String, String)> {
+    params
+        .split(';')
+        .filter_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            Some((
+                key.trim().to_string(),
+                value.trim().trim_matches('"').to_string(),
+            ))
+        })
+        .collect()
+}
+
+/// Writes seconds the way people read them, e.g. `1h 30m`.
+fn duration(seconds: u64) -> String {
+    let units = [(86_400, "d"), (3_600, "h"), (60, "m"), (1, "s")];
+    let mut left = seconds;
+    let parts: Vec<String> = units
+        .iter()
+        .filter_map(|(size, unit)| {
+            let amount = left / size;
+            left %= size;
+            (amount > 0).then(|| format!("{}{}", amount, unit))
+        })
+        .collect();
+    if parts.is_empty() {
+        String::from("0s")
+    } else {
+        parts.join(" ")
+    }
+}
+
+/// A popup with the parts of a header and their explanations.
+pub struct HeaderDetail<'a> {
+    name: &'a str,
+    value: &'a str,
+}
+
+impl<'a> HeaderDetail<'a> {
+    pub fn new(name: &'a str, value: &'a str) -> Self {
+        Self { name, value }
+    }
+}
+
+impl Widget for HeaderDetail<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let lines: Vec<Line> = match explain(self.name, self.value) {
+            Some(parts) => parts
+                .iter()
+                .map(|part| {
+                    Line::from(vec![
+                        Span::from(part.value().to_string())
+                            .style(Style::new().fg(Color::LightYellow)),
+                        Span::from(format!("  {}", part.explanation()))
+                            .style(Style::new().fg(Color::DarkGray)),
+                    ])
+                })
+                .collect(),
+            None => vec![Line::from(self.value)],
+        };
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title(self.name))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_explain_structured_headers() {
+        let parts = explain("Cache-Control", "public, max-age=5400, no-transform").unwrap();
+        assert_eq!(
+            parts[1],
+            HeaderPart::new("max-age=5400", "fresh for 1h 30m")
+        );
+        let parts = explain("Content-Disposition", "attachment; filename=\"report.csv\"").unwrap();
+        assert_eq!(parts[1].value(), "filename=report.csv");
+        assert_eq!(
+            explain("X-RateLimit-Remaining", "42").unwrap()[0].explanation(),
+            "requests left in the window"
+        );
+        assert_eq!(explain("Content-Type", "application/json"), None);
+
+        let links = parse_links(
+            "<https://api.example.com/users?page=2>; rel=\"next\", <https://api.example.com/users?page=9>; rel=\"last\"",
+        );
+        assert_eq!(links.len(), 2);
+        assert!(links[0].has_rel("next"));
+        assert_eq!(links[1].url(), "https://api.example.com/users?page=9");
+    }
+}
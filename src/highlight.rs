@@ -0,0 +1,159 @@
+//! Pretty-printing and coloring of JSON response bodies. The body is reindented token by token
+//! instead of going through serde_json::Value, so keys keep the order the server sent them in and
+//! numbers are shown as they were written.
+
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+use serde::de::IgnoredAny;
+
+/// The spaces every level of the pretty JSON is indented by.
+const INDENT: &str = "  ";
+
+/// Reindents the JSON body, one value per line. None when the body is not JSON, e.g. a spilled
+/// body of which only the head and tail are kept.
+pub fn pretty_json(body: &str) -> Option<String> {
+    serde_json::from_str::<IgnoredAny>(body).ok()?;
+    let mut pretty = String::with_capacity(body.len() * 2);
+    let mut depth = 0;
+    let mut chars = body.chars().peekable();
+    let newline = |pretty: &mut String, depth: usize| {
+        pretty.push('\n');
+        pretty.push_str(&INDENT.repeat(depth));
+    };
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                pretty.push(ch);
+                while let Some(ch) = chars.next() {
+                    pretty.push(ch);
+                    match ch {
+                        '\\' => pretty.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '{' | '[' => {
+                pretty.push(ch);
+                while chars.next_if(|ch| ch.is_whitespace()).is_some() {}
+                // empty objects and arrays stay on one line
+                if let Some(close) = chars.next_if(|close| matches!(close, '}' | ']')) {
+                    pretty.push(close);
+                } else {
+                    depth += 1;
+                    newline(&mut pretty, depth);
+                }
+            }
+            '}' | ']' => {
+                depth = depth.saturating_sub(1);
+                newline(&mut pretty, depth);
+                pretty.push(ch);
+            }
+            ',' => {
+                pretty.push(ch);
+                newline(&mut pretty, depth);
+            }
+            ':' => pretty.push_str(": "),
+            ch if ch.is_whitespace() => {}
+            ch => pretty.push(ch),
+        }
+    }
+    Some(pretty)
+}
+
+/// Colors a line of pretty JSON: keys, strings, numbers and `true`, `false` and `null` each get
+/// their own color.
+pub fn highlight_json(line: &str) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut rest = line;
+    while let Some(ch) = rest.chars().next() {
+        let (len, style) = match ch {
+            '"' => {
+                let len = string_len(rest);
+                let is_key = rest[len..].trim_start().starts_with(':');
+                let color = if is_key {
+                    Color::LightBlue
+                } else {
+                    Color::LightGreen
+                };
+                (len, Style::new().fg(color))
+            }
+            '-' | '0'..='9' => {
+                let len = rest
+                    .find(|ch: char| !matches!(ch, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'))
+                    .unwrap_or(rest.len());
+                (len, Style::new().fg(Color::LightYellow))
+            }
+            'a'..='z' => {
+                let len = rest
+                    .find(|ch: char| !ch.is_ascii_lowercase())
+                    .unwrap_or(rest.len());
+                (len, Style::new().fg(Color::LightMagenta))
+            }
+            ch => (ch.len_utf8(), Style::new()),
+        };
+        spans.push(Span::styled(rest[..len].to_string(), style));
+        rest = &rest[len..];
+    }
+    Line::from(spans)
+}
+
+/// Gets the length of the string at the start of the text, quotes included. A string that is not
+/// closed runs to the end.
+fn string_len(text: &str) -> usize {
+    let mut escaped = false;
+    for (position, ch) in text.char_indices().skip(1) {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return position + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_pretty_print_json_keeping_its_order() {
+        let body = r#"{"zone": "eu", "id": 1.50, "tags": [], "meta": {"a\"b": [true, null]}}"#;
+        assert_eq!(
+            pretty_json(body).unwrap(),
+            r#"{
+  "zone": "eu",
+  "id": 1.50,
+  "tags": [],
+  "meta": {
+    "a\"b": [
+      true,
+      null
+    ]
+  }
+}"#
+        );
+        assert_eq!(pretty_json("{\"users\": [1, 2"), None);
+        assert_eq!(pretty_json("not json"), None);
+
+        let line = highlight_json(r#"    "a\"b": [-1.5e3, "x:y", false],"#);
+        let styled: Vec<_> = line
+            .spans
+            .iter()
+            .filter(|span| span.style.fg.is_some())
+            .map(|span| (span.content.as_ref(), span.style.fg.unwrap()))
+            .collect();
+        assert_eq!(
+            styled,
+            [
+                (r#""a\"b""#, Color::LightBlue),
+                ("-1.5e3", Color::LightYellow),
+                (r#""x:y""#, Color::LightGreen),
+                ("false", Color::LightMagenta),
+            ]
+        );
+    }
+}
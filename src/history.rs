@@ -0,0 +1,557 @@
+//! History of the requests that were sent. Entries can be bookmarked with a note, such as
+//! "repro of bug #123", so interesting captures are easy to find among hundreds of runs. Only the
+//! latest responses of every request are kept, bookmarked ones aside. The history is kept in
+//! `history.json` in the data directory.
+//!
+//! Changes are not written to `history.json` right away but appended to `history.log` next to it,
+//! one line each, and synced before they count as saved. Every so often and on load the log is
+//! applied to `history.json`, written atomically, and emptied. A crash then loses at most the
+//! change being written, a line cut short is skipped when the log is replayed, and changes that
+//! made it to `history.json` before the log was emptied are not applied twice thanks to their
+//! sequence numbers.
+
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::{redaction::Redaction, storage};
+
+/// The file in the data directory where the history is saved.
+const HISTORY_FILE: &str = "history.json";
+/// The extension of the log of the changes not in the history file yet, next to it.
+const LOG_EXTENSION: &str = "log";
+/// How many changes the log holds before they are written to the history file.
+const CHECKPOINT_EVERY: u64 = 50;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// The name of the collection of the request, empty for entries saved before it was kept.
+    #[serde(default)]
+    collection: String,
+    request: String,
+    method: String,
+    url: String,
+    /// None when the request failed before a response came back.
+    status: Option<u16>,
+    /// How long the response took to come back, in milliseconds.
+    #[serde(default)]
+    elapsed_ms: Option<u64>,
+    /// When the request was sent, in seconds since epoch.
+    sent_at: i64,
+    /// The note of the bookmark, an empty note is still a bookmark.
+    #[serde(default)]
+    bookmark: Option<String>,
+    /// What was overridden for this send only, e.g. `X-Debug: 1`.
+    #[serde(default)]
+    overridden: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn new(
+        collection: String,
+        request: String,
+        method: String,
+        url: String,
+        status: Option<u16>,
+        elapsed: Option<Duration>,
+        sent_at: i64,
+    ) -> Self {
+        Self {
+            collection,
+            request,
+            method,
+            url,
+            status,
+            elapsed_ms: elapsed.map(|elapsed| elapsed.as_millis() as u64),
+            sent_at,
+            bookmark: None,
+            overridden: None,
+        }
+    }
+
+    /// Whether the entry is a send of the request of the collection.
+    pub fn is_of(&self, collection: &str, request: &str) -> bool {
+        self.collection == collection && self.request == request
+    }
+
+    pub fn bookmark(&self) -> Option<&str> {
+        self.bookmark.as_deref()
+    }
+
+    pub fn set_overridden(&mut self, overridden: Option<String>) {
+        self.overridden = overridden;
+    }
+
+    /// Gets a single line describing the entry for lists.
+    pub fn summary(&self) -> String {
+        let sent_at = DateTime::from_timestamp(self.sent_at, 0)
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        let status = self
+            .status
+            .map(|status| status.to_string())
+            .unwrap_or_else(|| String::from("---"));
+        let elapsed = self
+            .elapsed_ms
+            .map(|elapsed| format!(" in {} ms", elapsed))
+            .unwrap_or_default();
+        let overridden = self
+            .overridden
+            .as_ref()
+            .map(|overridden| format!(", with {}", overridden))
+            .unwrap_or_default();
+        format!(
+            "{} {} {} {}{} ({}{})",
+            sent_at, status, self.method, self.url, elapsed, self.request, overridden
+        )
+    }
+}
+
+/// What the history file holds: the entries along with the sequence number of the last change
+/// applied to them. Files written before the log only had the entries.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Saved {
+    Checkpoint {
+        applied: u64,
+        entries: Vec<HistoryEntry>,
+    },
+    Entries(Vec<HistoryEntry>),
+}
+
+/// A change to the history, as written to the log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+enum Change {
+    Record {
+        entry: HistoryEntry,
+    },
+    /// The entry as it was before its bookmark changed, since indexes shift as entries are
+    /// dropped.
+    Bookmark {
+        entry: HistoryEntry,
+        note: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Logged {
+    sequence: u64,
+    #[serde(flatten)]
+    change: Change,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+    /// Where the history is saved. None keeps it in memory only.
+    path: Option<PathBuf>,
+    /// How many entries of each request are kept, not counting bookmarked ones. 0 keeps them all.
+    per_request: usize,
+    /// The sequence number of the last change applied to the entries.
+    applied: u64,
+    /// How many changes are in the log, not yet in the history file.
+    logged: u64,
+    /// What is redacted from entries before they are saved.
+    redaction: Redaction,
+}
+
+impl History {
+    /// Loads the history from the data directory, keeping the latest entries of every request. A
+    /// missing or broken file starts a new history.
+    pub fn load(per_request: usize, redaction: Redaction) -> Self {
+        let path = data_dir().map(|dir| dir.join(HISTORY_FILE));
+        Self {
+            redaction,
+            ..Self::load_from(path, per_request)
+        }
+    }
+
+    /// Loads the history file and replays the changes of its log that are not in it yet, then
+    /// writes them to the history file so the log starts empty.
+    fn load_from(path: Option<PathBuf>, per_request: usize) -> Self {
+        let saved = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok());
+        let (applied, entries) = match saved {
+            Some(Saved::Checkpoint { applied, entries }) => (applied, entries),
+            Some(Saved::Entries(entries)) => (0, entries),
+            None => (0, Vec::new()),
+        };
+        let mut history = Self {
+            entries,
+            path,
+            per_request,
+            applied,
+            logged: 0,
+            redaction: Redaction::default(),
+        };
+        let log = history
+            .path
+            .as_deref()
+            .and_then(|path| fs::read_to_string(log_path(path)).ok())
+            .unwrap_or_default();
+        if !log.is_empty() && !log.ends_with('\n') {
+            if let Some(path) = history.path.as_deref() {
+                // appending after it would run the next change into it, the error shows up then
+                let _ = end_last_line(&log_path(path), &log);
+            }
+        }
+        let checkpointed = history.applied;
+        // a line cut short by a crash does not parse and is skipped
+        for logged in log
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Logged>(line).ok())
+            .filter(|logged| logged.sequence > checkpointed)
+        {
+            history.apply(logged.change);
+            history.applied = logged.sequence;
+        }
+        if history.applied > checkpointed {
+            // the log is replayed again on the next load when this fails
+            let _ = history.checkpoint();
+        }
+        history
+    }
+
+    /// Adds an entry for a request that was just sent, dropping the oldest entries of the request
+    /// that are not bookmarked when it has too many.
+    pub fn record(&mut self, mut entry: HistoryEntry) -> io::Result<()> {
+        entry.url = self.redaction.redact(&entry.url);
+        entry.overridden = entry
+            .overridden
+            .map(|overridden| self.redaction.redact_line(&overridden));
+        self.change(Change::Record { entry })
+    }
+
+    fn push(&mut self, entry: HistoryEntry) {
+        if self.per_request > 0 {
+            let kept = self
+                .entries
+                .iter()
+                .filter(|kept| {
+                    kept.bookmark.is_none() && kept.is_of(&entry.collection, &entry.request)
+                })
+                .count();
+            let mut excess = (kept + 1).saturating_sub(self.per_request);
+            self.entries.retain(|kept| {
+                let drop = excess > 0
+                    && kept.bookmark.is_none()
+                    && kept.is_of(&entry.collection, &entry.request);
+                if drop {
+                    excess -= 1;
+                }
+                !drop
+            });
+        }
+        self.entries.push(entry);
+    }
+
+    /// Gets the entries with their index, newest first. Only bookmarked entries are returned when
+    /// bookmarks_only is set, and only the ones of a request when its collection and name are
+    /// given.
+    pub fn entries(
+        &self,
+        bookmarks_only: bool,
+        request: Option<(&str, &str)>,
+    ) -> Vec<(usize, &HistoryEntry)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, entry)| !bookmarks_only || entry.bookmark.is_some())
+            .filter(|(_, entry)| {
+                request.is_none_or(|(collection, request)| entry.is_of(collection, request))
+            })
+            .collect()
+    }
+
+    /// Bookmarks the entry with the note, or removes its bookmark when the note is None.
+    pub fn set_bookmark(&mut self, index: usize, note: Option<String>) -> io::Result<()> {
+        match self.entries.get(index) {
+            Some(entry) => self.change(Change::Bookmark {
+                entry: entry.clone(),
+                note,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Applies the change and appends it to the log, writing the history file once the log has
+    /// enough changes.
+    fn change(&mut self, change: Change) -> io::Result<()> {
+        self.apply(change.clone());
+        self.applied += 1;
+        let Some(path) = self.path.as_deref() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut line = serde_json::to_string(&Logged {
+            sequence: self.applied,
+            change,
+        })?;
+        line.push('\n');
+        let mut log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_path(path))?;
+        log.write_all(line.as_bytes())?;
+        log.sync_data()?;
+        self.logged += 1;
+        if self.logged >= CHECKPOINT_EVERY {
+            self.checkpoint()?;
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, change: Change) {
+        match change {
+            Change::Record { entry } => self.push(entry),
+            Change::Bookmark { entry, note } => {
+                if let Some(kept) = self.entries.iter_mut().rev().find(|kept| **kept == entry) {
+                    kept.bookmark = note;
+                }
+            }
+        }
+    }
+
+    /// Writes the entries to the history file and empties the log. The sequence number saved
+    /// along keeps the changes in the log from being applied twice when emptying it fails.
+    fn checkpoint(&mut self) -> io::Result<()> {
+        let Some(path) = self.path.as_deref() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = serde_json::to_string_pretty(&Saved::Checkpoint {
+            applied: self.applied,
+            entries: self.entries.clone(),
+        })?;
+        storage::overwrite(path, &contents).map_err(|err| io::Error::other(err.to_string()))?;
+        fs::File::create(log_path(path))?;
+        self.logged = 0;
+        Ok(())
+    }
+}
+
+/// Ends the last line of the log, which a crash left without its line break. It is cut off when
+/// it is not a whole change.
+fn end_last_line(path: &Path, log: &str) -> io::Result<()> {
+    let start = log.rfind('\n').map_or(0, |index| index + 1);
+    let mut file = OpenOptions::new().append(true).open(path)?;
+    if serde_json::from_str::<Logged>(&log[start..]).is_ok() {
+        file.write_all(b"\n")?;
+    } else {
+        file.set_len(start as u64)?;
+    }
+    file.sync_data()
+}
+
+fn log_path(path: &Path) -> PathBuf {
+    path.with_extension(LOG_EXTENSION)
+}
+
+/// Gets the directory where hermes keeps its data, `$XDG_DATA_HOME/hermes` or
+/// `~/.local/share/hermes`. When it can't be written, e.g. a read-only home on CI, the data goes to
+/// a private folder in the temp directory instead so it lasts for the session, rather than every
+/// save failing. None when neither can be used, and nothing is saved.
+pub fn data_dir() -> Option<PathBuf> {
+    static DATA_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+    DATA_DIR
+        .get_or_init(|| {
+            let dir = preferred_data_dir()?;
+            if storage::is_writable(&dir) {
+                Some(dir)
+            } else {
+                session_data_dir().ok()
+            }
+        })
+        .clone()
+}
+
+/// Creates a folder only this user can read in the temp directory. Its name is new for every
+/// session, since the temp directory is shared and anyone could have made a folder with a name
+/// known in advance.
+fn session_data_dir() -> io::Result<PathBuf> {
+    let dir = env::temp_dir().join(format!("hermes-{}", uuid::Uuid::new_v4().simple()));
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(&dir)?;
+    Ok(dir)
+}
+
+/// Gets the data directory hermes would rather use when it can't be written.
+pub fn unwritable_data_dir() -> Option<PathBuf> {
+    preferred_data_dir().filter(|dir| data_dir().as_ref() != Some(dir))
+}
+
+fn preferred_data_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(base.join("hermes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external;
+
+    fn sent(request: &str, status: u16) -> HistoryEntry {
+        HistoryEntry::new(
+            String::from("users"),
+            String::from(request),
+            String::from("POST"),
+            String::from("http://localhost/users"),
+            Some(status),
+            Some(Duration::from_millis(120)),
+            1_700_000_000,
+        )
+    }
+
+    #[test]
+    fn should_create_a_private_data_dir_for_the_session() {
+        let dir = session_data_dir().unwrap();
+        let other = session_data_dir().unwrap();
+        assert_ne!(dir, other);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        fs::remove_dir(dir).unwrap();
+        fs::remove_dir(other).unwrap();
+    }
+
+    #[test]
+    fn should_filter_bookmarked_entries() {
+        let mut history = History::default();
+        for status in [200, 500, 201] {
+            history.record(sent("create user", status)).unwrap();
+        }
+        history
+            .set_bookmark(1, Some(String::from("repro of bug #123")))
+            .unwrap();
+
+        let bookmarked = history.entries(true, None);
+        assert_eq!(bookmarked.len(), 1);
+        assert_eq!(bookmarked[0].0, 1);
+        assert_eq!(bookmarked[0].1.bookmark(), Some("repro of bug #123"));
+        assert_eq!(history.entries(false, None)[0].0, 2);
+        assert_eq!(
+            history.entries(false, None)[0].1.summary(),
+            "2023-11-14 22:13:20 201 POST http://localhost/users in 120 ms (create user)"
+        );
+        let mut overridden = sent("create user", 200);
+        overridden.set_overridden(Some(String::from("X-Debug: 1")));
+        assert!(overridden
+            .summary()
+            .ends_with("in 120 ms (create user, with X-Debug: 1)"));
+        let mut authorized = sent("create user", 200);
+        authorized.set_overridden(Some(String::from("Authorization: Bearer abc")));
+        history.record(authorized).unwrap();
+        assert!(history.entries(false, None)[0]
+            .1
+            .summary()
+            .ends_with("(create user, with Authorization: [redacted])"));
+
+        history.set_bookmark(1, None).unwrap();
+        assert!(history.entries(true, None).is_empty());
+    }
+
+    #[test]
+    fn should_keep_the_latest_entries_of_every_request() {
+        let mut history = History {
+            per_request: 2,
+            ..Default::default()
+        };
+        history.record(sent("create user", 500)).unwrap();
+        history
+            .set_bookmark(0, Some(String::from("the 500")))
+            .unwrap();
+        for status in [200, 201, 202] {
+            history.record(sent("create user", status)).unwrap();
+        }
+        history.record(sent("list users", 200)).unwrap();
+
+        let statuses: Vec<_> = history
+            .entries(false, Some(("users", "create user")))
+            .iter()
+            .map(|(_, entry)| entry.status)
+            .collect();
+        assert_eq!(statuses, [Some(202), Some(201), Some(500)]);
+        assert_eq!(
+            history.entries(false, Some(("users", "list users"))).len(),
+            1
+        );
+        assert!(history
+            .entries(false, Some(("teams", "create user")))
+            .is_empty());
+    }
+
+    #[test]
+    fn should_replay_the_log_after_a_crash() {
+        let path = external::temp_path("history", "json");
+        let mut history = History::load_from(Some(path.clone()), 0);
+        history.record(sent("create user", 500)).unwrap();
+        history.record(sent("create user", 201)).unwrap();
+        history
+            .set_bookmark(0, Some(String::from("the 500")))
+            .unwrap();
+        assert!(!path.exists());
+
+        // a crash while appending the next change leaves half a line
+        let mut log = OpenOptions::new()
+            .append(true)
+            .open(log_path(&path))
+            .unwrap();
+        log.write_all(br#"{"sequence":4,"change":"rec"#).unwrap();
+        let replayed = History::load_from(Some(path.clone()), 0);
+        assert_eq!(replayed.entries, history.entries);
+        assert_eq!(replayed.applied, 3);
+        assert_eq!(fs::read_to_string(log_path(&path)).unwrap(), "");
+
+        // changes already in the history file are not applied twice
+        fs::write(
+            log_path(&path),
+            r#"{"sequence":2,"change":"record","entry":{"request":"list users","method":"GET","url":"/","status":200,"sent_at":0}}"#,
+        )
+        .unwrap();
+        assert_eq!(History::load_from(Some(path.clone()), 0).entries.len(), 2);
+
+        let mut history = History::load_from(Some(path.clone()), 0);
+        for _ in 0..CHECKPOINT_EVERY {
+            history.record(sent("list users", 200)).unwrap();
+        }
+        assert_eq!(fs::read_to_string(log_path(&path)).unwrap(), "");
+        assert_eq!(History::load_from(Some(path.clone()), 0).entries.len(), 52);
+
+        // half a line with nothing to replay is cut off, so the next change is read back
+        fs::write(log_path(&path), r#"{"sequence":53,"change":"rec"#).unwrap();
+        let mut torn = History::load_from(Some(path.clone()), 0);
+        torn.record(sent("delete user", 204)).unwrap();
+        let reloaded = History::load_from(Some(path.clone()), 0);
+        assert_eq!(reloaded.entries.len(), 53);
+        assert_eq!(reloaded.entries[52].request, "delete user");
+        fs::remove_file(log_path(&path)).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,281 @@
+//! Imports of collections exported from other tools, converted to a collection and written as
+//! hermes files: `collection.hermes` with the name, variables and environments of the collection,
+//! and a file per request in the folders of the requests. What can't be converted, such as
+//! scripts, is left out and reported as a warning.
+
+pub mod bruno;
+pub mod insomnia;
+pub mod postman;
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+use crate::{
+    api::Collection,
+    source,
+    syntax::{write_name, write_value},
+};
+
+/// The file the collection block is written to.
+const COLLECTION_FILE: &str = "collection.hermes";
+/// The identifier of the variables block of the collection.
+const VARIABLES_BLOCK: &str = "collection-variables";
+/// The blocks written for a request, by the suffix of their identifier.
+const REQUEST_BLOCKS: [&str; 4] = ["", "-headers", "-queries", "-body"];
+
+/// The tools collections can be imported from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// A Postman collection v2.1, as exported from Postman.
+    Postman,
+    /// An Insomnia export, format 4.
+    Insomnia,
+    /// The folder of a Bruno collection, with its `bruno.json`.
+    Bruno,
+}
+
+impl Format {
+    /// Tells which tool the file or folder comes from, None when it is none of the supported ones.
+    pub fn detect(path: &Path) -> Option<Self> {
+        if path.is_dir() {
+            return bruno::is_bruno(path).then_some(Format::Bruno);
+        }
+        let contents = fs::read_to_string(path).ok()?;
+        if postman::is_postman(&contents) {
+            Some(Format::Postman)
+        } else if insomnia::is_insomnia(&contents) {
+            Some(Format::Insomnia)
+        } else {
+            None
+        }
+    }
+
+    /// Converts the export to a collection.
+    pub fn convert(self, path: &Path) -> Result<Imported, String> {
+        if self == Format::Bruno {
+            return bruno::convert(path);
+        }
+        let contents =
+            fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        match self {
+            Format::Postman => postman::convert(&contents),
+            Format::Insomnia => insomnia::convert(&contents),
+            Format::Bruno => unreachable!("Bruno collections are folders"),
+        }
+    }
+}
+
+/// A collection converted from another tool, with what was left out or changed on the way.
+#[derive(Debug)]
+pub struct Imported {
+    pub collection: Collection,
+    pub warnings: Vec<String>,
+}
+
+/// Gets the hermes files of the collection, by their path relative to the folder of the
+/// collection. Requests get identifiers that are unique in the collection, since blocks are
+/// referenced by their identifier across files.
+pub fn to_files(collection: &Collection) -> Vec<(PathBuf, String)> {
+    let mut files = Vec::new();
+    let mut taken = HashSet::from([VARIABLES_BLOCK.to_string()]);
+    let environments = collection.environment_names();
+    taken.extend(environments.iter().map(|name| name.to_string()));
+    let mut contents = format!(
+        "collection {{\n    name {}\n",
+        write_value(&collection.name())
+    );
+    if !collection.variables().is_empty() {
+        contents.push_str(&format!("    variables {}\n", VARIABLES_BLOCK));
+    }
+    for key in collection.secret_keys() {
+        contents.push_str(&format!("    secret {}\n", write_value(key)));
+    }
+    contents.push_str("}\n");
+    for name in environments {
+        let mut entries: Vec<_> = collection.environment(name).into_iter().flatten().collect();
+        entries.sort();
+        contents.push_str(&format!("\nenvironment as {} {{\n", name));
+        for (key, value) in entries {
+            contents.push_str(&format!("    {} {}\n", write_name(key), write_value(value)));
+        }
+        contents.push_str("}\n");
+    }
+    if !collection.variables().is_empty() {
+        let mut variables: Vec<_> = collection.variables().iter().collect();
+        variables.sort();
+        contents.push_str(&format!("\nvariables as {} {{\n", VARIABLES_BLOCK));
+        for (key, value) in variables {
+            contents.push_str(&format!("    {} {}\n", write_name(key), write_value(value)));
+        }
+        contents.push_str("}\n");
+    }
+    files.push((PathBuf::from(COLLECTION_FILE), contents));
+
+    for request in collection.iter() {
+        let base = request.get_identifier();
+        let mut identifier = base.clone();
+        let mut count = 1;
+        while REQUEST_BLOCKS
+            .iter()
+            .any(|suffix| taken.contains(&format!("{}{}", identifier, suffix)))
+        {
+            count += 1;
+            identifier = format!("{}-{}", base, count);
+        }
+        taken.extend(
+            REQUEST_BLOCKS
+                .iter()
+                .map(|suffix| format!("{}{}", identifier, suffix)),
+        );
+        let mut request = request.clone();
+        request.set_identifier(Some(identifier.clone()));
+        let path = Path::new(request.get_folder()).join(format!("{}.hermes", identifier));
+        files.push((path, source::write_request(&request)));
+    }
+    files
+}
+
+/// Writes the hermes files of the collection into the folder. Existing files are never replaced,
+/// nothing is written when one of them already exists. Returns the paths of the new files.
+pub fn write(collection: &Collection, dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let files: Vec<_> = to_files(collection)
+        .into_iter()
+        .map(|(path, contents)| (dir.join(path), contents))
+        .collect();
+    if let Some((path, _)) = files.iter().find(|(path, _)| path.exists()) {
+        return Err(format!("{} already exists", path.display()));
+    }
+    for (path, contents) in &files {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| format!("{}: {}", parent.display(), err))?;
+        }
+        fs::write(path, contents).map_err(|err| format!("{}: {}", path.display(), err))?;
+    }
+    Ok(files.into_iter().map(|(path, _)| path).collect())
+}
+
+/// Adds an environment to the collection without changing the active one. Its name is turned into
+/// an identifier, since environments are written as blocks named after them. Returns the name.
+pub fn add_environment(
+    collection: &mut Collection,
+    name: &str,
+    entries: impl IntoIterator<Item = (String, String)>,
+) -> String {
+    let name = source::identifier_of(name);
+    let active = collection.active_environment_name().to_string();
+    if collection.environment(&name).is_none() {
+        collection.new_environment(name.clone());
+    }
+    collection.set_active_environment(name.clone());
+    for (key, value) in entries {
+        collection.add_environment_entry(key, value);
+    }
+    collection.set_active_environment(active);
+    name
+}
+
+/// Replaces the backticks of a value, which hermes values can't hold, with quotes, warning about
+/// it with what the value is.
+pub fn without_backticks(text: &str, what: &str, warnings: &mut Vec<String>) -> String {
+    if !text.contains('`') {
+        return text.to_string();
+    }
+    warnings.push(format!("the backticks of {} were replaced by '", what));
+    text.replace('`', "'")
+}
+
+/// Gets the array under the key, empty when it is missing.
+pub fn array<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value[key].as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+/// Gets a value as text, exports allowing numbers and booleans where text is expected.
+pub fn text_of(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// Turns the name of a folder into the name of a directory, without separators and not hidden.
+pub fn folder_of(name: &str) -> String {
+    let folder = name.replace(['/', '\\'], "-");
+    match folder.trim().trim_start_matches('.') {
+        "" => source::identifier_of(name),
+        folder => folder.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        api::{HttpMethod, Request},
+        external, loader,
+        parser::ScanSettings,
+    };
+
+    #[test]
+    fn should_write_a_collection_that_loads_back() {
+        let mut collection = Collection::default();
+        collection.set_name(String::from("Petstore"));
+        collection.add_variable(String::from("BASE_URL"), String::from("http://localhost"));
+        let staging = [(String::from("BASE_URL"), String::from("https://staging"))];
+        assert_eq!(
+            add_environment(&mut collection, "Staging", staging),
+            "staging"
+        );
+        collection.mark_secret(String::from("TOKEN"));
+        for (name, folder) in [("List pets", "pets"), ("List pets", "pets/admin")] {
+            let mut request = Request::new(
+                String::from(name),
+                HttpMethod::Get,
+                String::from("{{BASE_URL}}/pets"),
+                None,
+                None,
+                HashMap::from([(String::from("Accept"), String::from("application/json"))]),
+            );
+            request.set_folder(String::from(folder));
+            collection.add_request(request);
+        }
+
+        let dir = external::temp_path("import", "d");
+        let written = write(&collection, &dir).unwrap();
+        assert_eq!(
+            written,
+            [
+                dir.join("collection.hermes"),
+                dir.join("pets/list-pets.hermes"),
+                dir.join("pets/admin/list-pets-2.hermes"),
+            ]
+        );
+        let loaded = loader::load_collection(&dir, &ScanSettings::default()).unwrap();
+        assert_eq!(loaded.name(), "Petstore");
+        assert_eq!(loaded.variables()["BASE_URL"], "http://localhost");
+        assert_eq!(
+            loaded.environment("staging").unwrap()["BASE_URL"],
+            "https://staging"
+        );
+        assert!(loaded.is_secret("TOKEN"));
+        let mut folders: Vec<_> = loaded.iter().map(Request::get_folder).collect();
+        folders.sort();
+        assert_eq!(folders, ["pets", "pets/admin"]);
+        assert!(loaded
+            .iter()
+            .all(|request| request.get_headers()["Accept"] == "application/json"));
+
+        assert_eq!(
+            write(&collection, &dir),
+            Err(format!("{} already exists", written[0].display()))
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
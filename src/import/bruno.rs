@@ -0,0 +1,559 @@
+//! Conversion of Bruno collections, read from their folder: `bruno.json` with the name of the
+//! collection, a `.bru` file per request in the folders of the collection, `folder.bru` and
+//! `collection.bru` with what the requests in them inherit, and `environments/*.bru`. Requests
+//! keep their method, url, query parameters, headers, body and auth, and get the headers of their
+//! folders and collection like Bruno sends them. Bruno writes variables as `{{name}}` like hermes,
+//! so they are kept as they are. Scripts and tests have no equivalent and are left out.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+use crate::{
+    api::{Collection, FormField, HttpBody, HttpMethod, QueryParam, Request},
+    auth::{Auth, KeyLocation},
+};
+
+use super::{add_environment, without_backticks, Imported};
+
+/// The file that makes a folder a Bruno collection.
+const CONFIG_FILE: &str = "bruno.json";
+/// The file with what every request of the collection inherits.
+const COLLECTION_FILE: &str = "collection.bru";
+/// The file with what the requests of a folder inherit.
+const FOLDER_FILE: &str = "folder.bru";
+/// The folder of the environments, at the root of the collection.
+const ENVIRONMENTS_DIR: &str = "environments";
+/// The blocks of a request holding the method and url, by the method.
+const METHODS: [&str; 7] = ["get", "post", "put", "patch", "delete", "options", "head"];
+
+/// Tells whether the folder is a Bruno collection.
+pub fn is_bruno(dir: &Path) -> bool {
+    dir.join(CONFIG_FILE).is_file()
+}
+
+/// Converts the Bruno collection in the folder to a collection.
+pub fn convert(dir: &Path) -> Result<Imported, String> {
+    let config: Value = serde_json::from_str(&read(&dir.join(CONFIG_FILE))?)
+        .map_err(|err| format!("{} is not JSON: {}", CONFIG_FILE, err))?;
+    let mut converter = Converter::default();
+    let name = config["name"].as_str().unwrap_or("bruno");
+    converter.collection.set_name(name.to_string());
+
+    let inherited = match dir.join(COLLECTION_FILE) {
+        path if path.is_file() => {
+            let blocks = parse(&read(&path)?);
+            for (key, value, enabled) in pairs(&blocks, "vars:pre-request") {
+                if enabled {
+                    let value = converter.text(&value, &format!("the variable {}", key));
+                    converter.collection.add_variable(key, value);
+                }
+            }
+            converter.warn_about_scripts(&blocks, "the collection");
+            converter.inherited(&blocks, &Inherited::default(), "the collection")
+        }
+        _ => Inherited::default(),
+    };
+    converter.environments(&dir.join(ENVIRONMENTS_DIR))?;
+    converter.folder(dir, "", &inherited)?;
+    Ok(Imported {
+        collection: converter.collection,
+        warnings: converter.warnings,
+    })
+}
+
+/// A block of a `.bru` file, e.g. `headers { ... }`, with the lines in it unindented.
+#[derive(Debug, PartialEq)]
+struct Block {
+    name: String,
+    lines: Vec<String>,
+}
+
+/// Reads the blocks of a `.bru` file. Blocks start with their name and `{`, or `[` for lists,
+/// and end with the `}` or `]` at the start of a line.
+fn parse(contents: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut lines = contents.lines();
+    while let Some(line) = lines.next() {
+        let close = match line.chars().last() {
+            Some('{') => "}",
+            Some('[') => "]",
+            _ => continue,
+        };
+        let name = &line[..line.len() - 1];
+        if name.trim().is_empty() || name.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let inside = lines
+            .by_ref()
+            .take_while(|line| line.trim_end() != close)
+            .map(|line| {
+                line.strip_prefix("  ")
+                    .unwrap_or(line.trim_start())
+                    .to_string()
+            })
+            .collect();
+        blocks.push(Block {
+            name: name.trim().to_string(),
+            lines: inside,
+        });
+    }
+    blocks
+}
+
+fn block<'a>(blocks: &'a [Block], name: &str) -> Option<&'a Block> {
+    blocks.iter().find(|block| block.name == name)
+}
+
+/// Gets the `key: value` lines of a block, with whether they are enabled, disabled ones starting
+/// with `~`.
+fn pairs(blocks: &[Block], name: &str) -> Vec<(String, String, bool)> {
+    let Some(block) = block(blocks, name) else {
+        return Vec::new();
+    };
+    block
+        .lines
+        .iter()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            let key = key.trim();
+            let (key, enabled) = match key.strip_prefix('~') {
+                Some(key) => (key, false),
+                None => (key, true),
+            };
+            Some((key.to_string(), value.trim().to_string(), enabled))
+        })
+        .filter(|(key, _, _)| !key.is_empty())
+        .collect()
+}
+
+/// Gets the value of an enabled `key: value` line of a block.
+fn value(blocks: &[Block], name: &str, key: &str) -> Option<String> {
+    pairs(blocks, name)
+        .into_iter()
+        .find(|(k, _, enabled)| *enabled && k == key)
+        .map(|(_, value, _)| value)
+}
+
+/// Gets the lines of a block as text.
+fn text_of(blocks: &[Block], name: &str) -> Option<String> {
+    let text = block(blocks, name)?.lines.join("\n");
+    Some(text.trim().to_string()).filter(|text| !text.is_empty())
+}
+
+/// What the requests of a folder get from the folder and the ones around it.
+#[derive(Debug, Default, Clone)]
+struct Inherited {
+    headers: HashMap<String, String>,
+    auth: Option<Auth>,
+}
+
+#[derive(Default)]
+struct Converter {
+    collection: Collection,
+    warnings: Vec<String>,
+}
+
+impl Converter {
+    /// Adds what the blocks of a `folder.bru` or `collection.bru` set to what is inherited.
+    fn inherited(&mut self, blocks: &[Block], inherited: &Inherited, name: &str) -> Inherited {
+        let mut headers = inherited.headers.clone();
+        for (key, value, enabled) in pairs(blocks, "headers") {
+            if enabled {
+                let value = self.text(&value, &format!("the header {} of {}", key, name));
+                headers.insert(key, value);
+            }
+        }
+        let auth = match value(blocks, "auth", "mode") {
+            Some(mode) => self.auth(blocks, &mode, inherited.auth.as_ref(), name),
+            None => inherited.auth.clone(),
+        };
+        Inherited { headers, auth }
+    }
+
+    /// Converts the requests of a folder, then the folders in it.
+    fn folder(&mut self, dir: &Path, folder: &str, inherited: &Inherited) -> Result<(), String> {
+        let mut requests = Vec::new();
+        let mut folders = Vec::new();
+        let entries = fs::read_dir(dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if path.is_dir() {
+                let skipped = file_name.starts_with('.')
+                    || file_name == "node_modules"
+                    || folder.is_empty() && file_name == ENVIRONMENTS_DIR;
+                if !skipped {
+                    folders.push((file_name, path));
+                }
+            } else if file_name.ends_with(".bru")
+                && file_name != FOLDER_FILE
+                && file_name != COLLECTION_FILE
+            {
+                let blocks = parse(&read(&path)?);
+                let seq = value(&blocks, "meta", "seq").and_then(|seq| seq.parse::<f64>().ok());
+                requests.push((seq.unwrap_or(f64::MAX), file_name, blocks));
+            }
+        }
+        requests.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+        for (_, file_name, blocks) in requests {
+            let name = value(&blocks, "meta", "name")
+                .unwrap_or_else(|| file_name.trim_end_matches(".bru").to_string());
+            if let Some(mut request) = self.request(&name, &blocks, inherited) {
+                request.set_folder(folder.to_string());
+                self.collection.add_request(request);
+            }
+        }
+
+        folders.sort();
+        for (file_name, path) in folders {
+            let inherited = match path.join(FOLDER_FILE) {
+                folder_file if folder_file.is_file() => {
+                    let blocks = parse(&read(&folder_file)?);
+                    let name = value(&blocks, "meta", "name").unwrap_or(file_name.clone());
+                    self.warn_about_scripts(&blocks, &name);
+                    self.inherited(&blocks, inherited, &name)
+                }
+                _ => inherited.clone(),
+            };
+            let folder = match folder {
+                "" => file_name,
+                parent => format!("{}/{}", parent, file_name),
+            };
+            self.folder(&path, &folder, &inherited)?;
+        }
+        Ok(())
+    }
+
+    fn request(&mut self, name: &str, blocks: &[Block], inherited: &Inherited) -> Option<Request> {
+        self.warn_about_scripts(blocks, name);
+        let Some(method_block) = blocks.iter().find(|block| METHODS.contains(&&*block.name)) else {
+            self.warnings.push(format!(
+                "{} was left out, hermes can only send http requests",
+                name
+            ));
+            return None;
+        };
+        let Ok(method) = method_block.name.parse::<HttpMethod>() else {
+            self.warnings.push(format!(
+                "{} was left out, hermes can't send {} requests",
+                name,
+                method_block.name.to_uppercase()
+            ));
+            return None;
+        };
+        let url = value(blocks, &method_block.name, "url").unwrap_or_default();
+        let url = self.text(&url, &format!("the url of {}", name));
+        // the query string of the url is in the query block too, disabled parameters included
+        let url = url.split_once('?').map_or(url.as_str(), |(base, _)| base);
+        let queries = pairs(blocks, "params:query")
+            .into_iter()
+            .map(|(key, value, enabled)| {
+                let value = self.text(&value, &format!("the query parameter {} of {}", key, name));
+                QueryParam::new(key, value, enabled)
+            })
+            .collect();
+        let mut headers = inherited.headers.clone();
+        for (key, value, enabled) in pairs(blocks, "headers") {
+            if enabled {
+                let value = self.text(&value, &format!("the header {} of {}", key, name));
+                headers.insert(key, value);
+            }
+        }
+        let auth = match value(blocks, &method_block.name, "auth") {
+            Some(mode) => self.auth(blocks, &mode, inherited.auth.as_ref(), name),
+            None => inherited.auth.clone(),
+        };
+        let mut request = Request::new(
+            name.to_string(),
+            method,
+            url.to_string(),
+            None,
+            None,
+            headers,
+        );
+        request.set_queries(queries);
+        request.set_auth(auth);
+        if let Some(docs) = text_of(blocks, "docs") {
+            let what = format!("the docs of {}", name);
+            request.set_description(Some(self.text(&docs, &what)));
+        }
+        if let Some(body) = value(blocks, &method_block.name, "body") {
+            self.body(name, blocks, &body, &mut request);
+        }
+        Some(request)
+    }
+
+    fn body(&mut self, name: &str, blocks: &[Block], mode: &str, request: &mut Request) {
+        let what = format!("the body of {}", name);
+        match mode {
+            "none" => {}
+            "json" | "text" | "xml" => {
+                let Some(text) = text_of(blocks, &format!("body:{}", mode)) else {
+                    return;
+                };
+                request.set_body_type(Some(if mode == "json" {
+                    HttpBody::Json
+                } else {
+                    HttpBody::Text
+                }));
+                request.set_body(Some(self.text(&text, &what)));
+            }
+            "formUrlEncoded" | "multipartForm" => {
+                let (block, body_type) = if mode == "formUrlEncoded" {
+                    ("body:form-urlencoded", HttpBody::FormUrlEncoded)
+                } else {
+                    ("body:multipart-form", HttpBody::MultipartForm)
+                };
+                let form = pairs(blocks, block)
+                    .into_iter()
+                    .map(|(key, value, enabled)| {
+                        // multipart files are written as their path after an @
+                        let value = match value
+                            .strip_prefix("@file(")
+                            .and_then(|path| path.strip_suffix(')'))
+                        {
+                            Some(path) => format!("@{}", path),
+                            None => value,
+                        };
+                        let value = self.text(&value, &format!("the field {} of {}", key, name));
+                        FormField::new(key, value, enabled)
+                    })
+                    .collect();
+                request.set_body_type(Some(body_type));
+                request.set_form(form);
+            }
+            "graphql" => {
+                let query = text_of(blocks, "body:graphql").unwrap_or_default();
+                let variables = text_of(blocks, "body:graphql:vars")
+                    .and_then(|variables| serde_json::from_str::<Value>(&variables).ok());
+                let json = serde_json::json!({
+                    "query": query,
+                    "variables": variables.unwrap_or(Value::Null),
+                });
+                let json = serde_json::to_string_pretty(&json).unwrap_or_default();
+                request.set_body_type(Some(HttpBody::Json));
+                request.set_body(Some(self.text(&json, &what)));
+            }
+            mode => self.warnings.push(format!(
+                "the {} body of {} was left out, hermes can't send it",
+                mode, name
+            )),
+        }
+    }
+
+    /// Gets the auth of the mode from its block, the inherited one when the mode is inherit.
+    fn auth(
+        &mut self,
+        blocks: &[Block],
+        mode: &str,
+        inherited: Option<&Auth>,
+        name: &str,
+    ) -> Option<Auth> {
+        let what = format!("the auth of {}", name);
+        let mut text = |key: &str| {
+            let value = value(blocks, &format!("auth:{}", mode), key).unwrap_or_default();
+            self.text(&value, &what)
+        };
+        match mode {
+            "none" => None,
+            "inherit" => inherited.cloned(),
+            "bearer" => Some(Auth::Bearer {
+                token: text("token"),
+            }),
+            "basic" => Some(Auth::Basic {
+                username: text("username"),
+                password: text("password"),
+            }),
+            "apikey" => Some(Auth::ApiKey {
+                name: value(blocks, "auth:apikey", "key")
+                    .unwrap_or_else(|| String::from("X-API-Key")),
+                value: text("value"),
+                location: match value(blocks, "auth:apikey", "placement").as_deref() {
+                    Some("queryparams") => KeyLocation::Query,
+                    _ => KeyLocation::Header,
+                },
+            }),
+            mode => {
+                self.warnings.push(format!(
+                    "the {} auth of {} was left out, hermes only has bearer, basic and api-key",
+                    mode, name
+                ));
+                None
+            }
+        }
+    }
+
+    /// Converts the environments, their secrets marked as secrets of the collection. Bruno keeps
+    /// the values of secrets out of the files, so they are empty.
+    fn environments(&mut self, dir: &Path) -> Result<(), String> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Ok(());
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "bru"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let blocks = parse(&read(&path)?);
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let mut entries = Vec::new();
+            for (key, value, enabled) in pairs(&blocks, "vars") {
+                if enabled {
+                    let value = self.text(&value, &format!("the variable {} of {}", key, name));
+                    entries.push((key, value));
+                }
+            }
+            for line in block(&blocks, "vars:secret")
+                .into_iter()
+                .flat_map(|b| &b.lines)
+            {
+                let key = line.trim().trim_end_matches(',');
+                if !key.is_empty() && !key.starts_with('~') {
+                    self.collection.mark_secret(key.to_string());
+                    entries.push((key.to_string(), String::new()));
+                }
+            }
+            add_environment(&mut self.collection, &name, entries);
+        }
+        Ok(())
+    }
+
+    fn warn_about_scripts(&mut self, blocks: &[Block], name: &str) {
+        let has = |prefix: &str| {
+            blocks
+                .iter()
+                .any(|block| block.name.starts_with(prefix) && !block.lines.is_empty())
+        };
+        if has("script:") || has("vars:post-response") {
+            self.warnings
+                .push(format!("the scripts of {} were left out", name));
+        }
+        if has("tests") {
+            self.warnings
+                .push(format!("the tests of {} were left out", name));
+        }
+    }
+
+    fn text(&mut self, text: &str, what: &str) -> String {
+        without_backticks(text, what, &mut self.warnings)
+    }
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external;
+
+    #[test]
+    fn should_convert_a_bruno_collection() {
+        let dir = external::temp_path("bruno", "d");
+        let files = [
+            (
+                CONFIG_FILE,
+                r#"{"version": "1", "name": "Petstore", "type": "collection"}"#,
+            ),
+            (
+                COLLECTION_FILE,
+                "headers {\n  X-Client: hermes\n}\n\nauth {\n  mode: bearer\n}\n\n\
+                 auth:bearer {\n  token: {{token}}\n}\n",
+            ),
+            (
+                "environments/Local.bru",
+                "vars {\n  baseUrl: http://localhost\n  ~old: 1\n}\nvars:secret [\n  token\n]\n",
+            ),
+            ("pets/folder.bru", "meta {\n  name: Pets\n}\n"),
+            (
+                "pets/Create pet.bru",
+                "meta {\n  name: Create pet\n  type: http\n  seq: 2\n}\n\n\
+                 post {\n  url: {{baseUrl}}/pets\n  body: json\n  auth: none\n}\n\n\
+                 body:json {\n  {\n    \"name\": \"rex\"\n  }\n}\n\n\
+                 tests {\n  test(\"created\", () => {});\n}\n",
+            ),
+            (
+                "pets/List pets.bru",
+                "meta {\n  name: List pets\n  type: http\n  seq: 1\n}\n\n\
+                 get {\n  url: {{baseUrl}}/pets?page=1\n  body: none\n  auth: inherit\n}\n\n\
+                 params:query {\n  page: 1\n  ~limit: 10\n}\n\n\
+                 headers {\n  Accept: application/json\n  ~X-Debug: 1\n}\n",
+            ),
+            (
+                "Upload.bru",
+                "meta {\n  name: Upload\n  seq: 1\n}\n\n\
+                 put {\n  url: {{baseUrl}}/upload\n  body: multipartForm\n  auth: digest\n}\n\n\
+                 body:multipart-form {\n  photo: @file(/tmp/rex.png)\n  caption: Rex\n}\n",
+            ),
+        ];
+        for (path, contents) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        assert!(is_bruno(&dir));
+        assert!(!is_bruno(&dir.join("pets")));
+
+        let Imported {
+            collection,
+            warnings,
+        } = convert(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(collection.name(), "Petstore");
+        let local = collection.environment("local").unwrap();
+        assert_eq!(local.len(), 2);
+        assert_eq!(local["baseUrl"], "http://localhost");
+        assert!(collection.is_secret("token"));
+
+        let names: Vec<_> = collection.iter().map(Request::get_name).collect();
+        assert_eq!(names, ["Upload", "List pets", "Create pet"]);
+
+        let upload = collection.iter().next().unwrap();
+        assert_eq!(upload.get_folder(), "");
+        assert_eq!(upload.get_auth(), None);
+        assert_eq!(upload.get_form()[0].value(), "@/tmp/rex.png");
+
+        let list = collection.iter().nth(1).unwrap();
+        assert_eq!(list.get_folder(), "pets");
+        assert_eq!(list.get_url(), "{{baseUrl}}/pets");
+        assert_eq!(
+            list.get_queries(),
+            [
+                QueryParam::new(String::from("page"), String::from("1"), true),
+                QueryParam::new(String::from("limit"), String::from("10"), false),
+            ]
+        );
+        assert_eq!(list.get_headers().len(), 2);
+        assert_eq!(list.get_headers()["X-Client"], "hermes");
+        assert_eq!(
+            list.get_auth(),
+            Some(&Auth::Bearer {
+                token: String::from("{{token}}")
+            })
+        );
+
+        let create = collection.iter().nth(2).unwrap();
+        assert_eq!(create.get_auth(), None);
+        assert_eq!(create.get_body_type(), Some(HttpBody::Json));
+        assert_eq!(create.get_body(), Some("{\n  \"name\": \"rex\"\n}"));
+
+        assert_eq!(
+            warnings,
+            [
+                "the digest auth of Upload was left out, hermes only has bearer, basic and api-key",
+                "the tests of Create pet were left out",
+            ]
+        );
+    }
+}
@@ -0,0 +1,453 @@
+//! Conversion of Insomnia exports, format 4, the JSON Insomnia exports workspaces in. Folders
+//! (request groups) become folders of the collection, in the order they have in Insomnia, and
+//! every request keeps its method, url, query parameters, headers, body and auth, inherited from
+//! its folders when it has none. The base environment becomes the variables of the collection and
+//! its sub environments become environments. Insomnia writes variables as `{{ _.name }}`, they
+//! are rewritten as `{{name}}`. Template tags, such as `{% response %}`, have no equivalent and are
+//! kept as they are.
+
+use std::collections::HashMap;
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{
+    api::{Collection, FormField, HttpBody, HttpMethod, QueryParam, Request},
+    auth::{Auth, KeyLocation},
+};
+
+use super::{add_environment, array, folder_of, text_of, without_backticks, Imported};
+
+/// The type of the resources of the export that are requests.
+const REQUEST: &str = "request";
+/// The type of the resources of the export that are folders.
+const FOLDER: &str = "request_group";
+
+/// Tells whether the contents are an Insomnia export.
+pub fn is_insomnia(contents: &str) -> bool {
+    serde_json::from_str::<Value>(contents)
+        .map(|value| value["_type"].as_str() == Some("export") && value["resources"].is_array())
+        .unwrap_or(false)
+}
+
+/// Converts an Insomnia export to a collection. Only the first workspace of the export is
+/// converted.
+pub fn convert(contents: &str) -> Result<Imported, String> {
+    let root: Value = serde_json::from_str(contents)
+        .map_err(|err| format!("the Insomnia export is not JSON: {}", err))?;
+    let resources = array(&root, "resources");
+    let mut workspaces = resources
+        .iter()
+        .filter(|resource| resource["_type"].as_str() == Some("workspace"));
+    let workspace = workspaces
+        .next()
+        .ok_or_else(|| String::from("the Insomnia export has no workspace"))?;
+    let mut converter = Converter {
+        children: HashMap::new(),
+        variable: Regex::new(r"\{\{\s*(?:_\.)?([^{}\s]+)\s*\}\}")
+            .expect("the variable pattern is valid"),
+        collection: Collection::default(),
+        warnings: Vec::new(),
+    };
+    for other in workspaces {
+        converter.warnings.push(format!(
+            "the workspace {} was left out, only the first one is imported",
+            other["name"].as_str().unwrap_or_default()
+        ));
+    }
+    for resource in resources {
+        if let Some(parent) = resource["parentId"].as_str() {
+            converter.children.entry(parent).or_default().push(resource);
+        }
+    }
+    for children in converter.children.values_mut() {
+        children.sort_by(|a, b| sort_key(a).total_cmp(&sort_key(b)));
+    }
+
+    let name = workspace["name"].as_str().unwrap_or("insomnia");
+    converter.collection.set_name(name.to_string());
+    converter.environments(id_of(workspace));
+    converter.items(id_of(workspace), "", None);
+    Ok(Imported {
+        collection: converter.collection,
+        warnings: converter.warnings,
+    })
+}
+
+struct Converter<'a> {
+    /// The resources of the export by the id of their parent, in the order Insomnia shows them.
+    children: HashMap<&'a str, Vec<&'a Value>>,
+    /// Insomnia's `{{ _.name }}` variables, the name in a group.
+    variable: Regex,
+    collection: Collection,
+    warnings: Vec<String>,
+}
+
+impl<'a> Converter<'a> {
+    /// Converts the base environment of the workspace to variables, and the environments under
+    /// it to environments.
+    fn environments(&mut self, workspace: &str) {
+        let Some(base) = self.child(workspace, "environment") else {
+            return;
+        };
+        for (key, value) in self.entries(base) {
+            self.collection.add_variable(key, value);
+        }
+        let environments: Vec<_> = self
+            .children
+            .get(id_of(base))
+            .into_iter()
+            .flatten()
+            .filter(|resource| resource["_type"].as_str() == Some("environment"))
+            .copied()
+            .collect();
+        for environment in environments {
+            let entries = self.entries(environment);
+            let name = environment["name"].as_str().unwrap_or("environment");
+            add_environment(&mut self.collection, name, entries);
+        }
+    }
+
+    /// Gets the data of an environment as variables, nested objects flattened to dotted keys as
+    /// Insomnia reads them, e.g. `api.url` for `{"api": {"url": ...}}`.
+    fn entries(&mut self, environment: &Value) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        let mut pending: Vec<(String, &Value)> = vec![(String::new(), &environment["data"])];
+        while let Some((prefix, value)) = pending.pop() {
+            match value {
+                Value::Object(object) => {
+                    for (key, value) in object {
+                        let key = match prefix.as_str() {
+                            "" => key.clone(),
+                            prefix => format!("{}.{}", prefix, key),
+                        };
+                        pending.push((key, value));
+                    }
+                }
+                value if !prefix.is_empty() => {
+                    let what = format!("the variable {}", prefix);
+                    let value = self.text(&text_of(value), &what);
+                    entries.push((prefix, value));
+                }
+                _ => {}
+            }
+        }
+        entries.sort();
+        entries
+    }
+
+    /// Converts the requests and folders under a workspace or folder.
+    fn items(&mut self, parent: &str, folder: &str, auth: Option<&Auth>) {
+        let children = self.children.get(parent).cloned().unwrap_or_default();
+        for item in children {
+            let name = item["name"].as_str().unwrap_or("request");
+            match item["_type"].as_str().unwrap_or_default() {
+                FOLDER => {
+                    let folder = match folder {
+                        "" => folder_of(name),
+                        parent => format!("{}/{}", parent, folder_of(name)),
+                    };
+                    let auth = self.auth(&item["authentication"], auth, name);
+                    self.items(id_of(item), &folder, auth.as_ref());
+                }
+                REQUEST => {
+                    let auth = self.auth(&item["authentication"], auth, name);
+                    if let Some(mut request) = self.request(name, item, auth) {
+                        request.set_folder(folder.to_string());
+                        self.collection.add_request(request);
+                    }
+                }
+                kind @ ("grpc_request" | "websocket_request") => self.warnings.push(format!(
+                    "{} was left out, hermes can't send {} requests",
+                    name,
+                    kind.trim_end_matches("_request")
+                )),
+                _ => {}
+            }
+        }
+    }
+
+    fn request(&mut self, name: &str, request: &Value, auth: Option<Auth>) -> Option<Request> {
+        let method = request["method"].as_str().unwrap_or("GET");
+        let Ok(method) = method.parse::<HttpMethod>() else {
+            self.warnings.push(format!(
+                "{} was left out, hermes can't send {} requests",
+                name, method
+            ));
+            return None;
+        };
+        let url = self.text(
+            request["url"].as_str().unwrap_or_default(),
+            &format!("the url of {}", name),
+        );
+        let (url, query) = url.split_once('?').unwrap_or((&url, ""));
+        let mut queries: Vec<_> = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                QueryParam::new(key.to_string(), value.to_string(), true)
+            })
+            .collect();
+        for param in array(request, "parameters") {
+            if let Some(key) = param["name"].as_str() {
+                let what = format!("the query parameter {} of {}", key, name);
+                let value = self.text(&text_of(&param["value"]), &what);
+                let enabled = param["disabled"].as_bool() != Some(true);
+                queries.push(QueryParam::new(key.to_string(), value, enabled));
+            }
+        }
+        let mut headers = HashMap::new();
+        for header in array(request, "headers") {
+            if header["disabled"].as_bool() == Some(true) {
+                continue;
+            }
+            if let Some(key) = header["name"].as_str().filter(|key| !key.is_empty()) {
+                let what = format!("the header {} of {}", key, name);
+                headers.insert(
+                    key.to_string(),
+                    self.text(&text_of(&header["value"]), &what),
+                );
+            }
+        }
+        let mut converted = Request::new(
+            name.to_string(),
+            method,
+            url.to_string(),
+            None,
+            None,
+            headers,
+        );
+        converted.set_queries(queries);
+        converted.set_auth(auth);
+        if let Some(description) = request["description"]
+            .as_str()
+            .filter(|d| !d.trim().is_empty())
+        {
+            let what = format!("the description of {}", name);
+            converted.set_description(Some(self.text(description, &what)));
+        }
+        self.body(name, &request["body"], &mut converted);
+        Some(converted)
+    }
+
+    fn body(&mut self, name: &str, body: &Value, request: &mut Request) {
+        let what = format!("the body of {}", name);
+        match body["mimeType"].as_str() {
+            Some(mime @ ("application/x-www-form-urlencoded" | "multipart/form-data")) => {
+                let mut form = Vec::new();
+                for param in array(body, "params") {
+                    let Some(key) = param["name"].as_str() else {
+                        continue;
+                    };
+                    // multipart files are written as their path after an @
+                    let value = match param["type"].as_str() {
+                        Some("file") => format!("@{}", text_of(&param["fileName"])),
+                        _ => text_of(&param["value"]),
+                    };
+                    let value = self.text(&value, &format!("the field {} of {}", key, name));
+                    let enabled = param["disabled"].as_bool() != Some(true);
+                    form.push(FormField::new(key.to_string(), value, enabled));
+                }
+                request.set_body_type(Some(if mime == "multipart/form-data" {
+                    HttpBody::MultipartForm
+                } else {
+                    HttpBody::FormUrlEncoded
+                }));
+                request.set_form(form);
+            }
+            Some("application/octet-stream") => self.warnings.push(format!(
+                "the file body of {} was left out, hermes can't send it",
+                name
+            )),
+            mime => {
+                let text = self.text(body["text"].as_str().unwrap_or_default(), &what);
+                if text.is_empty() {
+                    return;
+                }
+                // graphql bodies are kept as the JSON Insomnia sends
+                request.set_body_type(Some(match mime {
+                    Some("application/json" | "application/graphql") => HttpBody::Json,
+                    _ => HttpBody::Text,
+                }));
+                request.set_body(Some(text));
+            }
+        }
+    }
+
+    /// Gets the auth of a request or folder, the one it inherits when it has none.
+    fn auth(&mut self, auth: &Value, inherited: Option<&Auth>, name: &str) -> Option<Auth> {
+        let Some(kind) = auth["type"].as_str() else {
+            return inherited.cloned();
+        };
+        if auth["disabled"].as_bool() == Some(true) {
+            return None;
+        }
+        let what = format!("the auth of {}", name);
+        let mut text = |key: &str| {
+            let value = auth.get(key).map(text_of).unwrap_or_default();
+            self.text(&value, &what)
+        };
+        let converted = match kind {
+            "none" => None,
+            "inherit" => inherited.cloned(),
+            "bearer" => Some(Auth::Bearer {
+                token: text("token"),
+            }),
+            "basic" => Some(Auth::Basic {
+                username: text("username"),
+                password: text("password"),
+            }),
+            "apikey" => Some(Auth::ApiKey {
+                name: auth["key"].as_str().unwrap_or("X-API-Key").to_string(),
+                value: text("value"),
+                location: match auth["addTo"].as_str() {
+                    Some("queryParams") => KeyLocation::Query,
+                    _ => KeyLocation::Header,
+                },
+            }),
+            kind => {
+                self.warnings.push(format!(
+                    "the {} auth of {} was left out, hermes only has bearer, basic and api-key",
+                    kind, name
+                ));
+                None
+            }
+        };
+        // Insomnia lets the prefix be something else than Bearer, hermes can't
+        if kind == "bearer" && !matches!(auth["prefix"].as_str(), None | Some("" | "Bearer")) {
+            self.warnings.push(format!(
+                "the prefix of the bearer auth of {} was replaced by Bearer",
+                name
+            ));
+        }
+        converted
+    }
+
+    /// Gets the first child of the resource with the type.
+    fn child(&self, parent: &str, kind: &str) -> Option<&'a Value> {
+        self.children
+            .get(parent)?
+            .iter()
+            .find(|resource| resource["_type"].as_str() == Some(kind))
+            .copied()
+    }
+
+    /// Rewrites the variables of the text as hermes variables, warning about what can't be.
+    fn text(&mut self, text: &str, what: &str) -> String {
+        if text.contains("{%") {
+            self.warnings.push(format!(
+                "the template tags of {} were kept as they are, hermes can't run them",
+                what
+            ));
+        }
+        let text = self.variable.replace_all(text, "{{${1}}}");
+        without_backticks(&text, what, &mut self.warnings)
+    }
+}
+
+fn id_of(resource: &Value) -> &str {
+    resource["_id"].as_str().unwrap_or_default()
+}
+
+/// Gets where the resource is shown among the ones of its parent, Insomnia showing the smallest
+/// first.
+fn sort_key(resource: &Value) -> f64 {
+    resource["metaSortKey"].as_f64().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_convert_an_insomnia_export() {
+        let contents = r#"{
+            "_type": "export",
+            "__export_format": 4,
+            "resources": [
+                {"_id": "wrk_1", "_type": "workspace", "parentId": null, "name": "Petstore"},
+                {"_id": "env_1", "_type": "environment", "parentId": "wrk_1", "name": "Base Environment",
+                 "data": {"base_url": "http://localhost", "page": {"size": 10}}},
+                {"_id": "env_2", "_type": "environment", "parentId": "env_1", "name": "Staging",
+                 "data": {"base_url": "https://staging.example.com"}},
+                {"_id": "fld_1", "_type": "request_group", "parentId": "wrk_1", "name": "Pets",
+                 "authentication": {"type": "bearer", "token": "{{ _.token }}"}},
+                {"_id": "req_2", "_type": "request", "parentId": "fld_1", "name": "Create pet",
+                 "metaSortKey": -1, "method": "POST", "url": "{{ _.base_url }}/pets",
+                 "body": {"mimeType": "application/json", "text": "{\"id\": \"{% uuid 'v4' %}\"}"},
+                 "authentication": {}},
+                {"_id": "req_1", "_type": "request", "parentId": "fld_1", "name": "List pets",
+                 "metaSortKey": -2, "method": "GET", "url": "{{ _.base_url }}/pets?sort=name",
+                 "parameters": [{"name": "size", "value": "{{ _.page.size }}", "disabled": true}],
+                 "headers": [{"name": "Accept", "value": "application/json"},
+                             {"name": "X-Debug", "value": "1", "disabled": true}],
+                 "authentication": {"type": "apikey", "key": "key", "value": "abc", "addTo": "queryParams"}},
+                {"_id": "req_3", "_type": "request", "parentId": "wrk_1", "name": "Upload", "method": "PUT",
+                 "url": "{{base_url}}/upload", "authentication": {"type": "bearer", "token": "t", "disabled": true},
+                 "body": {"mimeType": "multipart/form-data", "params": [
+                     {"name": "photo", "type": "file", "fileName": "/tmp/rex.png"},
+                     {"name": "caption", "value": "Rex"}
+                 ]}},
+                {"_id": "ws_1", "_type": "websocket_request", "parentId": "wrk_1", "name": "Live"}
+            ]
+        }"#;
+        assert!(is_insomnia(contents));
+        assert!(!is_insomnia(r#"{"_type": "export"}"#));
+
+        let Imported {
+            collection,
+            warnings,
+        } = convert(contents).unwrap();
+        assert_eq!(collection.name(), "Petstore");
+        assert_eq!(collection.variables()["page.size"], "10");
+        assert_eq!(
+            collection.environment("staging").unwrap()["base_url"],
+            "https://staging.example.com"
+        );
+
+        let names: Vec<_> = collection.iter().map(Request::get_name).collect();
+        assert_eq!(names, ["List pets", "Create pet", "Upload"]);
+        let list = collection.iter().next().unwrap();
+        assert_eq!(list.get_folder(), "Pets");
+        assert_eq!(list.get_url(), "{{base_url}}/pets");
+        assert_eq!(
+            list.get_queries(),
+            [
+                QueryParam::new(String::from("sort"), String::from("name"), true),
+                QueryParam::new(String::from("size"), String::from("{{page.size}}"), false),
+            ]
+        );
+        assert_eq!(list.get_headers().len(), 1);
+        assert_eq!(
+            list.get_auth(),
+            Some(&Auth::ApiKey {
+                name: String::from("key"),
+                value: String::from("abc"),
+                location: KeyLocation::Query,
+            })
+        );
+
+        let create = collection.iter().nth(1).unwrap();
+        assert_eq!(
+            create.get_auth(),
+            Some(&Auth::Bearer {
+                token: String::from("{{token}}")
+            })
+        );
+        assert_eq!(create.get_body_type(), Some(HttpBody::Json));
+
+        let upload = collection.iter().nth(2).unwrap();
+        assert_eq!(upload.get_folder(), "");
+        assert_eq!(upload.get_auth(), None);
+        assert_eq!(upload.get_form()[0].value(), "@/tmp/rex.png");
+
+        assert_eq!(
+            warnings,
+            [
+                "the template tags of the body of Create pet were kept as they are, hermes can't run them",
+                "Live was left out, hermes can't send websocket requests",
+            ]
+        );
+    }
+}
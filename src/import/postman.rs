@@ -0,0 +1,444 @@
+//! Conversion of Postman collections v2.1, the format Postman exports collections in. Folders
+//! become folders of the collection, and every request keeps its method, url, query parameters,
+//! headers, body and auth, inherited from its folders when it has none. Postman writes variables
+//! as `{{name}}` like hermes, so they are kept as they are. Scripts have no equivalent and are
+//! left out.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::{
+    api::{Collection, FormField, HttpBody, HttpMethod, QueryParam, Request},
+    auth::{Auth, KeyLocation},
+};
+
+use super::{array, folder_of, text_of, without_backticks, Imported};
+
+/// What the schema of a Postman collection ends with, whatever host serves it.
+const SCHEMA: &str = "/collection/v2.1.0/collection.json";
+
+/// Tells whether the contents are a Postman collection v2.1.
+pub fn is_postman(contents: &str) -> bool {
+    serde_json::from_str::<Value>(contents)
+        .ok()
+        .and_then(|value| {
+            value
+                .pointer("/info/schema")
+                .and_then(Value::as_str)
+                .map(|schema| schema.ends_with(SCHEMA))
+        })
+        .unwrap_or(false)
+}
+
+/// Converts a Postman collection v2.1 to a collection.
+pub fn convert(contents: &str) -> Result<Imported, String> {
+    let root: Value = serde_json::from_str(contents)
+        .map_err(|err| format!("the Postman collection is not JSON: {}", err))?;
+    let mut converter = Converter::default();
+    let name = root
+        .pointer("/info/name")
+        .and_then(Value::as_str)
+        .unwrap_or("postman");
+    converter.collection.set_name(name.to_string());
+    for variable in array(&root, "variable") {
+        if variable["disabled"].as_bool() == Some(true) {
+            continue;
+        }
+        if let Some(key) = variable["key"].as_str() {
+            let what = format!("the variable {}", key);
+            let value = converter.text(&text_of(&variable["value"]), &what);
+            converter.collection.add_variable(key.to_string(), value);
+        }
+    }
+    converter.warn_about_scripts(&root, name);
+    let auth = converter.auth(&root["auth"], None, name);
+    converter.items(array(&root, "item"), "", auth.as_ref());
+    Ok(Imported {
+        collection: converter.collection,
+        warnings: converter.warnings,
+    })
+}
+
+#[derive(Default)]
+struct Converter {
+    collection: Collection,
+    warnings: Vec<String>,
+}
+
+impl Converter {
+    /// Converts the items of a folder, requests and the folders in it.
+    fn items(&mut self, items: &[Value], folder: &str, auth: Option<&Auth>) {
+        for item in items {
+            let name = item["name"].as_str().unwrap_or("request");
+            self.warn_about_scripts(item, name);
+            if let Some(items) = item["item"].as_array() {
+                let folder = match folder {
+                    "" => folder_of(name),
+                    parent => format!("{}/{}", parent, folder_of(name)),
+                };
+                let auth = self.auth(&item["auth"], auth, name);
+                self.items(items, &folder, auth.as_ref());
+            } else if item["request"].is_object() || item["request"].is_string() {
+                // folders keep their auth in the item, requests in the request
+                let auth = self.auth(&item["request"]["auth"], auth, name);
+                if let Some(mut request) = self.request(name, &item["request"], auth) {
+                    request.set_folder(folder.to_string());
+                    self.collection.add_request(request);
+                }
+            }
+        }
+    }
+
+    fn request(&mut self, name: &str, request: &Value, auth: Option<Auth>) -> Option<Request> {
+        // a request can be only its url
+        if let Some(url) = request.as_str() {
+            let url = self.text(url, &format!("the url of {}", name));
+            return Some(Request::new(
+                name.to_string(),
+                HttpMethod::Get,
+                url,
+                None,
+                None,
+                HashMap::new(),
+            ));
+        }
+        let method = request["method"].as_str().unwrap_or("GET");
+        let Ok(method) = method.parse::<HttpMethod>() else {
+            self.warnings.push(format!(
+                "{} was left out, hermes can't send {} requests",
+                name, method
+            ));
+            return None;
+        };
+        let (url, queries) = self.url(name, &request["url"]);
+        let mut headers = HashMap::new();
+        for header in array(request, "header") {
+            if header["disabled"].as_bool() == Some(true) {
+                continue;
+            }
+            if let Some(key) = header["key"].as_str() {
+                let what = format!("the header {} of {}", key, name);
+                headers.insert(
+                    key.to_string(),
+                    self.text(&text_of(&header["value"]), &what),
+                );
+            }
+        }
+        let mut converted = Request::new(name.to_string(), method, url, None, None, headers);
+        converted.set_queries(queries);
+        converted.set_auth(auth);
+        let description = match &request["description"] {
+            Value::String(description) => Some(description.as_str()),
+            description => description["content"].as_str(),
+        };
+        if let Some(description) = description.filter(|d| !d.trim().is_empty()) {
+            let what = format!("the description of {}", name);
+            converted.set_description(Some(self.text(description, &what)));
+        }
+        self.body(name, &request["body"], &mut converted);
+        Some(converted)
+    }
+
+    /// Gets the url without its query string, and the query parameters apart.
+    fn url(&mut self, name: &str, url: &Value) -> (String, Vec<QueryParam>) {
+        let what = format!("the url of {}", name);
+        let raw = match url {
+            Value::String(raw) => raw.as_str(),
+            url => url["raw"].as_str().unwrap_or_default(),
+        };
+        let raw = self.text(raw, &what);
+        let (base, query) = raw.split_once('?').unwrap_or((&raw, ""));
+        let queries = match url["query"].as_array() {
+            Some(query) => query
+                .iter()
+                .filter_map(|param| {
+                    let key = param["key"].as_str()?;
+                    Some(QueryParam::new(
+                        key.to_string(),
+                        text_of(&param["value"]),
+                        param["disabled"].as_bool() != Some(true),
+                    ))
+                })
+                .collect(),
+            None => query
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| {
+                    let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                    QueryParam::new(key.to_string(), value.to_string(), true)
+                })
+                .collect(),
+        };
+        (base.to_string(), queries)
+    }
+
+    fn body(&mut self, name: &str, body: &Value, request: &mut Request) {
+        let what = format!("the body of {}", name);
+        match body["mode"].as_str() {
+            Some("raw") => {
+                let raw = self.text(body["raw"].as_str().unwrap_or_default(), &what);
+                if raw.is_empty() {
+                    return;
+                }
+                let language = body
+                    .pointer("/options/raw/language")
+                    .and_then(Value::as_str);
+                request.set_body_type(Some(match language {
+                    Some("json") => HttpBody::Json,
+                    _ => HttpBody::Text,
+                }));
+                request.set_body(Some(raw));
+            }
+            Some(mode @ ("urlencoded" | "formdata")) => {
+                let mut form = Vec::new();
+                for field in array(body, mode) {
+                    let Some(key) = field["key"].as_str() else {
+                        continue;
+                    };
+                    // multipart files are written as their path after an @
+                    let value = match field["type"].as_str() {
+                        Some("file") => format!("@{}", text_of(&field["src"])),
+                        _ => text_of(&field["value"]),
+                    };
+                    let value = self.text(&value, &format!("the field {} of {}", key, name));
+                    let enabled = field["disabled"].as_bool() != Some(true);
+                    form.push(FormField::new(key.to_string(), value, enabled));
+                }
+                request.set_body_type(Some(if mode == "urlencoded" {
+                    HttpBody::FormUrlEncoded
+                } else {
+                    HttpBody::MultipartForm
+                }));
+                request.set_form(form);
+            }
+            Some("graphql") => {
+                let query = body.pointer("/graphql/query").and_then(Value::as_str);
+                let variables = body
+                    .pointer("/graphql/variables")
+                    .and_then(Value::as_str)
+                    .and_then(|variables| serde_json::from_str::<Value>(variables).ok());
+                let json = serde_json::json!({
+                    "query": query.unwrap_or_default(),
+                    "variables": variables.unwrap_or(Value::Null),
+                });
+                let json = serde_json::to_string_pretty(&json).unwrap_or_default();
+                request.set_body_type(Some(HttpBody::Json));
+                request.set_body(Some(self.text(&json, &what)));
+            }
+            Some(mode) => self.warnings.push(format!(
+                "the {} body of {} was left out, hermes can't send it",
+                mode, name
+            )),
+            None => {}
+        }
+    }
+
+    /// Gets the auth of an item, the one it inherits when it has none or is set to inherit.
+    fn auth(&mut self, auth: &Value, inherited: Option<&Auth>, name: &str) -> Option<Auth> {
+        let Some(kind) = auth["type"].as_str() else {
+            return inherited.cloned();
+        };
+        let field = |name: &str| {
+            array(auth, kind)
+                .iter()
+                .find(|field| field["key"].as_str() == Some(name))
+                .map(|field| text_of(&field["value"]))
+        };
+        let text = |name: &str| field(name).unwrap_or_default();
+        match kind {
+            "noauth" => None,
+            "inherit" => inherited.cloned(),
+            "bearer" => Some(Auth::Bearer {
+                token: text("token"),
+            }),
+            "basic" => Some(Auth::Basic {
+                username: text("username"),
+                password: text("password"),
+            }),
+            "apikey" => Some(Auth::ApiKey {
+                name: field("key").unwrap_or_else(|| String::from("X-API-Key")),
+                value: text("value"),
+                location: match field("in").as_deref() {
+                    Some("query") => KeyLocation::Query,
+                    _ => KeyLocation::Header,
+                },
+            }),
+            kind => {
+                self.warnings.push(format!(
+                    "the {} auth of {} was left out, hermes only has bearer, basic and api-key",
+                    kind, name
+                ));
+                None
+            }
+        }
+    }
+
+    fn warn_about_scripts(&mut self, item: &Value, name: &str) {
+        let has_script = array(item, "event").iter().any(|event| {
+            event
+                .pointer("/script/exec")
+                .and_then(Value::as_array)
+                .is_some_and(|lines| lines.iter().any(|line| line.as_str() != Some("")))
+        });
+        if has_script {
+            self.warnings
+                .push(format!("the scripts of {} were left out", name));
+        }
+    }
+
+    fn text(&mut self, text: &str, what: &str) -> String {
+        without_backticks(text, what, &mut self.warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{external, import, loader, parser::ScanSettings};
+
+    #[test]
+    fn should_convert_a_postman_collection() {
+        let contents = r#"{
+            "info": {
+                "name": "Petstore",
+                "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json"
+            },
+            "auth": {"type": "bearer", "bearer": [{"key": "token", "value": "{{TOKEN}}"}]},
+            "variable": [{"key": "BASE_URL", "value": "http://localhost"}, {"key": "PAGE", "value": 2}],
+            "item": [
+                {
+                    "name": "Pets",
+                    "item": [
+                        {
+                            "name": "List pets",
+                            "event": [{"listen": "test", "script": {"exec": ["pm.test()"]}}],
+                            "request": {
+                                "method": "GET",
+                                "header": [
+                                    {"key": "Accept", "value": "application/json"},
+                                    {"key": "X-Debug", "value": "1", "disabled": true}
+                                ],
+                                "url": {
+                                    "raw": "{{BASE_URL}}/pets?page={{PAGE}}&limit=10",
+                                    "query": [
+                                        {"key": "page", "value": "{{PAGE}}"},
+                                        {"key": "limit", "value": "10", "disabled": true}
+                                    ]
+                                }
+                            }
+                        },
+                        {
+                            "name": "Create pet",
+                            "request": {
+                                "auth": {"type": "noauth"},
+                                "method": "POST",
+                                "url": "{{BASE_URL}}/pets",
+                                "body": {
+                                    "mode": "raw",
+                                    "raw": "{\"name\": \"`rex`\"}",
+                                    "options": {"raw": {"language": "json"}}
+                                }
+                            }
+                        },
+                        {"name": "Upload", "request": {
+                            "method": "PUT",
+                            "url": "{{BASE_URL}}/pets/1/photo",
+                            "body": {"mode": "formdata", "formdata": [
+                                {"key": "photo", "type": "file", "src": "/tmp/rex.png"},
+                                {"key": "caption", "value": "Rex", "type": "text"}
+                            ]}
+                        }},
+                        {"name": "Check", "request": {"method": "HEAD", "url": "{{BASE_URL}}"}}
+                    ]
+                }
+            ]
+        }"#;
+        assert!(is_postman(contents));
+        assert!(!is_postman(r#"{"info": {"name": "not postman"}}"#));
+
+        let Imported {
+            collection,
+            warnings,
+        } = convert(contents).unwrap();
+        assert_eq!(collection.name(), "Petstore");
+        assert_eq!(collection.variables()["PAGE"], "2");
+        assert_eq!(collection.get_request_count(), 3);
+
+        let list = collection.iter().next().unwrap();
+        assert_eq!(list.get_folder(), "Pets");
+        assert_eq!(list.get_url(), "{{BASE_URL}}/pets");
+        assert_eq!(
+            list.get_queries(),
+            [
+                QueryParam::new(String::from("page"), String::from("{{PAGE}}"), true),
+                QueryParam::new(String::from("limit"), String::from("10"), false),
+            ]
+        );
+        assert_eq!(list.get_headers().len(), 1);
+        assert_eq!(
+            list.get_auth(),
+            Some(&Auth::Bearer {
+                token: String::from("{{TOKEN}}")
+            })
+        );
+
+        let create = collection.iter().nth(1).unwrap();
+        assert_eq!(create.get_auth(), None);
+        assert_eq!(create.get_body_type(), Some(HttpBody::Json));
+        assert_eq!(create.get_body(), Some("{\"name\": \"'rex'\"}"));
+
+        let upload = collection.iter().nth(2).unwrap();
+        assert_eq!(upload.get_body_type(), Some(HttpBody::MultipartForm));
+        assert_eq!(upload.get_form()[0].value(), "@/tmp/rex.png");
+
+        assert_eq!(
+            warnings,
+            [
+                "the scripts of List pets were left out",
+                "the backticks of the body of Create pet were replaced by '",
+                "Check was left out, hermes can't send HEAD requests",
+            ]
+        );
+    }
+
+    #[test]
+    fn should_load_what_it_imports() {
+        let contents = r#"{
+            "info": {
+                "name": "Signup",
+                "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json"
+            },
+            "variable": [{"key": "api key", "value": "`secret`"}],
+            "item": [
+                {
+                    "name": "Sign up",
+                    "request": {
+                        "method": "POST",
+                        "header": [{"key": "X Api Key", "value": "{{api key}}"}],
+                        "url": {
+                            "raw": "http://localhost/signup?page[size]=2",
+                            "query": [{"key": "page[size]", "value": "2"}]
+                        },
+                        "body": {"mode": "urlencoded", "urlencoded": [
+                            {"key": "first name", "value": "Ada"}
+                        ]}
+                    }
+                }
+            ]
+        }"#;
+        let imported = convert(contents).unwrap();
+        let dir = external::temp_path("postman", "d");
+        import::write(&imported.collection, &dir).unwrap();
+
+        let loaded = loader::load_collection(&dir, &ScanSettings::default()).unwrap();
+        assert_eq!(loaded.variables()["api key"], "'secret'");
+        let request = loaded.iter().next().unwrap();
+        assert_eq!(request.get_headers()["X Api Key"], "{{api key}}");
+        assert_eq!(request.get_queries()[0].key(), "page[size]");
+        assert_eq!(request.get_form()[0].name(), "first name");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
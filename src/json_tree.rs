@@ -0,0 +1,345 @@
+//! A collapsible tree of a JSON response, to move through deeply nested payloads one level at a
+//! time instead of scrolling pretty-printed text. Nodes are addressed by their JSONPath, e.g.
+//! `$.users[0].name`, which can be copied along with their value. The folds of a tree can be
+//! restored on the tree of another response, which opens the nodes it has.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The root of every JSONPath.
+const ROOT: &str = "$";
+
+/// A node of the tree as shown, one per line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    path: String,
+    depth: usize,
+    /// The key or the index of the node in its parent, empty for the root.
+    label: String,
+    /// The value of scalars, or how many children objects and arrays have.
+    summary: String,
+    /// Whether the node has children that can be shown.
+    expandable: bool,
+    expanded: bool,
+}
+
+impl Row {
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn summary(&self) -> &str {
+        &self.summary
+    }
+
+    pub fn is_expandable(&self) -> bool {
+        self.expandable
+    }
+
+    pub fn is_expanded(&self) -> bool {
+        self.expanded
+    }
+}
+
+/// Which nodes of a tree are expanded and which one is selected, by their JSONPath.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Folds {
+    /// Sorted.
+    expanded: Vec<String>,
+    selected: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonTree {
+    root: Value,
+    /// The paths of the nodes showing their children.
+    expanded: HashSet<String>,
+    /// The index of the selected row.
+    selected: usize,
+}
+
+impl JsonTree {
+    /// Reads a JSON body into a tree with only the root expanded. None when it is not JSON.
+    pub fn parse(body: &str) -> Option<Self> {
+        let root = serde_json::from_str(body).ok()?;
+        Some(Self {
+            root,
+            expanded: HashSet::from([String::from(ROOT)]),
+            selected: 0,
+        })
+    }
+
+    /// Gets the nodes that are shown, in order.
+    pub fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        self.push_rows(&self.root, String::from(ROOT), String::new(), 0, &mut rows);
+        rows
+    }
+
+    fn push_rows(
+        &self,
+        value: &Value,
+        path: String,
+        label: String,
+        depth: usize,
+        rows: &mut Vec<Row>,
+    ) {
+        let expanded = self.expanded.contains(&path);
+        let (summary, expandable) = match value {
+            Value::Object(map) => (format!("{{{}}}", map.len()), !map.is_empty()),
+            Value::Array(items) => (format!("[{}]", items.len()), !items.is_empty()),
+            scalar => (scalar.to_string(), false),
+        };
+        rows.push(Row {
+            path: path.clone(),
+            depth,
+            label,
+            summary,
+            expandable,
+            expanded: expandable && expanded,
+        });
+        if !expanded {
+            return;
+        }
+        match value {
+            Value::Object(map) => {
+                for (key, child) in map {
+                    self.push_rows(child, child_path(&path, key), key.clone(), depth + 1, rows);
+                }
+            }
+            Value::Array(items) => {
+                for (index, child) in items.iter().enumerate() {
+                    let path = format!("{}[{}]", path, index);
+                    self.push_rows(child, path, index.to_string(), depth + 1, rows);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn next(&mut self) {
+        if self.selected + 1 < self.rows().len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Shows the children of the selected node.
+    pub fn expand(&mut self) {
+        if let Some(row) = self.selected_row().filter(|row| row.expandable) {
+            self.expanded.insert(row.path);
+        }
+    }
+
+    /// Hides the children of the selected node, or selects its parent when they are hidden
+    /// already, so collapsing repeatedly walks up the tree.
+    pub fn collapse(&mut self) {
+        let Some(row) = self.selected_row() else {
+            return;
+        };
+        if row.expanded {
+            self.expanded.remove(&row.path);
+            return;
+        }
+        let rows = self.rows();
+        if let Some(parent) = rows[..self.selected]
+            .iter()
+            .rposition(|parent| parent.depth + 1 == row.depth)
+        {
+            self.selected = parent;
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        match self.selected_row() {
+            Some(row) if row.expanded => self.collapse(),
+            _ => self.expand(),
+        }
+    }
+
+    fn selected_row(&self) -> Option<Row> {
+        self.rows().into_iter().nth(self.selected)
+    }
+
+    pub fn folds(&self) -> Folds {
+        let mut expanded: Vec<String> = self.expanded.iter().cloned().collect();
+        expanded.sort();
+        Folds {
+            expanded,
+            selected: self.selected_path(),
+        }
+    }
+
+    /// Expands and selects the nodes of the folds, the ones this tree does not have are skipped.
+    pub fn restore(&mut self, folds: &Folds) {
+        self.expanded = folds
+            .expanded
+            .iter()
+            .filter(|path| lookup(&self.root, path).is_some())
+            .cloned()
+            .collect();
+        self.selected = self
+            .rows()
+            .iter()
+            .position(|row| row.path == folds.selected)
+            .unwrap_or_default();
+    }
+
+    /// Gets the JSONPath of the selected node.
+    pub fn selected_path(&self) -> String {
+        self.selected_row()
+            .map_or_else(|| String::from(ROOT), |row| row.path)
+    }
+
+    /// Gets the selected node.
+    pub fn selected_json(&self) -> Option<&Value> {
+        lookup(&self.root, &self.selected_path())
+    }
+
+    /// Gets the value of the selected node: strings as they are, anything else as pretty JSON.
+    pub fn selected_value(&self) -> String {
+        match self.selected_json() {
+            Some(Value::String(text)) => text.clone(),
+            Some(value) => serde_json::to_string_pretty(value).unwrap_or_default(),
+            None => String::new(),
+        }
+    }
+}
+
+/// Gets the node at a JSONPath made of the steps the tree uses: `$` followed by `.key`,
+/// `['any key']` or `[0]`. Wildcards and filters are not supported.
+pub fn lookup<'a>(root: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut rest = path.trim().strip_prefix(ROOT)?;
+    let mut value = root;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            value = value.get(&after[..end])?;
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix("['") {
+            let mut key = String::new();
+            let mut chars = after.char_indices();
+            let end = loop {
+                match chars.next()? {
+                    (_, '\\') => key.push(chars.next()?.1),
+                    (index, '\'') => break index,
+                    (_, ch) => key.push(ch),
+                }
+            };
+            value = value.get(&key)?;
+            rest = after[end + 1..].strip_prefix(']')?;
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let (index, after) = after.split_once(']')?;
+            value = value.get(index.parse::<usize>().ok()?)?;
+            rest = after;
+        } else {
+            return None;
+        }
+    }
+    Some(value)
+}
+
+/// Gets a variable name for the node at the path from its last key, e.g. `ACCESS_TOKEN` for
+/// `$.data['access-token']`.
+pub fn variable_name(path: &str) -> String {
+    path.rsplit(['.', '['])
+        .map(|step| step.trim_matches(|ch: char| !ch.is_alphanumeric()))
+        .find(|step| step.chars().next().is_some_and(char::is_alphabetic))
+        .unwrap_or("value")
+        .chars()
+        .map(|ch| {
+            if ch.is_alphanumeric() {
+                ch.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Gets the path of a key of an object, in bracket notation when it is not a plain identifier.
+pub fn child_path(parent: &str, key: &str) -> String {
+    let plain = key
+        .chars()
+        .next()
+        .is_some_and(|ch| ch.is_alphabetic() || ch == '_')
+        && key.chars().all(|ch| ch.is_alphanumeric() || ch == '_');
+    if plain {
+        format!("{}.{}", parent, key)
+    } else {
+        format!(
+            "{}['{}']",
+            parent,
+            key.replace('\\', "\\\\").replace('\'', "\\'")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_navigate_nested_json() {
+        let mut tree =
+            JsonTree::parse(r#"{"users": [{"name": "Ada", "first name": "A"}], "total": 1}"#)
+                .unwrap();
+        let labels: Vec<_> = tree
+            .rows()
+            .iter()
+            .map(|row| row.label().to_string())
+            .collect();
+        assert_eq!(labels, ["", "total", "users"]);
+
+        tree.next();
+        tree.next();
+        tree.expand();
+        tree.next();
+        tree.expand();
+        let rows = tree.rows();
+        assert_eq!(rows.len(), 6);
+        assert_eq!(rows[3].summary(), "{2}");
+
+        tree.next();
+        assert_eq!(tree.selected_path(), "$.users[0]['first name']");
+        assert_eq!(tree.selected_value(), "A");
+        tree.next();
+        assert_eq!(tree.selected_path(), "$.users[0].name");
+
+        tree.collapse();
+        assert_eq!(tree.selected_path(), "$.users[0]");
+        tree.collapse();
+        assert_eq!(tree.rows().len(), 4);
+        assert!(tree.selected_value().contains("\"name\": \"Ada\""));
+        assert!(JsonTree::parse("not json").is_none());
+
+        assert_eq!(variable_name("$.users[0]['first name']"), "FIRST_NAME");
+        assert_eq!(variable_name("$.items[3]"), "ITEMS");
+        assert_eq!(variable_name("$"), "VALUE");
+        assert_eq!(lookup(&tree.root, "$.users[1]"), None);
+
+        // the folds open the nodes the tree of another response has
+        let folds = tree.folds();
+        let mut other =
+            JsonTree::parse(r#"{"users": [{"name": "Bob"}, {"name": "Eve"}]}"#).unwrap();
+        other.restore(&folds);
+        assert_eq!(other.selected_path(), "$.users[0]");
+        assert_eq!(other.rows().len(), 4);
+        let mut empty = JsonTree::parse("[]").unwrap();
+        empty.restore(&folds);
+        assert_eq!(empty.selected_path(), "$");
+    }
+}
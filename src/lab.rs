@@ -0,0 +1,268 @@
+//! Lab mode, for security testers checking how their own servers handle malformed requests:
+//! duplicate or folded headers, conflicting lengths, bare line feeds. A raw request skips the http
+//! client, which would reject or fix such requests, and is written to the connection byte for
+//! byte. Raw requests are only sent when `lab_mode` is on in the settings.
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use reqwest::Url;
+
+use crate::{api::IpFamily, client::SendError};
+
+/// How long each address gets to accept the TCP connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long the server gets to answer. Servers that keep the connection open without saying how
+/// long the body is are read until they go quiet for this long.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A response read off the wire, as loosely as possible since the request may have confused the
+/// server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawResponse {
+    pub protocol: String,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// The body as received, chunked framing included.
+    pub body: Vec<u8>,
+    pub remote_addr: SocketAddr,
+}
+
+/// Turns a raw request as written into the bytes sent. Line breaks become `\r\n`, as HTTP expects,
+/// while the escapes `\r`, `\n`, `\t`, `\\` and `\xNN` write exactly that byte, e.g. a bare `\n`.
+pub fn encode(raw: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\r' => {}
+            '\n' => bytes.extend_from_slice(b"\r\n"),
+            '\\' => match chars.next() {
+                Some('r') => bytes.push(b'\r'),
+                Some('n') => bytes.push(b'\n'),
+                Some('t') => bytes.push(b'\t'),
+                Some('\\') => bytes.push(b'\\'),
+                Some('x') => {
+                    let hex: String = chars.by_ref().take(2).collect();
+                    let byte = u8::from_str_radix(&hex, 16)
+                        .map_err(|_| format!("invalid escape \\x{}", hex))?;
+                    bytes.push(byte);
+                }
+                Some(other) => return Err(format!("unknown escape \\{}", other)),
+                None => return Err(String::from("the raw request ends with \\")),
+            },
+            ch => bytes.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    Ok(bytes)
+}
+
+/// Writes the bytes to the host of the url, over TLS for https urls, and reads the response. The
+/// connection goes to address instead when given, e.g. the local end of an ssh tunnel.
+pub fn send(
+    url: &str,
+    bytes: &[u8],
+    address: Option<SocketAddr>,
+    family: IpFamily,
+    ca_certificate: Option<&str>,
+) -> Result<RawResponse, SendError> {
+    let parsed = Url::parse(url).map_err(|err| SendError::Failed(format!("{}: {}", url, err)))?;
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| SendError::Failed(format!("{} has no host", url)))?
+        .to_string();
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    let connection_error = |message: String| SendError::Connection {
+        url: url.to_string(),
+        message,
+    };
+    let addresses: Vec<SocketAddr> = match address {
+        Some(address) => vec![address],
+        None => (host.as_str(), port)
+            .to_socket_addrs()
+            .map_err(|err| connection_error(err.to_string()))?
+            .filter(|address| family.allows(&address.ip()))
+            .collect(),
+    };
+    let mut last_error = format!("{} has no addresses", host);
+    let mut connected = None;
+    for address in addresses {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => {
+                connected = Some((stream, address));
+                break;
+            }
+            Err(err) => last_error = format!("{}: {}", address, err),
+        }
+    }
+    let (stream, remote_addr) = connected.ok_or_else(|| connection_error(last_error))?;
+    stream
+        .set_read_timeout(Some(READ_TIMEOUT))
+        .map_err(|err| SendError::Failed(err.to_string()))?;
+
+    let received = match parsed.scheme() {
+        "https" => {
+            let mut builder = native_tls::TlsConnector::builder();
+            if let Some(path) = ca_certificate {
+                let pem = fs::read(path).map_err(|err| {
+                    SendError::Failed(format!("reading CA certificate {}: {}", path, err))
+                })?;
+                let certificate = native_tls::Certificate::from_pem(&pem).map_err(|err| {
+                    SendError::Failed(format!("CA certificate {}: {}", path, err))
+                })?;
+                builder.add_root_certificate(certificate);
+            }
+            let connector = builder
+                .build()
+                .map_err(|err| SendError::Failed(err.to_string()))?;
+            let stream = connector
+                .connect(&host, stream)
+                .map_err(|err| connection_error(err.to_string()))?;
+            exchange(stream, bytes)
+        }
+        _ => exchange(stream, bytes),
+    }
+    .map_err(|err| SendError::Failed(err.to_string()))?;
+    parse_response(&received, remote_addr).ok_or_else(|| {
+        let start = String::from_utf8_lossy(&received[..received.len().min(80)]).into_owned();
+        SendError::Failed(format!("the server did not answer with HTTP: {:?}", start))
+    })
+}
+
+/// Writes the request and reads until the server closes the connection, the whole body announced
+/// by Content-Length came in, or the server goes quiet.
+fn exchange(mut stream: impl Read + Write, bytes: &[u8]) -> io::Result<Vec<u8>> {
+    stream.write_all(bytes)?;
+    stream.flush()?;
+    let mut received = Vec::new();
+    let mut buffer = [0; 8192];
+    loop {
+        match stream.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => {
+                received.extend_from_slice(&buffer[..read]);
+                if is_complete(&received) {
+                    break;
+                }
+            }
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) && !received.is_empty() =>
+            {
+                break
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(received)
+}
+
+/// Checks if the response has the whole body its Content-Length announced.
+fn is_complete(received: &[u8]) -> bool {
+    let Some((head, body)) = split_head(received) else {
+        return false;
+    };
+    head.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, length)| length.trim().parse::<usize>().ok())
+        .is_some_and(|length| body.len() >= length)
+}
+
+/// Splits a response at the blank line after the headers.
+fn split_head(received: &[u8]) -> Option<(String, &[u8])> {
+    let (end, separator) = received
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .map(|end| (end, 4))
+        .or_else(|| {
+            received
+                .windows(2)
+                .position(|window| window == b"\n\n")
+                .map(|end| (end, 2))
+        })?;
+    let head = String::from_utf8_lossy(&received[..end]).into_owned();
+    Some((head, &received[end + separator..]))
+}
+
+/// Reads the status line and headers of a response. Folded header lines are joined to the header
+/// before them.
+fn parse_response(received: &[u8], remote_addr: SocketAddr) -> Option<RawResponse> {
+    let (head, body) =
+        split_head(received).unwrap_or((String::from_utf8_lossy(received).into_owned(), &[][..]));
+    let mut lines = head.lines();
+    let mut status_line = lines.next()?.split_whitespace();
+    let protocol = status_line
+        .next()
+        .filter(|protocol| protocol.starts_with("HTTP/"))?;
+    let status = status_line.next()?.parse().ok()?;
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in lines {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+    Some(RawResponse {
+        protocol: protocol.to_string(),
+        status,
+        headers,
+        body: body.to_vec(),
+        remote_addr,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{Ipv4Addr, TcpListener},
+        thread,
+    };
+
+    use super::*;
+
+    #[test]
+    fn should_send_malformed_requests_as_written() {
+        let raw = "GET / HTTP/1.1\nHost: a\nHost: b\nX-Folded: one\n two\\n\\x00\n\n";
+        let bytes = encode(raw).unwrap();
+        assert_eq!(
+            bytes,
+            b"GET / HTTP/1.1\r\nHost: a\r\nHost: b\r\nX-Folded: one\r\n two\n\x00\r\n\r\n"
+        );
+        assert!(encode("\\q").is_err());
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let expected = bytes.clone();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = vec![0; expected.len()];
+            stream.read_exact(&mut received).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 400 Bad Request\r\nX-Reason: duplicate\r\n host\r\nContent-Length: 3\r\n\r\nbad")
+                .unwrap();
+            received
+        });
+
+        let url = format!("http://127.0.0.1:{}/", port);
+        let response = send(&url, &bytes, None, IpFamily::Any, None).unwrap();
+        assert_eq!(server.join().unwrap(), bytes);
+        assert_eq!(response.protocol, "HTTP/1.1");
+        assert_eq!(response.status, 400);
+        assert_eq!(
+            response.headers[0],
+            (String::from("x-reason"), String::from("duplicate host"))
+        );
+        assert_eq!(response.body, b"bad");
+    }
+}
@@ -0,0 +1,826 @@
+//! Loads a collection folder, or a single hermes file, into the model. Every hermes file is read
+//! with the spanned reader, so problems point at the file, line and column they come from, and
+//! blocks can reference blocks of other files in the collection.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt, fs,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    api::{AcceptPreset, Collection, HttpMethod, IpFamily, ProxyRoute, Request},
+    migrations,
+    pagination::Cursor,
+    parser::{self, ScanSettings},
+    runner::{Precondition, Scope},
+    signing::BodySigning,
+    source::{self, Diagnostic, Severity, SourceBlock, SourceValue},
+    storage::{self, FileSnapshot},
+    variables,
+};
+
+/// The file with the collection block and the hooks of the whole collection.
+const COLLECTION_FILE: &str = "collection.hermes";
+/// The file with the hooks of the folder it is in.
+const FOLDER_FILE: &str = "folder.hermes";
+/// The environment made from the environment fields of the collection block when none of them
+/// references a named environment.
+const DEFAULT_ENVIRONMENT: &str = "default";
+
+/// A problem found while loading a collection, shown as `path:line:column: message`. Warnings
+/// are shown as `path:line:column: warning: message` and don't keep the collection from loading.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadError {
+    severity: Severity,
+    path: PathBuf,
+    /// The line and column of the problem, counting from 1.
+    position: Option<(usize, usize)>,
+    message: String,
+    /// The problem rendered along with the line it is on, for problems found reading the source.
+    snippet: Option<String>,
+}
+
+impl LoadError {
+    fn new(path: &Path, position: Option<(usize, usize)>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            path: path.to_path_buf(),
+            position,
+            message: message.into(),
+            snippet: None,
+        }
+    }
+
+    /// A problem found reading the contents of the file at the path.
+    fn in_source(path: &Path, contents: &str, diagnostic: &Diagnostic) -> Self {
+        Self {
+            severity: diagnostic.severity(),
+            snippet: Some(diagnostic.render(&path.display().to_string(), contents)),
+            ..Self::new(
+                path,
+                Some((diagnostic.line() + 1, diagnostic.start() + 1)),
+                diagnostic.message(),
+            )
+        }
+    }
+
+    /// A problem with a whole block, pointing at the line it starts on.
+    fn in_block(path: &Path, block: &SourceBlock, message: impl Into<String>) -> Self {
+        Self::new(path, Some((block.line() + 1, 1)), message)
+    }
+
+    /// Something most likely wrong with a whole block that still works.
+    fn warning(path: &Path, block: &SourceBlock, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::in_block(path, block, message)
+        }
+    }
+
+    /// Something worth knowing about a whole file or folder, such as an entry of the folder that
+    /// can't be read.
+    fn file_warning(path: &Path, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::new(path, None, message)
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets the line of the problem, counting from 1.
+    pub fn line(&self) -> Option<usize> {
+        self.position.map(|(line, _)| line)
+    }
+
+    /// Renders the problem for the terminal, with the line it is on when it is known.
+    pub fn render(&self) -> String {
+        self.snippet.clone().unwrap_or_else(|| self.to_string())
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "",
+            Severity::Warning => "warning: ",
+        };
+        match self.position {
+            Some((line, column)) => write!(
+                f,
+                "{}:{}:{}: {}{}",
+                self.path.display(),
+                line,
+                column,
+                severity,
+                self.message
+            ),
+            None => write!(f, "{}: {}{}", self.path.display(), severity, self.message),
+        }
+    }
+}
+
+/// A hermes file of the collection with its blocks.
+struct File {
+    path: PathBuf,
+    /// The folder of the file relative to the collection root, empty for the root. Included files
+    /// from outside the root are relative to the folder of what was included.
+    folder: String,
+    blocks: Vec<SourceBlock>,
+    snapshot: Option<FileSnapshot>,
+    /// Whether the file was only read because a collection block includes it. The collection
+    /// blocks of included files only bring in what they include in turn.
+    included: bool,
+}
+
+/// Loads every hermes file in the folder into a collection. A single hermes file is a collection of
+/// its own, rooted at the folder it is in. Collections are named after the folder or the file
+/// unless a collection block names them. The files and folders the collection blocks include are
+/// loaded along with them, each file once, so includes that loop back are harmless. All the
+/// problems found are returned, not only the first one, so they can be fixed in one go. Warnings
+/// don't keep the collection from loading, they are kept in it to be shown along with it.
+pub fn load_collection(path: &Path, settings: &ScanSettings) -> Result<Collection, Vec<LoadError>> {
+    let (mut collection, warnings) = load(path, settings)?;
+    for warning in warnings {
+        collection.add_warning(warning.render());
+    }
+    Ok(collection)
+}
+
+/// Loads the collection again only to find its problems as the files are now on disk, errors and
+/// warnings alike. The collection that is open is left as it is.
+pub fn check_collection(path: &Path, settings: &ScanSettings) -> Vec<LoadError> {
+    match load(path, settings) {
+        Ok((_, warnings)) => warnings,
+        Err(errors) => errors,
+    }
+}
+
+/// Loads the collection along with the warnings found loading it.
+fn load(
+    path: &Path,
+    settings: &ScanSettings,
+) -> Result<(Collection, Vec<LoadError>), Vec<LoadError>> {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    let (dir, paths) = if path.is_dir() {
+        let scan = parser::get_hermes_files(&path.to_string_lossy(), settings);
+        warnings.extend(
+            scan.warnings
+                .into_iter()
+                .map(|warning| LoadError::file_warning(path, warning)),
+        );
+        (path, scan.files)
+    } else if path.is_file() && path.extension().is_some_and(|ext| ext == "hermes") {
+        let dir = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        (dir, vec![path.to_path_buf()])
+    } else {
+        return Err(vec![LoadError::new(
+            path,
+            None,
+            "not a collection folder or hermes file",
+        )]);
+    };
+    let mut files = Vec::new();
+    // the files to read along with the folder their folder is relative to, None for the root
+    let mut pending: VecDeque<(PathBuf, Option<PathBuf>)> =
+        paths.into_iter().map(|path| (path, None)).collect();
+    let mut seen = HashSet::new();
+    while let Some((path, base)) = pending.pop_front() {
+        if !seen.insert(fs::canonicalize(&path).unwrap_or_else(|_| path.clone())) {
+            continue;
+        }
+        // taken first, so a change made while the file is read is a conflict when it is saved
+        let mut snapshot = FileSnapshot::of(&path).ok();
+        // files are read a line at a time, their text is only held for the ones that need it
+        let read = fs::File::open(&path).and_then(|file| source::read_from(BufReader::new(file)));
+        let (mut blocks, diagnostics) = match read {
+            Ok(read) => read,
+            Err(err) => {
+                errors.push(LoadError::new(&path, None, err.to_string()));
+                continue;
+            }
+        };
+        if !diagnostics.is_empty() || !migrations::is_current(&blocks) {
+            let contents = match fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(err) => {
+                    errors.push(LoadError::new(&path, None, err.to_string()));
+                    continue;
+                }
+            };
+            // files written for older versions are read as if they were migrated, and upgraded on
+            // disk when they can be written. Problems are looked for in the file as written, so
+            // they point at the right lines.
+            let migrated = match migrations::migrate(&contents) {
+                Ok(migrated) => migrated,
+                Err(err) => {
+                    errors.push(LoadError::new(&path, None, err.to_string()));
+                    continue;
+                }
+            };
+            if migrated.from != migrations::CURRENT_VERSION {
+                blocks = source::read(&migrated.contents).0;
+                let broken = diagnostics.iter().any(Diagnostic::is_error);
+                if !broken && storage::is_writable(path.parent().unwrap_or(Path::new("."))) {
+                    let migrated = migrations::migrate_file(&path);
+                    snapshot = FileSnapshot::of(&path).ok();
+                    let message = match migrated {
+                        // upgraded by something else since it was read
+                        Ok(None) => None,
+                        Ok(Some(backup)) => Some(format!(
+                            "upgraded to format version {}, the original is kept in {}",
+                            migrations::CURRENT_VERSION,
+                            backup.display()
+                        )),
+                        Err(err) => Some(format!(
+                            "can't upgrade to format version {}: {}",
+                            migrations::CURRENT_VERSION,
+                            err
+                        )),
+                    };
+                    warnings.extend(message.map(|message| LoadError::file_warning(&path, message)));
+                }
+            }
+            for diagnostic in &diagnostics {
+                let problem = LoadError::in_source(&path, &contents, diagnostic);
+                if diagnostic.is_error() {
+                    errors.push(problem);
+                } else {
+                    warnings.push(problem);
+                }
+            }
+        }
+        for include in includes(&blocks) {
+            let Ok(target) =
+                fs::canonicalize(path.parent().unwrap_or(Path::new(".")).join(include))
+            else {
+                errors.push(LoadError::new(
+                    &path,
+                    None,
+                    format!("can't include {}: no such file or folder", include),
+                ));
+                continue;
+            };
+            let base = target.parent().unwrap_or(Path::new(".")).to_path_buf();
+            if target.is_dir() {
+                let scan = parser::get_hermes_files(&target.to_string_lossy(), settings);
+                warnings.extend(
+                    scan.warnings
+                        .into_iter()
+                        .map(|warning| LoadError::file_warning(&target, warning)),
+                );
+                pending.extend(
+                    scan.files
+                        .into_iter()
+                        .map(|file| (file, Some(base.clone()))),
+                );
+            } else {
+                pending.push_back((target, Some(base)));
+            }
+        }
+        let folder = path
+            .parent()
+            .and_then(|parent| parent.strip_prefix(base.as_deref().unwrap_or(dir)).ok())
+            .map(|folder| folder.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+        files.push(File {
+            path,
+            folder,
+            blocks,
+            snapshot,
+            included: base.is_some(),
+        });
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    // references can point at blocks of any file in the collection
+    let blocks: Vec<SourceBlock> = files
+        .iter()
+        .flat_map(|file| file.blocks.iter().cloned())
+        .collect();
+    let mut collection = Collection::default();
+    collection.set_read_only(!storage::is_writable(dir));
+    collection.set_root(dir.to_path_buf());
+    collection.set_loaded_from(path.to_path_buf());
+    if let Some(name) = name_of(path) {
+        collection.set_name(name);
+    }
+    for file in &files {
+        collection.add_loaded_file(file.path.clone(), file.snapshot.clone());
+        for block in &file.blocks {
+            let loaded = match block.block_type() {
+                "environment" if !block.identifier().is_empty() => {
+                    load_environment(&mut collection, block);
+                    Ok(())
+                }
+                "request" => load_request(&mut collection, block, &blocks, file),
+                "hooks" => load_hooks(&mut collection, block, file),
+                "folder" => load_folder(&mut collection, block, file),
+                _ => Ok(()),
+            };
+            if let Err(message) = loaded {
+                errors.push(LoadError::in_block(&file.path, block, message));
+            }
+        }
+    }
+    // the collection block comes last so its environment is the active one
+    for file in files.iter().filter(|file| !file.included) {
+        for block in file
+            .blocks
+            .iter()
+            .filter(|b| b.block_type() == "collection")
+        {
+            if let Err(message) = load_collection_block(&mut collection, block, &blocks, dir) {
+                errors.push(LoadError::in_block(&file.path, block, message));
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    warnings.extend(lint(&files, &blocks));
+    Ok((collection, warnings))
+}
+
+/// Looks for blocks that are most likely a mistake though the collection loads fine: named
+/// environments setting none of the variables used anywhere else, and headers blocks that no
+/// block uses.
+fn lint(files: &[File], blocks: &[SourceBlock]) -> Vec<LoadError> {
+    let mut referenced = HashSet::new();
+    let mut used_variables = HashSet::new();
+    for block in blocks {
+        let is_environment = block.block_type() == "environment";
+        visit(block, &mut |name, value| match value {
+            SourceValue::Reference(identifier) => {
+                referenced.insert(identifier.as_str());
+            }
+            // requests pin environments by name
+            SourceValue::Text(text) if name == "environment" => {
+                referenced.insert(text.as_str());
+            }
+            SourceValue::Text(text) if !is_environment => {
+                used_variables.extend(variables::names_in(text));
+            }
+            _ => {}
+        });
+    }
+    let mut warnings = Vec::new();
+    for file in files {
+        for block in &file.blocks {
+            let identifier = block.identifier();
+            match block.block_type() {
+                "environment"
+                    if !identifier.is_empty()
+                        && !referenced.contains(identifier)
+                        && !block
+                            .toggled_entries()
+                            .any(|(name, _, _)| used_variables.contains(name)) =>
+                {
+                    warnings.push(LoadError::warning(
+                        &file.path,
+                        block,
+                        format!(
+                            "the environment {} sets none of the variables the collection uses",
+                            identifier
+                        ),
+                    ));
+                }
+                "headers" if identifier.is_empty() => warnings.push(LoadError::warning(
+                    &file.path,
+                    block,
+                    "this headers block has no identifier, so no block can use it",
+                )),
+                "headers" if !referenced.contains(identifier) => warnings.push(LoadError::warning(
+                    &file.path,
+                    block,
+                    format!("the headers block {} is never used", identifier),
+                )),
+                _ => {}
+            }
+        }
+    }
+    warnings
+}
+
+/// Calls visit with the name and value of every field of the block and of the blocks written
+/// inline in it, disabled fields included.
+fn visit<'a>(block: &'a SourceBlock, visit_field: &mut impl FnMut(&'a str, &'a SourceValue)) {
+    for (name, _, value) in block.toggled_entries() {
+        visit_field(name, value);
+        if let SourceValue::Block(inline) = value {
+            visit(inline, visit_field);
+        }
+    }
+}
+
+/// Gets the paths the collection blocks include, as written.
+fn includes(blocks: &[SourceBlock]) -> Vec<&str> {
+    blocks
+        .iter()
+        .filter(|block| block.block_type() == "collection")
+        .flat_map(|block| block.fields("include"))
+        .filter_map(|value| match value {
+            SourceValue::Text(path) => Some(path.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Gets the name of the folder or the hermes file, without its extension.
+fn name_of(path: &Path) -> Option<String> {
+    let name = if path.is_dir() {
+        fs::canonicalize(path).ok()?.file_name()?.to_owned()
+    } else {
+        path.file_stem()?.to_owned()
+    };
+    Some(name.to_string_lossy().to_string())
+}
+
+fn load_collection_block(
+    collection: &mut Collection,
+    block: &SourceBlock,
+    blocks: &[SourceBlock],
+    dir: &Path,
+) -> Result<(), String> {
+    if let Some(name) = block.text("name") {
+        collection.set_name(name.to_string());
+    }
+
+    // every environment field is merged into one, later fields replace the keys of earlier ones
+    let mut environment = Vec::new();
+    let mut environment_name = None;
+    for value in block.fields("environment") {
+        match value {
+            SourceValue::Text(path) => {
+                let contents = fs::read_to_string(dir.join(path))
+                    .map_err(|err| format!("can't read the environment file {}: {}", path, err))?;
+                environment.extend(dotenv(&contents));
+            }
+            value => {
+                if let SourceValue::Reference(identifier) = value {
+                    environment_name.get_or_insert_with(|| identifier.clone());
+                }
+                environment.extend(texts(source::resolve(value, blocks, "environment")?));
+            }
+        }
+    }
+    if !environment.is_empty() || environment_name.is_some() {
+        let name = environment_name.unwrap_or_else(|| String::from(DEFAULT_ENVIRONMENT));
+        collection.new_environment(name.clone());
+        collection.set_active_environment(name);
+        for (key, value) in environment {
+            collection.add_environment_entry(key, value);
+        }
+        collection.enable_active_environment();
+    }
+
+    for value in block.fields("variables") {
+        for (key, value) in texts(source::resolve(value, blocks, "variables")?) {
+            collection.add_variable(key, value);
+        }
+    }
+    for value in block.fields("headers") {
+        for (name, value) in texts(source::resolve(value, blocks, "headers")?) {
+            collection.add_default_header(name, value);
+        }
+    }
+    for (field, value) in block.entries() {
+        let SourceValue::Text(text) = value else {
+            continue;
+        };
+        match field {
+            "secret" => collection.mark_secret(text.clone()),
+            "ca" => collection.set_ca_certificate(Some(text.clone())),
+            "accept" => collection.set_default_accept(text.parse::<AcceptPreset>()?),
+            "ip-family" => collection.set_ip_family(text.parse::<IpFamily>()?),
+            "proxy" => collection.set_proxy(text.parse::<ProxyRoute>()?),
+            "precondition" => collection.add_precondition(Precondition::parse(text)?),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Adds a named environment block as an environment of the collection, keeping the active one.
+fn load_environment(collection: &mut Collection, block: &SourceBlock) {
+    let active = collection.active_environment_name().to_string();
+    collection.new_environment(block.identifier().to_string());
+    collection.set_active_environment(block.identifier().to_string());
+    for (key, value) in texts(block) {
+        collection.add_environment_entry(key, value);
+    }
+    collection.set_active_environment(active);
+}
+
+fn load_request(
+    collection: &mut Collection,
+    block: &SourceBlock,
+    blocks: &[SourceBlock],
+    file: &File,
+) -> Result<(), String> {
+    let mut request = Request::new(
+        String::new(),
+        HttpMethod::default(),
+        String::new(),
+        None,
+        None,
+        Default::default(),
+    );
+    source::apply_request_block(&mut request, block, blocks)?;
+    for value in block.fields("settings") {
+        let settings = source::resolve(value, blocks, "settings")?;
+        if let Some(viewer) = settings.text("viewer") {
+            request.set_viewer(Some(viewer.parse()?));
+        }
+        if let Some(family) = settings.text("ip-family") {
+            request.set_ip_family(Some(family.parse()?));
+        }
+        if let Some(proxy) = settings.text("proxy") {
+            request.set_proxy(Some(proxy.parse()?));
+        }
+        if let Some(cursor) = settings.text("next-cursor") {
+            request.set_next_cursor(Some(Cursor::parse(cursor)?));
+        }
+        if let Some(retries) = settings.text("retries") {
+            let retries = retries
+                .trim()
+                .parse()
+                .map_err(|_| format!("retries must be a number, got `{}`", retries))?;
+            request.set_retries(retries);
+        }
+        match (settings.text("sign-body"), settings.text("signing-key")) {
+            (Some(method), Some(key)) => {
+                request.set_body_signing(Some(BodySigning::new(method.parse()?, key.to_string())))
+            }
+            (Some(_), None) => return Err(String::from("sign-body needs a signing-key")),
+            _ => {}
+        }
+        match settings.text("idempotency-key").map(str::trim) {
+            Some("auto") => request.set_auto_idempotency_key(true),
+            Some(other) => return Err(format!("idempotency-key must be `auto`, got `{}`", other)),
+            None => {}
+        }
+    }
+    request.set_folder(file.folder.clone());
+    request.set_file(file.path.clone());
+    collection.add_request(request);
+    Ok(())
+}
+
+/// Reads a hooks block, which only has a meaning in a collection or folder file.
+fn load_hooks(collection: &mut Collection, block: &SourceBlock, file: &File) -> Result<(), String> {
+    let scope = match file.path.file_name().and_then(|name| name.to_str()) {
+        Some(COLLECTION_FILE) if file.folder.is_empty() => Scope::Collection,
+        Some(FOLDER_FILE) => Scope::Folder(file.folder.clone()),
+        _ => {
+            return Err(format!(
+                "hooks go in {} or {}",
+                COLLECTION_FILE, FOLDER_FILE
+            ))
+        }
+    };
+    let hooks = collection.hooks_mut(scope);
+    for (field, value) in block.entries() {
+        match (field, value) {
+            ("before", SourceValue::Text(request)) => hooks.add_before(request.clone()),
+            ("after", SourceValue::Text(request)) => hooks.add_after(request.clone()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Reads a folder block, which describes the folder its folder.hermes file is in.
+fn load_folder(
+    collection: &mut Collection,
+    block: &SourceBlock,
+    file: &File,
+) -> Result<(), String> {
+    if file.path.file_name().and_then(|name| name.to_str()) != Some(FOLDER_FILE) {
+        return Err(format!("folder blocks go in {}", FOLDER_FILE));
+    }
+    if let Some(message) = block.text("deprecated") {
+        collection.deprecate_folder(file.folder.clone(), message.to_string());
+    }
+    Ok(())
+}
+
+/// Gets the fields of a block that have text values, such as the entries of an environment.
+fn texts(block: &SourceBlock) -> Vec<(String, String)> {
+    block
+        .entries()
+        .filter_map(|(key, value)| match value {
+            SourceValue::Text(text) => Some((key.to_string(), text.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Reads the `KEY=VALUE` lines of a dotenv file, skipping comments. Quotes around values are
+/// dropped.
+pub fn dotenv(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            let value = value.trim();
+            let unquoted = ['"', '\'']
+                .iter()
+                .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+                .unwrap_or(value);
+            (
+                key.trim_start_matches("export ").trim().to_string(),
+                unquoted.to_string(),
+            )
+        })
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::external;
+
+    #[test]
+    fn should_load_requests_across_files() {
+        let dir = external::temp_path("loader", "d");
+        fs::create_dir_all(dir.join("users")).unwrap();
+        fs::write(
+            dir.join(COLLECTION_FILE),
+            "collection {\n    name `api`\n    environment 1 local\n    secret 1 `TOKEN`\n    headers defaults\n}\n\n\
+             headers as defaults {\n    User-Agent 1 `hermes`\n    X-Debug 0 `1`\n}\n\n\
+             environment as local {\n    TOKEN `abc`\n}\n\n\
+             hooks {\n    before 1 `login`\n}\n\n\
+             request as login {\n    url `{{BASE}}/login`\n    method `post`\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("users/list.hermes"),
+            "request {\n    name `list users`\n    url `/users`\n    headers auth\n    tests {\n        status 1 `200`\n    }\n}\n\n\
+             headers as auth {\n    Authorization `Bearer {{TOKEN}}`\n}\n",
+        )
+        .unwrap();
+
+        // the files have no meta block, the first load upgrades them from version 1
+        let upgraded = load_collection(&dir, &ScanSettings::default()).unwrap();
+        assert_eq!(upgraded.warnings().len(), 2);
+        let collection = load_collection(&dir, &ScanSettings::default()).unwrap();
+        assert!(collection.warnings().is_empty());
+        assert_eq!(collection.name(), "api");
+        assert_eq!(
+            collection
+                .active_environment()
+                .and_then(|env| env.get("TOKEN")),
+            Some(&String::from("abc"))
+        );
+        assert!(collection.is_secret("TOKEN"));
+        assert_eq!(
+            collection.default_headers(),
+            &HashMap::from([(String::from("User-Agent"), String::from("hermes"))])
+        );
+        assert_eq!(collection.hooks()[&Scope::Collection].before(), ["login"]);
+        let list = collection
+            .iter()
+            .find(|request| request.get_name() == "list users")
+            .unwrap();
+        assert_eq!(list.get_folder(), "users");
+        assert_eq!(list.get_assertions().len(), 1);
+        assert_eq!(list.get_headers()["Authorization"], "Bearer {{TOKEN}}");
+
+        let single = load_collection(&dir.join("users/list.hermes"), &ScanSettings::default());
+        let single = single.unwrap();
+        assert_eq!(single.name(), "list");
+        assert_eq!(single.get_request_count(), 1);
+
+        // what the scan of the folder ran into is a warning of the collection
+        let shallow = ScanSettings {
+            max_depth: 1,
+            ..ScanSettings::default()
+        };
+        let shallow = load_collection(&dir, &shallow).unwrap();
+        assert_eq!(shallow.get_request_count(), 1);
+        assert!(shallow.warnings()[0].contains("deeper than 1 folders"));
+
+        // a folder next to the collection that includes the collection back
+        let shared = dir.with_extension("shared");
+        fs::create_dir_all(shared.join("auth")).unwrap();
+        let shared_name = shared.file_name().unwrap().to_string_lossy();
+        fs::write(
+            shared.join("auth/refresh.hermes"),
+            format!(
+                "collection {{\n    name `shared`\n    include `../../{}`\n}}\n\n\
+                 request {{\n    name `refresh`\n    url `/refresh`\n}}\n",
+                dir.file_name().unwrap().to_string_lossy()
+            ),
+        )
+        .unwrap();
+        let contents = fs::read_to_string(dir.join(COLLECTION_FILE)).unwrap();
+        fs::write(
+            dir.join(COLLECTION_FILE),
+            contents.replacen(
+                "collection {\n",
+                &format!("collection {{\n    include `../{}`\n", shared_name),
+                1,
+            ),
+        )
+        .unwrap();
+        let included = load_collection(&dir, &ScanSettings::default()).unwrap();
+        assert_eq!(included.name(), "api");
+        assert_eq!(included.get_request_count(), 3);
+        let refresh = included
+            .iter()
+            .find(|request| request.get_name() == "refresh")
+            .unwrap();
+        assert_eq!(refresh.get_folder(), format!("{}/auth", shared_name));
+        fs::remove_dir_all(shared).unwrap();
+        let errors = load_collection(&dir, &ScanSettings::default()).unwrap_err();
+        assert!(errors[0].to_string().ends_with("no such file or folder"));
+        fs::write(dir.join(COLLECTION_FILE), contents).unwrap();
+
+        fs::write(
+            dir.join("stale.hermes"),
+            format!(
+                "meta {{\n    version `{}`\n}}\n\n\
+                 environment as old {{\n    LEGACY_HOST `old.example.com`\n}}\n\n\
+                 headers as unused {{\n    Accept `*/*`\n}}\n",
+                migrations::CURRENT_VERSION
+            ),
+        )
+        .unwrap();
+        let warned = load_collection(&dir, &ScanSettings::default()).unwrap();
+        let stale = dir.join("stale.hermes").display().to_string();
+        assert_eq!(
+            warned.warnings(),
+            [
+                format!(
+                    "{}:5:1: warning: the environment old sets none of the variables the collection uses",
+                    stale
+                ),
+                format!("{}:9:1: warning: the headers block unused is never used", stale),
+            ]
+        );
+        let problems = check_collection(&dir, &ScanSettings::default());
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].severity(), Severity::Warning);
+        assert_eq!(problems[0].path(), dir.join("stale.hermes"));
+        assert_eq!(problems[0].line(), Some(5));
+        fs::remove_file(dir.join("stale.hermes")).unwrap();
+
+        // files of an older version are upgraded on disk, keeping the original
+        let old = "request {\n    name `legacy`\n    url `/legacy`\n}\n";
+        fs::write(dir.join("legacy.hermes"), old).unwrap();
+        let upgraded = load_collection(&dir, &ScanSettings::default()).unwrap();
+        let backup = dir.join("legacy.hermes.v1.bak");
+        assert_eq!(
+            upgraded.warnings(),
+            [format!(
+                "{}: warning: upgraded to format version {}, the original is kept in {}",
+                dir.join("legacy.hermes").display(),
+                migrations::CURRENT_VERSION,
+                backup.display()
+            )]
+        );
+        assert_eq!(fs::read_to_string(&backup).unwrap(), old);
+        let contents = fs::read_to_string(dir.join("legacy.hermes")).unwrap();
+        assert_eq!(
+            migrations::version_of(&contents).unwrap(),
+            migrations::CURRENT_VERSION
+        );
+        let reloaded = load_collection(&dir, &ScanSettings::default()).unwrap();
+        assert!(reloaded.warnings().is_empty());
+        assert_eq!(reloaded.get_request_count(), upgraded.get_request_count());
+        fs::remove_file(dir.join("legacy.hermes")).unwrap();
+        fs::remove_file(backup).unwrap();
+
+        fs::write(dir.join("broken.hermes"), "request {\n    url\n}\n").unwrap();
+        let errors = load_collection(&dir, &ScanSettings::default()).unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            format!(
+                "{}:2:5: the field url has no value",
+                dir.join("broken.hermes").display()
+            )
+        );
+        assert_eq!(check_collection(&dir, &ScanSettings::default()), errors);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,132 @@
+//! Keyboard macros. A macro is a sequence of keys recorded into a register, like in vim, that can
+//! be replayed with a single keybinding. Recorded macros are kept in `macros.json` in the config
+//! directory, macros can also be written by hand in the `macros` setting.
+
+use std::{collections::HashMap, fs, io, path::PathBuf};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::{settings, storage};
+
+/// The file in the config directory where recorded macros are saved.
+const MACROS_FILE: &str = "macros.json";
+
+/// Macros by register, each one a list of keys written like `a`, `<enter>` or `<c-t>`.
+#[derive(Debug, Default, Clone)]
+pub struct Macros {
+    macros: HashMap<char, Vec<String>>,
+}
+
+impl Macros {
+    /// Loads the macros from the settings and the recorded ones, recorded macros win when both
+    /// use the same register.
+    pub fn load(configured: &HashMap<String, Vec<String>>) -> Self {
+        let mut macros: HashMap<char, Vec<String>> = configured
+            .iter()
+            .filter_map(|(register, keys)| Some((register.chars().next()?, keys.clone())))
+            .collect();
+        if let Some(recorded) = macros_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str::<HashMap<char, Vec<String>>>(&contents).ok())
+        {
+            macros.extend(recorded);
+        }
+        Self { macros }
+    }
+
+    /// Gets the keys of the macro in the register. Keys that can't be read are skipped.
+    pub fn get(&self, register: char) -> Option<Vec<KeyEvent>> {
+        self.macros
+            .get(&register)
+            .map(|keys| keys.iter().filter_map(|key| parse_key(key)).collect())
+    }
+
+    /// Stores the keys in the register and saves every recorded macro.
+    pub fn record(&mut self, register: char, keys: &[KeyEvent]) -> io::Result<()> {
+        self.macros
+            .insert(register, keys.iter().filter_map(key_to_string).collect());
+        let Some(path) = macros_path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = serde_json::to_string_pretty(&self.macros)?;
+        storage::overwrite(&path, &contents).map_err(|err| io::Error::other(err.to_string()))?;
+        Ok(())
+    }
+}
+
+fn macros_path() -> Option<PathBuf> {
+    Some(settings::config_dir()?.join(MACROS_FILE))
+}
+
+/// Writes a key the way it is stored in a macro. Returns None for keys macros don't support.
+pub fn key_to_string(key: &KeyEvent) -> Option<String> {
+    let name = match key.code {
+        KeyCode::Char(ch) if key.modifiers == KeyModifiers::CONTROL => {
+            return Some(format!("<c-{}>", ch))
+        }
+        KeyCode::Char('<') => String::from("<lt>"),
+        KeyCode::Char(ch) => return Some(ch.to_string()),
+        KeyCode::Enter => String::from("<enter>"),
+        KeyCode::Esc => String::from("<esc>"),
+        KeyCode::Tab => String::from("<tab>"),
+        KeyCode::BackTab => String::from("<s-tab>"),
+        KeyCode::Backspace => String::from("<bs>"),
+        KeyCode::Up => String::from("<up>"),
+        KeyCode::Down => String::from("<down>"),
+        KeyCode::Left => String::from("<left>"),
+        KeyCode::Right => String::from("<right>"),
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Reads a key written by key_to_string.
+pub fn parse_key(key: &str) -> Option<KeyEvent> {
+    let mut chars = key.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        return Some(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE));
+    }
+    let name = key.strip_prefix('<')?.strip_suffix('>')?.to_lowercase();
+    let code = match name.as_str() {
+        "lt" => KeyCode::Char('<'),
+        "enter" | "cr" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "s-tab" => KeyCode::BackTab,
+        "bs" => KeyCode::Backspace,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        _ => {
+            let ch = name.strip_prefix("c-")?;
+            let mut chars = ch.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(ch), None) => Some(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::CONTROL)),
+                _ => None,
+            };
+        }
+    };
+    Some(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_write_and_read_keys() {
+        for key in ["a", "<", "<enter>", "<c-t>", "<s-tab>", "<bs>"] {
+            let parsed = parse_key(key).unwrap();
+            assert_eq!(parse_key(&key_to_string(&parsed).unwrap()), Some(parsed));
+        }
+        assert_eq!(
+            parse_key("<C-T>"),
+            Some(KeyEvent::new(KeyCode::Char('t'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(parse_key("<nope>"), None);
+    }
+}
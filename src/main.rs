@@ -1,67 +1,328 @@
-use std::io;
+use std::{
+    io,
+    path::Path,
+    process,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use clap::{CommandFactory, Parser};
+
+use cli::{Cli, Command, DeprecatedPolicy, RunArgs};
 
 mod api;
 mod app;
+mod assertions;
+mod ast;
+mod auth;
+mod background;
+mod bulk;
+mod capture;
+mod changelog;
+mod charset;
+mod cli;
+mod client;
+mod complete;
 mod components;
-mod lexer;
+mod cookies;
+mod cors;
+mod credentials;
+mod diagnostics;
+mod drafts;
+mod editor;
+mod etags;
+mod expected;
+mod external;
+mod faker;
+mod filter;
+mod folders;
+mod har;
+mod headers;
+mod highlight;
+mod history;
+mod import;
+mod json_tree;
+mod jwt;
+mod lab;
+mod loader;
+mod macros;
+mod migrations;
+mod network;
+mod notifications;
+mod overrides;
+mod pagination;
 mod parser;
-mod transition_table;
+mod places;
+mod queue;
+mod redaction;
+mod runner;
+mod search;
+mod secrets;
+mod settings;
+mod share;
+mod signing;
+mod snapshots;
+mod source;
+mod storage;
+mod syntax;
+mod table;
+mod tabs;
 mod tui;
+mod tunnel;
+mod usage;
+mod utilities;
+mod variables;
+mod worker;
 
-// fn main() -> io::Result<()> {
-//     let tokens = parser::parser::parse("metadata { name some-name_hey1}");
-//     println!("{:?}", tokens);
-//     let mut terminal = tui::init()?;
-//     let app_result = app::App::default().run(&mut terminal);
-//     tui::restore()?;
-//     app_result
-// }
+fn main() -> io::Result<()> {
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Run(args)) => process::exit(run(&args)),
+        Some(Command::Complete(args)) => {
+            match complete::run(&args) {
+                Ok(candidates) => println!("{}", candidates),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    process::exit(2);
+                }
+            }
+            return Ok(());
+        }
+        Some(Command::Ast { file, json }) => {
+            match ast::run(&file, json) {
+                Ok((printed, has_problems)) => {
+                    print!("{}", printed);
+                    if has_problems {
+                        process::exit(1);
+                    }
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    process::exit(2);
+                }
+            }
+            return Ok(());
+        }
+        Some(Command::Usage { collection }) => process::exit(print_usage(&collection)),
+        Some(Command::Import {
+            source,
+            into,
+            dry_run,
+        }) => process::exit(run_import(&source, &into, dry_run)),
+        Some(Command::Completions { shell }) => {
+            clap_complete::generate(shell, &mut Cli::command(), "hermes", &mut io::stdout());
+            return Ok(());
+        }
+        Some(Command::Man) => {
+            return clap_mangen::Man::new(Cli::command()).render(&mut io::stdout())
+        }
+        None => {}
+    }
+    let settings = load_settings();
+    let mut collections = Vec::new();
+    let mut errors = Vec::new();
+    for path in &cli.collections {
+        match loader::load_collection(path, &settings.scan) {
+            Ok(collection) => collections.push(collection),
+            Err(err) => errors.extend(err),
+        }
+    }
+    if !errors.is_empty() {
+        for err in errors {
+            eprintln!("{}", err.render());
+        }
+        process::exit(runner::Exit::Invalid.code());
+    }
+    let mut terminal = tui::init()?;
+    let app_result = app::App::new(settings, collections).run(&mut terminal);
+    tui::restore()?;
+    app_result
+}
+
+fn load_settings() -> settings::Settings {
+    match settings::Settings::load() {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("Error reading hermes config, using defaults: {}", err);
+            settings::Settings::default()
+        }
+    }
+}
+
+/// Runs `hermes import`, returning the exit code. Collections of other tools are told apart by
+/// their contents, anything else is read as a share.
+fn run_import(source: &str, into: &Path, dry_run: bool) -> i32 {
+    let Some(format) = import::Format::detect(Path::new(source)) else {
+        if dry_run {
+            eprintln!("--dry-run only previews collections");
+            return 1;
+        }
+        return match share::import(source, into) {
+            Ok(path) => {
+                println!("Imported {}", path.display());
+                0
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                1
+            }
+        };
+    };
+    let imported = match format.convert(Path::new(source)) {
+        Ok(imported) => imported,
+        Err(err) => {
+            eprintln!("{}", err);
+            return 1;
+        }
+    };
+    for warning in &imported.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    if dry_run {
+        for (path, _) in import::to_files(&imported.collection) {
+            println!("Would write {}", into.join(path).display());
+        }
+        return 0;
+    }
+    match import::write(&imported.collection, into) {
+        Ok(paths) => {
+            println!(
+                "Imported {} requests of {} into {} files",
+                imported.collection.get_request_count(),
+                imported.collection.name(),
+                paths.len()
+            );
+            0
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    }
+}
+
+/// Runs `hermes usage`, returning the exit code.
+fn print_usage(path: &Path) -> i32 {
+    let settings = load_settings();
+    let collection = match loader::load_collection(path, &settings.scan) {
+        Ok(collection) => collection,
+        Err(errors) => {
+            for err in errors {
+                eprintln!("{}", err.render());
+            }
+            return runner::Exit::Invalid.code();
+        }
+    };
+    let names: Vec<String> = collection
+        .iter()
+        .map(|request| request.get_name())
+        .collect();
+    let exported =
+        usage::Usage::load().export(&collection.name(), names.iter().map(String::as_str));
+    match exported {
+        Ok(exported) => {
+            println!("{}", exported);
+            0
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    }
+}
 
-fn main() {
-    // let mut lexer = parse::lexer::Lexer::new(
-    //     r#"
-    // collection::C {
-    //     name "my collection"
-    //     include "."
-    //     environment 0 dev
-    // }
-    //
-    // environment::dev {
-    //     URL "/url"
-    // }
-    // "#,
-    // );
-    // let mut parser = parse::collection::CollectionParser::default();
-    // let collection = parser.parse(&mut lexer);
-    // println!("{:?}", collection);
-    // let tokens = parser::parse::parse(
-    //     r#"
-    // metadata::m {
-    //     name 0 "some value and escaped \n \""
-    //     some-other2123_?dksj
-    // }
-    // "#,
-    // );
-    // println!("{:?}", tokens);
-    // let mut terminal = tui:: lginit()?;
-    // let app_result = app::App::default().run(&mut terminal);
-    // tui::restore()?;
-    // app_result
-    //
-    // let input = r#"
-    //     body.json::json {
-    //         value 1 `{
-    //             "name": {
-    //                 "first": "Juan",
-    //                 "last": "Wu"
-    //             }
-    //         }`
-    //     }
-    // "#;
-    // let input = r#"body.json as json {name_2 0 `some value @
-    //
-    //
-    // \``}collection as c {"kdjaskjd kkdjas d 0 k}"""#;
-    let dir = "./examples";
-    parser::parse(dir);
+/// Runs `hermes run`, returning the exit code.
+fn run(args: &RunArgs) -> i32 {
+    let settings = load_settings();
+    let mut collection = match loader::load_collection(&args.collection, &settings.scan) {
+        Ok(collection) => collection,
+        Err(errors) => {
+            for err in errors {
+                eprintln!("{}", err.render());
+            }
+            return runner::Exit::Invalid.code();
+        }
+    };
+    if !args.quiet {
+        for warning in collection.warnings() {
+            eprintln!("{}", warning);
+        }
+    }
+    if let Some(env) = &args.env {
+        if collection.environment(env).is_none() {
+            eprintln!("environment `{}` does not exist", env);
+            return runner::Exit::Usage.code();
+        }
+        collection.set_active_environment(env.clone());
+        collection.enable_active_environment();
+    }
+    if let Some(request) = &args.request {
+        if let Err(err) = runner::only(&mut collection, request) {
+            eprintln!("{}", err);
+            return runner::Exit::Usage.code();
+        }
+    }
+    let options = client::Options {
+        ip_family: collection.ip_family(),
+        proxy: collection.proxy().clone(),
+        ca_certificate: collection.ca_certificate().map(String::from),
+        max_body_bytes: settings.max_body_bytes,
+        network: None,
+        lab_mode: settings.lab_mode,
+        retries: 0,
+        cancel: background::Cancel::default(),
+        tunnel: collection
+            .active_environment()
+            .and_then(tunnel::SshTunnel::from_environment)
+            .map(|tunnel| Arc::new(Mutex::new(tunnel))),
+        timeout: None,
+    };
+    let verbosity = if args.quiet {
+        runner::Verbosity::Quiet
+    } else if args.verbose {
+        runner::Verbosity::Verbose
+    } else {
+        runner::Verbosity::Normal
+    };
+    let deprecated = match args.deprecated {
+        DeprecatedPolicy::Warn => runner::Deprecated::Warn,
+        DeprecatedPolicy::Skip => runner::Deprecated::Skip,
+        DeprecatedPolicy::Fail => runner::Deprecated::Fail,
+    };
+    let mut resolver = variables::Resolver::new(settings.allowed_commands);
+    // the output ends up in CI logs, which must not hold what is redacted
+    let mut out = settings.redaction.writer(io::stdout());
+    let started = Instant::now();
+    let result = runner::run(
+        &collection,
+        &options,
+        &mut resolver,
+        verbosity,
+        deprecated,
+        args.dry_run,
+        &mut out,
+    );
+    let body = match &result {
+        Ok(Ok(summary)) => summary.to_string(),
+        Ok(Err((_, err))) => err.clone(),
+        Err(err) => err.to_string(),
+    };
+    let summary = format!("hermes run: {}", collection.name());
+    if let Some(notification) = settings
+        .notifications
+        .finished(started.elapsed(), summary, body)
+    {
+        let _ = notification.join();
+    }
+    match result {
+        Ok(Ok(summary)) => summary.exit().code(),
+        Ok(Err((exit, err))) => {
+            eprintln!("{}", err);
+            exit.code()
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            runner::Exit::Invalid.code()
+        }
+    }
 }
@@ -0,0 +1,228 @@
+//! Versioning of the hermes format. Every file carries its format version in a `meta` block, files
+//! without one are version 1. Older files are upgraded when they are loaded, after a backup of the
+//! original is written next to them, and files from a newer Hermes are refused instead of being
+//! read wrong.
+
+use std::{
+    fmt, fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use crate::{source::SourceBlock, storage};
+
+/// The format version this Hermes reads and writes.
+pub const CURRENT_VERSION: u32 = 2;
+
+/// An upgrade of the format from one version to the next.
+struct Migration {
+    from: u32,
+    description: &'static str,
+    apply: fn(&str) -> String,
+}
+
+/// Every migration in the order they run. The migration from version N turns a file into version
+/// N + 1, the version in the meta block is updated separately.
+const MIGRATIONS: [Migration; 1] = [Migration {
+    from: 1,
+    description: "use self-requests and self-environments for the reserved identifiers",
+    apply: |contents| {
+        replace_word(
+            &replace_word(contents, "self_requests", "self-requests"),
+            "self_environments",
+            "self-environments",
+        )
+    },
+}];
+
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The file was written for a newer format than this Hermes knows.
+    TooNew(u32),
+    InvalidVersion(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::TooNew(version) => write!(
+                f,
+                "format version {} is newer than this Hermes supports ({}), update Hermes to open it",
+                version, CURRENT_VERSION
+            ),
+            MigrationError::InvalidVersion(version) => {
+                write!(f, "format version `{}` is not a number", version)
+            }
+            MigrationError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for MigrationError {
+    fn from(err: io::Error) -> Self {
+        MigrationError::Io(err)
+    }
+}
+
+/// The result of bringing a file up to the current version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migrated {
+    pub contents: String,
+    /// The version the file was in before.
+    pub from: u32,
+    /// What every migration that ran changed.
+    pub applied: Vec<&'static str>,
+}
+
+/// Gets the format version of the contents, 1 when there is no version in a meta block.
+pub fn version_of(contents: &str) -> Result<u32, MigrationError> {
+    match find_version(contents) {
+        Some(range) => contents[range.clone()]
+            .trim()
+            .parse()
+            .map_err(|_| MigrationError::InvalidVersion(contents[range].to_string())),
+        None => Ok(1),
+    }
+}
+
+/// Tells whether the blocks read from a file are in the current version, without needing its
+/// text. A version that is not a number is not current.
+pub fn is_current(blocks: &[SourceBlock]) -> bool {
+    blocks
+        .iter()
+        .find(|block| block.block_type() == "meta")
+        .and_then(|meta| meta.text("version"))
+        .is_some_and(|version| version.trim().parse() == Ok(CURRENT_VERSION))
+}
+
+/// Runs every migration the contents need to be in the current version.
+pub fn migrate(contents: &str) -> Result<Migrated, MigrationError> {
+    let from = version_of(contents)?;
+    if from > CURRENT_VERSION {
+        return Err(MigrationError::TooNew(from));
+    }
+    let mut migrated = Migrated {
+        contents: contents.to_string(),
+        from,
+        applied: Vec::new(),
+    };
+    if from == CURRENT_VERSION {
+        return Ok(migrated);
+    }
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= from) {
+        migrated.contents = (migration.apply)(&migrated.contents);
+        migrated.applied.push(migration.description);
+    }
+    migrated.contents = set_version(&migrated.contents, CURRENT_VERSION);
+    Ok(migrated)
+}
+
+/// Upgrades the file to the current version, keeping the original in `<file>.v<version>.bak`.
+/// Returns the path of the backup, or None when the file was already up to date.
+pub fn migrate_file(path: &Path) -> Result<Option<PathBuf>, MigrationError> {
+    let contents = fs::read_to_string(path)?;
+    let migrated = migrate(&contents)?;
+    if migrated.from == CURRENT_VERSION {
+        return Ok(None);
+    }
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".v{}.bak", migrated.from));
+    let backup = PathBuf::from(backup);
+    fs::copy(path, &backup)?;
+    storage::overwrite(path, &migrated.contents).map_err(|err| match err {
+        storage::SaveError::Io(err) => MigrationError::Io(err),
+        err => MigrationError::Io(io::Error::other(err.to_string())),
+    })?;
+    Ok(Some(backup))
+}
+
+/// Finds where the value of the version field of the meta block is, without the backticks.
+fn find_version(contents: &str) -> Option<Range<usize>> {
+    let mut offset = 0;
+    let mut meta = None;
+    for line in contents.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed
+            .strip_prefix("meta")
+            .is_some_and(|rest| rest.starts_with([' ', '\t', '{']))
+        {
+            meta = Some(offset);
+            break;
+        }
+        offset += line.len();
+    }
+    let start = meta?;
+    let end = start + contents[start..].find('}')?;
+    let block = &contents[start..end];
+    let field = block.find("version")?;
+    let open = field + block[field..].find('`')? + 1;
+    let close = open + block[open..].find('`')?;
+    Some(start + open..start + close)
+}
+
+/// Writes the version in the meta block, adding the block at the top when there is none.
+fn set_version(contents: &str, version: u32) -> String {
+    match find_version(contents) {
+        Some(range) => format!(
+            "{}{}{}",
+            &contents[..range.start],
+            version,
+            &contents[range.end..]
+        ),
+        None => format!("meta {{\n    version `{}`\n}}\n\n{}", version, contents),
+    }
+}
+
+/// Replaces the word wherever it is not part of a longer identifier.
+fn replace_word(contents: &str, word: &str, replacement: &str) -> String {
+    let is_identifier = |ch: char| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-';
+    let mut result = String::new();
+    let mut rest = contents;
+    while let Some(index) = rest.find(word) {
+        let before = rest[..index].chars().next_back();
+        let after = rest[index + word.len()..].chars().next();
+        result.push_str(&rest[..index]);
+        if before.is_some_and(is_identifier) || after.is_some_and(is_identifier) {
+            result.push_str(word);
+        } else {
+            result.push_str(replacement);
+        }
+        rest = &rest[index + word.len()..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{external, source};
+
+    #[test]
+    fn should_upgrade_old_files_with_a_backup() {
+        let path = external::temp_path("migrations", "hermes");
+        let old = "collection {\n    include 1 self_requests\n    include 1 my_self_requests\n}\n";
+        fs::write(&path, old).unwrap();
+
+        let backup = migrate_file(&path).unwrap().unwrap();
+        assert_eq!(fs::read_to_string(&backup).unwrap(), old);
+        let upgraded = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            upgraded,
+            "meta {\n    version `2`\n}\n\ncollection {\n    include 1 self-requests\n    include 1 my_self_requests\n}\n"
+        );
+        assert_eq!(version_of(&upgraded).unwrap(), CURRENT_VERSION);
+        assert!(is_current(&source::read(&upgraded).0));
+        assert!(!is_current(&source::read(old).0));
+        assert_eq!(migrate_file(&path).unwrap(), None);
+
+        fs::write(&path, "meta {\n    version `99`\n}\n").unwrap();
+        assert!(matches!(
+            migrate_file(&path),
+            Err(MigrationError::TooNew(99))
+        ));
+        fs::remove_file(path).unwrap();
+        fs::remove_file(backup).unwrap();
+    }
+}
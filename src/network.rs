@@ -0,0 +1,76 @@
+//! Simulation of poor network conditions, applied to outgoing requests when turned on. This is
+//! useful to see how an API behaves under latency and to test retry logic.
+
+use std::time::Duration;
+
+use rand::Rng;
+use serde::Deserialize;
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct NetworkSimulation {
+    /// Whether the simulation is on when hermes starts. It can be toggled from the TUI.
+    pub enabled: bool,
+    /// Extra latency added before each request is sent, in milliseconds.
+    pub latency_ms: u64,
+    /// Maximum number of bytes per second when receiving a response. 0 means no cap.
+    pub bandwidth_bytes_per_second: u64,
+    /// Percentage (0-100) of requests that fail before being sent.
+    pub failure_percent: u8,
+}
+
+impl NetworkSimulation {
+    /// Gets how long to wait before sending a request.
+    pub fn latency(&self) -> Duration {
+        Duration::from_millis(self.latency_ms)
+    }
+
+    /// Rolls the dice to decide if the next request should fail.
+    pub fn should_fail(&self) -> bool {
+        self.failure_percent > 0 && rand::thread_rng().gen_range(0..100) < self.failure_percent
+    }
+
+    /// Gets how long receiving the given number of bytes takes with the bandwidth cap.
+    pub fn transfer_time(&self, bytes: usize) -> Duration {
+        if self.bandwidth_bytes_per_second == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(bytes as f64 / self.bandwidth_bytes_per_second as f64)
+    }
+
+    /// Gets a short summary of the simulated conditions for the status line.
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("+{}ms", self.latency_ms)];
+        if self.bandwidth_bytes_per_second > 0 {
+            parts.push(format!("{}B/s", self.bandwidth_bytes_per_second));
+        }
+        if self.failure_percent > 0 {
+            parts.push(format!("{}% fail", self.failure_percent));
+        }
+        parts.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_simulate_the_network_conditions() {
+        let simulation = NetworkSimulation {
+            enabled: true,
+            latency_ms: 200,
+            bandwidth_bytes_per_second: 500,
+            failure_percent: 100,
+        };
+        assert_eq!(simulation.latency(), Duration::from_millis(200));
+        assert_eq!(simulation.transfer_time(1000), Duration::from_secs(2));
+        assert!(simulation.should_fail());
+        assert_eq!(simulation.summary(), "+200ms 500B/s 100% fail");
+
+        let simulation = NetworkSimulation::default();
+        assert_eq!(simulation.transfer_time(1000), Duration::ZERO);
+        assert!(!simulation.should_fail());
+        assert_eq!(simulation.summary(), "+0ms");
+    }
+}
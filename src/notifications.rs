@@ -0,0 +1,77 @@
+//! Desktop notifications when a request or a `hermes run` that took a while completes, so users can
+//! switch to something else while waiting. They are off unless the `notifications` setting turns
+//! them on.
+
+use std::{
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use notify_rust::Notification;
+use serde::Deserialize;
+
+/// The default for after_seconds.
+const DEFAULT_AFTER_SECONDS: u64 = 10;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Notifications {
+    /// Whether a notification is shown when something slow completes.
+    pub enabled: bool,
+    /// How many seconds a request or a run must take for its completion to be notified.
+    pub after_seconds: u64,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            after_seconds: DEFAULT_AFTER_SECONDS,
+        }
+    }
+}
+
+impl Notifications {
+    /// Tells whether something that took this long is notified.
+    pub fn is_due(&self, elapsed: Duration) -> bool {
+        self.enabled && elapsed >= Duration::from_secs(self.after_seconds)
+    }
+
+    /// Shows a notification when something that took this long is notified. It is shown from
+    /// another thread since talking to the notification daemon can be slow, returned so a process
+    /// about to exit can wait for it. Failing to show it is ignored: the terminal tells the outcome
+    /// anyway.
+    pub fn finished(
+        &self,
+        elapsed: Duration,
+        summary: String,
+        body: String,
+    ) -> Option<JoinHandle<()>> {
+        if !self.is_due(elapsed) {
+            return None;
+        }
+        Some(thread::spawn(move || {
+            let _ = Notification::new()
+                .appname("hermes")
+                .summary(&summary)
+                .body(&body)
+                .show();
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_notify_only_what_took_long_enough() {
+        let notifications = Notifications {
+            enabled: true,
+            after_seconds: 5,
+        };
+        assert!(!notifications.is_due(Duration::from_millis(4999)));
+        assert!(notifications.is_due(Duration::from_secs(5)));
+        assert!(!Notifications::default().is_due(Duration::from_secs(60)));
+    }
+}
@@ -0,0 +1,159 @@
+//! Overrides of a single variable, header or query parameter for one send, to try a request with
+//! another value without editing it. Variables are overridden like prompted values, winning over
+//! every scope, while headers and query parameters are set on the resolved request.
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use reqwest::Url;
+
+use crate::client::Outgoing;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Override {
+    /// Written `name=value`.
+    Variable { name: String, value: String },
+    /// Written `Name: value`, replacing the headers with the same name.
+    Header { name: String, value: String },
+    /// Written `?key=value`, replacing the query parameters with the same key.
+    Query { key: String, value: String },
+}
+
+impl Override {
+    /// Adds the variable to the prompted values the request is resolved with.
+    pub fn add_to_prompts(&self, prompts: &mut HashMap<String, String>) {
+        if let Override::Variable { name, value } = self {
+            prompts.insert(name.clone(), value.clone());
+        }
+    }
+
+    /// Sets the header or query parameter on the resolved request. Raw requests are sent as they
+    /// are written.
+    pub fn apply(&self, outgoing: &mut Outgoing) {
+        if outgoing.raw.is_some() {
+            return;
+        }
+        match self {
+            Override::Variable { .. } => {}
+            Override::Header { name, value } => {
+                outgoing
+                    .headers
+                    .retain(|(header, _)| !header.eq_ignore_ascii_case(name));
+                outgoing.headers.push((name.clone(), value.clone()));
+            }
+            Override::Query { key, value } => {
+                let Ok(mut url) = Url::parse(&outgoing.url) else {
+                    return;
+                };
+                let pairs: Vec<(String, String)> = url
+                    .query_pairs()
+                    .filter(|(name, _)| name != key)
+                    .map(|(name, value)| (name.into_owned(), value.into_owned()))
+                    .collect();
+                url.query_pairs_mut()
+                    .clear()
+                    .extend_pairs(pairs)
+                    .append_pair(key, value);
+                outgoing.url = url.to_string();
+            }
+        }
+    }
+}
+
+impl FromStr for Override {
+    type Err = String;
+
+    /// Parses an override the way it is written in the prompt: a leading `?` for a query
+    /// parameter, a `:` before any `=` for a header, and a variable otherwise.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || String::from("expected `name=value`, `Header: value` or `?key=value`");
+        if let Some(query) = s.strip_prefix('?') {
+            let (key, value) = query.split_once('=').unwrap_or((query, ""));
+            if key.trim().is_empty() {
+                return Err(invalid());
+            }
+            return Ok(Override::Query {
+                key: key.trim().to_string(),
+                value: value.trim().to_string(),
+            });
+        }
+        let header = s
+            .find(':')
+            .filter(|colon| s.find('=').is_none_or(|eq| colon < &eq));
+        if let Some(colon) = header {
+            let name = s[..colon].trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(invalid());
+            }
+            return Ok(Override::Header {
+                name: name.to_string(),
+                value: s[colon + 1..].trim().to_string(),
+            });
+        }
+        match s.split_once('=') {
+            Some((name, value)) if !name.trim().is_empty() => Ok(Override::Variable {
+                name: name.trim().to_string(),
+                value: value.trim().to_string(),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Override {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Override::Variable { name, value } => write!(f, "{}={}", name, value),
+            Override::Header { name, value } => write!(f, "{}: {}", name, value),
+            Override::Query { key, value } => write!(f, "?{}={}", key, value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::HttpMethod;
+
+    #[test]
+    fn should_override_one_value_for_one_send() {
+        let mut outgoing = Outgoing {
+            method: HttpMethod::Get,
+            url: String::from("https://api.example.com/users?page=1&sort=name"),
+            headers: vec![(String::from("x-debug"), String::from("0"))],
+            body: None,
+            raw: None,
+            form: Vec::new(),
+        };
+        let header: Override = "X-Debug: 1".parse().unwrap();
+        header.apply(&mut outgoing);
+        assert_eq!(
+            outgoing.headers,
+            [(String::from("X-Debug"), String::from("1"))]
+        );
+        let query: Override = "?page=2".parse().unwrap();
+        query.apply(&mut outgoing);
+        assert_eq!(
+            outgoing.url,
+            "https://api.example.com/users?sort=name&page=2"
+        );
+        assert_eq!(query.to_string(), "?page=2");
+
+        let variable: Override = "base_url = http://localhost:8080".parse().unwrap();
+        assert_eq!(
+            variable,
+            Override::Variable {
+                name: String::from("base_url"),
+                value: String::from("http://localhost:8080"),
+            }
+        );
+        let mut prompts = HashMap::new();
+        variable.add_to_prompts(&mut prompts);
+        header.add_to_prompts(&mut prompts);
+        assert_eq!(prompts.len(), 1);
+
+        assert!("X Debug: 1".parse::<Override>().is_err());
+        assert!("just text".parse::<Override>().is_err());
+        assert!("?=2".parse::<Override>().is_err());
+    }
+}
@@ -0,0 +1,213 @@
+//! Walking paginated APIs. The next page is found from a `Link` header with `rel="next"` or from a
+//! cursor in the JSON body, and the results of every page are put together in a single list.
+
+use std::collections::HashSet;
+
+use reqwest::Url;
+use serde_json::Value;
+
+use crate::headers;
+
+/// Stops runaway walks, e.g. an API that always returns a next page.
+const MAX_PAGES: usize = 100;
+/// The query parameter a cursor is sent in when none is configured.
+const DEFAULT_CURSOR_PARAM: &str = "cursor";
+
+/// Where to find the cursor of the next page in a JSON body and how to send it back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    /// A dotted path to the cursor, e.g. `meta.next_cursor`. Array indexes are numbers.
+    path: String,
+    /// The query parameter the cursor is sent in.
+    param: String,
+}
+
+impl Cursor {
+    /// Reads a cursor written as `path` or `path param`, e.g. `meta.next after`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut parts = value.split_whitespace();
+        let path = parts
+            .next()
+            .ok_or_else(|| String::from("next-cursor is missing the path"))?;
+        let param = parts.next().unwrap_or(DEFAULT_CURSOR_PARAM);
+        if parts.next().is_some() {
+            return Err(format!("next-cursor `{}` must be `path param`", value));
+        }
+        Ok(Self {
+            path: path.to_string(),
+            param: param.to_string(),
+        })
+    }
+
+    /// Builds the url of the next page from the body, None when the body has no cursor. Cursors
+    /// that are urls are used as is.
+    fn next_url(&self, url: &Url, body: &Value) -> Option<Url> {
+        let cursor = match lookup(body, &self.path)? {
+            Value::String(cursor) if cursor.is_empty() => return None,
+            Value::String(cursor) => cursor.clone(),
+            Value::Number(cursor) => cursor.to_string(),
+            _ => return None,
+        };
+        if cursor.starts_with("http://") || cursor.starts_with("https://") {
+            return Url::parse(&cursor).ok();
+        }
+        let mut next = url.clone();
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(key, _)| key != &self.param)
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect();
+        next.query_pairs_mut()
+            .clear()
+            .extend_pairs(pairs)
+            .append_pair(&self.param, &cursor);
+        Some(next)
+    }
+}
+
+/// A page as returned by the fetch function of walk.
+#[derive(Debug, Default, Clone)]
+pub struct Page {
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// The results of every page that was fetched.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Walk {
+    pages: usize,
+    items: Vec<Value>,
+    /// Whether the walk stopped at MAX_PAGES while there were more pages.
+    truncated: bool,
+}
+
+impl Walk {
+    pub fn items(&self) -> &[Value] {
+        &self.items
+    }
+
+    /// Gets the results of every page as a single JSON array.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.items).unwrap_or_default()
+    }
+
+    /// Describes the walk for the status bar, e.g. `Fetched 3 pages with 75 results`.
+    pub fn summary(&self) -> String {
+        let summary = format!(
+            "Fetched {} pages with {} results",
+            self.pages,
+            self.items.len()
+        );
+        if self.truncated {
+            format!("{}, stopped after {} pages", summary, MAX_PAGES)
+        } else {
+            summary
+        }
+    }
+}
+
+/// Fetches the url and every next page. The results of a page are the body when it is a JSON
+/// array, otherwise the first array in the body object. A url that was already fetched ends the
+/// walk so APIs that link back to the first page don't loop forever.
+pub fn walk(
+    url: &str,
+    cursor: Option<&Cursor>,
+    mut fetch: impl FnMut(&str) -> Result<Page, String>,
+) -> Result<Walk, String> {
+    let mut next = Some(Url::parse(url).map_err(|err| format!("invalid url {}: {}", url, err))?);
+    let mut seen = HashSet::new();
+    let mut walk = Walk::default();
+    while let Some(url) = next.take() {
+        if !seen.insert(url.to_string()) {
+            break;
+        }
+        if walk.pages == MAX_PAGES {
+            walk.truncated = true;
+            break;
+        }
+        let page = fetch(url.as_str())?;
+        walk.pages += 1;
+        let body: Value = serde_json::from_str(&page.body)
+            .map_err(|err| format!("page {} is not JSON: {}", walk.pages, err))?;
+        match results(&body) {
+            Some(items) => walk.items.extend(items.iter().cloned()),
+            None => return Err(format!("page {} has no list of results", walk.pages)),
+        }
+        next = next_link(&url, &page.headers)
+            .or_else(|| cursor.and_then(|cursor| cursor.next_url(&url, &body)));
+    }
+    Ok(walk)
+}
+
+/// Finds the next page in the Link headers, relative links are resolved against the url.
+fn next_link(url: &Url, headers: &[(String, String)]) -> Option<Url> {
+    headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("link"))
+        .flat_map(|(_, value)| headers::parse_links(value))
+        .find(|link| link.has_rel("next"))
+        .and_then(|link| url.join(link.url()).ok())
+}
+
+fn results(body: &Value) -> Option<&Vec<Value>> {
+    match body {
+        Value::Array(items) => Some(items),
+        Value::Object(fields) => fields.values().find_map(Value::as_array),
+        _ => None,
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| match value {
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        _ => value.get(key),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_walk_link_headers_and_cursors() {
+        let walked = walk("https://api.example.com/users?limit=2", None, |url| {
+            Ok(match url {
+                "https://api.example.com/users?limit=2" => Page {
+                    headers: vec![(
+                        String::from("Link"),
+                        String::from("</users?limit=2&page=2>; rel=\"next\""),
+                    )],
+                    body: String::from("[1, 2]"),
+                },
+                _ => Page {
+                    body: String::from("[3]"),
+                    ..Page::default()
+                },
+            })
+        })
+        .unwrap();
+        assert_eq!(walked.summary(), "Fetched 2 pages with 3 results");
+        assert_eq!(walked.items()[2], 3);
+
+        let cursor = Cursor::parse("meta.next after").unwrap();
+        let mut fetched = Vec::new();
+        let walked = walk(
+            "https://api.example.com/users?after=",
+            Some(&cursor),
+            |url| {
+                fetched.push(url.to_string());
+                let body = match fetched.len() {
+                    1 => r#"{"data": [1, 2], "meta": {"next": "abc"}}"#,
+                    _ => r#"{"data": [3], "meta": {"next": null}}"#,
+                };
+                Ok(Page {
+                    body: String::from(body),
+                    ..Page::default()
+                })
+            },
+        )
+        .unwrap();
+        assert_eq!(fetched[1], "https://api.example.com/users?after=abc");
+        assert_eq!(walked.summary(), "Fetched 2 pages with 3 results");
+    }
+}
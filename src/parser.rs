@@ -1,184 +1,203 @@
-use std::{collections::HashMap, fs, path::PathBuf};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::Deserialize;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
 use walkdir::WalkDir;
 
-use crate::lexer::{Lexer, Token};
+use crate::{syntax, worker::Progress};
 
-struct BlockField {
-    identifier: String,
-    enabled: bool,
-    value: String,
+/// The file with the paths to skip when looking for hermes files, in gitignore syntax.
+const IGNORE_FILE: &str = ".hermesignore";
+/// The default for how many folders deep a scan goes.
+const DEFAULT_MAX_DEPTH: usize = 32;
+
+/// How collection folders are scanned for hermes files.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ScanSettings {
+    /// Whether symbolic links are followed. Links are skipped when false.
+    pub follow_symlinks: bool,
+    /// How many folders deep to look for hermes files.
+    pub max_depth: usize,
 }
 
-impl BlockField {
-    pub fn new(identifier: String, enabled: bool, value: String) -> Self {
-        BlockField {
-            identifier,
-            enabled,
-            value,
+impl Default for ScanSettings {
+    fn default() -> Self {
+        Self {
+            follow_symlinks: false,
+            max_depth: DEFAULT_MAX_DEPTH,
         }
     }
 }
 
-struct Block {
-    identifier: String,
-    block_type: String,
-    sub_block_type: String,
-    fields: Vec<BlockField>,
+/// The hermes files found by a scan, along with what could not be scanned and why.
+#[derive(Debug, Default, Clone)]
+pub struct Scan {
+    pub files: Vec<PathBuf>,
+    pub warnings: Vec<String>,
 }
 
-impl Block {
-    pub fn new(
-        block_type: String,
-        sub_block_type: String,
-        identifier: String,
-        fields: Vec<BlockField>,
-    ) -> Self {
-        Block {
-            block_type,
-            sub_block_type,
-            identifier,
-            fields,
-        }
-    }
-
-    pub fn add_field(&mut self, field: BlockField) {
-        self.fields.push(field);
-    }
+/// Finds the hermes files in dir that use the name as a value but were not loaded, so their
+/// references can't be updated when the name changes.
+pub fn find_unloaded_references(
+    dir: &str,
+    settings: &ScanSettings,
+    loaded: &[PathBuf],
+    name: &str,
+) -> Vec<PathBuf> {
+    let value = syntax::write_value(name);
+    get_hermes_files(dir, settings)
+        .files
+        .into_iter()
+        .filter(|file| !loaded.contains(file))
+        .filter(|file| read_file_contents(file).is_ok_and(|contents| contents.contains(&value)))
+        .collect()
 }
 
-pub fn parse(dir: &str) {
-    let hermes_files = get_hermes_files(dir);
+pub fn get_hermes_files(dir: &str, settings: &ScanSettings) -> Scan {
+    scan_hermes_files(dir, settings, &Progress::default()).unwrap_or_default()
+}
 
-    let mut lexer: Lexer;
-    for hermes_file in hermes_files {
-        let contents = match read_file_contents(&hermes_file) {
-            Ok(contents) => contents,
+/// Walks dir for hermes files, reporting every file found to the progress. Entries that can't be
+/// read, such as folders without permission or symbolic link loops, end up in the warnings.
+/// Returns None when the scan was cancelled.
+pub fn scan_hermes_files(dir: &str, settings: &ScanSettings, progress: &Progress) -> Option<Scan> {
+    let mut scan = Scan::default();
+    let ignore = load_ignore_rules(dir, &mut scan.warnings);
+    let entries = WalkDir::new(dir)
+        .follow_links(settings.follow_symlinks)
+        .max_depth(settings.max_depth)
+        .into_iter()
+        .filter_entry(|entry| {
+            // the root itself is never ignored, only what is inside of it
+            entry.depth() == 0
+                || !ignore
+                    .matched(entry.path(), entry.file_type().is_dir())
+                    .is_ignore()
+        });
+    for entry in entries {
+        if progress.is_cancelled() {
+            return None;
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
             Err(err) => {
-                eprintln!(
-                    "Error reading hermes file {}: {}",
-                    hermes_file.display(),
-                    err
-                );
+                scan.warnings.push(err.to_string());
                 continue;
             }
         };
-        let symbol_table: HashMap<String, String> = HashMap::new();
-        let mut blocks: Vec<Block> = Vec::new();
-        let mut tokens: Vec<Token> = Vec::new();
-        lexer = Lexer::new(&contents);
-        while let Some(t) = lexer.next_token() {
-            tokens.push(t);
+        // the file type is the one of the link itself when links are not followed, so links are
+        // skipped here
+        let file_type = entry.file_type();
+        if file_type.is_dir() && entry.depth() == settings.max_depth && entry.depth() > 0 {
+            scan.warnings.push(format!(
+                "{}: deeper than {} folders",
+                entry.path().display(),
+                settings.max_depth
+            ));
         }
-        let mut current_token_idx = 0;
-        let mut current_block_idx = 0;
-        while current_token_idx < tokens.len() {
-            let t = tokens[current_token_idx].clone();
-            match t {
-                Token::BlockType(block_type) => {
-                    let mut next_idx = if current_token_idx + 1 >= tokens.len() {
-                        // TODO: clean up and log error message
-                        // last token, break out of loop
-                        break;
-                    } else {
-                        current_token_idx + 1
-                    };
-                    let sub_block_type = match tokens[next_idx].clone() {
-                        Token::SubBlockType(sub_block_type) => {
-                            current_token_idx = current_token_idx + 1;
-                            sub_block_type
-                        }
-                        _ => String::new(),
-                    };
-                    next_idx = if current_token_idx + 1 >= tokens.len() {
-                        // TODO: clean up and log error message
-                        // last token, break out of loop
-                        break;
-                    } else {
-                        current_token_idx + 1
-                    };
-                    let identifier = match tokens[next_idx].clone() {
-                        Token::Identifier(identifier) => {
-                            current_token_idx = current_token_idx + 1;
-                            identifier
-                        }
-                        _ => String::new(),
-                    };
-                    let block = Block::new(block_type, sub_block_type, identifier, Vec::new());
-                    blocks.push(block);
-                }
-                Token::Delimeter(d) if d == '{' => {
-                    current_block_idx = blocks.len() - 1;
-                }
-                Token::Identifier(identifier) => {
-                    let mut next_idx = if current_token_idx + 1 >= tokens.len() {
-                        break;
-                    } else {
-                        current_token_idx + 1
-                    };
-                    let enabled = match tokens[next_idx].clone() {
-                        Token::Digit(d) => {
-                            current_token_idx = current_token_idx + 1;
-                            d == 1
-                        }
-                        _ => {
-                            // TODO: error state, expecting a status
-                            false
-                        }
-                    };
-                    next_idx = if current_token_idx + 1 >= tokens.len() {
-                        break;
-                    } else {
-                        current_token_idx + 1
-                    };
-                    let value = match tokens[next_idx].clone() {
-                        Token::StringValue(s) => {
-                            current_token_idx = current_token_idx + 1;
-                            s
-                        }
-                        Token::Identifier(id) => {
-                            match symbol_table.get(&id) {
-                                Some(v) => v.clone(),
-                                None => {
-                                    // TODO: pending resolution
-                                    // add to pending identifier
-                                    String::new()
-                                }
-                            }
-                        }
-                        _ => {
-                            // TODO: error state
-                            String::new()
-                        }
-                    };
-                    blocks[current_block_idx]
-                        .add_field(BlockField::new(identifier, enabled, value));
+        let path = entry.path();
+        if file_type.is_file() && path.extension().and_then(|s| s.to_str()) == Some("hermes") {
+            match fs::canonicalize(path) {
+                // a file can be reached through more than one link
+                Ok(abs_path) if scan.files.contains(&abs_path) => {}
+                Ok(abs_path) => {
+                    progress.set_message(abs_path.display().to_string());
+                    progress.advance(1);
+                    scan.files.push(abs_path);
                 }
-                _ => {}
+                Err(err) => scan.warnings.push(format!("{}: {}", path.display(), err)),
             }
         }
     }
-
-    // let mut lexer = Lexer::new(input);
-    // // for _ in 0..6 {
-    // //     println!("{:?}", lexer.next_token());
-    // // }
-    // let mut symbol_table = HashMap::<String, Token>::new();
-    // let blocks = vec![];
+    Some(scan)
 }
 
-fn get_hermes_files(dir: &str) -> Vec<PathBuf> {
-    let mut hermes_files = Vec::new();
-    for entry in WalkDir::new(dir).into_iter().filter_map(Result::ok) {
-        let path = entry.path();
-        if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("hermes") {
-            if let Ok(abs_path) = fs::canonicalize(path) {
-                hermes_files.push(abs_path);
-            }
+/// Reads the `.hermesignore` file at the root of dir, which uses the gitignore syntax. Broken
+/// patterns are skipped and no file means nothing is ignored. A file that can't be read ends up in
+/// the warnings.
+fn load_ignore_rules(dir: &str, warnings: &mut Vec<String>) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(dir);
+    if let Some(err) = builder.add(Path::new(dir).join(IGNORE_FILE)) {
+        if err.io_error().map(io::Error::kind) != Some(io::ErrorKind::NotFound) {
+            warnings.push(format!("{}: {}", IGNORE_FILE, err));
         }
     }
-    hermes_files
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
 }
 
 fn read_file_contents(file_path: &PathBuf) -> std::io::Result<String> {
     fs::read_to_string(file_path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external;
+
+    #[test]
+    fn should_skip_paths_in_hermesignore() {
+        let root = external::temp_path("scan", "d");
+        fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+        fs::create_dir_all(root.join("users")).unwrap();
+        fs::write(root.join(IGNORE_FILE), "node_modules/\n*.draft.hermes\n").unwrap();
+        for file in [
+            "collection.hermes",
+            "users/get.hermes",
+            "users/new.draft.hermes",
+            "node_modules/pkg/vendored.hermes",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+
+        let mut files: Vec<String> =
+            get_hermes_files(&root.to_string_lossy(), &ScanSettings::default())
+                .files
+                .iter()
+                .filter_map(|file| file.file_name()?.to_str().map(String::from))
+                .collect();
+        files.sort();
+        assert_eq!(files, ["collection.hermes", "get.hermes"]);
+
+        // an ignore file that can't be read is a warning, not something printed over the TUI
+        fs::remove_file(root.join(IGNORE_FILE)).unwrap();
+        fs::create_dir(root.join(IGNORE_FILE)).unwrap();
+        let scan = get_hermes_files(&root.to_string_lossy(), &ScanSettings::default());
+        assert_eq!(scan.files.len(), 4);
+        assert!(scan.warnings[0].starts_with(IGNORE_FILE));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn should_handle_symlinks_and_depth_per_settings() {
+        let root = external::temp_path("scan", "d");
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("a/b/deep.hermes"), "").unwrap();
+        std::os::unix::fs::symlink(root.join("a"), root.join("link")).unwrap();
+        std::os::unix::fs::symlink(&root, root.join("a/loop")).unwrap();
+        let dir = root.to_string_lossy();
+
+        let scan = get_hermes_files(&dir, &ScanSettings::default());
+        assert_eq!(scan.files.len(), 1);
+        assert!(scan.warnings.is_empty());
+
+        let settings = ScanSettings {
+            follow_symlinks: true,
+            max_depth: 3,
+        };
+        let scan = get_hermes_files(&dir, &settings);
+        assert_eq!(scan.files.len(), 1);
+        assert!(scan.warnings.iter().any(|w| w.contains("loop")));
+
+        let settings = ScanSettings {
+            follow_symlinks: false,
+            max_depth: 1,
+        };
+        let scan = get_hermes_files(&dir, &settings);
+        assert!(scan.files.is_empty());
+        assert_eq!(scan.warnings.len(), 1);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,92 @@
+//! The folds of the JSON tree of every request, saved to `folds.json` in the data directory when
+//! `persist_json_folds` is on, so the tree of a large response opens as it was left after a
+//! restart too. Tabs keep their folds while Hermes runs either way.
+
+use std::{collections::HashMap, fs, io, path::PathBuf};
+
+use crate::{history, json_tree::Folds, storage};
+
+/// The file in the data directory where the folds are saved.
+const FOLDS_FILE: &str = "folds.json";
+
+#[derive(Debug, Default, Clone)]
+pub struct Places {
+    /// The folds by collection, then by request.
+    folds: HashMap<String, HashMap<String, Folds>>,
+    /// Where the folds are saved. None keeps them in memory only.
+    path: Option<PathBuf>,
+}
+
+impl Places {
+    /// Loads the folds saved in the data directory when they are persisted, starting over when
+    /// the file is missing or broken.
+    pub fn load(persist: bool) -> Self {
+        let path = history::data_dir()
+            .filter(|_| persist)
+            .map(|dir| dir.join(FOLDS_FILE));
+        let folds = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        Self { folds, path }
+    }
+
+    pub fn folds(&self, collection: &str, request: &str) -> Option<&Folds> {
+        self.folds.get(collection)?.get(request)
+    }
+
+    /// Keeps the folds of the tree of the request, saving them when they are persisted.
+    pub fn set_folds(&mut self, collection: &str, request: &str, folds: Folds) -> io::Result<()> {
+        let kept = self.folds.entry(collection.to_string()).or_default();
+        if kept.get(request) == Some(&folds) {
+            return Ok(());
+        }
+        kept.insert(request.to_string(), folds);
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = self.path.as_deref() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = serde_json::to_string_pretty(&self.folds)?;
+        storage::overwrite(path, &contents)
+            .map(|_| ())
+            .map_err(|err| io::Error::other(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{external, json_tree::JsonTree};
+
+    #[test]
+    fn should_save_the_folds_of_every_request() {
+        let path = external::temp_path("folds", "json");
+        let mut places = Places {
+            path: Some(path.clone()),
+            ..Default::default()
+        };
+        let mut tree = JsonTree::parse(r#"{"users": [{"name": "Ada"}]}"#).unwrap();
+        tree.next();
+        tree.expand();
+        places
+            .set_folds("users", "list users", tree.folds())
+            .unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let saved = Places {
+            folds: serde_json::from_str(&contents).unwrap(),
+            path: None,
+        };
+        assert_eq!(saved.folds("users", "list users"), Some(&tree.folds()));
+        assert_eq!(saved.folds("users", "get user"), None);
+        assert_eq!(saved.folds("teams", "list users"), None);
+        fs::remove_file(path).unwrap();
+    }
+}
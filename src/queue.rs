@@ -0,0 +1,163 @@
+//! The sends waiting for their turn. Sending requests faster than they come back queues them, so
+//! they go out one at a time in the order they were sent and their responses come in that order
+//! too. Each one is resolved right before it goes out, so it sees what the responses before it
+//! captured, e.g. the token of a login sent right before.
+
+use std::collections::VecDeque;
+
+/// A send waiting in the queue, or the one in flight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Queued<T> {
+    /// The index of the request in the collection.
+    index: usize,
+    name: String,
+    /// When the send was queued, in seconds since epoch.
+    queued_at: i64,
+    /// What the send needs once it goes out.
+    payload: T,
+}
+
+impl<T> Queued<T> {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn queued_at(&self) -> i64 {
+        self.queued_at
+    }
+
+    pub fn into_payload(self) -> T {
+        self.payload
+    }
+}
+
+/// Where a request is in the queue.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SendState {
+    /// Sent and waiting for its response.
+    InFlight,
+    /// Waiting for the sends before it, 1 being the next to go out.
+    Pending(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct SendQueue<T> {
+    pending: VecDeque<Queued<T>>,
+    /// The index and name of the request being sent, the queue waits for it before sending the
+    /// next one.
+    in_flight: Option<(usize, String)>,
+}
+
+impl<T> Default for SendQueue<T> {
+    fn default() -> Self {
+        Self {
+            pending: VecDeque::new(),
+            in_flight: None,
+        }
+    }
+}
+
+impl<T> SendQueue<T> {
+    /// Queues a send of the request at the index. Returns how many sends are ahead of it,
+    /// including the one in flight.
+    pub fn push(&mut self, index: usize, name: String, queued_at: i64, payload: T) -> usize {
+        let ahead = self.pending.len() + usize::from(self.in_flight.is_some());
+        self.pending.push_back(Queued {
+            index,
+            name,
+            queued_at,
+            payload,
+        });
+        ahead
+    }
+
+    /// Takes the next send to go out, marking it as in flight. None while a send is in flight or
+    /// when nothing is queued.
+    pub fn start_next(&mut self) -> Option<Queued<T>> {
+        if self.in_flight.is_some() {
+            return None;
+        }
+        let next = self.pending.pop_front()?;
+        self.in_flight = Some((next.index, next.name.clone()));
+        Some(next)
+    }
+
+    /// Marks the send in flight as done, letting the next one go out.
+    pub fn finish(&mut self) {
+        self.in_flight = None;
+    }
+
+    /// Drops every send that has not gone out yet, returning how many were dropped. The one in
+    /// flight is already on the wire and still gets its response.
+    pub fn cancel_all(&mut self) -> usize {
+        let cancelled = self.pending.len();
+        self.pending.clear();
+        cancelled
+    }
+
+    /// Gets where the request at the index is in the queue, the earliest of its sends when it
+    /// was sent more than once. None when it is not queued.
+    pub fn state_of(&self, index: usize) -> Option<SendState> {
+        if self
+            .in_flight
+            .as_ref()
+            .is_some_and(|(sending, _)| *sending == index)
+        {
+            return Some(SendState::InFlight);
+        }
+        self.pending
+            .iter()
+            .position(|queued| queued.index == index)
+            .map(|position| SendState::Pending(position + 1))
+    }
+
+    /// Gets the name of the request in flight.
+    pub fn in_flight(&self) -> Option<&str> {
+        self.in_flight.as_ref().map(|(_, name)| name.as_str())
+    }
+
+    /// Gets the sends waiting for their turn, the next one to go out first.
+    pub fn pending(&self) -> impl Iterator<Item = &Queued<T>> {
+        self.pending.iter()
+    }
+
+    /// Whether nothing is in flight nor waiting.
+    pub fn is_empty(&self) -> bool {
+        self.in_flight.is_none() && self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_send_one_at_a_time_in_order() {
+        let mut queue = SendQueue::default();
+        assert_eq!(queue.push(3, String::from("login"), 0, 'a'), 0);
+        assert_eq!(queue.push(5, String::from("list users"), 1, 'b'), 1);
+        assert_eq!(queue.push(3, String::from("login"), 2, 'c'), 2);
+
+        let first = queue.start_next().unwrap();
+        assert_eq!((first.index(), first.into_payload()), (3, 'a'));
+        assert_eq!(queue.start_next(), None);
+        assert_eq!(queue.in_flight(), Some("login"));
+        assert_eq!(queue.state_of(3), Some(SendState::InFlight));
+        assert_eq!(queue.state_of(5), Some(SendState::Pending(1)));
+        assert_eq!(queue.state_of(7), None);
+
+        queue.finish();
+        assert_eq!(queue.state_of(3), Some(SendState::Pending(2)));
+        assert_eq!(queue.start_next().unwrap().name(), "list users");
+        assert_eq!(queue.cancel_all(), 1);
+        assert_eq!(queue.pending().count(), 0);
+        assert!(!queue.is_empty());
+        queue.finish();
+        assert!(queue.is_empty());
+        assert_eq!(queue.start_next(), None);
+    }
+}
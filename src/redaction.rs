@@ -0,0 +1,212 @@
+//! Redaction of what must not be saved nor printed, for compliance-sensitive users: the values of
+//! headers by name, such as `Authorization`, and whatever matches the patterns of the
+//! `redaction` setting. Redaction is applied before requests and responses are saved to the
+//! history, printed by `hermes run` or exported. Unlike the masker, which hides the secrets of
+//! the collection wherever they are shown, it leaves the TUI alone.
+
+use std::io::{self, Write};
+
+use regex::Regex;
+use serde::Deserialize;
+
+/// What redacted values are replaced with.
+pub const REDACTED: &str = "[redacted]";
+
+/// The headers redacted unless the setting lists its own.
+const DEFAULT_HEADERS: [&str; 4] = [
+    "Authorization",
+    "Proxy-Authorization",
+    "Cookie",
+    "Set-Cookie",
+];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Redaction {
+    /// The headers whose values are redacted, whatever their case.
+    headers: Vec<String>,
+    /// Patterns redacted wherever they match. Only the groups are redacted when the pattern has
+    /// some, e.g. `api_key=([^&]+)` keeps `api_key=`.
+    patterns: Vec<Pattern>,
+}
+
+impl Default for Redaction {
+    fn default() -> Self {
+        Self {
+            headers: DEFAULT_HEADERS.map(String::from).to_vec(),
+            patterns: Vec::new(),
+        }
+    }
+}
+
+/// A regex of the setting, compiled when the settings are read so a broken one is reported then.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+struct Pattern(Regex);
+
+impl TryFrom<String> for Pattern {
+    type Error = regex::Error;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        Regex::new(&pattern).map(Pattern)
+    }
+}
+
+impl Redaction {
+    /// Redacts what the patterns match in the text.
+    pub fn redact(&self, text: &str) -> String {
+        let mut redacted = text.to_string();
+        for Pattern(pattern) in &self.patterns {
+            let mut ranges = Vec::new();
+            for captures in pattern.captures_iter(&redacted) {
+                if captures.len() == 1 {
+                    ranges.extend(captures.get(0).map(|found| found.range()));
+                } else {
+                    ranges.extend(captures.iter().skip(1).flatten().map(|found| found.range()));
+                }
+            }
+            // nested groups are redacted along with the group around them
+            ranges.sort_by_key(|range| (range.start, std::cmp::Reverse(range.end)));
+            ranges.dedup_by(|inner, outer| inner.start < outer.end);
+            // replaced from the end so the ranges before stay where they are
+            for range in ranges.into_iter().rev() {
+                redacted.replace_range(range, REDACTED);
+            }
+        }
+        redacted
+    }
+
+    /// Redacts the value of the header when its name is one of the redacted headers, and what
+    /// the patterns match otherwise.
+    pub fn redact_header(&self, name: &str, value: &str) -> String {
+        if self
+            .headers
+            .iter()
+            .any(|header| header.eq_ignore_ascii_case(name.trim()))
+        {
+            String::from(REDACTED)
+        } else {
+            self.redact(value)
+        }
+    }
+
+    /// Redacts a line of text, as a header when it looks like `Name: value`.
+    pub fn redact_line(&self, line: &str) -> String {
+        let header = line.split_once(':').filter(|(name, _)| {
+            let name = name.trim_start();
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+        });
+        match header {
+            Some((name, value)) if self.redact_header(name, value) == REDACTED => {
+                format!("{}: {}", name, REDACTED)
+            }
+            _ => self.redact(line),
+        }
+    }
+
+    /// Wraps the writer so every line written to it is redacted.
+    pub fn writer<W: Write>(&self, inner: W) -> Redacting<'_, W> {
+        Redacting {
+            redaction: self,
+            inner,
+            line: Vec::new(),
+        }
+    }
+}
+
+/// A writer redacting every line before passing it on, e.g. to redact the output of a run.
+pub struct Redacting<'a, W: Write> {
+    redaction: &'a Redaction,
+    inner: W,
+    /// What was written of the line that is not over yet.
+    line: Vec<u8>,
+}
+
+impl<W: Write> Redacting<'_, W> {
+    fn write_line(&mut self) -> io::Result<()> {
+        let line = String::from_utf8_lossy(&self.line).into_owned();
+        self.line.clear();
+        let (text, newline) = match line.strip_suffix('\n') {
+            Some(text) => (text, "\n"),
+            None => (line.as_str(), ""),
+        };
+        write!(
+            self.inner,
+            "{}{}",
+            self.redaction.redact_line(text),
+            newline
+        )
+    }
+}
+
+impl<W: Write> Write for Redacting<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.line.push(byte);
+            if byte == b'\n' {
+                self.write_line()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            self.write_line()?;
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for Redacting<'_, W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_redact_headers_and_patterns() {
+        let redaction: Redaction = serde_json::from_str(
+            r#"{"headers": ["authorization", "X-Api-Key"], "patterns": ["api_key=([^&]+)", "\\d{4}-\\d{4}"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            redaction.redact_header("Authorization", "Bearer abc"),
+            REDACTED
+        );
+        assert_eq!(redaction.redact_header("Accept", "*/*"), "*/*");
+        assert_eq!(
+            redaction.redact("/users?api_key=abc&card=1234-5678"),
+            "/users?api_key=[redacted]&card=[redacted]"
+        );
+        assert_eq!(
+            redaction.redact_line("    x-api-key: abc"),
+            "    x-api-key: [redacted]"
+        );
+        assert_eq!(
+            redaction.redact_line("PASS GET login: 200 OK"),
+            "PASS GET login: 200 OK"
+        );
+        assert!(serde_json::from_str::<Redaction>(r#"{"patterns": ["("]}"#).is_err());
+        assert!(Redaction::default().redact_header("set-cookie", "id=1") == REDACTED);
+
+        let mut out = Vec::new();
+        {
+            let mut writer = redaction.writer(&mut out);
+            write!(writer, "    Author").unwrap();
+            writeln!(writer, "ization: Bearer abc").unwrap();
+            write!(writer, "api_key=abc").unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "    Authorization: [redacted]\napi_key=[redacted]"
+        );
+    }
+}
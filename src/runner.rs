@@ -0,0 +1,955 @@
+//! Planning of collection runs. Collections and folders can declare `before` and `after` hooks,
+//! which are requests that run around the requests they contain, e.g. to create a test user and
+//! delete it again. Variables captured while a scope is open are dropped when it is left.
+//! Collections can also declare preconditions, endpoints that must answer before a run starts.
+//! Runs print a line per request and end with an exit code telling CI what went wrong, if anything.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{self, Write},
+    time::Duration,
+};
+
+use chrono::Utc;
+
+use crate::{
+    api::{Collection, HttpMethod, Request},
+    client::{self, Options, Outgoing},
+    expected,
+    variables::{Resolver, Scopes},
+};
+
+/// A part of a collection that can have hooks.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Scope {
+    Collection,
+    /// A folder by its path relative to the collection root, e.g. `users/admin`.
+    Folder(String),
+}
+
+/// The names of the requests to run before and after the requests of a scope.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Hooks {
+    before: Vec<String>,
+    after: Vec<String>,
+}
+
+impl Hooks {
+    pub fn add_before(&mut self, request: String) {
+        self.before.push(request);
+    }
+
+    pub fn add_after(&mut self, request: String) {
+        self.after.push(request);
+    }
+
+    pub fn before(&self) -> &[String] {
+        &self.before
+    }
+
+    pub fn after(&self) -> &[String] {
+        &self.after
+    }
+
+    /// Replaces every use of the request name, returning how many were replaced.
+    pub fn rename(&mut self, from: &str, to: &str) -> usize {
+        let mut renamed = 0;
+        for name in self.before.iter_mut().chain(self.after.iter_mut()) {
+            if name == from {
+                *name = to.to_string();
+                renamed += 1;
+            }
+        }
+        renamed
+    }
+
+    /// Checks if the request is used as a hook, such requests are not run on their own.
+    fn contains(&self, request: &str) -> bool {
+        self.before
+            .iter()
+            .chain(self.after.iter())
+            .any(|r| r == request)
+    }
+}
+
+/// A single step of a run. Requests are referenced by their index in the collection.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Enter(Scope),
+    Hook(usize),
+    Run(usize),
+    Leave(Scope),
+}
+
+/// Builds the steps to run every request of the collection with the hooks around them. Requests
+/// are grouped by folder so the hooks of a folder only run once.
+pub fn plan(collection: &Collection) -> Result<Vec<Step>, String> {
+    let hooks = collection.hooks();
+    let mut requests: Vec<(Vec<&str>, usize)> = collection
+        .iter()
+        .enumerate()
+        .filter(|(_, request)| !hooks.values().any(|h| h.contains(&request.get_name())))
+        .map(|(index, request)| (folder_chain(request.get_folder()), index))
+        .collect();
+    // a stable sort keeps the order of the requests inside each folder
+    requests.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut steps = Vec::new();
+    let mut open: Vec<&str> = Vec::new();
+    enter(collection, Scope::Collection, &mut steps)?;
+    for (chain, index) in requests {
+        let shared = open
+            .iter()
+            .zip(chain.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        while open.len() > shared {
+            leave(collection, folder_scope(&open), &mut steps)?;
+            open.pop();
+        }
+        for folder in &chain[shared..] {
+            open.push(folder);
+            enter(collection, folder_scope(&open), &mut steps)?;
+        }
+        steps.push(Step::Run(index));
+    }
+    while !open.is_empty() {
+        leave(collection, folder_scope(&open), &mut steps)?;
+        open.pop();
+    }
+    leave(collection, Scope::Collection, &mut steps)?;
+    Ok(steps)
+}
+
+fn folder_chain(folder: &str) -> Vec<&str> {
+    folder.split('/').filter(|part| !part.is_empty()).collect()
+}
+
+fn folder_scope(chain: &[&str]) -> Scope {
+    Scope::Folder(chain.join("/"))
+}
+
+fn enter(collection: &Collection, scope: Scope, steps: &mut Vec<Step>) -> Result<(), String> {
+    steps.push(Step::Enter(scope.clone()));
+    if let Some(hooks) = collection.hooks().get(&scope) {
+        for name in hooks.before() {
+            steps.push(Step::Hook(find_request(collection, name)?));
+        }
+    }
+    Ok(())
+}
+
+fn leave(collection: &Collection, scope: Scope, steps: &mut Vec<Step>) -> Result<(), String> {
+    if let Some(hooks) = collection.hooks().get(&scope) {
+        for name in hooks.after() {
+            steps.push(Step::Hook(find_request(collection, name)?));
+        }
+    }
+    steps.push(Step::Leave(scope));
+    Ok(())
+}
+
+fn find_request(collection: &Collection, name: &str) -> Result<usize, String> {
+    collection
+        .iter()
+        .position(|request| request.get_name() == name)
+        .ok_or_else(|| format!("hook `{}` does not match any request", name))
+}
+
+/// Narrows the collection down to the request to run, given by its identifier or its name, and
+/// the hooks, so a run of it still has the hooks of its folders around it.
+pub fn only(collection: &mut Collection, request: &str) -> Result<(), String> {
+    let is_hook = |name: &str| collection.hooks().values().any(|h| h.contains(name));
+    let by_identifier: Vec<_> = collection
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.get_identifier() == request)
+        .map(|(index, _)| index)
+        .collect();
+    let matching = match by_identifier.as_slice() {
+        [] => collection
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.get_name() == request)
+            .map(|(index, _)| index)
+            .collect(),
+        _ => by_identifier,
+    };
+    let index = match matching.as_slice() {
+        [index] => *index,
+        [] => return Err(format!("no request matches `{}`", request)),
+        _ => {
+            return Err(format!(
+                "several requests are named `{}`, give the identifier of one",
+                request
+            ))
+        }
+    };
+    let name = collection.iter().nth(index).map(Request::get_name);
+    if name.as_deref().is_some_and(is_hook) {
+        return Err(format!(
+            "`{}` is a hook, it runs around the requests of its folder",
+            request
+        ));
+    }
+    let keep: Vec<_> = collection
+        .iter()
+        .enumerate()
+        .map(|(i, r)| i == index || is_hook(&r.get_name()))
+        .collect();
+    for (i, keep) in keep.into_iter().enumerate().rev() {
+        if !keep {
+            collection.remove_request(i);
+        }
+    }
+    Ok(())
+}
+
+/// How long a precondition ping may take before the target is considered down.
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An endpoint that must answer with a status before a run starts, e.g. a health check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Precondition {
+    url: String,
+    status: u16,
+}
+
+impl Precondition {
+    /// Reads a precondition written as `url` or `url status`. The status defaults to 200.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut parts = value.split_whitespace();
+        let url = parts
+            .next()
+            .ok_or_else(|| String::from("precondition is missing the url"))?;
+        let status = match parts.next() {
+            Some(status) => status
+                .parse()
+                .map_err(|_| format!("precondition status `{}` is not a number", status))?,
+            None => 200,
+        };
+        if parts.next().is_some() {
+            return Err(format!("precondition `{}` must be `url status`", value));
+        }
+        Ok(Self {
+            url: url.to_string(),
+            status,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+}
+
+/// Pings every precondition of the collection in order and stops at the first one that fails, so
+/// a run against an environment that is down fails fast. The ping gets the url and returns the
+/// status it answered with.
+pub fn check_preconditions(
+    collection: &Collection,
+    mut ping: impl FnMut(&str) -> Result<u16, String>,
+) -> Result<(), String> {
+    for precondition in collection.preconditions() {
+        match ping(precondition.url()) {
+            Ok(status) if status == precondition.status() => {}
+            Ok(status) => {
+                return Err(format!(
+                    "precondition failed: {} answered {}, expected {}",
+                    precondition.url(),
+                    status,
+                    precondition.status()
+                ))
+            }
+            Err(err) => {
+                return Err(format!(
+                    "precondition failed: {} is unreachable: {}",
+                    precondition.url(),
+                    err
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Sends a GET request to the url and returns the status of the response. It goes the way the
+/// requests of the run go, through the same proxy, CA certificate and ssh tunnel, but is only sent
+/// once.
+pub fn ping(url: &str, options: &Options) -> Result<u16, String> {
+    let request = Outgoing {
+        method: HttpMethod::Get,
+        url: url.to_string(),
+        headers: Vec::new(),
+        body: None,
+        raw: None,
+        form: Vec::new(),
+    };
+    let options = Options {
+        retries: 0,
+        timeout: Some(PING_TIMEOUT),
+        ..options.clone()
+    };
+    client::send(&request, &options)
+        .map(|response| response.facts().status)
+        .map_err(|err| err.to_string())
+}
+
+/// The variables captured during a run, one layer per open scope. A capture is visible to every
+/// request in the scope it was made in and is dropped when the scope is left.
+#[derive(Debug, Default, Clone)]
+pub struct ScopedVariables {
+    layers: Vec<(Scope, HashMap<String, String>)>,
+}
+
+impl ScopedVariables {
+    pub fn enter(&mut self, scope: Scope) {
+        self.layers.push((scope, HashMap::new()));
+    }
+
+    pub fn leave(&mut self) {
+        self.layers.pop();
+    }
+
+    /// Captures a variable in the innermost open scope.
+    pub fn capture(&mut self, key: String, value: String) {
+        if let Some((_, variables)) = self.layers.last_mut() {
+            variables.insert(key, value);
+        }
+    }
+
+    /// Flattens the captures of every open scope, ready to be used as the capture scope of
+    /// variables::Scopes.
+    pub fn flatten(&self) -> HashMap<String, String> {
+        let mut flattened = HashMap::new();
+        for (_, variables) in &self.layers {
+            flattened.extend(variables.clone());
+        }
+        flattened
+    }
+}
+
+/// How much of every response a run prints.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Verbosity {
+    /// Only the summary of the run.
+    Quiet,
+    /// A line per request, with the assertions that failed.
+    #[default]
+    Normal,
+    /// Also the headers and the body of every response.
+    Verbose,
+}
+
+/// What a run does with requests that are deprecated. Hooks always run, since the requests after
+/// them may depend on them.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Deprecated {
+    /// Send them and print why they are deprecated.
+    #[default]
+    Warn,
+    /// Don't send them.
+    Skip,
+    /// Don't send them and count them as failed.
+    Fail,
+}
+
+/// The exit codes of `hermes run`, so CI can tell why a run failed. 2 is left to usage errors,
+/// which the command line reports on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Exit {
+    Success = 0,
+    /// Every request was sent but some assertions failed.
+    TestsFailed = 1,
+    /// The command line is wrong, e.g. it names an environment or a request that does not exist.
+    /// Arguments that can't be read at all exit with it too, from clap.
+    Usage = 2,
+    /// The collection could not be read, or a request could not be built from it.
+    Invalid = 3,
+    /// A precondition or a request could not reach the server.
+    Unreachable = 4,
+}
+
+impl Exit {
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+/// The counts of a finished run.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Summary {
+    passed: usize,
+    failed: usize,
+    /// Requests that could not be built, e.g. a command variable that failed.
+    invalid: usize,
+    /// Requests that could not be sent.
+    unreachable: usize,
+    /// Deprecated requests that were not sent.
+    skipped: usize,
+    /// Requests that were built but not sent, in a dry run.
+    rendered: usize,
+}
+
+impl Summary {
+    /// Gets how the run ended. Problems with the collection win over network problems, which win
+    /// over failed assertions, since they hide whether the tests would pass.
+    pub fn exit(&self) -> Exit {
+        if self.invalid > 0 {
+            Exit::Invalid
+        } else if self.unreachable > 0 {
+            Exit::Unreachable
+        } else if self.failed > 0 {
+            Exit::TestsFailed
+        } else {
+            Exit::Success
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} requests: {} passed, {} failed, {} errors",
+            self.passed
+                + self.failed
+                + self.invalid
+                + self.unreachable
+                + self.skipped
+                + self.rendered,
+            self.passed,
+            self.failed,
+            self.invalid + self.unreachable
+        )?;
+        if self.skipped > 0 {
+            write!(f, ", {} skipped", self.skipped)?;
+        }
+        if self.rendered > 0 {
+            write!(f, ", {} not sent", self.rendered)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs every request of the collection with its hooks, checking the assertions of each response
+/// and printing the results to out. Requests use the active environment unless they are pinned to
+/// one, and deprecated requests are handled as the policy says. A run that can't start, because of
+/// a hook that does not exist or a precondition that failed, returns the exit code and why.
+///
+/// A dry run builds every request, variables and signing included, and prints it instead of
+/// sending it, to check that the collection can run. Preconditions are not pinged, and what a
+/// request captures is a placeholder naming the request.
+pub fn run(
+    collection: &Collection,
+    options: &Options,
+    resolver: &mut Resolver,
+    verbosity: Verbosity,
+    deprecated: Deprecated,
+    dry_run: bool,
+    out: &mut impl Write,
+) -> io::Result<Result<Summary, (Exit, String)>> {
+    let steps = match plan(collection) {
+        Ok(steps) => steps,
+        Err(err) => return Ok(Err((Exit::Invalid, err))),
+    };
+    let environment = collection.active_environment().cloned().unwrap_or_default();
+    let scopes = Scopes::new(
+        HashMap::new(),
+        environment.clone(),
+        collection.variables().clone(),
+    );
+    // a dry run sends nothing, not even the pings
+    let preconditions = if dry_run {
+        Ok(())
+    } else {
+        check_preconditions(collection, |url| {
+            ping(
+                &resolver
+                    .interpolate(url, &scopes)
+                    .map_err(|err| err.to_string())?,
+                options,
+            )
+        })
+    };
+    if let Err(err) = preconditions {
+        return Ok(Err((Exit::Unreachable, err)));
+    }
+
+    let masker = collection.masker();
+    let mut variables = ScopedVariables::default();
+    let mut summary = Summary::default();
+    // the cookies set by a response go with the requests after it, like in the TUI
+    let mut cookies = collection.cookies().clone();
+    // and the ETags it read go in the If-Match of the updates after it
+    let mut etags = collection.etags().clone();
+    for step in steps {
+        let (index, hook) = match step {
+            Step::Enter(scope) => {
+                variables.enter(scope);
+                continue;
+            }
+            Step::Leave(_) => {
+                variables.leave();
+                continue;
+            }
+            Step::Hook(index) => (index, true),
+            Step::Run(index) => (index, false),
+        };
+        let Some(request) = collection.iter().nth(index) else {
+            continue;
+        };
+        let deprecation = collection.deprecation(request).filter(|_| !hook);
+        match (deprecation, deprecated) {
+            (Some(message), Deprecated::Skip) => {
+                summary.skipped += 1;
+                report(
+                    out,
+                    verbosity,
+                    "SKIP",
+                    request,
+                    &format!("deprecated: {}", message),
+                )?;
+                continue;
+            }
+            (Some(message), Deprecated::Fail) => {
+                summary.failed += 1;
+                report(
+                    out,
+                    verbosity,
+                    "FAIL",
+                    request,
+                    &format!("deprecated: {}", message),
+                )?;
+                continue;
+            }
+            _ => {}
+        }
+        let environment = match request.get_pinned_environment() {
+            Some(name) => match collection.environment(name) {
+                Some(environment) => environment.clone(),
+                None => {
+                    summary.invalid += 1;
+                    let err = format!("pinned environment `{}` does not exist", name);
+                    report(out, verbosity, "ERROR", request, &err)?;
+                    continue;
+                }
+            },
+            None => environment.clone(),
+        };
+        let scopes = Scopes::new(
+            variables.flatten(),
+            environment,
+            collection.variables().clone(),
+        );
+        let mut outgoing =
+            match client::prepare(request, collection.default_headers(), &scopes, resolver) {
+                Ok(outgoing) => outgoing,
+                Err(err) => {
+                    summary.invalid += 1;
+                    report(out, verbosity, "ERROR", request, &err.to_string())?;
+                    continue;
+                }
+            };
+        if dry_run {
+            summary.rendered += 1;
+            report(out, verbosity, "DRY", request, "not sent")?;
+            for capture in request.get_captures() {
+                let placeholder =
+                    format!("<{} captured by {}>", capture.name(), request.get_name());
+                variables.capture(capture.name().to_string(), placeholder);
+            }
+            if verbosity == Verbosity::Quiet {
+                continue;
+            }
+            if let Some(message) = deprecation {
+                writeln!(out, "    deprecated: {}", message)?;
+            }
+            writeln!(out)?;
+            for line in masker.mask(&outgoing.render()).lines() {
+                writeln!(out, "    {}", line)?;
+            }
+            writeln!(out)?;
+            continue;
+        }
+        let options = Options {
+            ip_family: request.get_ip_family(options.ip_family),
+            proxy: request.get_proxy(&options.proxy),
+            retries: request.get_retries(),
+            ..options.clone()
+        };
+        cookies.add_to(&mut outgoing, Utc::now().timestamp());
+        etags.add_to(&mut outgoing);
+        let retry_warning = client::retry_warning(&outgoing, options.retries);
+        let response = match client::send(&outgoing, &options) {
+            Ok(response) => response,
+            Err(err) => {
+                summary.unreachable += 1;
+                report(
+                    out,
+                    verbosity,
+                    "ERROR",
+                    request,
+                    &masker.mask(&err.to_string()),
+                )?;
+                continue;
+            }
+        };
+        let facts = response.facts();
+        let url = facts.redirects.last().unwrap_or(&outgoing.url);
+        cookies.store(url, &facts.headers, Utc::now().timestamp());
+        etags.store(outgoing.method, url, facts);
+        let mut failures: Vec<String> = request
+            .get_assertions()
+            .iter()
+            .filter_map(|assertion| assertion.check(response.facts()).err())
+            .collect();
+        if let Some(expected) = request.get_expected() {
+            let drift = expected::drift(expected, &response.facts().body);
+            failures.extend(drift.into_iter().map(|drift| format!("drift: {}", drift)));
+        }
+        // what a request captures is seen by the requests after it in the same scope
+        for capture in request.get_captures() {
+            match capture.extract(&response.facts().body) {
+                Ok(value) => variables.capture(capture.name().to_string(), value),
+                Err(err) => failures.push(err),
+            }
+        }
+        if failures.is_empty() {
+            summary.passed += 1;
+            report(out, verbosity, "PASS", request, &response.summary())?;
+        } else {
+            summary.failed += 1;
+            report(out, verbosity, "FAIL", request, &response.summary())?;
+        }
+        if verbosity == Verbosity::Quiet {
+            continue;
+        }
+        if let Some(message) = deprecation {
+            writeln!(out, "    deprecated: {}", message)?;
+        }
+        if let Some(warning) = retry_warning {
+            writeln!(out, "    warning: {}", warning)?;
+        }
+        for attempt in response.attempts() {
+            writeln!(out, "    attempt: {}", masker.mask(&attempt.outcome))?;
+        }
+        for failure in failures {
+            writeln!(out, "    {}", masker.mask(&failure))?;
+        }
+        if verbosity == Verbosity::Verbose {
+            let facts = response.facts();
+            for (name, value) in &facts.headers {
+                writeln!(out, "    {}: {}", name, masker.mask(value))?;
+            }
+            let body = response.body().preview(facts.header("content-type"));
+            writeln!(out)?;
+            for line in masker.mask(&body).lines() {
+                writeln!(out, "    {}", line)?;
+            }
+            writeln!(out)?;
+        }
+    }
+    writeln!(out, "{}", summary)?;
+    Ok(Ok(summary))
+}
+
+/// Prints the line of a request, e.g. `PASS GET login 200 OK in 87 ms`. Quiet runs skip it.
+fn report(
+    out: &mut impl Write,
+    verbosity: Verbosity,
+    status: &str,
+    request: &Request,
+    detail: &str,
+) -> io::Result<()> {
+    if verbosity == Verbosity::Quiet {
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "{:<5} {} {} {}",
+        status,
+        request.get_method(),
+        request.get_name(),
+        detail
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        io::Read,
+        net::{Ipv4Addr, TcpListener},
+        thread,
+    };
+
+    use super::*;
+    use crate::api::{CaptureRule, ProxyRoute, Request};
+
+    fn request(name: &str, folder: &str) -> Request {
+        let mut request = Request::new(
+            String::from(name),
+            HttpMethod::Get,
+            String::new(),
+            None,
+            None,
+            HashMap::new(),
+        );
+        request.set_folder(String::from(folder));
+        request
+    }
+
+    #[test]
+    fn should_plan_hooks_around_folders() {
+        let mut collection = Collection::default();
+        collection.add_request(request("create user", ""));
+        collection.add_request(request("delete user", ""));
+        collection.add_request(request("get user", "users"));
+        collection.add_request(request("health", ""));
+        collection.add_request(request("login", "users"));
+        collection.add_request(request("seed", "users"));
+        collection
+            .hooks_mut(Scope::Collection)
+            .add_before(String::from("create user"));
+        collection
+            .hooks_mut(Scope::Collection)
+            .add_after(String::from("delete user"));
+        collection
+            .hooks_mut(Scope::Folder(String::from("users")))
+            .add_before(String::from("seed"));
+
+        let users = Scope::Folder(String::from("users"));
+        assert_eq!(
+            plan(&collection),
+            Ok(vec![
+                Step::Enter(Scope::Collection),
+                Step::Hook(0),
+                Step::Run(3),
+                Step::Enter(users.clone()),
+                Step::Hook(5),
+                Step::Run(2),
+                Step::Run(4),
+                Step::Leave(users),
+                Step::Hook(1),
+                Step::Leave(Scope::Collection),
+            ])
+        );
+
+        collection
+            .hooks_mut(Scope::Collection)
+            .add_after(String::from("missing"));
+        assert!(plan(&collection).is_err());
+    }
+
+    #[test]
+    fn should_run_only_one_request_with_its_hooks() {
+        let mut collection = Collection::default();
+        collection.add_request(request("create user", ""));
+        collection.add_request(request("get user", "users"));
+        collection.add_request(request("get user", "admins"));
+        collection.add_request(request("seed", "users"));
+        collection.add_request(request("health", ""));
+        collection
+            .hooks_mut(Scope::Collection)
+            .add_before(String::from("create user"));
+        collection
+            .hooks_mut(Scope::Folder(String::from("users")))
+            .add_before(String::from("seed"));
+
+        assert_eq!(
+            only(&mut collection.clone(), "get user"),
+            Err(String::from(
+                "several requests are named `get user`, give the identifier of one"
+            ))
+        );
+        assert!(only(&mut collection.clone(), "seed").is_err());
+        assert!(only(&mut collection.clone(), "missing").is_err());
+
+        collection
+            .get_request_mut(1)
+            .unwrap()
+            .set_identifier(Some(String::from("get-member")));
+        only(&mut collection, "get-member").unwrap();
+        let names: Vec<_> = collection.iter().map(Request::get_name).collect();
+        assert_eq!(names, ["create user", "get user", "seed"]);
+        let users = Scope::Folder(String::from("users"));
+        assert_eq!(
+            plan(&collection),
+            Ok(vec![
+                Step::Enter(Scope::Collection),
+                Step::Hook(0),
+                Step::Enter(users.clone()),
+                Step::Hook(2),
+                Step::Run(1),
+                Step::Leave(users),
+                Step::Leave(Scope::Collection),
+            ])
+        );
+    }
+
+    #[test]
+    fn should_stop_at_the_first_failed_precondition() {
+        let mut collection = Collection::default();
+        for value in [
+            "http://db/health",
+            "http://api/health 204",
+            "http://cache/health",
+        ] {
+            collection.add_precondition(Precondition::parse(value).unwrap());
+        }
+        assert!(Precondition::parse("http://api/health ok").is_err());
+
+        let mut pinged = Vec::new();
+        let result = check_preconditions(&collection, |url| {
+            pinged.push(url.to_string());
+            match url {
+                "http://db/health" => Ok(200),
+                _ => Ok(503),
+            }
+        });
+        assert_eq!(
+            result,
+            Err(String::from(
+                "precondition failed: http://api/health answered 503, expected 204"
+            ))
+        );
+        assert_eq!(pinged, ["http://db/health", "http://api/health"]);
+        assert!(
+            check_preconditions(&collection, |_| Err(String::from("refused")))
+                .unwrap_err()
+                .contains("unreachable: refused")
+        );
+    }
+
+    #[test]
+    fn should_update_hooks_when_renaming_a_request() {
+        let mut collection = Collection::default();
+        collection.add_request(request("create user", ""));
+        collection.add_request(request("get user", "users"));
+        collection
+            .hooks_mut(Scope::Folder(String::from("users")))
+            .add_before(String::from("create user"));
+
+        assert!(collection
+            .rename_request(0, String::from("get user"))
+            .is_err());
+        assert!(collection.rename_request(0, String::from(" ")).is_err());
+        assert_eq!(
+            collection.rename_request(0, String::from("create test user")),
+            Ok(1)
+        );
+        assert_eq!(
+            collection.hooks()[&Scope::Folder(String::from("users"))].before(),
+            [String::from("create test user")]
+        );
+    }
+
+    #[test]
+    fn should_drop_captures_when_leaving_a_scope() {
+        let mut variables = ScopedVariables::default();
+        variables.enter(Scope::Collection);
+        variables.capture(String::from("TOKEN"), String::from("outer"));
+        variables.enter(Scope::Folder(String::from("users")));
+        variables.capture(String::from("TOKEN"), String::from("inner"));
+        assert_eq!(variables.flatten()["TOKEN"], "inner");
+        variables.leave();
+        assert_eq!(variables.flatten()["TOKEN"], "outer");
+    }
+
+    #[test]
+    fn should_print_requests_without_sending_them() {
+        let mut collection = Collection::default();
+        collection.new_environment(String::from("local"));
+        collection.set_active_environment(String::from("local"));
+        collection.enable_active_environment();
+        // nothing listens on the discard port, a request that was sent would be unreachable
+        collection.add_environment_entry(String::from("BASE"), String::from("http://127.0.0.1:9"));
+        collection.add_precondition(Precondition::parse("{{BASE}}/health").unwrap());
+        let mut login = request("login", "");
+        login.set_url(String::from("{{BASE}}/login"));
+        login.add_capture(CaptureRule::new(
+            String::from("TOKEN"),
+            String::from("$.token"),
+        ));
+        collection.add_request(login);
+        let mut profile = request("profile", "users");
+        profile.set_url(String::from("{{BASE}}/me"));
+        profile.set_headers(HashMap::from([(
+            String::from("Authorization"),
+            String::from("Bearer {{TOKEN}}"),
+        )]));
+        collection.add_request(profile);
+
+        let mut out = Vec::new();
+        let summary = run(
+            &collection,
+            &Options::default(),
+            &mut Resolver::default(),
+            Verbosity::Normal,
+            Deprecated::Warn,
+            true,
+            &mut out,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(summary.exit(), Exit::Success);
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("DRY   GET login not sent\n\n    GET http://127.0.0.1:9/login\n"));
+        assert!(out.contains("    Authorization: Bearer <TOKEN captured by login>\n"));
+        assert!(out.ends_with("2 requests: 0 passed, 0 failed, 0 errors, 2 not sent\n"));
+    }
+
+    #[test]
+    fn should_exit_with_the_worst_outcome() {
+        let mut summary = Summary {
+            passed: 2,
+            ..Summary::default()
+        };
+        assert_eq!(summary.exit(), Exit::Success);
+        summary.failed = 1;
+        assert_eq!(summary.exit(), Exit::TestsFailed);
+        summary.unreachable = 1;
+        assert_eq!(summary.exit(), Exit::Unreachable);
+        summary.invalid = 1;
+        assert_eq!(summary.exit().code(), 3);
+        // a wrong request or environment exits like the arguments clap rejects
+        assert_eq!(Exit::Usage.code(), 2);
+        assert_eq!(
+            summary.to_string(),
+            "5 requests: 2 passed, 1 failed, 2 errors"
+        );
+        summary.skipped = 1;
+        assert_eq!(
+            summary.to_string(),
+            "6 requests: 2 passed, 1 failed, 2 errors, 1 skipped"
+        );
+    }
+
+    #[test]
+    fn should_ping_through_the_proxy_of_the_run() {
+        let proxy = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let options = Options {
+            proxy: ProxyRoute::Via(format!("http://{}", proxy.local_addr().unwrap())),
+            max_body_bytes: 1024,
+            ..Options::default()
+        };
+        let serving = thread::spawn(move || {
+            let (mut stream, _) = proxy.accept().unwrap();
+            let mut buffer = [0; 1024];
+            let read = stream.read(&mut buffer).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            String::from_utf8_lossy(&buffer[..read]).to_string()
+        });
+
+        assert_eq!(ping("http://health.invalid/ping", &options), Ok(204));
+        let received = serving.join().unwrap();
+        assert!(received.starts_with("GET http://health.invalid/ping HTTP/1.1"));
+    }
+}
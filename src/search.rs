@@ -0,0 +1,268 @@
+//! Finding requests in large collections. Names are matched fuzzily, while urls, headers, bodies
+//! and descriptions are searched in full, so questions like "which requests still call
+//! /v1/users?" are answered without opening every request. The words of every request are kept in
+//! an inverted index built when the collection is loaded, so searching only looks at the requests
+//! that have every word of the query.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::api::{Collection, Request};
+
+/// How many characters of the matched text are shown before and after it.
+const SNIPPET_CONTEXT: usize = 24;
+
+/// Where a request matched the query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Url,
+    Header,
+    Body,
+    Description,
+}
+
+impl Field {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Field::Name => "name",
+            Field::Url => "url",
+            Field::Header => "header",
+            Field::Body => "body",
+            Field::Description => "description",
+        }
+    }
+}
+
+/// A request that matched a query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    request: usize,
+    field: Field,
+    /// The text around the match. Empty for names, which are shown anyway.
+    snippet: String,
+}
+
+impl Match {
+    /// Gets the index of the request in the collection.
+    pub fn request(&self) -> usize {
+        self.request
+    }
+
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    pub fn snippet(&self) -> &str {
+        &self.snippet
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SearchIndex {
+    /// The name of every request, in the order of the collection.
+    names: Vec<String>,
+    /// The text of every request that is searched in full, by field.
+    documents: Vec<Vec<(Field, String)>>,
+    /// The requests every word shows up in. Sorted, so words can be looked up by prefix.
+    postings: BTreeMap<String, BTreeSet<usize>>,
+}
+
+impl SearchIndex {
+    pub fn build(collection: &Collection) -> Self {
+        let mut index = Self::default();
+        for (request, document) in collection.iter().enumerate() {
+            let document = document_of(document);
+            for (_, text) in &document {
+                for word in words(text) {
+                    index.postings.entry(word).or_default().insert(request);
+                }
+            }
+            index.documents.push(document);
+        }
+        index.names = collection.iter().map(Request::get_name).collect();
+        index
+    }
+
+    /// Finds the requests that match the query: first the ones whose name matches it fuzzily, best
+    /// first, then the ones that contain it, in the order of the collection. Every request shows
+    /// up once. An empty query matches every request.
+    pub fn search(&self, query: &str) -> Vec<Match> {
+        let query = query.trim();
+        let mut named: Vec<(i64, usize)> = self
+            .names
+            .iter()
+            .enumerate()
+            .filter_map(|(request, name)| Some((fuzzy_score(query, name)?, request)))
+            .collect();
+        named.sort_by_key(|(score, request)| (-score, *request));
+        let mut matches: Vec<Match> = named
+            .into_iter()
+            .map(|(_, request)| Match {
+                request,
+                field: Field::Name,
+                snippet: String::new(),
+            })
+            .collect();
+
+        let needle = query.to_ascii_lowercase();
+        for request in self.candidates(query) {
+            if matches.iter().any(|found| found.request == request) {
+                continue;
+            }
+            let found = self.documents[request].iter().find_map(|(field, text)| {
+                let at = text.to_ascii_lowercase().find(&needle)?;
+                Some(Match {
+                    request,
+                    field: *field,
+                    snippet: snippet(text, at, needle.len()),
+                })
+            });
+            matches.extend(found);
+        }
+        matches
+    }
+
+    /// Gets the requests that have every word of the query. The last word may be cut short, since
+    /// it is likely still being typed.
+    fn candidates(&self, query: &str) -> BTreeSet<usize> {
+        let words: Vec<String> = words(query).collect();
+        let Some((last, rest)) = words.split_last() else {
+            return BTreeSet::new();
+        };
+        let mut candidates: BTreeSet<usize> = self
+            .postings
+            .range(last.clone()..)
+            .take_while(|(word, _)| word.starts_with(last.as_str()))
+            .flat_map(|(_, requests)| requests.iter().copied())
+            .collect();
+        for word in rest {
+            match self.postings.get(word) {
+                Some(requests) => candidates.retain(|request| requests.contains(request)),
+                None => return BTreeSet::new(),
+            }
+        }
+        candidates
+    }
+}
+
+/// Gets the text of a request that is searched in full.
+fn document_of(request: &Request) -> Vec<(Field, String)> {
+    let mut document = vec![(Field::Url, request.get_url())];
+    let mut headers: Vec<_> = request.get_headers().iter().collect();
+    headers.sort();
+    document.extend(
+        headers
+            .into_iter()
+            .map(|(name, value)| (Field::Header, format!("{}: {}", name, value))),
+    );
+    if let Some(body) = request.get_body() {
+        document.push((Field::Body, body.to_string()));
+    }
+    if let Some(description) = request.get_description() {
+        document.push((Field::Description, description.to_string()));
+    }
+    document
+}
+
+/// Splits text into lowercase words of letters and digits, so `/v1/users` has `v1` and `users`.
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Scores how well the characters of the query show up in order in the text, ignoring case.
+/// Characters next to each other and at the start of words score more. None when they don't all
+/// show up.
+fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().collect();
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    let mut next = 0;
+    for wanted in query.chars().filter(|ch| !ch.is_whitespace()) {
+        let wanted = wanted.to_lowercase().next();
+        let position = next
+            + text[next..]
+                .iter()
+                .position(|ch| ch.to_lowercase().next() == wanted)?;
+        next = position + 1;
+        score += 1;
+        if position == 0 || !text[position - 1].is_alphanumeric() {
+            score += 3;
+        }
+        if previous.is_some_and(|previous| previous + 1 == position) {
+            score += 5;
+        }
+        previous = Some(position);
+    }
+    Some(score)
+}
+
+/// Gets the text around a match, on a single line.
+fn snippet(text: &str, at: usize, len: usize) -> String {
+    let start = text[..at]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_CONTEXT - 1)
+        .map_or(0, |(index, _)| index);
+    let end = text[at + len..]
+        .char_indices()
+        .nth(SNIPPET_CONTEXT)
+        .map_or(text.len(), |(index, _)| at + len + index);
+    let mut snippet = text[start..end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < text.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::api::HttpMethod;
+
+    #[test]
+    fn should_find_requests_by_name_and_contents() {
+        let mut collection = Collection::default();
+        for (name, url, body) in [
+            ("List users", "{{BASE_URL}}/v1/users", None),
+            (
+                "Create user",
+                "{{BASE_URL}}/v2/users",
+                Some("{\"legacy\": \"/v1/users\"}"),
+            ),
+            ("Login", "{{BASE_URL}}/login", None),
+        ] {
+            collection.add_request(Request::new(
+                String::from(name),
+                HttpMethod::Get,
+                String::from(url),
+                body.map(String::from),
+                None,
+                HashMap::new(),
+            ));
+        }
+        let index = SearchIndex::build(&collection);
+
+        let found = index.search("/v1/users");
+        let requests: Vec<_> = found.iter().map(Match::request).collect();
+        assert_eq!(requests, [0, 1]);
+        assert_eq!(found[1].field(), Field::Body);
+        assert_eq!(found[1].snippet(), "{\"legacy\": \"/v1/users\"}");
+
+        let found = index.search("lgn");
+        assert_eq!(found[0].request(), 2);
+        assert_eq!(found[0].field(), Field::Name);
+        assert_eq!(index.search("/v1/us").len(), 2);
+        assert!(index.search("/v3/users").is_empty());
+        assert_eq!(index.search("").len(), 3);
+    }
+}
@@ -0,0 +1,71 @@
+//! Masking of secret values before they are shown or copied anywhere.
+
+use std::io::{self, Write};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// What a secret value is replaced with.
+pub const MASK: &str = "••••••";
+
+/// Masker replaces every occurrence of the known secret values in a text. Every view that shows
+/// user values (response viewer, raw request, logs, exports) should go through the same Masker so
+/// secrets are hidden consistently.
+#[derive(Debug, Default, Clone)]
+pub struct Masker {
+    secrets: Vec<String>,
+}
+
+impl Masker {
+    pub fn new(secrets: impl IntoIterator<Item = String>) -> Self {
+        let mut secrets: Vec<String> = secrets.into_iter().filter(|s| !s.is_empty()).collect();
+        // replace the longest values first so a secret that contains another one is fully masked
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        secrets.dedup();
+        Self { secrets }
+    }
+
+    /// Adds more secret values to mask.
+    pub fn extend(&mut self, secrets: impl IntoIterator<Item = String>) {
+        let secrets = self
+            .secrets
+            .drain(..)
+            .chain(secrets)
+            .collect::<Vec<String>>();
+        *self = Self::new(secrets);
+    }
+
+    /// Returns the text with all the secret values replaced by MASK.
+    pub fn mask(&self, text: &str) -> String {
+        let mut masked = text.to_string();
+        for secret in self.secrets.iter() {
+            masked = masked.replace(secret.as_str(), MASK);
+        }
+        masked
+    }
+}
+
+/// Copies the text to the system clipboard using the OSC 52 terminal escape sequence, which works
+/// through ssh and tmux as long as the terminal supports it.
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_mask_all_secrets() {
+        let masker = Masker::new(vec![
+            String::from("abc"),
+            String::from("abcdef"),
+            String::new(),
+        ]);
+        assert_eq!(
+            masker.mask("token=abcdef&key=abc&empty="),
+            format!("token={}&key={}&empty=", MASK, MASK)
+        );
+    }
+}
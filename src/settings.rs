@@ -0,0 +1,129 @@
+//! User settings read from `$XDG_CONFIG_HOME/hermes/config.yaml` (or `~/.config/hermes`). Every
+//! setting is optional, missing ones fall back to their defaults.
+
+use std::{collections::HashMap, env, path::PathBuf};
+
+use config::{Config, ConfigError, File};
+use serde::Deserialize;
+
+use crate::network::NetworkSimulation;
+use crate::notifications::Notifications;
+use crate::parser::ScanSettings;
+use crate::redaction::Redaction;
+
+/// The default for max_body_bytes, 10 MiB.
+const DEFAULT_MAX_BODY_BYTES: u64 = 10 * 1024 * 1024;
+/// The default for history_per_request.
+const DEFAULT_HISTORY_PER_REQUEST: usize = 50;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub network_simulation: NetworkSimulation,
+    /// Response bodies bigger than this many bytes are written to a temp file instead of being
+    /// kept in memory.
+    pub max_body_bytes: u64,
+    /// Command used to page response bodies, e.g. `bat -l json` or `jless`. Defaults to $PAGER.
+    pub pager: Option<String>,
+    /// Command used to show diffs, given the paths of the two files, e.g. `delta`. Without one,
+    /// `diff -u` is shown in the pager.
+    pub diff_tool: Option<String>,
+    /// Commands that `$(command)` variables can run without asking for confirmation first.
+    pub allowed_commands: Vec<String>,
+    /// How collection folders are scanned for hermes files.
+    pub scan: ScanSettings,
+    /// Who changes to requests are recorded as in their changelog. Defaults to the name from the
+    /// git config, then the user of the system.
+    pub author: Option<String>,
+    /// Keyboard macros by register, each one a list of keys such as `j`, `<enter>` or `<c-t>`.
+    pub macros: HashMap<String, Vec<String>>,
+    /// Allows raw requests, which are sent byte for byte even when they are malformed, to test how
+    /// servers handle them.
+    pub lab_mode: bool,
+    /// The Origin CORS preflights are sent from, e.g. `https://app.example.com`. Defaults to
+    /// `http://localhost:3000`.
+    pub cors_origin: Option<String>,
+    /// How many responses of every request the history keeps, bookmarked ones aside. 0 keeps them
+    /// all.
+    pub history_per_request: usize,
+    /// Saves the folds of the JSON tree of every request, so trees open as they were left after a
+    /// restart too.
+    pub persist_json_folds: bool,
+    /// The headers and patterns redacted before anything is saved to the history, printed by
+    /// `hermes run` or exported.
+    pub redaction: Redaction,
+    /// Desktop notifications when a slow request or run completes.
+    pub notifications: Notifications,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            network_simulation: NetworkSimulation::default(),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            pager: None,
+            diff_tool: None,
+            allowed_commands: Vec::new(),
+            scan: ScanSettings::default(),
+            author: None,
+            macros: HashMap::new(),
+            lab_mode: false,
+            cors_origin: None,
+            history_per_request: DEFAULT_HISTORY_PER_REQUEST,
+            persist_json_folds: false,
+            redaction: Redaction::default(),
+            notifications: Notifications::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the settings from the config file. A missing config file is not an error.
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut builder = Config::builder();
+        if let Some(dir) = config_dir() {
+            builder = builder.add_source(File::from(dir.join("config.yaml")).required(false));
+        }
+        builder.build()?.try_deserialize()
+    }
+}
+
+/// Gets the directory where hermes keeps its config.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("hermes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use config::FileFormat;
+
+    use super::*;
+
+    fn parse(yaml: &str) -> Settings {
+        Config::builder()
+            .add_source(File::from_str(yaml, FileFormat::Yaml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap()
+    }
+
+    #[test]
+    fn should_fill_the_network_simulation_with_its_defaults() {
+        let settings = parse("network_simulation:\n  latency_ms: 300\n");
+        assert_eq!(settings.network_simulation.latency_ms, 300);
+        assert_eq!(settings.network_simulation.failure_percent, 0);
+        assert!(!settings.network_simulation.enabled);
+    }
+
+    #[test]
+    fn should_read_the_external_tools() {
+        let settings = parse("pager: bat -l json\n");
+        assert_eq!(settings.pager.as_deref(), Some("bat -l json"));
+        assert_eq!(settings.diff_tool, None);
+    }
+}
@@ -0,0 +1,240 @@
+//! A self-contained format to share a single request with someone who does not have the
+//! collection. The request is written as hermes source with every variable that is not secret
+//! resolved, optionally along with an example response. A share is either a JSON file or a link,
+//! the same JSON encoded in base64 behind `hermes-share:`, and is imported with `hermes import`.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{FormField, Request},
+    client::Response,
+    secrets::Masker,
+    source::{self, SourceValue},
+};
+
+/// What links to a shared request start with.
+pub const LINK_PREFIX: &str = "hermes-share:";
+/// The extension of shared request files.
+pub const FILE_EXTENSION: &str = "hermes-share.json";
+/// Tells a share apart from any other JSON file.
+const FORMAT: &str = "hermes-share";
+/// The version of the format, bumped when a share can't be read by older versions of hermes.
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Share {
+    format: String,
+    version: u32,
+    /// The request as hermes source.
+    source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    example: Option<Example>,
+}
+
+/// A response of the request, to show what it answers without sending it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Example {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Share {
+    /// Bundles the request with the variables given replaced by their values. Secrets must not be
+    /// part of the variables, the masker hides the ones that show up in the example anyway.
+    pub fn new(
+        request: &Request,
+        variables: &HashMap<String, String>,
+        response: Option<&Response>,
+        masker: &Masker,
+    ) -> Self {
+        let resolve = |text: &str| {
+            variables
+                .iter()
+                .fold(text.to_string(), |text, (name, value)| {
+                    text.replace(&format!("{{{{{}}}}}", name), value)
+                })
+        };
+        let mut request = request.clone();
+        request.set_url(resolve(&request.get_url()));
+        request.set_headers(
+            request
+                .get_headers()
+                .iter()
+                .map(|(name, value)| (name.clone(), resolve(value)))
+                .collect(),
+        );
+        request.set_auth(
+            request
+                .get_auth()
+                .and_then(|auth| auth.resolve(|text| Ok::<_, ()>(resolve(text))).ok()),
+        );
+        request.set_body(request.get_body().map(resolve));
+        request.set_form(
+            request
+                .get_form()
+                .iter()
+                .map(|field| {
+                    FormField::new(
+                        field.name().to_string(),
+                        resolve(field.value()),
+                        field.is_enabled(),
+                    )
+                })
+                .collect(),
+        );
+        let example = response.map(|response| {
+            let facts = response.facts();
+            Example {
+                status: facts.status,
+                headers: facts
+                    .headers
+                    .iter()
+                    .map(|(name, value)| (name.clone(), masker.mask(value)))
+                    .collect(),
+                body: masker.mask(&facts.body),
+            }
+        });
+        Self {
+            format: String::from(FORMAT),
+            version: VERSION,
+            source: source::write_request(&request),
+            example,
+        }
+    }
+
+    /// Reads a share from a link or from the contents of a share file.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let json = match text.strip_prefix(LINK_PREFIX) {
+            Some(encoded) => {
+                let bytes = URL_SAFE_NO_PAD
+                    .decode(encoded)
+                    .map_err(|err| format!("the share link is broken: {}", err))?;
+                String::from_utf8(bytes).map_err(|err| err.to_string())?
+            }
+            None => text.to_string(),
+        };
+        let share: Self =
+            serde_json::from_str(&json).map_err(|err| format!("not a shared request: {}", err))?;
+        if share.format != FORMAT {
+            return Err(String::from("not a shared request"));
+        }
+        if share.version > VERSION {
+            return Err(format!(
+                "the request was shared by a newer version of hermes (format {}), update hermes to import it",
+                share.version
+            ));
+        }
+        Ok(share)
+    }
+
+    pub fn to_link(&self) -> String {
+        let json = serde_json::to_string(self).unwrap_or_default();
+        format!("{}{}", LINK_PREFIX, URL_SAFE_NO_PAD.encode(json))
+    }
+
+    pub fn to_file(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Gets the identifier of the shared request, used to name the imported file.
+    pub fn identifier(&self) -> String {
+        source::read(&self.source)
+            .0
+            .iter()
+            .find(|block| block.block_type() == "request")
+            .map(|block| match block.fields("name").next() {
+                Some(SourceValue::Text(name)) => source::identifier_of(name),
+                _ => block.identifier().to_string(),
+            })
+            .unwrap_or_else(|| String::from("request"))
+    }
+
+    /// Gets the hermes file of the request, with the example response in comments above it.
+    pub fn to_hermes(&self) -> String {
+        let Some(example) = &self.example else {
+            return self.source.clone();
+        };
+        let mut contents = format!("# example response: {}\n", example.status);
+        for (name, value) in &example.headers {
+            contents.push_str(&format!("# {}: {}\n", name, value));
+        }
+        contents.push_str("#\n");
+        for line in example.body.lines() {
+            match line {
+                "" => contents.push_str("#\n"),
+                line => contents.push_str(&format!("# {}\n", line)),
+            }
+        }
+        contents.push('\n');
+        contents.push_str(&self.source);
+        contents
+    }
+}
+
+/// Imports a share, given as a link or the path to a share file, into a hermes file in the folder.
+/// Existing files are never replaced. Returns the path of the new file.
+pub fn import(share: &str, dir: &Path) -> Result<PathBuf, String> {
+    let share = if share.trim().starts_with(LINK_PREFIX) {
+        Share::parse(share)?
+    } else {
+        let contents = fs::read_to_string(share).map_err(|err| format!("{}: {}", share, err))?;
+        Share::parse(&contents)?
+    };
+    let path = dir.join(format!("{}.hermes", share.identifier()));
+    if path.exists() {
+        return Err(format!("{} already exists", path.display()));
+    }
+    fs::write(&path, share.to_hermes()).map_err(|err| format!("{}: {}", path.display(), err))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::HttpMethod;
+
+    #[test]
+    fn should_share_a_request_without_its_secrets() {
+        let mut request = Request::new(
+            String::from("Get user"),
+            HttpMethod::Get,
+            String::from("{{BASE_URL}}/users/1"),
+            None,
+            None,
+            HashMap::from([(
+                String::from("Authorization"),
+                String::from("Bearer {{TOKEN}}"),
+            )]),
+        );
+        request.set_folder(String::from("users"));
+        let variables = HashMap::from([(
+            String::from("BASE_URL"),
+            String::from("https://api.example.com"),
+        )]);
+        let share = Share::new(&request, &variables, None, &Masker::default());
+
+        let imported = Share::parse(&share.to_link()).unwrap();
+        assert_eq!(imported, Share::parse(&share.to_file()).unwrap());
+        assert_eq!(imported.identifier(), "get-user");
+        let mut copy = request.clone();
+        source::apply_request(&mut copy, &imported.to_hermes()).unwrap();
+        assert_eq!(copy.get_url(), "https://api.example.com/users/1");
+        assert_eq!(copy.get_headers()["Authorization"], "Bearer {{TOKEN}}");
+
+        assert!(Share::parse("hermes-share:???").is_err());
+        assert!(
+            Share::parse(r#"{"format":"hermes-share","version":9,"source":""}"#)
+                .unwrap_err()
+                .contains("newer version")
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Signing and encrypting request bodies, for APIs that only take signed or encrypted payloads.
+//! A request picks how in its settings along with the key, usually a `{{VARIABLE}}` pointing at a
+//! secret of the environment so the key is masked and never written in the collection. JWS and
+//! JWE are built here, PGP is left to the `gpg` found on the PATH and its keyring.
+
+use std::{
+    fmt, fs,
+    io::Write,
+    process::{Command, Stdio},
+    str::FromStr,
+};
+
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use ring::{
+    aead, hmac,
+    rand::{SecureRandom, SystemRandom},
+    signature,
+};
+
+/// How a body is turned into what is sent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SigningMethod {
+    /// A compact JWS signed with HMAC SHA-256, the key being the shared secret.
+    JwsHs256,
+    /// A compact JWS signed with RSA SHA-256, the key being a PKCS#8 private key in PEM, or the
+    /// path to one.
+    JwsRs256,
+    /// A compact JWE encrypted with AES-256-GCM, the key being the 32 bytes shared with the
+    /// server in base64url, like the `k` of a JWK.
+    JweA256Gcm,
+    /// A clearsigned PGP message, the key being the id of a secret key in the gpg keyring.
+    GpgSign,
+    /// An armored PGP message encrypted to the key, the id of a public key in the gpg keyring.
+    GpgEncrypt,
+}
+
+impl SigningMethod {
+    /// Gets the Content-Type sent with a body signed this way when the request does not set one.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            SigningMethod::JwsHs256 | SigningMethod::JwsRs256 | SigningMethod::JweA256Gcm => {
+                "application/jose"
+            }
+            SigningMethod::GpgSign => "text/plain",
+            SigningMethod::GpgEncrypt => "application/pgp-encrypted",
+        }
+    }
+}
+
+impl fmt::Display for SigningMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let method = match self {
+            SigningMethod::JwsHs256 => "jws-hs256",
+            SigningMethod::JwsRs256 => "jws-rs256",
+            SigningMethod::JweA256Gcm => "jwe-a256gcm",
+            SigningMethod::GpgSign => "gpg-sign",
+            SigningMethod::GpgEncrypt => "gpg-encrypt",
+        };
+        write!(f, "{}", method)
+    }
+}
+
+impl FromStr for SigningMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "jws-hs256" => Ok(SigningMethod::JwsHs256),
+            "jws-rs256" => Ok(SigningMethod::JwsRs256),
+            "jwe-a256gcm" => Ok(SigningMethod::JweA256Gcm),
+            "gpg-sign" => Ok(SigningMethod::GpgSign),
+            "gpg-encrypt" => Ok(SigningMethod::GpgEncrypt),
+            _ => Err(format!(
+                "unknown body signing '{}', expected jws-hs256, jws-rs256, jwe-a256gcm, gpg-sign or gpg-encrypt",
+                s
+            )),
+        }
+    }
+}
+
+/// How the body of a request is signed or encrypted, from its settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BodySigning {
+    method: SigningMethod,
+    /// The key as written, variables included.
+    key: String,
+}
+
+impl BodySigning {
+    pub fn new(method: SigningMethod, key: String) -> Self {
+        Self { method, key }
+    }
+
+    pub fn method(&self) -> SigningMethod {
+        self.method
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+/// Signs or encrypts the body with the key, once its variables are resolved.
+pub fn apply(method: SigningMethod, key: &str, body: &str) -> Result<String, String> {
+    match method {
+        SigningMethod::JwsHs256 => {
+            let signing_input = signing_input("HS256", body);
+            let key = hmac::Key::new(hmac::HMAC_SHA256, key.as_bytes());
+            let tag = hmac::sign(&key, signing_input.as_bytes());
+            Ok(format!(
+                "{}.{}",
+                signing_input,
+                URL_SAFE_NO_PAD.encode(tag.as_ref())
+            ))
+        }
+        SigningMethod::JwsRs256 => {
+            let der = private_key_der(key)?;
+            let key_pair = signature::RsaKeyPair::from_pkcs8(&der)
+                .map_err(|err| format!("the RSA key is not a PKCS#8 private key: {}", err))?;
+            let signing_input = signing_input("RS256", body);
+            let mut signed = vec![0; key_pair.public().modulus_len()];
+            key_pair
+                .sign(
+                    &signature::RSA_PKCS1_SHA256,
+                    &SystemRandom::new(),
+                    signing_input.as_bytes(),
+                    &mut signed,
+                )
+                .map_err(|_| String::from("signing with the RSA key failed"))?;
+            Ok(format!(
+                "{}.{}",
+                signing_input,
+                URL_SAFE_NO_PAD.encode(signed)
+            ))
+        }
+        SigningMethod::JweA256Gcm => encrypt_jwe(key, body),
+        SigningMethod::GpgSign => gpg(&["--clearsign", "--local-user", key], body),
+        SigningMethod::GpgEncrypt => gpg(&["--encrypt", "--recipient", key], body),
+    }
+}
+
+/// Gets the part of a JWS that is signed: the encoded header and payload.
+fn signing_input(algorithm: &str, body: &str) -> String {
+    let header = format!("{{\"alg\":\"{}\"}}", algorithm);
+    format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(header),
+        URL_SAFE_NO_PAD.encode(body)
+    )
+}
+
+/// Reads a private key in PEM, written as is or in the file at the path.
+fn private_key_der(key: &str) -> Result<Vec<u8>, String> {
+    let pem = if key.trim_start().starts_with("-----BEGIN") {
+        key.to_string()
+    } else {
+        fs::read_to_string(key.trim())
+            .map_err(|err| format!("reading the key {}: {}", key.trim(), err))?
+    };
+    let encoded: String = pem
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .map(str::trim)
+        .collect();
+    STANDARD
+        .decode(encoded)
+        .map_err(|err| format!("the key is not valid PEM: {}", err))
+}
+
+/// Encrypts the body into a compact JWE with the key used directly as the content key.
+fn encrypt_jwe(key: &str, body: &str) -> Result<String, String> {
+    let key = URL_SAFE_NO_PAD
+        .decode(key.trim())
+        .map_err(|err| format!("the JWE key is not base64url: {}", err))?;
+    let key = aead::UnboundKey::new(&aead::AES_256_GCM, &key)
+        .map_err(|_| String::from("the JWE key must be 32 bytes for A256GCM"))?;
+    let key = aead::LessSafeKey::new(key);
+    let mut iv = [0; aead::NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut iv)
+        .map_err(|_| String::from("no randomness for the JWE iv"))?;
+    let header = URL_SAFE_NO_PAD.encode("{\"alg\":\"dir\",\"enc\":\"A256GCM\"}");
+    let mut ciphertext = body.as_bytes().to_vec();
+    let tag = key
+        .seal_in_place_separate_tag(
+            aead::Nonce::assume_unique_for_key(iv),
+            aead::Aad::from(header.as_bytes()),
+            &mut ciphertext,
+        )
+        .map_err(|_| String::from("encrypting the body failed"))?;
+    // the encrypted key is empty since the key is used directly
+    Ok(format!(
+        "{}..{}.{}.{}",
+        header,
+        URL_SAFE_NO_PAD.encode(iv),
+        URL_SAFE_NO_PAD.encode(ciphertext),
+        URL_SAFE_NO_PAD.encode(tag.as_ref())
+    ))
+}
+
+/// Runs gpg on the body with the arguments, in batch mode so it never asks anything in the
+/// terminal the TUI is drawn in.
+fn gpg(args: &[&str], body: &str) -> Result<String, String> {
+    let mut child = Command::new("gpg")
+        .args(["--batch", "--yes", "--armor"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("running gpg: {}", err))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(body.as_bytes())
+            .map_err(|err| format!("writing to gpg: {}", err))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|err| format!("running gpg: {}", err))?;
+    if !output.status.success() {
+        return Err(format!(
+            "gpg failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_sign_and_encrypt_bodies() {
+        let jws = apply(SigningMethod::JwsHs256, "secret", "{\"amount\":10}").unwrap();
+        let parts: Vec<_> = jws.split('.').collect();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(
+            URL_SAFE_NO_PAD.decode(parts[1]).unwrap(),
+            b"{\"amount\":10}"
+        );
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+        let signature = URL_SAFE_NO_PAD.decode(parts[2]).unwrap();
+        let signing_input = format!("{}.{}", parts[0], parts[1]);
+        assert!(hmac::verify(&key, signing_input.as_bytes(), &signature).is_ok());
+
+        let key = URL_SAFE_NO_PAD.encode([7; 32]);
+        let jwe = apply(SigningMethod::JweA256Gcm, &key, "hello").unwrap();
+        let parts: Vec<_> = jwe.split('.').collect();
+        assert_eq!(parts.len(), 5);
+        assert!(parts[1].is_empty());
+        let opening =
+            aead::LessSafeKey::new(aead::UnboundKey::new(&aead::AES_256_GCM, &[7; 32]).unwrap());
+        let iv: [u8; aead::NONCE_LEN] = URL_SAFE_NO_PAD
+            .decode(parts[2])
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let mut sealed = URL_SAFE_NO_PAD.decode(parts[3]).unwrap();
+        sealed.extend(URL_SAFE_NO_PAD.decode(parts[4]).unwrap());
+        let opened = opening
+            .open_in_place(
+                aead::Nonce::assume_unique_for_key(iv),
+                aead::Aad::from(parts[0].as_bytes()),
+                &mut sealed,
+            )
+            .unwrap();
+        assert_eq!(opened, b"hello");
+        assert!(apply(SigningMethod::JweA256Gcm, "c2hvcnQ", "hello").is_err());
+
+        assert_eq!("JWS-RS256".parse(), Ok(SigningMethod::JwsRs256));
+        assert!("pgp".parse::<SigningMethod>().is_err());
+    }
+}
@@ -0,0 +1,129 @@
+//! Snapshots of the captured variables, taken every time a response changes them. Chaining a few
+//! requests leaves captures behind that win over the environment, so a snapshot can be restored
+//! to go back to how the variables were, or the baseline to drop every capture and use the
+//! environment as written in the collection again. Snapshots only live while the TUI is open.
+
+use std::collections::HashMap;
+
+use chrono::DateTime;
+
+/// How many snapshots are kept, the oldest ones are dropped first.
+const MAX_SNAPSHOTS: usize = 50;
+
+/// The captured variables right after a response changed them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// The name of the request whose response changed the captures.
+    request: String,
+    /// When the snapshot was taken, in seconds since epoch.
+    taken_at: i64,
+    /// The captures that were added or given another value, sorted.
+    changed: Vec<String>,
+    captures: HashMap<String, String>,
+}
+
+impl Snapshot {
+    pub fn captures(&self) -> &HashMap<String, String> {
+        &self.captures
+    }
+
+    /// Gets a single line describing the snapshot for lists.
+    pub fn summary(&self) -> String {
+        let taken_at = DateTime::from_timestamp(self.taken_at, 0)
+            .map(|time| time.format("%H:%M:%S").to_string())
+            .unwrap_or_default();
+        format!(
+            "{} {} set {}",
+            taken_at,
+            self.request,
+            self.changed.join(", ")
+        )
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Snapshots {
+    /// Oldest first.
+    snapshots: Vec<Snapshot>,
+}
+
+impl Snapshots {
+    /// Takes a snapshot of the captures after the response of the request, unless they are the
+    /// same as in the last snapshot. Returns whether one was taken.
+    pub fn record(
+        &mut self,
+        request: &str,
+        taken_at: i64,
+        captures: &HashMap<String, String>,
+    ) -> bool {
+        let empty = HashMap::new();
+        let last = self
+            .snapshots
+            .last()
+            .map_or(&empty, |snapshot| &snapshot.captures);
+        let mut changed: Vec<String> = captures
+            .iter()
+            .filter(|(key, value)| last.get(*key) != Some(value))
+            .map(|(key, _)| key.clone())
+            .collect();
+        if changed.is_empty() && captures.len() == last.len() {
+            return false;
+        }
+        changed.sort();
+        if self.snapshots.len() == MAX_SNAPSHOTS {
+            self.snapshots.remove(0);
+        }
+        self.snapshots.push(Snapshot {
+            request: request.to_string(),
+            taken_at,
+            changed,
+            captures: captures.clone(),
+        });
+        true
+    }
+
+    /// Gets the snapshots, newest first.
+    pub fn newest_first(&self) -> impl Iterator<Item = &Snapshot> {
+        self.snapshots.iter().rev()
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_snapshot_changed_captures() {
+        let mut snapshots = Snapshots::default();
+        let mut captures = HashMap::from([(String::from("TOKEN"), String::from("a"))]);
+        assert!(snapshots.record("login", 0, &captures));
+        assert!(!snapshots.record("login", 1, &captures));
+
+        captures.insert(String::from("USER_ID"), String::from("7"));
+        captures.insert(String::from("TOKEN"), String::from("b"));
+        assert!(snapshots.record("create user", 2, &captures));
+        assert_eq!(snapshots.len(), 2);
+        let newest = snapshots.newest_first().next().unwrap();
+        assert_eq!(newest.summary(), "00:00:02 create user set TOKEN, USER_ID");
+        let oldest = snapshots.newest_first().last().unwrap();
+        assert_eq!(oldest.captures()["TOKEN"], "a");
+
+        for at in 0..MAX_SNAPSHOTS {
+            captures.insert(String::from("TOKEN"), at.to_string());
+            snapshots.record("refresh", at as i64, &captures);
+        }
+        assert_eq!(snapshots.len(), MAX_SNAPSHOTS);
+        assert_eq!(
+            snapshots.newest_first().last().unwrap().captures()["TOKEN"],
+            "0"
+        );
+    }
+}
@@ -0,0 +1,1545 @@
+//! The hermes source of a single request, written from the model so it can be edited in the TUI
+//! and read back into the model once it is saved. Reading keeps the position of every problem it
+//! finds so the editor can underline them while typing. It is the only reader of hermes files,
+//! the loader, `hermes ast` and completions all go through it, reading the tokens of syntax.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{self, BufRead},
+};
+
+use serde::Serialize;
+
+use crate::api::{
+    CaptureRule, FormField, HttpBody, HttpMethod, PromptVariable, QueryParam, Request,
+};
+use crate::assertions::Assertion;
+use crate::auth::Auth;
+use crate::changelog::Change;
+use crate::syntax::{
+    tokenize, unescaped_backticks, write_name, write_value, Span, Token, TokenKind, BLOCK_TYPES,
+};
+
+/// How bad a problem is. Errors keep the source from being loaded or saved, warnings point at
+/// what is most likely a mistake but still works.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in the source, spanning from start to end (exclusive) on the line. Lines and
+/// columns count from 0, columns are in characters.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    severity: Severity,
+    line: usize,
+    start: usize,
+    end: usize,
+    message: String,
+    /// What could have been written there instead, when it is known, e.g. `` `{` ``.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    expected: Vec<&'static str>,
+}
+
+impl Diagnostic {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            line: span.line,
+            start: span.start,
+            end: span.end.max(span.start + 1),
+            message: message.into(),
+            expected: Vec::new(),
+        }
+    }
+
+    fn warning(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::new(span, message)
+        }
+    }
+
+    fn expecting(mut self, expected: &[&'static str]) -> Self {
+        self.expected = expected.to_vec();
+        self
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn expected(&self) -> &[&'static str] {
+        &self.expected
+    }
+
+    /// Renders the problem for people to read: the message, where it is in the file at the path,
+    /// the line of the contents it is on with the problem underlined, and what was expected.
+    pub fn render(&self, path: &str, contents: &str) -> String {
+        let number = (self.line + 1).to_string();
+        let gutter = " ".repeat(number.len());
+        let text = contents.lines().nth(self.line).unwrap_or_default();
+        // tabs are kept so the underline lines up however wide they are shown
+        let padding: String = text
+            .chars()
+            .take(self.start)
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect();
+        let mut rendered = format!(
+            "{}: {}\n{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}",
+            self.severity,
+            self.message,
+            gutter,
+            path,
+            self.line + 1,
+            self.start + 1,
+            gutter,
+            number,
+            text,
+            gutter,
+            padding,
+            "^".repeat(self.end - self.start)
+        );
+        match self.expected.as_slice() {
+            [] => {}
+            [expected] => rendered.push_str(&format!("\n{} = expected {}", gutter, expected)),
+            expected => rendered.push_str(&format!(
+                "\n{} = expected one of {}",
+                gutter,
+                expected.join(", ")
+            )),
+        }
+        rendered
+    }
+}
+
+/// A block read from the source, e.g. `body.json as my-body { ... }`. It is serialized as the
+/// tree printed by `hermes ast --json`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SourceBlock {
+    #[serde(rename = "type")]
+    block_type: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    sub_type: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    identifier: String,
+    fields: Vec<SourceField>,
+    /// The line the block starts on, counting from 0.
+    line: usize,
+    /// The column the block starts at, counting from 0.
+    column: usize,
+    /// The line of the brace closing the block, counting from 0.
+    end_line: usize,
+}
+
+impl SourceBlock {
+    pub fn block_type(&self) -> &str {
+        &self.block_type
+    }
+
+    pub fn sub_type(&self) -> &str {
+        &self.sub_type
+    }
+
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Gets the enabled fields with the name, in order.
+    pub fn fields<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a SourceValue> {
+        self.fields
+            .iter()
+            .filter(move |field| field.enabled && field.name == name)
+            .map(|field| &field.value)
+    }
+
+    /// Gets the names and values of the enabled fields, in order.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &SourceValue)> {
+        self.fields
+            .iter()
+            .filter(|field| field.enabled)
+            .map(|field| (field.name.as_str(), &field.value))
+    }
+
+    /// Gets the names and values of every field along with whether it is enabled, in order.
+    pub fn toggled_entries(&self) -> impl Iterator<Item = (&str, bool, &SourceValue)> {
+        self.fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.enabled, &field.value))
+    }
+
+    /// Gets the text of the first enabled field with the name.
+    pub fn text<'a>(&'a self, name: &'a str) -> Option<&'a str> {
+        self.fields(name).find_map(|value| match value {
+            SourceValue::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Describes the block for messages, e.g. `the request block`. Inline blocks without a type
+    /// are `this block`.
+    fn described(&self) -> String {
+        if self.block_type.is_empty() {
+            String::from("this block")
+        } else {
+            format!("the {} block", self.block_type)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct SourceField {
+    name: String,
+    enabled: bool,
+    value: SourceValue,
+    /// Where the name of the field is.
+    span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "lowercase")]
+pub enum SourceValue {
+    /// A value written between backticks.
+    Text(String),
+    /// The identifier of another block.
+    Reference(String),
+    /// A block written inline.
+    Block(SourceBlock),
+}
+
+/// Reads the blocks of the source, skipping the parts with problems so the rest can still be
+/// checked. Blocks of unknown types are read like the others.
+pub fn read(contents: &str) -> (Vec<SourceBlock>, Vec<Diagnostic>) {
+    read_tokens(tokenize(contents.chars()))
+}
+
+/// Reads the blocks from the reader like `read`, a line at a time so the whole file is never held
+/// as text, e.g. for collections with big bodies written inline. Fails when the reader does or
+/// when it is not UTF-8.
+pub fn read_from(reader: impl BufRead) -> io::Result<(Vec<SourceBlock>, Vec<Diagnostic>)> {
+    let mut chars = ReadChars {
+        reader,
+        line: String::new(),
+        offset: 0,
+        error: None,
+    };
+    let tokenized = tokenize(&mut chars);
+    match chars.error {
+        Some(err) => Err(err),
+        None => Ok(read_tokens(tokenized)),
+    }
+}
+
+/// The characters of a reader, read a line at a time. Reading stops at the first error, which is
+/// kept to be returned once the characters are used.
+struct ReadChars<R> {
+    reader: R,
+    line: String,
+    /// Where the next character is in the line, in bytes.
+    offset: usize,
+    error: Option<io::Error>,
+}
+
+impl<R: BufRead> Iterator for ReadChars<R> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if let Some(ch) = self.line[self.offset..].chars().next() {
+            self.offset += ch.len_utf8();
+            return Some(ch);
+        }
+        if self.error.is_some() {
+            return None;
+        }
+        self.line.clear();
+        self.offset = 0;
+        match self.reader.read_line(&mut self.line) {
+            Ok(0) => None,
+            Ok(_) => self.next(),
+            Err(err) => {
+                self.error = Some(err);
+                None
+            }
+        }
+    }
+}
+
+fn read_tokens(
+    (tokens, mut diagnostics): (Vec<Token>, Vec<Diagnostic>),
+) -> (Vec<SourceBlock>, Vec<Diagnostic>) {
+    let mut reader = Reader {
+        tokens,
+        index: 0,
+        diagnostics: Vec::new(),
+    };
+    let mut blocks = Vec::new();
+    while let Some(token) = reader.peek().cloned() {
+        match token.kind {
+            TokenKind::Newline => reader.index += 1,
+            TokenKind::Word(word) => {
+                reader.index += 1;
+                if let Some(block) = reader.block(&word, token.span) {
+                    blocks.push(block);
+                }
+            }
+            TokenKind::Close => {
+                reader.index += 1;
+                reader.error(token.span, "there is no block to close here");
+            }
+            _ => {
+                reader.error_expecting(
+                    token.span,
+                    "expected a block, such as `request {`",
+                    &["a block type"],
+                );
+                reader.skip_line();
+            }
+        }
+    }
+    diagnostics.extend(reader.diagnostics);
+    for block in &blocks {
+        warn_repeated_queries(block, &mut diagnostics);
+    }
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.start));
+    (blocks, diagnostics)
+}
+
+/// Warns about query parameters set twice in the same queries block. Both are sent, which some
+/// servers read as a list, but it is more often a copy that was meant to be changed.
+fn warn_repeated_queries(block: &SourceBlock, diagnostics: &mut Vec<Diagnostic>) {
+    for field in &block.fields {
+        if let SourceValue::Block(inline) = &field.value {
+            if field.name == "queries" && inline.block_type.is_empty() {
+                warn_repeated_fields(inline, diagnostics);
+            }
+            warn_repeated_queries(inline, diagnostics);
+        }
+    }
+    if block.block_type == "queries" {
+        warn_repeated_fields(block, diagnostics);
+    }
+}
+
+fn warn_repeated_fields(block: &SourceBlock, diagnostics: &mut Vec<Diagnostic>) {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for field in block.fields.iter().filter(|field| field.enabled) {
+        if let Some(line) = seen.get(field.name.as_str()) {
+            diagnostics.push(Diagnostic::warning(
+                field.span,
+                format!(
+                    "the query parameter {} is already set on line {}, both are sent",
+                    field.name,
+                    line + 1
+                ),
+            ));
+        } else {
+            seen.insert(&field.name, field.span.line);
+        }
+    }
+}
+
+struct Reader {
+    tokens: Vec<Token>,
+    index: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Reader {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn error(&mut self, span: Span, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic::new(span, message));
+    }
+
+    /// Reports a problem along with what could have been written there instead.
+    fn error_expecting(
+        &mut self,
+        span: Span,
+        message: impl Into<String>,
+        expected: &[&'static str],
+    ) {
+        self.diagnostics
+            .push(Diagnostic::new(span, message).expecting(expected));
+    }
+
+    /// Skips the rest of the line, along with any block opened on it.
+    fn skip_line(&mut self) {
+        let mut depth = 0;
+        while let Some(token) = self.next() {
+            match token.kind {
+                TokenKind::Open => depth += 1,
+                TokenKind::Close if depth > 0 => depth -= 1,
+                TokenKind::Close => {
+                    // the close belongs to the block the line is in
+                    self.index -= 1;
+                    return;
+                }
+                TokenKind::Newline if depth == 0 => return,
+                _ => {}
+            }
+        }
+    }
+
+    /// Whether the word is the type of a block about to be opened, i.e. followed by `as` or `{`.
+    fn starts_block(&self, word: &str) -> bool {
+        let block_type = word.split('.').next().unwrap_or_default();
+        BLOCK_TYPES.contains(&block_type)
+            && match self.peek().map(|token| &token.kind) {
+                Some(TokenKind::Open) => true,
+                Some(TokenKind::Word(next)) => next == "as",
+                _ => false,
+            }
+    }
+
+    /// Reads a block from after its type, written as `type[.sub-type] [as identifier] {`.
+    fn block(&mut self, header: &str, span: Span) -> Option<SourceBlock> {
+        let (block_type, sub_type) = header.split_once('.').unwrap_or((header, ""));
+        let mut block = SourceBlock {
+            block_type: block_type.to_string(),
+            sub_type: sub_type.to_string(),
+            line: span.line,
+            column: span.start,
+            ..SourceBlock::default()
+        };
+        if block_type.is_empty() {
+            self.error_expecting(
+                span,
+                "expected a block type before the `.`",
+                &["a block type"],
+            );
+        }
+        if let Some(TokenKind::Word(word)) = self.peek().map(|token| token.kind.clone()) {
+            if word == "as" {
+                self.index += 1;
+                match self.next() {
+                    Some(Token {
+                        kind: TokenKind::Word(identifier),
+                        ..
+                    }) => block.identifier = identifier,
+                    _ => {
+                        self.error_expecting(
+                            span,
+                            "expected an identifier after `as`",
+                            &["an identifier"],
+                        );
+                        self.index -= 1;
+                    }
+                }
+            }
+        }
+        match self.next() {
+            Some(Token {
+                kind: TokenKind::Open,
+                ..
+            }) => {}
+            other => {
+                let span = other.map_or(span, |token| token.span);
+                let expected: &[&str] = if block.identifier.is_empty() {
+                    &["`as`", "`{`"]
+                } else {
+                    &["`{`"]
+                };
+                self.error_expecting(
+                    span,
+                    format!("expected a `{{` to open the {} block", header),
+                    expected,
+                );
+                self.index -= 1;
+                self.skip_line();
+                return None;
+            }
+        }
+        self.fields(&mut block, span);
+        Some(block)
+    }
+
+    /// Reads the fields of a block until its closing brace.
+    fn fields(&mut self, block: &mut SourceBlock, header: Span) {
+        loop {
+            let Some(token) = self.next() else {
+                self.error_expecting(
+                    header,
+                    format!("{} is never closed, add a `}}`", block.described()),
+                    &["`}`"],
+                );
+                return;
+            };
+            let name = match token.kind {
+                TokenKind::Newline => continue,
+                TokenKind::Close => {
+                    block.end_line = token.span.line;
+                    return;
+                }
+                TokenKind::Word(name) if token.span.start == 0 && self.starts_block(&name) => {
+                    // a block at the start of a line is not a field, this block was never closed.
+                    // Stopping before its line lets the next block be read on its own.
+                    self.error_expecting(
+                        header,
+                        format!(
+                            "{} is never closed, add a `}}` before line {}",
+                            block.described(),
+                            token.span.line + 1
+                        ),
+                        &["`}`"],
+                    );
+                    self.index -= 2;
+                    return;
+                }
+                TokenKind::Word(name) | TokenKind::Quoted(name) => name,
+                _ => {
+                    self.error_expecting(
+                        token.span,
+                        "expected the name of a field",
+                        &["the name of a field", "`}`"],
+                    );
+                    self.index -= 1;
+                    self.skip_line();
+                    continue;
+                }
+            };
+            let mut enabled = true;
+            if let Some(Token {
+                kind: TokenKind::Word(state),
+                span,
+            }) = self.peek().cloned()
+            {
+                if state.chars().all(|ch| ch.is_ascii_digit()) {
+                    self.index += 1;
+                    match state.as_str() {
+                        "0" => enabled = false,
+                        "1" => {}
+                        _ => self.error_expecting(
+                            span,
+                            "the state of a field is 0 or 1",
+                            &["`0`", "`1`"],
+                        ),
+                    }
+                }
+            }
+            let value = match self.next() {
+                Some(Token {
+                    kind: TokenKind::Value(text),
+                    ..
+                }) => SourceValue::Text(text),
+                Some(Token {
+                    kind: TokenKind::Open,
+                    ..
+                }) => {
+                    let mut inline = SourceBlock {
+                        line: token.span.line,
+                        column: token.span.start,
+                        ..SourceBlock::default()
+                    };
+                    self.fields(&mut inline, token.span);
+                    SourceValue::Block(inline)
+                }
+                Some(Token {
+                    kind: TokenKind::Word(word),
+                    span,
+                }) => {
+                    let is_block = matches!(
+                        self.peek(),
+                        Some(Token {
+                            kind: TokenKind::Open,
+                            ..
+                        })
+                    ) || matches!(
+                        self.peek(),
+                        Some(Token { kind: TokenKind::Word(next), .. }) if next == "as"
+                    );
+                    let block_type = word.split('.').next().unwrap_or_default();
+                    if is_block && BLOCK_TYPES.contains(&block_type) {
+                        match self.block(&word, span) {
+                            Some(block) => SourceValue::Block(block),
+                            None => continue,
+                        }
+                    } else {
+                        SourceValue::Reference(word)
+                    }
+                }
+                other => {
+                    self.error_expecting(
+                        token.span,
+                        format!("the field {} has no value", name),
+                        &["a value between backticks", "an identifier", "`{`"],
+                    );
+                    if other.is_some() {
+                        self.index -= 1;
+                    }
+                    self.skip_line();
+                    continue;
+                }
+            };
+            block.fields.push(SourceField {
+                name,
+                enabled,
+                value,
+                span: token.span,
+            });
+            match self.peek().cloned() {
+                None
+                | Some(Token {
+                    kind: TokenKind::Newline | TokenKind::Close,
+                    ..
+                }) => {}
+                Some(token) => {
+                    self.error_expecting(
+                        token.span,
+                        "expected the field to end here",
+                        &["the end of the line", "`}`"],
+                    );
+                    self.skip_line();
+                }
+            }
+        }
+    }
+}
+
+/// Writes the request as hermes source, with its headers and body in blocks of their own.
+pub fn write_request(request: &Request) -> String {
+    let identifier = request.get_identifier();
+    let mut source = format!("request as {} {{\n", identifier);
+    source.push_str(&format!("    name {}\n", write_value(&request.get_name())));
+    source.push_str(&format!("    url {}\n", write_value(&request.get_url())));
+    source.push_str(&format!(
+        "    method `{}`\n",
+        request.get_method().to_str().to_lowercase()
+    ));
+    if let Some(environment) = request.get_pinned_environment() {
+        source.push_str(&format!("    environment 1 {}\n", write_value(environment)));
+    }
+    for prompt in request.get_prompts() {
+        source.push_str(&format!(
+            "    prompt 1 {}\n",
+            write_value(&format!("{} {}", prompt.name(), prompt.label()))
+        ));
+    }
+    for capture in request.get_captures() {
+        source.push_str(&format!(
+            "    capture 1 {}\n",
+            write_value(&format!("{} {}", capture.name(), capture.path()))
+        ));
+    }
+    if let Some(description) = request.get_description() {
+        source.push_str(&format!("    description {}\n", write_value(description)));
+    }
+    if let Some(raw) = request.get_raw() {
+        source.push_str(&format!("    raw {}\n", write_value(raw)));
+    }
+    if let Some(owner) = request.get_owner() {
+        source.push_str(&format!("    owner {}\n", write_value(owner)));
+    }
+    if let Some(message) = request.get_deprecated() {
+        source.push_str(&format!("    deprecated 1 {}\n", write_value(message)));
+    }
+    for change in request.get_changelog() {
+        source.push_str(&format!(
+            "    changelog 1 {}\n",
+            write_value(&change.to_string())
+        ));
+    }
+    if !request.get_headers().is_empty() {
+        source.push_str(&format!("    headers {}-headers\n", identifier));
+    }
+    if !request.get_queries().is_empty() {
+        source.push_str(&format!("    queries {}-queries\n", identifier));
+    }
+    if request.get_body().is_some() || request.get_body_type().is_some_and(HttpBody::is_form) {
+        source.push_str(&format!("    body {}-body\n", identifier));
+    }
+    if let Some(auth) = request.get_auth() {
+        source.push_str(&format!("    auth auth.{} {{\n", auth.sub_type()));
+        for (name, value) in auth.fields() {
+            source.push_str(&format!("        {} {}\n", name, write_value(&value)));
+        }
+        source.push_str("    }\n");
+    }
+    if !request.get_assertions().is_empty() {
+        source.push_str("    tests {\n");
+        for assertion in request.get_assertions() {
+            let (field, value) = assertion.to_field();
+            source.push_str(&format!("        {} 1 {}\n", field, write_value(&value)));
+        }
+        source.push_str("    }\n");
+    }
+    if let Some(expected) = request.get_expected() {
+        source.push_str(&format!("    expected {}\n", write_value(expected)));
+    }
+    source.push_str("}\n");
+
+    if !request.get_headers().is_empty() {
+        let mut headers: Vec<_> = request.get_headers().iter().collect();
+        headers.sort();
+        source.push_str(&format!("\nheaders as {}-headers {{\n", identifier));
+        for (key, value) in headers {
+            source.push_str(&format!("    {} {}\n", write_name(key), write_value(value)));
+        }
+        source.push_str("}\n");
+    }
+    if !request.get_queries().is_empty() {
+        source.push_str(&format!("\nqueries as {}-queries {{\n", identifier));
+        for query in request.get_queries() {
+            source.push_str(&format!(
+                "    {} {} {}\n",
+                write_name(query.key()),
+                u8::from(query.is_enabled()),
+                write_value(query.value())
+            ));
+        }
+        source.push_str("}\n");
+    }
+    match request.get_body_type() {
+        Some(body_type) if body_type.is_form() => {
+            source.push_str(&format!(
+                "\nbody.{} as {}-body {{\n",
+                body_type.sub_type(),
+                identifier
+            ));
+            for field in request.get_form() {
+                source.push_str(&format!(
+                    "    {} {} {}\n",
+                    write_name(field.name()),
+                    u8::from(field.is_enabled()),
+                    write_value(field.value())
+                ));
+            }
+            source.push_str("}\n");
+        }
+        body_type => {
+            if let Some(body) = request.get_body() {
+                source.push_str(&format!(
+                    "\nbody.{} as {}-body {{\n    value 1 {}\n}}\n",
+                    body_type.unwrap_or(HttpBody::Text).sub_type(),
+                    identifier,
+                    write_value(body)
+                ));
+            }
+        }
+    }
+    source
+}
+
+/// Finds the block of the request with the name, named by its name field or else its identifier.
+fn find_request<'a>(blocks: &'a [SourceBlock], name: &str) -> Option<&'a SourceBlock> {
+    blocks.iter().find(|block| {
+        block.block_type == "request" && block.text("name").unwrap_or(&block.identifier) == name
+    })
+}
+
+/// Removes the block of the request with the name from the source of a hermes file, keeping the
+/// rest as it was written. The blocks it references are kept since other requests may use them.
+/// None when the request is not in the source, or the source has problems.
+pub fn remove_request(contents: &str, name: &str) -> Option<String> {
+    let (blocks, diagnostics) = read(contents);
+    if !diagnostics.is_empty() {
+        return None;
+    }
+    let block = find_request(&blocks, name)?;
+    let mut lines: Vec<&str> = contents.split('\n').collect();
+    let mut end = (block.end_line + 1).min(lines.len());
+    // the blank line after the block goes too, so blocks stay one blank line apart
+    if end + 1 < lines.len() && lines[end].trim().is_empty() {
+        end += 1;
+    }
+    lines.drain(block.line..end);
+    Some(lines.join("\n"))
+}
+
+/// Renames the request in the source of a hermes file, along with the hooks that run it, keeping
+/// the rest as it was written. None when neither is in the source, or the source has problems.
+pub fn rename_request(contents: &str, old: &str, new: &str) -> Option<String> {
+    let (blocks, diagnostics) = read(contents);
+    if !diagnostics.is_empty() {
+        return None;
+    }
+    let mut lines: Vec<String> = contents.split('\n').map(String::from).collect();
+    let (old_value, new_value) = (format!("`{}`", old), write_value(new));
+    let mut changed = false;
+    for block in blocks.iter().filter(|block| block.block_type == "hooks") {
+        for line in &mut lines[block.line..=block.end_line] {
+            if line.contains(&old_value) {
+                *line = line.replace(&old_value, &new_value);
+                changed = true;
+            }
+        }
+    }
+    // the request goes last, since adding a name field moves the lines after it
+    if let Some(block) = find_request(&blocks, old) {
+        let name_line = (block.line..=block.end_line).find(|&index| {
+            let line = lines[index].trim_start();
+            line.starts_with("name ") && line.contains(&old_value)
+        });
+        match name_line {
+            Some(index) => lines[index] = lines[index].replacen(&old_value, &new_value, 1),
+            None => {
+                let header = &lines[block.line];
+                lines[block.line] = header.replacen('{', &format!("{{\n    name {}", new_value), 1);
+            }
+        }
+        changed = true;
+    }
+    changed.then(|| lines.join("\n"))
+}
+
+/// Sets the expected result of the request with the name in the source of a hermes file, replacing
+/// the one it has, keeping the rest as it was written. None when the request is not in the source
+/// or the source has problems.
+pub fn set_expected(contents: &str, name: &str, expected: &str) -> Option<String> {
+    let (blocks, diagnostics) = read(contents);
+    if !diagnostics.is_empty() {
+        return None;
+    }
+    let block = find_request(&blocks, name)?;
+    let mut lines: Vec<&str> = contents.split('\n').collect();
+    // lines in the middle of a value that spans several lines are not fields
+    let mut in_value = false;
+    let mut start = None;
+    let mut existing = None;
+    for (index, line) in lines
+        .iter()
+        .enumerate()
+        .take(block.end_line)
+        .skip(block.line + 1)
+    {
+        if !in_value && line.trim_start().starts_with("expected ") {
+            start = Some(index);
+        }
+        in_value ^= unescaped_backticks(line) % 2 == 1;
+        if let Some(start) = start.filter(|_| !in_value) {
+            existing = Some(start..=index);
+            break;
+        }
+    }
+    let field = format!("    expected {}", write_value(expected));
+    let at = match existing {
+        Some(range) => {
+            let at = *range.start();
+            lines.drain(range);
+            at
+        }
+        None => block.end_line,
+    };
+    lines.insert(at, &field);
+    Some(lines.join("\n"))
+}
+
+/// The fields of request blocks that write_request writes, the others are kept by replace_request.
+const WRITTEN_FIELDS: [&str; 17] = [
+    "name",
+    "url",
+    "method",
+    "environment",
+    "prompt",
+    "capture",
+    "description",
+    "raw",
+    "owner",
+    "deprecated",
+    "changelog",
+    "headers",
+    "queries",
+    "body",
+    "auth",
+    "tests",
+    "expected",
+];
+
+/// Replaces the request with the name in the source of a hermes file by the request, written as
+/// write_request does, keeping the rest as it was written. The fields write_request doesn't write,
+/// such as its settings, are kept, and the blocks it referenced are removed unless another request
+/// references them too. None when the request is not in the source, or the source has problems.
+pub fn replace_request(contents: &str, name: &str, request: &Request) -> Option<String> {
+    let (blocks, diagnostics) = read(contents);
+    if !diagnostics.is_empty() {
+        return None;
+    }
+    let block = find_request(&blocks, name)?;
+    let lines: Vec<&str> = contents.split('\n').collect();
+
+    let mut kept = Vec::new();
+    let mut removed = vec![(block.line, block.end_line)];
+    for field in &block.fields {
+        if !WRITTEN_FIELDS.contains(&field.name.as_str()) {
+            let end = match &field.value {
+                SourceValue::Block(inline) => inline.end_line,
+                _ => value_end(&lines, field.span.line),
+            };
+            kept.extend_from_slice(&lines[field.span.line..=end.min(block.end_line)]);
+            continue;
+        }
+        let SourceValue::Reference(identifier) = &field.value else {
+            continue;
+        };
+        let shared = blocks.iter().any(|other| {
+            other.block_type == "request"
+                && other.line != block.line
+                && other.fields.iter().any(|field| {
+                    matches!(&field.value, SourceValue::Reference(used) if used == identifier)
+                })
+        });
+        let referenced = blocks
+            .iter()
+            .find(|other| other.block_type != "request" && other.identifier == *identifier);
+        if let (false, Some(referenced)) = (shared, referenced) {
+            removed.push((referenced.line, referenced.end_line));
+        }
+    }
+
+    let written = write_request(request);
+    let mut replacement: Vec<&str> = written.trim_end_matches('\n').split('\n').collect();
+    let close = replacement.iter().position(|line| *line == "}")?;
+    replacement.splice(close..close, kept);
+
+    removed.sort_unstable();
+    let mut edited = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        match removed.iter().find(|(start, _)| *start == index) {
+            Some(&(start, end)) if start == block.line => {
+                edited.extend_from_slice(&replacement);
+                index = end + 1;
+            }
+            Some(&(_, end)) => {
+                index = end + 1;
+                // the blank line after the block goes too, so blocks stay one blank line apart
+                if lines.get(index).is_some_and(|line| line.trim().is_empty()) {
+                    index += 1;
+                }
+            }
+            None => {
+                edited.push(lines[index]);
+                index += 1;
+            }
+        }
+    }
+    let mut edited = edited.join("\n").trim_end().to_string();
+    if contents.ends_with('\n') {
+        edited.push('\n');
+    }
+    Some(edited)
+}
+
+/// Gets the line a field starting on the line ends on, after the values that span several lines.
+fn value_end(lines: &[&str], line: usize) -> usize {
+    let mut in_value = false;
+    for (index, text) in lines.iter().enumerate().skip(line) {
+        in_value ^= unescaped_backticks(text) % 2 == 1;
+        if !in_value {
+            return index;
+        }
+    }
+    lines.len() - 1
+}
+
+/// Reads the request block of the source into the request. The request is left as it was when
+/// the source has problems.
+pub fn apply_request(request: &mut Request, contents: &str) -> Result<(), Vec<Diagnostic>> {
+    let (blocks, diagnostics) = read(contents);
+    if diagnostics.iter().any(Diagnostic::is_error) {
+        return Err(diagnostics
+            .into_iter()
+            .filter(Diagnostic::is_error)
+            .collect());
+    }
+    let problem = |line: usize, message: String| {
+        vec![Diagnostic::new(
+            Span {
+                line,
+                start: 0,
+                end: 1,
+            },
+            message,
+        )]
+    };
+    let Some(block) = blocks.iter().find(|block| block.block_type == "request") else {
+        return Err(problem(0, String::from("there is no request block")));
+    };
+    apply_request_block(request, block, &blocks).map_err(|message| problem(block.line, message))
+}
+
+/// Reads a request block into the request. References to other blocks, such as the headers, are
+/// looked up in blocks. The request is left as it was when the block has problems.
+pub fn apply_request_block(
+    request: &mut Request,
+    block: &SourceBlock,
+    blocks: &[SourceBlock],
+) -> Result<(), String> {
+    let method = match block.text("method") {
+        Some(method) => method.parse::<HttpMethod>()?,
+        None => HttpMethod::default(),
+    };
+    let mut prompts = Vec::new();
+    for value in block.fields("prompt") {
+        if let SourceValue::Text(text) = value {
+            prompts.push(PromptVariable::parse(text)?);
+        }
+    }
+    let mut captures = Vec::new();
+    for value in block.fields("capture") {
+        if let SourceValue::Text(text) = value {
+            captures.push(CaptureRule::parse(text)?);
+        }
+    }
+    let mut assertions = Vec::new();
+    for value in block.fields("tests") {
+        for (field, value) in resolve(value, blocks, "tests")?.entries() {
+            if let SourceValue::Text(text) = value {
+                assertions.push(Assertion::parse(field, text)?);
+            }
+        }
+    }
+    let mut changelog = Vec::new();
+    for value in block.fields("changelog") {
+        if let SourceValue::Text(text) = value {
+            changelog.push(Change::parse(text)?);
+        }
+    }
+    let mut headers = HashMap::new();
+    for value in block.fields("headers") {
+        for (name, value) in resolve(value, blocks, "headers")?.entries() {
+            if let SourceValue::Text(text) = value {
+                headers.insert(name.to_string(), text.clone());
+            }
+        }
+    }
+    let mut queries = Vec::new();
+    for value in block.fields("queries") {
+        for (key, enabled, value) in resolve(value, blocks, "queries")?.toggled_entries() {
+            if let SourceValue::Text(text) = value {
+                queries.push(QueryParam::new(key.to_string(), text.clone(), enabled));
+            }
+        }
+    }
+    let mut auth = None;
+    if let Some(value) = block.fields("auth").next() {
+        let auth_block = resolve(value, blocks, "auth")?;
+        auth = Some(Auth::from_fields(&auth_block.sub_type, |name| {
+            auth_block.text(name).map(String::from)
+        })?);
+    }
+    let mut body = None;
+    let mut body_type = None;
+    let mut form = Vec::new();
+    if let Some(value) = block.fields("body").next() {
+        let body_block = resolve(value, blocks, "body")?;
+        body_type = match body_block.sub_type.as_str() {
+            "" => None,
+            sub_type => Some(
+                HttpBody::from_sub_type(sub_type)
+                    .ok_or_else(|| format!("unknown type of body `{}`", sub_type))?,
+            ),
+        };
+        // the fields of forms are the form itself, other bodies are written in their value
+        if body_type.is_some_and(HttpBody::is_form) {
+            for (name, enabled, value) in body_block.toggled_entries() {
+                if let SourceValue::Text(text) = value {
+                    form.push(FormField::new(name.to_string(), text.clone(), enabled));
+                }
+            }
+        } else {
+            body = Some(body_block.text("value").unwrap_or_default().to_string());
+        }
+    }
+
+    let name = block
+        .text("name")
+        .map(String::from)
+        .unwrap_or_else(|| block.identifier.clone());
+    if name.is_empty() {
+        return Err(String::from("the request needs a name"));
+    }
+    request.set_name(name);
+    request.set_identifier(Some(block.identifier.clone()).filter(|id| !id.is_empty()));
+    request.set_method(method);
+    request.set_url(block.text("url").unwrap_or_default().to_string());
+    request.pin_environment(block.text("environment").map(String::from));
+    request.set_prompts(prompts);
+    request.set_captures(captures);
+    request.set_assertions(assertions);
+    request.set_expected(block.text("expected").map(String::from));
+    request.set_description(block.text("description").map(String::from));
+    request.set_raw(block.text("raw").map(String::from));
+    request.set_owner(block.text("owner").map(String::from));
+    request.set_deprecated(block.text("deprecated").map(String::from));
+    request.set_changelog(changelog);
+    request.set_headers(headers);
+    request.set_queries(queries);
+    request.set_auth(auth);
+    request.set_body(body);
+    request.set_body_type(body_type);
+    request.set_form(form);
+    Ok(())
+}
+
+/// Gets the block a field points to, either written inline or referenced by its identifier. What
+/// names the kind of block expected, for the error.
+pub fn resolve<'a>(
+    value: &'a SourceValue,
+    blocks: &'a [SourceBlock],
+    what: &str,
+) -> Result<&'a SourceBlock, String> {
+    match value {
+        SourceValue::Block(block) => Ok(block),
+        SourceValue::Reference(identifier) => blocks
+            .iter()
+            .find(|block| block.identifier == *identifier)
+            .ok_or_else(|| format!("the {} `{}` are not defined", what, identifier)),
+        SourceValue::Text(text) => Err(format!(
+            "expected a {} block or its identifier, got `{}`",
+            what, text
+        )),
+    }
+}
+
+/// Turns a request name into an identifier, e.g. `Create user` into `create-user`.
+pub fn identifier_of(name: &str) -> String {
+    let identifier: String = name
+        .to_lowercase()
+        .chars()
+        .map(|ch| if ch.is_alphanumeric() { ch } else { '-' })
+        .collect();
+    match identifier.trim_matches('-') {
+        "" => String::from("request"),
+        identifier => identifier.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::KeyLocation;
+
+    #[test]
+    fn should_read_from_a_reader_like_from_text() {
+        let contents = "request as é {\n    body {\n        value `{\n  \"name\": \"Zoë\"\n}`\n    }\n    url\n}\n";
+        let read_from_bytes = read_from(contents.as_bytes()).unwrap();
+        assert_eq!(read_from_bytes, read(contents));
+        assert_eq!(read_from_bytes.1[0].line(), 6);
+
+        let invalid: &[u8] = b"request {\n    url `\xff`\n}\n";
+        assert_eq!(
+            read_from(invalid).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn should_point_at_problems_in_the_source() {
+        let (blocks, diagnostics) =
+            read("request as a {\n    url\n    method 2 `get`\n}\n\nheaders {\n    Accept `*/*\n");
+        assert_eq!(blocks.len(), 2);
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.line(), d.start(), d.end(), d.message()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (1, 4, 7, "the field url has no value"),
+                (2, 11, 12, "the state of a field is 0 or 1"),
+                (5, 0, 7, "the headers block is never closed, add a `}`"),
+                (6, 11, 12, "this value is never closed, add a ` after it"),
+            ]
+        );
+        assert_eq!(diagnostics[1].expected(), ["`0`", "`1`"]);
+        assert_eq!(
+            diagnostics[0].render("api/a.hermes", "request as a {\n    url\n}"),
+            "error: the field url has no value\n --> api/a.hermes:2:5\n  |\n2 |     url\n  |     ^^^\n  = expected one of a value between backticks, an identifier, `{`"
+        );
+    }
+
+    #[test]
+    fn should_read_the_blocks_after_one_never_closed() {
+        let (blocks, diagnostics) = read(
+            "request as a {\n    url `/a`\n    body {\n        value `{}`\n\nheaders as h {\n    Accept `*/*`\n}\n\nrequest as b {\n    headers h\n}\n",
+        );
+        let found: Vec<_> = blocks
+            .iter()
+            .map(|block| (block.block_type(), block.identifier()))
+            .collect();
+        assert_eq!(
+            found,
+            [("request", "a"), ("headers", "h"), ("request", "b")]
+        );
+        assert_eq!(blocks[0].text("url"), Some("/a"));
+        assert_eq!(blocks[1].text("Accept"), Some("*/*"));
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.line(), d.message()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    0,
+                    "the request block is never closed, add a `}` before line 6"
+                ),
+                (2, "this block is never closed, add a `}` before line 6"),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_warn_about_repeated_queries() {
+        let contents = "request {\n    name `list users`\n    url `/users`\n    queries {\n        page `1`\n        sort `name`\n        page `2`\n        sort 0 `age`\n    }\n}\n";
+        let (blocks, diagnostics) = read(contents);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity(), Severity::Warning);
+        assert!(diagnostics[0]
+            .render("users.hermes", contents)
+            .starts_with("warning: the query parameter page is already set on line 5, both are sent\n --> users.hermes:7:9\n"));
+
+        let mut request = Request::new(
+            String::from("list users"),
+            HttpMethod::Get,
+            String::new(),
+            None,
+            None,
+            HashMap::new(),
+        );
+        assert_eq!(apply_request(&mut request, contents), Ok(()));
+        assert_eq!(request.get_queries().len(), 4);
+    }
+
+    #[test]
+    fn should_apply_edited_source_to_the_request() {
+        let mut request = Request::new(
+            String::from("Create user"),
+            HttpMethod::Post,
+            String::from("http://localhost/users"),
+            Some(String::from("{\"name\": \"a\"}")),
+            Some(HttpBody::Json),
+            HashMap::from([(String::from("Accept"), String::from("*/*"))]),
+        );
+        let source = write_request(&request);
+        assert!(source.starts_with("request as create-user {\n"));
+
+        let edited = source
+            .replace("method `post`", "method `put`")
+            .replace("Accept `*/*`", "Accept `application/json`\n    X-Old 0 `1`");
+        apply_request(&mut request, &edited).unwrap();
+        assert_eq!(request.get_method(), HttpMethod::Put);
+        assert_eq!(request.get_headers().len(), 1);
+        assert_eq!(request.get_headers()["Accept"], "application/json");
+        assert_eq!(request.get_body(), Some("{\"name\": \"a\"}"));
+        assert_eq!(
+            write_request(&request),
+            edited.replace("\n    X-Old 0 `1`", "")
+        );
+
+        request.add_capture(CaptureRule::new(String::from("ID"), String::from("$.id")));
+        request.add_assertion(Assertion::parse("json", "$.name == \"a\"").unwrap());
+        request.add_assertion(Assertion::parse("header", "Location").unwrap());
+        request.set_queries(vec![
+            QueryParam::new(String::from("page"), String::from("2"), true),
+            QueryParam::new(String::from("debug"), String::from("1"), false),
+        ]);
+        request.set_auth(Some(Auth::ApiKey {
+            name: String::from("api_key"),
+            value: String::from("{{API_KEY}}"),
+            location: KeyLocation::Query,
+        }));
+        request.set_expected(Some(String::from("{\n  \"name\": \"a\"\n}")));
+        let mut copy = request.clone();
+        apply_request(&mut copy, &write_request(&request)).unwrap();
+        assert_eq!(copy.get_captures(), request.get_captures());
+        assert_eq!(copy.get_assertions(), request.get_assertions());
+        assert_eq!(copy.get_queries(), request.get_queries());
+        assert_eq!(copy.get_auth(), request.get_auth());
+        assert_eq!(copy.get_expected(), request.get_expected());
+
+        // the identifier given in the source is kept, even when it does not match the name
+        let mut login = request.clone();
+        let source = "request as login {\n    name `Log in`\n    headers 1 auth\n    auth token\n}\n\nheaders as auth {\n    Accept `*/*`\n}\n\nauth.bearer as token {\n    token `{{TOKEN}}`\n}\n";
+        apply_request(&mut login, source).unwrap();
+        assert_eq!(
+            login.get_auth(),
+            Some(&Auth::Bearer {
+                token: String::from("{{TOKEN}}")
+            })
+        );
+        let written = write_request(&login);
+        assert!(written.starts_with("request as login {\n    name `Log in`\n"));
+        assert!(written.contains("\nheaders as login-headers {\n"));
+        assert_eq!(write_request(&login), written);
+
+        let errors = apply_request(&mut request, "request {\n    method `fetch`\n}").unwrap_err();
+        assert_eq!(errors[0].message(), "unknown http method 'fetch'");
+        assert_eq!(request.get_method(), HttpMethod::Put);
+    }
+
+    #[test]
+    fn should_remove_and_rename_requests_in_the_source() {
+        let source = "\
+# users
+request as list-users {
+    url `/users`
+}
+
+request as login {
+    name `Log in`
+    url `/login`
+}
+
+hooks {
+    before 1 `Log in`
+}
+";
+        let removed = remove_request(source, "list-users").unwrap();
+        assert!(removed.starts_with("# users\nrequest as login {"));
+        assert!(remove_request(source, "missing").is_none());
+
+        let renamed = rename_request(source, "Log in", "Sign in").unwrap();
+        assert!(renamed.contains("    name `Sign in`\n"));
+        assert!(renamed.contains("    before 1 `Sign in`\n"));
+        let renamed = rename_request(&renamed, "list-users", "List users").unwrap();
+        assert!(
+            renamed.contains("request as list-users {\n    name `List users`\n    url `/users`")
+        );
+        assert!(rename_request(source, "missing", "other").is_none());
+        assert!(rename_request("request {", "Log in", "Sign in").is_none());
+
+        let expected = set_expected(source, "Log in", "{\n  \"ok\": true\n}").unwrap();
+        assert!(expected.contains("    url `/login`\n    expected `{\n  \"ok\": true\n}`\n}\n"));
+        let replaced = set_expected(&expected, "Log in", "ok").unwrap();
+        assert!(replaced.contains("    url `/login`\n    expected `ok`\n}\n\nhooks"));
+        assert_eq!(
+            set_expected(&replaced, "Log in", "{\n}").unwrap(),
+            expected.replace("  \"ok\": true\n", "")
+        );
+        assert!(set_expected(source, "missing", "ok").is_none());
+    }
+
+    #[test]
+    fn should_keep_unknown_blocks_in_edited_sources() {
+        // e.g. written by a newer version, with braces in its values
+        let workflow =
+            "workflow as w {\n    step 1 `a {`\n    then {\n        step 1 `b`\n    }\n}\n";
+        let source = format!(
+            "request as a {{\n    url `/a`\n}}\n\n{}\nrequest as b {{\n    url `/b`\n}}\n",
+            workflow
+        );
+        let (blocks, diagnostics) = read(&source);
+        assert!(diagnostics.is_empty());
+        assert_eq!(blocks[1].block_type(), "workflow");
+
+        let renamed = rename_request(&source, "b", "B").unwrap();
+        let expected = set_expected(&renamed, "B", "ok").unwrap();
+        let removed = remove_request(&expected, "a").unwrap();
+        assert!(removed.starts_with(workflow));
+        assert!(
+            removed.ends_with("request as b {\n    name `B`\n    url `/b`\n    expected `ok`\n}\n")
+        );
+    }
+
+    #[test]
+    fn should_replace_requests_in_the_source() {
+        let source = "\
+# users
+request as login {
+    name `Log in`
+    url `/login`
+    headers login-headers
+    body shared
+    settings {
+        retries `2`
+    }
+}
+
+headers as login-headers {
+    Accept `*/*`
+}
+
+body.json as shared {
+    value 1 `{}`
+}
+
+request as other {
+    url `/other`
+    body shared
+}
+";
+        let (blocks, _) = read(source);
+        let mut request = Request::new(
+            String::new(),
+            HttpMethod::Get,
+            String::new(),
+            None,
+            None,
+            HashMap::new(),
+        );
+        apply_request_block(&mut request, &blocks[0], &blocks).unwrap();
+        request.set_method(HttpMethod::Post);
+        request.set_headers(HashMap::from([(
+            String::from("Accept"),
+            String::from("application/json"),
+        )]));
+
+        let replaced = replace_request(source, "Log in", &request).unwrap();
+        assert_eq!(
+            replaced,
+            "\
+# users
+request as login {
+    name `Log in`
+    url `/login`
+    method `post`
+    headers login-headers
+    body login-body
+    settings {
+        retries `2`
+    }
+}
+
+headers as login-headers {
+    Accept `application/json`
+}
+
+body.json as login-body {
+    value 1 `{}`
+}
+
+body.json as shared {
+    value 1 `{}`
+}
+
+request as other {
+    url `/other`
+    body shared
+}
+"
+        );
+        let (blocks, diagnostics) = read(&replaced);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(blocks.len(), 5);
+        assert!(replace_request(source, "missing", &request).is_none());
+    }
+
+    #[test]
+    fn should_escape_backticks_in_written_values() {
+        let mut request = Request::new(
+            String::from("Run `ls`"),
+            HttpMethod::Post,
+            String::from("http://localhost/run"),
+            Some(String::from("{\"command\": \"`ls`\"}")),
+            Some(HttpBody::Json),
+            HashMap::from([(String::from("X-Shell"), String::from("`sh`"))]),
+        );
+        let source = write_request(&request);
+        assert!(source.contains("    name `Run \\`ls\\``\n"));
+        assert!(source.contains("    X-Shell `\\`sh\\``\n"));
+        assert!(source.contains("    value 1 `{\"command\": \"\\`ls\\`\"}`\n"));
+        apply_request(&mut request, &source).unwrap();
+        assert_eq!(request.get_name(), "Run `ls`");
+        assert_eq!(request.get_headers()["X-Shell"], "`sh`");
+        assert_eq!(request.get_body(), Some("{\"command\": \"`ls`\"}"));
+
+        let source = "request as login {\n    url `/login`\n}\n";
+        let expected = set_expected(source, "login", "`ok`\n").unwrap();
+        assert!(expected.contains("    expected `\\`ok\\`\n`\n}\n"));
+        // the escaped backticks don't end the value, so it is replaced as a whole
+        let expected = set_expected(&expected, "login", "ok").unwrap();
+        assert_eq!(
+            expected,
+            "request as login {\n    url `/login`\n    expected `ok`\n}\n"
+        );
+        assert!(rename_request(source, "login", "Log `in`")
+            .unwrap()
+            .contains("request as login {\n    name `Log \\`in\\``\n"));
+    }
+
+    #[test]
+    fn should_read_every_type_of_body() {
+        let mut request = Request::new(
+            String::new(),
+            HttpMethod::Post,
+            String::new(),
+            None,
+            None,
+            HashMap::new(),
+        );
+        let source = "\
+request as upload {
+    body body.multipart-form {
+        title 1 `Report`
+        file 1 `@/tmp/report.pdf`
+        draft 0 `true`
+    }
+}
+";
+        apply_request(&mut request, source).unwrap();
+        assert_eq!(request.get_body_type(), Some(HttpBody::MultipartForm));
+        assert_eq!(request.get_body(), None);
+        let form = request.get_form();
+        assert_eq!(form.len(), 3);
+        assert_eq!(
+            (form[1].name(), form[1].value()),
+            ("file", "@/tmp/report.pdf")
+        );
+        assert!(!form[2].is_enabled());
+        let mut copy = request.clone();
+        apply_request(&mut copy, &write_request(&request)).unwrap();
+        assert_eq!(copy.get_form(), request.get_form());
+
+        apply_request(
+            &mut request,
+            "request as note {\n    body body.text {\n        value 1 `hi`\n    }\n}",
+        )
+        .unwrap();
+        assert_eq!(request.get_body_type(), Some(HttpBody::Text));
+        assert_eq!(request.get_body(), Some("hi"));
+        assert!(request.get_form().is_empty());
+        let errors = apply_request(
+            &mut request,
+            "request as note {\n    body body.xml {\n    }\n}",
+        )
+        .unwrap_err();
+        assert_eq!(errors[0].message(), "unknown type of body `xml`");
+    }
+}
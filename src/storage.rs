@@ -0,0 +1,273 @@
+//! Saving hermes files without losing edits. Files are written atomically through a temp file in
+//! the same folder, and a save is refused when the file changed on disk since it was loaded, for
+//! example because it was also edited in another editor. The user can then merge or overwrite.
+
+use std::{
+    fmt, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use sha2::{Digest, Sha256};
+
+/// What a file looked like when it was loaded or last saved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSnapshot {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    hash: Vec<u8>,
+}
+
+impl FileSnapshot {
+    /// Remembers the state of the file without keeping its contents, which are hashed a chunk at a
+    /// time, e.g. when a collection is loaded.
+    pub fn of(path: &Path) -> io::Result<Self> {
+        let modified = fs::metadata(path)?.modified().ok();
+        let mut file = fs::File::open(path)?;
+        let mut hasher = Sha256::new();
+        let mut chunk = [0; 8192];
+        loop {
+            match file.read(&mut chunk)? {
+                0 => break,
+                read => hasher.update(&chunk[..read]),
+            }
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            modified,
+            hash: hasher.finalize().to_vec(),
+        })
+    }
+
+    fn new(path: &Path, contents: &str) -> io::Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            modified: fs::metadata(path)?.modified().ok(),
+            hash: Sha256::digest(contents).to_vec(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets the contents on disk if they changed since the snapshot. The hash is only compared
+    /// when the modification time moved, since reading the file is the expensive part.
+    pub fn changed_on_disk(&self) -> io::Result<Option<String>> {
+        let modified = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.modified().ok(),
+            // a deleted file is a change too, saving recreates it
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Some(String::new())),
+            Err(err) => return Err(err),
+        };
+        if modified.is_some() && modified == self.modified {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(&self.path)?;
+        if Sha256::digest(&contents).as_slice() == self.hash.as_slice() {
+            Ok(None)
+        } else {
+            Ok(Some(contents))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    /// The file changed on disk since it was loaded. Holds what is on disk now so it can be merged.
+    Conflict {
+        path: PathBuf,
+        on_disk: String,
+        /// What was about to be saved.
+        ours: String,
+    },
+    Io(io::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Conflict { path, .. } => {
+                write!(f, "{} changed on disk since it was loaded", path.display())
+            }
+            SaveError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+/// Saves the contents if the file did not change since the snapshot and returns the snapshot of
+/// the saved file. Pass no snapshot for a new file.
+pub fn save(
+    path: &Path,
+    contents: &str,
+    snapshot: Option<&FileSnapshot>,
+) -> Result<FileSnapshot, SaveError> {
+    if let Some(snapshot) = snapshot {
+        if let Some(on_disk) = snapshot.changed_on_disk()? {
+            return Err(SaveError::Conflict {
+                path: path.to_path_buf(),
+                on_disk,
+                ours: contents.to_string(),
+            });
+        }
+    }
+    overwrite(path, contents)
+}
+
+/// Edits the file as it is on disk and saves it atomically, unless it changed since the snapshot,
+/// which is then a conflict holding the edit made to what is on disk now. Returns the snapshot of
+/// the saved file, or None when the edit changed nothing.
+pub fn edit(
+    snapshot: &FileSnapshot,
+    edit: impl FnOnce(&str) -> Option<String>,
+) -> Result<Option<FileSnapshot>, SaveError> {
+    let path = snapshot.path();
+    let contents = fs::read_to_string(path)?;
+    match edit(&contents) {
+        Some(edited) if edited != contents => save(path, &edited, Some(snapshot)).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Saves the contents no matter what is on disk, for when the user chose to overwrite a conflict.
+pub fn overwrite(path: &Path, contents: &str) -> Result<FileSnapshot, SaveError> {
+    write_atomically(path, contents)?;
+    Ok(FileSnapshot::new(path, contents)?)
+}
+
+/// Builds the text to resolve a conflict by hand in $EDITOR. The lines both versions share at the
+/// start and the end are kept, the rest is wrapped in git style conflict markers.
+pub fn with_conflict_markers(on_disk: &str, ours: &str) -> String {
+    let theirs: Vec<&str> = on_disk.lines().collect();
+    let ours: Vec<&str> = ours.lines().collect();
+    let prefix = theirs
+        .iter()
+        .zip(ours.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = theirs[prefix..]
+        .iter()
+        .rev()
+        .zip(ours[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut merged: Vec<&str> = theirs[..prefix].to_vec();
+    merged.push("<<<<<<< on disk");
+    merged.extend_from_slice(&theirs[prefix..theirs.len() - suffix]);
+    merged.push("=======");
+    merged.extend_from_slice(&ours[prefix..ours.len() - suffix]);
+    merged.push(">>>>>>> hermes");
+    merged.extend_from_slice(&theirs[theirs.len() - suffix..]);
+    merged.join("\n") + "\n"
+}
+
+/// Tells whether the text still has the conflict markers of with_conflict_markers.
+pub fn has_conflict_markers(text: &str) -> bool {
+    text.lines().any(|line| {
+        line.starts_with("<<<<<<< ") || line == "=======" || line.starts_with(">>>>>>> ")
+    })
+}
+
+/// Checks whether files can be saved in the folder, creating it when it is missing, by writing a
+/// file and removing it. Permissions alone don't tell, e.g. for a read-only mount or when running
+/// as root.
+pub fn is_writable(dir: &Path) -> bool {
+    if fs::create_dir_all(dir).is_err() {
+        return false;
+    }
+    let probe = dir.join(format!(".hermes.{}.probe", std::process::id()));
+    let created = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .is_ok();
+    created && fs::remove_file(&probe).is_ok()
+}
+
+/// Writes to a temp file next to the target and renames it over the target, so the file is
+/// never left half written.
+fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path is not a file"))?;
+    let temp = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    let result = fs::File::create(&temp).and_then(|mut file| {
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    });
+    match result.and_then(|_| fs::rename(&temp, path)) {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            let _ = fs::remove_file(&temp);
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external;
+
+    #[test]
+    fn should_refuse_to_save_over_external_changes() {
+        let path = external::temp_path("storage", "hermes");
+        fs::write(&path, "request { name `a` }").unwrap();
+        let snapshot = FileSnapshot::of(&path).unwrap();
+
+        let snapshot = save(&path, "request { name `b` }", Some(&snapshot)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "request { name `b` }");
+        let snapshot = edit(&snapshot, |contents| Some(contents.replace('b', "c")))
+            .unwrap()
+            .unwrap();
+        assert_eq!(snapshot, FileSnapshot::of(&path).unwrap());
+        assert!(edit(&snapshot, |_| None).unwrap().is_none());
+
+        // the edit is made to what is on disk now, but the file changed since the snapshot
+        fs::write(&path, "request { name `external` }").unwrap();
+        match edit(&snapshot, |contents| {
+            Some(contents.replace("external", "d"))
+        }) {
+            Err(SaveError::Conflict { on_disk, ours, .. }) => {
+                assert_eq!(on_disk, "request { name `external` }");
+                assert_eq!(ours, "request { name `d` }");
+            }
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+
+        assert_eq!(
+            with_conflict_markers(
+                "request {\n    name `external`\n}",
+                "request {\n    name `c`\n}"
+            ),
+            "request {\n<<<<<<< on disk\n    name `external`\n=======\n    name `c`\n>>>>>>> hermes\n}\n"
+        );
+        assert!(has_conflict_markers(&with_conflict_markers("a", "b")));
+        assert!(!has_conflict_markers("request {\n    name `=======`\n}\n"));
+        overwrite(&path, "request { name `c` }").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "request { name `c` }");
+
+        // a folder can't be made under a file, whatever the permissions
+        let dir = path.parent().unwrap();
+        assert!(is_writable(dir));
+        assert!(!is_writable(&path.join("collection")));
+        assert!(fs::read_dir(dir).unwrap().all(|entry| !entry
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .ends_with(".probe")));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,456 @@
+//! The tokens of hermes files: words, names between double quotes, values between backticks,
+//! braces and line ends, each with where it is in the file. source reads blocks out of them.
+
+mod transition_table;
+
+use std::iter;
+
+use serde::Serialize;
+
+use crate::source::Diagnostic;
+
+use transition_table::{
+    build_transition_table, char_to_input, is_transitional_state, Input, State,
+};
+
+/// Every block type Hermes understands. Blocks of other types are read like the others and kept
+/// as they are when a file is edited, e.g. ones written by a newer version.
+pub const BLOCK_TYPES: [&str; 12] = [
+    "collection",
+    "request",
+    "environment",
+    "body",
+    "headers",
+    "queries",
+    "settings",
+    "tests",
+    "auth",
+    "hooks",
+    "folder",
+    "meta",
+];
+
+/// Where a token is, with the same counting as Diagnostic.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Span {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Word(String),
+    /// A field name between double quotes.
+    Quoted(String),
+    /// A value between backticks, which can span many lines.
+    Value(String),
+    Open,
+    Close,
+    Newline,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// Splits the characters into tokens, reporting the names and values that are never closed and
+/// the characters that can't start a token. The transition table tells where each token ends;
+/// words are the identifiers, sub block types and digits written with nothing between them.
+pub fn tokenize(chars: impl Iterator<Item = char>) -> (Vec<Token>, Vec<Diagnostic>) {
+    let table = build_transition_table();
+    let mut tokens = Vec::new();
+    let mut diagnostics = Vec::new();
+    let mut chars = chars.peekable();
+    let (mut line, mut column) = (0, 0);
+    let mut state = State::Start;
+    let mut start = Span::default();
+    let mut text = String::new();
+    loop {
+        let ch = chars.peek().copied();
+        let input = ch.map_or(Input::Eof, char_to_input);
+        let next = table.get(&(state, input)).copied().unwrap_or(State::Error);
+        if state == State::Start {
+            start = Span {
+                line,
+                start: column,
+                end: column + 1,
+            };
+            text.clear();
+        }
+        match next {
+            State::Eof => break,
+            State::Error => {
+                chars.next();
+                column += 1;
+                diagnostics.push(Diagnostic::new(
+                    start,
+                    "unexpected character, put names that are not a single word between \"",
+                ));
+                state = State::Start;
+            }
+            next if is_transitional_state(next) => {
+                let Some(ch) = chars.next() else {
+                    break;
+                };
+                // the opening quote or backtick is not part of the text, nor are comments
+                let opening = state == State::Start
+                    && matches!(next, State::ReadString | State::ReadSpecialIdentifier);
+                if !opening && !matches!(next, State::Start | State::ReadComment) {
+                    text.push(ch);
+                }
+                if ch == '\n' {
+                    if next == State::Start {
+                        tokens.push(Token {
+                            kind: TokenKind::Newline,
+                            span: Span {
+                                line,
+                                start: column,
+                                end: column + 1,
+                            },
+                        });
+                    }
+                    line += 1;
+                    column = 0;
+                } else {
+                    column += 1;
+                }
+                state = next;
+            }
+            end => {
+                // delimiters, digits and closing quotes are part of the token, what ends a word
+                // is not
+                let closing = match end {
+                    State::EndString => Some('`'),
+                    State::EndSpecialIdentifier => Some('"'),
+                    _ => None,
+                };
+                let consumed = matches!(end, State::EndDelimeter | State::EndDigit)
+                    || (closing.is_some() && ch == closing);
+                if consumed {
+                    chars.next();
+                    column += 1;
+                }
+                if closing.is_some() && !consumed {
+                    let message = if end == State::EndString {
+                        "this value is never closed, add a ` after it"
+                    } else {
+                        "this name is never closed, add a \" after it"
+                    };
+                    // still read as a value so the field is not reported as missing one
+                    diagnostics.push(Diagnostic::new(start, message));
+                }
+                let span = Span {
+                    end: if line == start.line {
+                        column
+                    } else {
+                        start.end
+                    },
+                    ..start
+                };
+                match end {
+                    State::EndDelimeter if ch == Some('{') => tokens.push(Token {
+                        kind: TokenKind::Open,
+                        span: start,
+                    }),
+                    State::EndDelimeter => tokens.push(Token {
+                        kind: TokenKind::Close,
+                        span: start,
+                    }),
+                    State::EndString => tokens.push(Token {
+                        kind: TokenKind::Value(unescape(&text, consumed)),
+                        span,
+                    }),
+                    State::EndSpecialIdentifier => tokens.push(Token {
+                        kind: TokenKind::Quoted(text.clone()),
+                        span,
+                    }),
+                    State::EndDigit => {
+                        push_word(&mut tokens, &String::from(ch.unwrap_or_default()), span)
+                    }
+                    _ => push_word(&mut tokens, &text, span),
+                }
+                state = State::Start;
+            }
+        }
+    }
+    (tokens, diagnostics)
+}
+
+/// Adds the text to the word written right before it, or starts a word with it.
+fn push_word(tokens: &mut Vec<Token>, text: &str, span: Span) {
+    match tokens.last_mut() {
+        Some(Token {
+            kind: TokenKind::Word(word),
+            span: last,
+        }) if last.line == span.line && last.end == span.start => {
+            word.push_str(text);
+            last.end = span.end;
+        }
+        _ => tokens.push(Token {
+            kind: TokenKind::Word(String::from(text)),
+            span,
+        }),
+    }
+}
+
+/// Reads the text of a value as it was written. The backslashes right before a backtick escape
+/// each other and the backtick, the same goes for those before the closing backtick. Any other
+/// backslash is kept, so paths and JSON escapes read as they were written.
+fn unescape(text: &str, closed: bool) -> String {
+    let mut value = String::new();
+    let mut backslashes = 0;
+    for ch in text.chars() {
+        match ch {
+            '\\' => backslashes += 1,
+            '`' => {
+                value.extend(iter::repeat_n('\\', backslashes / 2));
+                value.push('`');
+                backslashes = 0;
+            }
+            ch => {
+                value.extend(iter::repeat_n('\\', backslashes));
+                value.push(ch);
+                backslashes = 0;
+            }
+        }
+    }
+    let kept = if closed { backslashes / 2 } else { backslashes };
+    value.extend(iter::repeat_n('\\', kept));
+    value
+}
+
+/// Counts the backticks of the line that start or end a value, leaving out the escaped ones.
+pub fn unescaped_backticks(line: &str) -> usize {
+    let mut backslashes = 0;
+    let mut count = 0;
+    for ch in line.chars() {
+        match ch {
+            '\\' => backslashes += 1,
+            '`' if backslashes % 2 == 0 => {
+                count += 1;
+                backslashes = 0;
+            }
+            _ => backslashes = 0,
+        }
+    }
+    count
+}
+
+/// Writes the value between backticks, escaping its backticks and the backslashes before them.
+pub fn write_value(value: &str) -> String {
+    let mut written = String::from("`");
+    let mut backslashes = 0;
+    for ch in value.chars() {
+        match ch {
+            '\\' => backslashes += 1,
+            '`' => {
+                written.extend(iter::repeat_n('\\', backslashes * 2 + 1));
+                written.push('`');
+                backslashes = 0;
+            }
+            ch => {
+                written.extend(iter::repeat_n('\\', backslashes));
+                written.push(ch);
+                backslashes = 0;
+            }
+        }
+    }
+    written.extend(iter::repeat_n('\\', backslashes * 2));
+    written.push('`');
+    written
+}
+
+/// Writes the name of a field as it is when it reads back as a single word, or else between
+/// double quotes. Names can't hold a double quote or a line end, those become ' and a space.
+pub fn write_name(name: &str) -> String {
+    let (tokens, diagnostics) = tokenize(name.chars());
+    match tokens.as_slice() {
+        [Token {
+            kind: TokenKind::Word(word),
+            ..
+        }] if diagnostics.is_empty() && word == name => name.to_string(),
+        _ => format!("\"{}\"", name.replace('"', "'").replace('\n', " ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(contents: &str) -> Vec<TokenKind> {
+        let (tokens, diagnostics) = tokenize(contents.chars());
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        tokens.into_iter().map(|token| token.kind).collect()
+    }
+
+    fn word(text: &str) -> TokenKind {
+        TokenKind::Word(String::from(text))
+    }
+
+    #[test]
+    fn should_track_where_tokens_start() {
+        let (tokens, diagnostics) = tokenize("request {\n    url 1 `x` # a comment\n}".chars());
+        assert!(diagnostics.is_empty());
+        let at = |line, start, end| Span { line, start, end };
+        let found: Vec<_> = tokens
+            .into_iter()
+            .map(|token| (token.kind, token.span))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (TokenKind::Word(String::from("request")), at(0, 0, 7)),
+                (TokenKind::Open, at(0, 8, 9)),
+                (TokenKind::Newline, at(0, 9, 10)),
+                (TokenKind::Word(String::from("url")), at(1, 4, 7)),
+                (TokenKind::Word(String::from("1")), at(1, 8, 9)),
+                (TokenKind::Value(String::from("x")), at(1, 10, 13)),
+                (TokenKind::Newline, at(1, 25, 26)),
+                (TokenKind::Close, at(2, 0, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_skip_spaces_and_comments_between_tokens() {
+        assert_eq!(
+            kinds(" \t{}\r\n# only a comment {\n  }"),
+            [
+                TokenKind::Open,
+                TokenKind::Close,
+                TokenKind::Newline,
+                TokenKind::Newline,
+                TokenKind::Close,
+            ]
+        );
+        assert!(kinds("").is_empty());
+    }
+
+    #[test]
+    fn should_read_words_until_a_space_or_a_delimiter() {
+        assert_eq!(
+            kinds("self_requests self-requests v2{x}y`z`w\"n\"a#b"),
+            [
+                word("self_requests"),
+                word("self-requests"),
+                word("v2"),
+                TokenKind::Open,
+                word("x"),
+                TokenKind::Close,
+                word("y"),
+                TokenKind::Value(String::from("z")),
+                word("w"),
+                TokenKind::Quoted(String::from("n")),
+                word("a"),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_read_sub_block_types_in_the_word() {
+        assert_eq!(
+            kinds("body.json as my-body {\n}"),
+            [
+                word("body.json"),
+                word("as"),
+                word("my-body"),
+                TokenKind::Open,
+                TokenKind::Newline,
+                TokenKind::Close,
+            ]
+        );
+        assert_eq!(kinds(".json"), [word(".json")]);
+    }
+
+    #[test]
+    fn should_read_quoted_names_on_a_single_line() {
+        assert_eq!(
+            kinds("\"X Api {Key}\" `1`"),
+            [
+                TokenKind::Quoted(String::from("X Api {Key}")),
+                TokenKind::Value(String::from("1")),
+            ]
+        );
+        let (tokens, diagnostics) = tokenize("\"X Api\nKey".chars());
+        assert_eq!(tokens[0].kind, TokenKind::Quoted(String::from("X Api")));
+        assert_eq!(tokens[1].kind, TokenKind::Newline);
+        assert_eq!(
+            diagnostics[0].message(),
+            "this name is never closed, add a \" after it"
+        );
+    }
+
+    #[test]
+    fn should_read_values_across_lines() {
+        assert_eq!(
+            kinds("`{\n  \"a\": \"#1\"\n}` x"),
+            [
+                TokenKind::Value(String::from("{\n  \"a\": \"#1\"\n}")),
+                word("x"),
+            ]
+        );
+        let (tokens, diagnostics) = tokenize("`never\nclosed".chars());
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(
+            tokens[0].kind,
+            TokenKind::Value(String::from("never\nclosed"))
+        );
+        assert_eq!(diagnostics[0].line(), 0);
+        assert_eq!(
+            diagnostics[0].message(),
+            "this value is never closed, add a ` after it"
+        );
+    }
+
+    #[test]
+    fn should_escape_backticks_in_values() {
+        // backslashes only escape before a backtick, the others are kept as written
+        assert_eq!(
+            kinds(r#"`run \`ls\`` `C:\temp\\` `{"a": "\n\\"}`"#),
+            [
+                TokenKind::Value(String::from("run `ls`")),
+                TokenKind::Value(String::from(r"C:\temp\")),
+                TokenKind::Value(String::from(r#"{"a": "\n\\"}"#)),
+            ]
+        );
+        for value in ["run `ls`", r"C:\temp\", r"\`", "``", r#"{"a": "\n\\"}"#, ""] {
+            let written = write_value(value);
+            assert_eq!(kinds(&written), [TokenKind::Value(String::from(value))]);
+            assert_eq!(unescaped_backticks(&written), 2);
+        }
+    }
+
+    #[test]
+    fn should_report_characters_that_start_no_token() {
+        let (tokens, diagnostics) = tokenize("page[size] `2`".chars());
+        assert_eq!(tokens[0].kind, TokenKind::Word(String::from("page")));
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!((diagnostics[0].line(), diagnostics[0].start()), (0, 4));
+        assert_eq!(
+            diagnostics[0].message(),
+            "unexpected character, put names that are not a single word between \""
+        );
+        assert_eq!(
+            kinds("\"page[size]\" `2`")[0],
+            TokenKind::Quoted(String::from("page[size]"))
+        );
+    }
+
+    #[test]
+    fn should_quote_names_that_are_not_a_word() {
+        for (name, written) in [
+            ("Content-Type", "Content-Type"),
+            ("page_size", "page_size"),
+            ("first name", "\"first name\""),
+            ("page[size]", "\"page[size]\""),
+            ("#tag", "\"#tag\""),
+            ("", "\"\""),
+        ] {
+            assert_eq!(write_name(name), written);
+        }
+        assert_eq!(write_name("say \"hi\""), "\"say 'hi'\"");
+    }
+}
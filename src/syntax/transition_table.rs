@@ -1,7 +1,8 @@
-use std::{collections::HashMap, slice::Iter};
+//! The transition table of the tokenizer: which state reading a character of a kind leads to.
+//! Missing entries in the transition table mean that the (State, Input) combination results in
+//! State::Error.
 
-/// ! Missing entries in the transition table
-/// ! means that the (State, Input) combination results in State::Error
+use std::{collections::HashMap, slice::Iter};
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum State {
@@ -44,12 +45,15 @@ pub enum State {
     /// go back to complete the read on the string value.
     ReadEscapedCharacter,
 
+    /// A comment starts with a # and runs until the end of the line, which is read on its own.
+    ReadComment,
+
     /// Delimeters are single character entries, just like digits, they not need any intermediate
     /// state to complete the read.
     EndDelimeter,
 
     /// End of File state
-    EOF,
+    Eof,
 
     /// Error state, something unknown or unexpected happened during a read.
     Error,
@@ -68,13 +72,14 @@ pub enum Input {
     Backslash,
     Digit,
     DoubleQuote,
+    Hash,
     Other,
-    EOF,
+    Eof,
 }
 
 impl Input {
     pub fn iterator() -> Iter<'static, Input> {
-        static INPUTS: [Input; 13] = [
+        static INPUTS: [Input; 14] = [
             Input::NewLine,
             Input::Whitespace,
             Input::Character,
@@ -86,8 +91,9 @@ impl Input {
             Input::Backslash,
             Input::Digit,
             Input::DoubleQuote,
+            Input::Hash,
             Input::Other,
-            Input::EOF,
+            Input::Eof,
         ];
         INPUTS.iter()
     }
@@ -96,9 +102,9 @@ impl Input {
 /// Match the given character with an Input type to use with a transition table.
 pub fn char_to_input(ch: char) -> Input {
     match ch {
-        ' ' | '\t' => Input::Whitespace,
         '\n' => Input::NewLine,
-        'a'..='z' | 'A'..'Z' => Input::Character,
+        ch if ch.is_whitespace() => Input::Whitespace,
+        'a'..='z' | 'A'..='Z' => Input::Character,
         '-' => Input::Phiten,
         '_' => Input::Underscore,
         '{' | '}' => Input::Delimeter,
@@ -107,7 +113,9 @@ pub fn char_to_input(ch: char) -> Input {
         '\\' => Input::Backslash,
         '0'..='9' => Input::Digit,
         '"' => Input::DoubleQuote,
-        '\0' => Input::EOF,
+        '#' => Input::Hash,
+        // letters of other alphabets read like the ascii ones
+        ch if ch.is_alphabetic() => Input::Character,
         _ => Input::Other,
     }
 }
@@ -122,20 +130,22 @@ pub fn build_transition_table() -> HashMap<(State, Input), State> {
     insert_read_string_states(&mut table);
     insert_read_escaped_character_states(&mut table);
     insert_read_sub_block_type_states(&mut table);
+    insert_read_comment_states(&mut table);
 
     table
 }
 
 pub fn is_transitional_state(state: State) -> bool {
-    match state {
+    matches!(
+        state,
         State::Start
-        | State::ReadIdentifier
-        | State::ReadSubBlockType
-        | State::ReadSpecialIdentifier
-        | State::ReadString
-        | State::ReadEscapedCharacter => true,
-        _ => false,
-    }
+            | State::ReadIdentifier
+            | State::ReadSubBlockType
+            | State::ReadSpecialIdentifier
+            | State::ReadString
+            | State::ReadEscapedCharacter
+            | State::ReadComment
+    )
 }
 
 fn insert_start_states(table: &mut HashMap<(State, Input), State>) {
@@ -149,10 +159,11 @@ fn insert_start_states(table: &mut HashMap<(State, Input), State>) {
             Input::Dot => State::ReadSubBlockType,
             Input::Digit => State::EndDigit,
             Input::DoubleQuote => State::ReadSpecialIdentifier,
+            Input::Hash => State::ReadComment,
             Input::Phiten => State::Error,
             Input::Backslash => State::Error,
             Input::Tilt => State::ReadString,
-            Input::EOF => State::EOF,
+            Input::Eof => State::Eof,
             Input::Other => State::Error,
         };
         table.insert((State::Start, *input), next_state);
@@ -170,10 +181,11 @@ fn insert_read_identifier_states(table: &mut HashMap<(State, Input), State>) {
             Input::Dot => State::EndIdentifier,
             Input::Digit => State::ReadIdentifier,
             Input::DoubleQuote => State::EndIdentifier,
+            Input::Hash => State::EndIdentifier,
             Input::Phiten => State::ReadIdentifier,
             Input::Backslash => State::EndIdentifier,
             Input::Tilt => State::EndIdentifier,
-            Input::EOF => State::EndIdentifier,
+            Input::Eof => State::EndIdentifier,
             Input::Other => State::EndIdentifier,
         };
         table.insert((State::ReadIdentifier, *input), next_state);
@@ -191,10 +203,11 @@ fn insert_read_special_identifier_states(table: &mut HashMap<(State, Input), Sta
             Input::Dot => State::ReadSpecialIdentifier,
             Input::Digit => State::ReadSpecialIdentifier,
             Input::DoubleQuote => State::EndSpecialIdentifier,
+            Input::Hash => State::ReadSpecialIdentifier,
             Input::Phiten => State::ReadSpecialIdentifier,
             Input::Backslash => State::ReadSpecialIdentifier,
             Input::Tilt => State::ReadSpecialIdentifier,
-            Input::EOF => State::EndSpecialIdentifier,
+            Input::Eof => State::EndSpecialIdentifier,
             Input::Other => State::ReadSpecialIdentifier,
         };
         table.insert((State::ReadSpecialIdentifier, *input), next_state);
@@ -211,10 +224,11 @@ fn insert_read_string_states(table: &mut HashMap<(State, Input), State>) {
             Input::Dot => State::ReadString,
             Input::Digit => State::ReadString,
             Input::DoubleQuote => State::ReadString,
+            Input::Hash => State::ReadString,
             Input::Phiten => State::ReadString,
             Input::Backslash => State::ReadEscapedCharacter,
             Input::Tilt => State::EndString,
-            Input::EOF => State::EndString,
+            Input::Eof => State::EndString,
             Input::Other => State::ReadString,
         };
         table.insert((State::ReadString, *input), next_state);
@@ -232,10 +246,11 @@ fn insert_read_escaped_character_states(table: &mut HashMap<(State, Input), Stat
             Input::Dot => State::ReadString,
             Input::Digit => State::ReadString,
             Input::DoubleQuote => State::ReadString,
+            Input::Hash => State::ReadString,
             Input::Phiten => State::ReadString,
             Input::Backslash => State::ReadString,
             Input::Tilt => State::ReadString,
-            Input::EOF => State::EndString,
+            Input::Eof => State::EndString,
             Input::Other => State::ReadString,
         };
         table.insert((State::ReadEscapedCharacter, *input), next_state);
@@ -253,16 +268,28 @@ fn insert_read_sub_block_type_states(table: &mut HashMap<(State, Input), State>)
             Input::Dot => State::EndSubBlockType,
             Input::Digit => State::ReadSubBlockType,
             Input::DoubleQuote => State::EndSubBlockType,
+            Input::Hash => State::EndSubBlockType,
             Input::Phiten => State::ReadSubBlockType,
             Input::Backslash => State::EndSubBlockType,
             Input::Tilt => State::EndSubBlockType,
-            Input::EOF => State::EndSubBlockType,
+            Input::Eof => State::EndSubBlockType,
             Input::Other => State::EndSubBlockType,
         };
         table.insert((State::ReadSubBlockType, *input), next_state);
     }
 }
 
+fn insert_read_comment_states(table: &mut HashMap<(State, Input), State>) {
+    for input in Input::iterator() {
+        let next_state = match input {
+            Input::NewLine => State::Start,
+            Input::Eof => State::Eof,
+            _ => State::ReadComment,
+        };
+        table.insert((State::ReadComment, *input), next_state);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use core::panic;
@@ -302,11 +329,12 @@ mod tests {
             (State::ReadString, true),
             (State::EndString, false),
             (State::ReadEscapedCharacter, true),
+            (State::ReadComment, true),
             (State::ReadSpecialIdentifier, true),
             (State::EndSpecialIdentifier, false),
             (State::EndDelimeter, false),
             (State::EndDigit, false),
-            (State::EOF, false),
+            (State::Eof, false),
             (State::Error, false),
         ];
         for case in test_cases {
@@ -334,10 +362,11 @@ mod tests {
                 Input::Dot => State::ReadSubBlockType,
                 Input::Digit => State::EndDigit,
                 Input::DoubleQuote => State::ReadSpecialIdentifier,
+                Input::Hash => State::ReadComment,
                 Input::Phiten => State::Error,
                 Input::Backslash => State::Error,
                 Input::Tilt => State::ReadString,
-                Input::EOF => State::EOF,
+                Input::Eof => State::Eof,
                 Input::Other => State::Error,
             };
             states.push(((state, *input), next_state));
@@ -361,10 +390,11 @@ mod tests {
                 Input::Dot => State::EndIdentifier,
                 Input::Digit => State::ReadIdentifier,
                 Input::DoubleQuote => State::EndIdentifier,
+                Input::Hash => State::EndIdentifier,
                 Input::Phiten => State::ReadIdentifier,
                 Input::Backslash => State::EndIdentifier,
                 Input::Tilt => State::EndIdentifier,
-                Input::EOF => State::EndIdentifier,
+                Input::Eof => State::EndIdentifier,
                 Input::Other => State::EndIdentifier,
             };
             states.push(((state, *input), next_state));
@@ -388,10 +418,11 @@ mod tests {
                 Input::Dot => State::ReadSpecialIdentifier,
                 Input::Digit => State::ReadSpecialIdentifier,
                 Input::DoubleQuote => State::EndSpecialIdentifier,
+                Input::Hash => State::ReadSpecialIdentifier,
                 Input::Phiten => State::ReadSpecialIdentifier,
                 Input::Backslash => State::ReadSpecialIdentifier,
                 Input::Tilt => State::ReadSpecialIdentifier,
-                Input::EOF => State::EndSpecialIdentifier,
+                Input::Eof => State::EndSpecialIdentifier,
                 Input::Other => State::ReadSpecialIdentifier,
             };
             states.push(((state, *input), next_state));
@@ -415,10 +446,11 @@ mod tests {
                 Input::Dot => State::ReadString,
                 Input::Digit => State::ReadString,
                 Input::DoubleQuote => State::ReadString,
+                Input::Hash => State::ReadString,
                 Input::Phiten => State::ReadString,
                 Input::Backslash => State::ReadEscapedCharacter,
                 Input::Tilt => State::EndString,
-                Input::EOF => State::EndString,
+                Input::Eof => State::EndString,
                 Input::Other => State::ReadString,
             };
             states.push(((state, *input), next_state));
@@ -442,10 +474,11 @@ mod tests {
                 Input::Dot => State::ReadString,
                 Input::Digit => State::ReadString,
                 Input::DoubleQuote => State::ReadString,
+                Input::Hash => State::ReadString,
                 Input::Phiten => State::ReadString,
                 Input::Backslash => State::ReadString,
                 Input::Tilt => State::ReadString,
-                Input::EOF => State::EndString,
+                Input::Eof => State::EndString,
                 Input::Other => State::ReadString,
             };
             states.push(((state, *input), next_state));
@@ -469,10 +502,11 @@ mod tests {
                 Input::Dot => State::EndSubBlockType,
                 Input::Digit => State::ReadSubBlockType,
                 Input::DoubleQuote => State::EndSubBlockType,
+                Input::Hash => State::EndSubBlockType,
                 Input::Phiten => State::ReadSubBlockType,
                 Input::Backslash => State::EndSubBlockType,
                 Input::Tilt => State::EndSubBlockType,
-                Input::EOF => State::EndSubBlockType,
+                Input::Eof => State::EndSubBlockType,
                 Input::Other => State::EndSubBlockType,
             };
             states.push(((state, *input), next_state));
@@ -481,4 +515,21 @@ mod tests {
         insert_read_sub_block_type_states(&mut table);
         verify_result(&table, states);
     }
+
+    #[test]
+    fn should_insert_read_comment_states() {
+        let mut states: Vec<((State, Input), State)> = Vec::new();
+        let state = State::ReadComment;
+        for input in Input::iterator() {
+            let next_state = match input {
+                Input::NewLine => State::Start,
+                Input::Eof => State::Eof,
+                _ => State::ReadComment,
+            };
+            states.push(((state, *input), next_state));
+        }
+        let mut table = HashMap::new();
+        insert_read_comment_states(&mut table);
+        verify_result(&table, states);
+    }
 }
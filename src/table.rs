@@ -0,0 +1,323 @@
+//! Tabular viewer for CSV/TSV responses and JSON arrays of flat objects.
+
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Widget},
+};
+use serde_json::Value;
+
+/// The widest a column can get before its cells are cut off.
+const MAX_COLUMN_WIDTH: usize = 40;
+/// The number of spaces between two columns.
+const COLUMN_GAP: usize = 2;
+
+/// TableData is the parsed content of a response that can be displayed as a table. The first
+/// row of a delimited response is treated as the column names.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TableData {
+    columns: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl TableData {
+    /// Builds a table from a response body, using the content type to tell TSV apart from CSV.
+    /// JSON bodies are only accepted if they are an array of flat objects.
+    pub fn from_response(body: &str, content_type: &str) -> Option<Self> {
+        let content_type = content_type.to_lowercase();
+        if content_type.contains("json") {
+            Self::from_json_array(body)
+        } else if content_type.contains("tab-separated-values") {
+            Some(Self::from_delimited(body, '\t'))
+        } else {
+            Some(Self::from_delimited(body, ','))
+        }
+    }
+
+    /// Parses delimited text such as CSV (',') or TSV ('\t'). Fields wrapped in double quotes can
+    /// contain the delimiter, newlines and escaped double quotes ("").
+    pub fn from_delimited(text: &str, delimiter: char) -> Self {
+        let mut records: Vec<Vec<String>> = Vec::new();
+        let mut record: Vec<String> = Vec::new();
+        let mut field = String::new();
+        let mut in_quotes = false;
+        let mut chars = text.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                '"' if in_quotes => {
+                    if chars.peek() == Some(&'"') {
+                        field.push('"');
+                        chars.next();
+                    } else {
+                        in_quotes = false;
+                    }
+                }
+                '"' if field.is_empty() => in_quotes = true,
+                '\r' if !in_quotes => {}
+                '\n' if !in_quotes => {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                _ if ch == delimiter && !in_quotes => {
+                    record.push(std::mem::take(&mut field));
+                }
+                _ => field.push(ch),
+            }
+        }
+        if !field.is_empty() || !record.is_empty() {
+            record.push(field);
+            records.push(record);
+        }
+        // blank lines do not make rows
+        records.retain(|r| !(r.len() == 1 && r[0].is_empty()));
+
+        let mut records = records.into_iter();
+        let columns = records.next().unwrap_or_default();
+        Self {
+            columns,
+            rows: records.collect(),
+        }
+    }
+
+    /// Builds a table from a JSON array of flat objects. The columns are the union of all the keys
+    /// in the order they are first seen. Returns None if the JSON is not an array of objects.
+    pub fn from_json_array(text: &str) -> Option<Self> {
+        let items = match serde_json::from_str::<Value>(text).ok()? {
+            Value::Array(items) => items,
+            _ => return None,
+        };
+        let mut columns: Vec<String> = Vec::new();
+        for item in items.iter() {
+            for key in item.as_object()?.keys() {
+                if !columns.contains(key) {
+                    columns.push(key.clone());
+                }
+            }
+        }
+        let rows = items
+            .iter()
+            .filter_map(|item| item.as_object())
+            .map(|object| {
+                columns
+                    .iter()
+                    .map(|column| match object.get(column) {
+                        Some(Value::String(s)) => s.clone(),
+                        Some(Value::Null) | None => String::new(),
+                        Some(value) => value.to_string(),
+                    })
+                    .collect()
+            })
+            .collect();
+        Some(Self { columns, rows })
+    }
+
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn rows(&self) -> &[Vec<String>] {
+        &self.rows
+    }
+
+    /// Gets the width each column needs to fit its widest cell, capped at MAX_COLUMN_WIDTH.
+    fn column_widths(&self) -> Vec<usize> {
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                self.rows
+                    .iter()
+                    .filter_map(|row| row.get(index))
+                    .map(|cell| cell.chars().count())
+                    .chain(std::iter::once(column.chars().count()))
+                    .max()
+                    .unwrap_or_default()
+                    .min(MAX_COLUMN_WIDTH)
+            })
+            .collect()
+    }
+}
+
+/// TableViewer renders TableData with aligned columns. It keeps track of the scroll position and
+/// which columns the user has hidden.
+#[derive(Debug, Default, Clone)]
+pub struct TableViewer {
+    data: TableData,
+    /// The first column shown, the columns to its left are scrolled out of view.
+    column_offset: usize,
+    row_offset: usize,
+    /// The column that hide/show actions apply to.
+    selected_column: usize,
+    hidden_columns: Vec<usize>,
+}
+
+impl TableViewer {
+    pub fn new(data: TableData) -> Self {
+        Self {
+            data,
+            ..Self::default()
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        if self.row_offset + 1 < self.data.rows.len() {
+            self.row_offset += 1;
+        }
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.row_offset = self.row_offset.saturating_sub(1);
+    }
+
+    /// Moves the selection one column to the right, scrolling horizontally with it.
+    pub fn scroll_right(&mut self) {
+        if self.selected_column + 1 < self.data.columns.len() {
+            self.selected_column += 1;
+            self.column_offset = self
+                .column_offset
+                .max(self.selected_column.saturating_sub(1));
+        }
+    }
+
+    /// Moves the selection one column to the left, scrolling horizontally with it.
+    pub fn scroll_left(&mut self) {
+        self.selected_column = self.selected_column.saturating_sub(1);
+        self.column_offset = self.column_offset.min(self.selected_column);
+    }
+
+    /// Hides the selected column, or shows it again if it was already hidden.
+    pub fn toggle_selected_column(&mut self) {
+        match self
+            .hidden_columns
+            .iter()
+            .position(|c| *c == self.selected_column)
+        {
+            Some(index) => {
+                self.hidden_columns.remove(index);
+            }
+            None => self.hidden_columns.push(self.selected_column),
+        }
+    }
+
+    pub fn show_all_columns(&mut self) {
+        self.hidden_columns.clear();
+    }
+
+    fn visible_columns(&self) -> Vec<usize> {
+        (self.column_offset..self.data.columns.len())
+            .filter(|c| !self.hidden_columns.contains(c) || *c == self.selected_column)
+            .collect()
+    }
+
+    fn render_line(&self, cells: &[String], widths: &[usize], style: Style) -> Line<'static> {
+        let mut spans = Vec::new();
+        for column in self.visible_columns() {
+            let cell = cells.get(column).map(String::as_str).unwrap_or_default();
+            let width = widths[column];
+            let mut text: String = cell.chars().take(width).collect();
+            let padding = width + COLUMN_GAP - text.chars().count();
+            text.push_str(&" ".repeat(padding));
+            let cell_style = if self.hidden_columns.contains(&column) {
+                style.fg(Color::DarkGray)
+            } else if column == self.selected_column {
+                style.fg(Color::LightYellow)
+            } else {
+                style
+            };
+            spans.push(Span::styled(text, cell_style));
+        }
+        Line::from(spans)
+    }
+
+    /// Gets the lines of the table from the first row shown, the column names first.
+    pub fn lines(&self) -> Vec<Line<'static>> {
+        let widths = self.data.column_widths();
+        let mut lines = vec![self.render_line(
+            &self.data.columns,
+            &widths,
+            Style::new().fg(Color::LightBlue),
+        )];
+        for row in self.data.rows.iter().skip(self.row_offset) {
+            lines.push(self.render_line(row, &widths, Style::default()));
+        }
+        lines
+    }
+}
+
+impl Widget for TableViewer {
+    fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer) {
+        let lines = self.lines();
+        let title = format!(
+            "{} rows, {} hidden columns",
+            self.data.rows.len(),
+            self.hidden_columns.len()
+        );
+        Paragraph::new(lines)
+            .block(Block::bordered().title(title))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_csv_with_quoted_fields() {
+        let data = TableData::from_delimited(
+            "id,name,note\r\n1,\"Wu, Juan\",\"says \"\"hi\"\"\"\n2,Hermes,\"multi\nline\"\n",
+            ',',
+        );
+        assert_eq!(data.columns(), &["id", "name", "note"]);
+        assert_eq!(
+            data.rows(),
+            &[
+                vec!["1", "Wu, Juan", "says \"hi\""],
+                vec!["2", "Hermes", "multi\nline"],
+            ]
+        );
+    }
+
+    #[test]
+    fn should_parse_tsv() {
+        let data = TableData::from_delimited("a\tb\n1\t2", '\t');
+        assert_eq!(data.columns(), &["a", "b"]);
+        assert_eq!(data.rows(), &[vec!["1", "2"]]);
+    }
+
+    #[test]
+    fn should_build_table_from_json_array() {
+        let data = TableData::from_json_array(r#"[{"id": 1, "name": "a"}, {"id": 2, "ok": true}]"#)
+            .unwrap();
+        assert_eq!(data.columns(), &["id", "name", "ok"]);
+        assert_eq!(data.rows(), &[vec!["1", "a", ""], vec!["2", "", "true"]]);
+        assert_eq!(TableData::from_json_array(r#"{"id": 1}"#), None);
+        assert_eq!(TableData::from_json_array(r#"[1, 2]"#), None);
+    }
+
+    #[test]
+    fn should_scroll_and_hide_columns() {
+        let mut viewer = TableViewer::new(TableData::from_delimited("id,name\n1,a\n2,b\n", ','));
+        let text = |viewer: &TableViewer| -> Vec<String> {
+            viewer
+                .lines()
+                .iter()
+                .map(|line| line.to_string().trim_end().to_string())
+                .collect()
+        };
+        assert_eq!(text(&viewer), ["id  name", "1   a", "2   b"]);
+
+        viewer.scroll_down();
+        viewer.scroll_down();
+        assert_eq!(text(&viewer), ["id  name", "2   b"]);
+
+        // the selected column stays shown while hidden, so it can be shown again
+        viewer.toggle_selected_column();
+        assert_eq!(text(&viewer), ["id  name", "2   b"]);
+        viewer.scroll_right();
+        assert_eq!(text(&viewer), ["name", "b"]);
+        viewer.show_all_columns();
+        assert_eq!(text(&viewer), ["id  name", "2   b"]);
+    }
+}
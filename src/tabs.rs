@@ -0,0 +1,322 @@
+//! Requests opened in tabs in the detail pane, like editor buffers. Every tab keeps its own
+//! scroll position and the folds of its JSON tree, so switching between requests to compare them
+//! does not lose your place. Closed tabs come back as they were left when the request is opened
+//! again. Two tabs can also be shown side by side, or one above the other, in a split view.
+
+use ratatui::layout::Direction;
+
+use crate::{
+    client::{Response, SendError},
+    json_tree::Folds,
+};
+
+/// A request opened in a tab, referenced by its index in the collection.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RequestTab {
+    request: usize,
+    scroll: u16,
+    /// The outcome of the last time the request was sent from this tab.
+    response: Option<Result<Response, SendError>>,
+    /// Whether the request is being sent, the last response is kept until the new one comes in.
+    sending: bool,
+    /// The folds of the JSON tree of the response when it was last closed.
+    folds: Option<Folds>,
+    /// Whether the body of the response is shown as it came in instead of pretty-printed.
+    raw_body: bool,
+    /// The filter the JSON body of the response is narrowed down with, see filter.
+    filter: Option<String>,
+}
+
+impl RequestTab {
+    pub fn request(&self) -> usize {
+        self.request
+    }
+
+    pub fn scroll(&self) -> u16 {
+        self.scroll
+    }
+
+    pub fn scroll_down(&mut self, lines: u16) {
+        self.scroll = self.scroll.saturating_add(lines);
+    }
+
+    pub fn scroll_up(&mut self, lines: u16) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    pub fn response(&self) -> Option<&Result<Response, SendError>> {
+        self.response.as_ref()
+    }
+
+    pub fn is_sending(&self) -> bool {
+        self.sending
+    }
+
+    pub fn set_sending(&mut self) {
+        self.sending = true;
+    }
+
+    pub fn shows_raw_body(&self) -> bool {
+        self.raw_body
+    }
+
+    pub fn toggle_raw_body(&mut self) {
+        self.raw_body = !self.raw_body;
+    }
+
+    pub fn filter(&self) -> Option<&str> {
+        self.filter.as_deref()
+    }
+
+    /// Sets the filter of the response, scrolling back to the top. An empty one shows the whole
+    /// body.
+    pub fn set_filter(&mut self, filter: String) {
+        self.filter = Some(filter).filter(|filter| !filter.trim().is_empty());
+        self.scroll = 0;
+    }
+
+    pub fn folds(&self) -> Option<&Folds> {
+        self.folds.as_ref()
+    }
+
+    pub fn set_folds(&mut self, folds: Folds) {
+        self.folds = Some(folds);
+    }
+
+    /// Stores the outcome of sending the request, scrolling back to the top.
+    pub fn set_response(&mut self, response: Result<Response, SendError>) {
+        self.response = Some(response);
+        self.sending = false;
+        self.scroll = 0;
+    }
+}
+
+/// A split view showing the active tab and another one at the same time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Split {
+    /// How the two panes are laid out, Horizontal puts them side by side.
+    direction: Direction,
+    /// The index of the tab shown in the pane without focus.
+    other: usize,
+}
+
+impl Split {
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    pub fn other(&self) -> usize {
+        self.other
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Tabs {
+    tabs: Vec<RequestTab>,
+    active: usize,
+    split: Option<Split>,
+    /// The tabs that were closed, opened again as they were left.
+    closed: Vec<RequestTab>,
+}
+
+impl Tabs {
+    /// Opens the request in a new tab, or goes to its tab if it is already open. A tab of the
+    /// request that was closed comes back with its response, scroll and folds.
+    pub fn open(&mut self, request: usize) {
+        if let Some(index) = self.tabs.iter().position(|tab| tab.request == request) {
+            self.active = index;
+            return;
+        }
+        let tab = match self.closed.iter().position(|tab| tab.request == request) {
+            Some(index) => self.closed.remove(index),
+            None => RequestTab {
+                request,
+                ..RequestTab::default()
+            },
+        };
+        self.tabs.push(tab);
+        self.active = self.tabs.len() - 1;
+    }
+
+    /// Marks the tab of the request as no longer sending, keeping its last response. Closed tabs
+    /// too, so they don't come back sending.
+    pub fn stop_sending(&mut self, request: usize) {
+        for tab in self.tabs.iter_mut().chain(self.closed.iter_mut()) {
+            if tab.request == request {
+                tab.sending = false;
+            }
+        }
+    }
+
+    /// Closes the active tab and moves to the one before it. The split view is closed when the
+    /// tab of the other pane is the one closed.
+    pub fn close_active(&mut self) {
+        if self.tabs.is_empty() {
+            return;
+        }
+        let closed = self.active;
+        self.closed.push(self.tabs.remove(closed));
+        self.active = self.active.saturating_sub(1);
+        if let Some(split) = &mut self.split {
+            if split.other == closed || self.tabs.len() < 2 {
+                self.split = None;
+            } else if split.other > closed {
+                split.other -= 1;
+            }
+        }
+    }
+
+    /// Forgets a request that was removed from the collection: its tab is closed and the tabs of
+    /// the requests after it point at their new index.
+    pub fn remove_request(&mut self, request: usize) {
+        if let Some(index) = self.tabs.iter().position(|tab| tab.request == request) {
+            let active = self.active;
+            self.active = index;
+            self.close_active();
+            // stay on the tab that was active, which moved back by one when it was after the closed one
+            if active != index {
+                self.active = if active > index { active - 1 } else { active };
+            }
+        }
+        self.closed.retain(|tab| tab.request != request);
+        for tab in self.tabs.iter_mut().chain(&mut self.closed) {
+            if tab.request > request {
+                tab.request -= 1;
+            }
+        }
+    }
+
+    /// Splits the view with the active tab and the one before it, or closes the split when it
+    /// already has the same direction. Returns false when there are not two tabs to show.
+    pub fn toggle_split(&mut self, direction: Direction) -> bool {
+        match self.split {
+            Some(split) if split.direction == direction => {
+                self.split = None;
+                true
+            }
+            Some(split) => {
+                self.split = Some(Split { direction, ..split });
+                true
+            }
+            None if self.tabs.len() < 2 => false,
+            None => {
+                let other = (self.active + self.tabs.len() - 1) % self.tabs.len();
+                self.split = Some(Split { direction, other });
+                true
+            }
+        }
+    }
+
+    /// Moves the focus to the other pane of the split view.
+    pub fn switch_focus(&mut self) {
+        if let Some(split) = &mut self.split {
+            std::mem::swap(&mut split.other, &mut self.active);
+        }
+    }
+
+    /// Goes to the tab at the index, if there is one.
+    pub fn focus(&mut self, index: usize) {
+        if index < self.tabs.len() {
+            self.active = index;
+        }
+    }
+
+    pub fn split(&self) -> Option<Split> {
+        self.split
+    }
+
+    pub fn get(&self, index: usize) -> Option<&RequestTab> {
+        self.tabs.get(index)
+    }
+
+    /// Goes to the next tab, wrapping around like `gt` in vim.
+    pub fn next(&mut self) {
+        if !self.tabs.is_empty() {
+            self.active = (self.active + 1) % self.tabs.len();
+        }
+    }
+
+    /// Goes to the previous tab, wrapping around like `gT` in vim.
+    pub fn prev(&mut self) {
+        if !self.tabs.is_empty() {
+            self.active = (self.active + self.tabs.len() - 1) % self.tabs.len();
+        }
+    }
+
+    pub fn active(&self) -> Option<&RequestTab> {
+        self.tabs.get(self.active)
+    }
+
+    pub fn active_mut(&mut self) -> Option<&mut RequestTab> {
+        self.tabs.get_mut(self.active)
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, RequestTab> {
+        self.tabs.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_keep_scroll_per_tab() {
+        let mut tabs = Tabs::default();
+        tabs.open(3);
+        tabs.active_mut().unwrap().scroll_down(10);
+        tabs.open(5);
+        assert_eq!(tabs.active().unwrap().scroll(), 0);
+
+        tabs.prev();
+        assert_eq!(tabs.active().unwrap().request(), 3);
+        assert_eq!(tabs.active().unwrap().scroll(), 10);
+        tabs.open(5);
+        assert_eq!(tabs.active_index(), 1);
+        tabs.next();
+        assert_eq!(tabs.active_index(), 0);
+
+        tabs.close_active();
+        assert_eq!(tabs.active().unwrap().request(), 5);
+        tabs.close_active();
+        assert_eq!(tabs.active(), None);
+
+        // closed tabs come back as they were left
+        tabs.open(3);
+        assert_eq!(tabs.active().unwrap().scroll(), 10);
+        tabs.close_active();
+        tabs.remove_request(0);
+        tabs.open(2);
+        assert_eq!(tabs.active().unwrap().scroll(), 10);
+        tabs.remove_request(2);
+        tabs.open(2);
+        assert_eq!(tabs.active().unwrap().scroll(), 0);
+    }
+
+    #[test]
+    fn should_switch_focus_between_split_panes() {
+        let mut tabs = Tabs::default();
+        tabs.open(3);
+        assert!(!tabs.toggle_split(Direction::Horizontal));
+        tabs.open(5);
+        tabs.open(7);
+        assert!(tabs.toggle_split(Direction::Horizontal));
+        assert_eq!(tabs.split().unwrap().other(), 1);
+
+        tabs.switch_focus();
+        assert_eq!(tabs.active().unwrap().request(), 5);
+        assert_eq!(tabs.split().unwrap().other(), 2);
+
+        tabs.toggle_split(Direction::Vertical);
+        assert_eq!(tabs.split().unwrap().direction(), Direction::Vertical);
+        tabs.prev();
+        tabs.close_active();
+        assert_eq!(tabs.split().unwrap().other(), 1);
+        tabs.close_active();
+        assert_eq!(tabs.split(), None);
+    }
+}
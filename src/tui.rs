@@ -1,12 +1,14 @@
 //! Template copied from ratatui counter app example
 
-use std::io::{self, stdout, Stdout};
+use std::io::{self, stdout, Stdout, Write};
 
 use ratatui::{
     backend::CrosstermBackend,
     crossterm::{
         execute,
-        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+        terminal::{
+            disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, SetTitle,
+        },
     },
     Terminal,
 };
@@ -14,8 +16,13 @@ use ratatui::{
 /// A type alias for the terminal type used in this application
 pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 
+/// Asks the terminal to save its title, restored by POP_TITLE. Terminals that can't ignore it.
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
+
 /// Initialize the terminal
 pub fn init() -> io::Result<Tui> {
+    write!(stdout(), "{}", PUSH_TITLE)?;
     execute!(stdout(), EnterAlternateScreen)?;
     enable_raw_mode()?;
     Terminal::new(CrosstermBackend::new(stdout()))
@@ -25,5 +32,23 @@ pub fn init() -> io::Result<Tui> {
 pub fn restore() -> io::Result<()> {
     execute!(stdout(), LeaveAlternateScreen)?;
     disable_raw_mode()?;
-    Ok(())
+    write!(stdout(), "{}", POP_TITLE)?;
+    stdout().flush()
+}
+
+/// Sets the title of the terminal, the window or tab hermes runs in.
+pub fn set_title(title: &str) -> io::Result<()> {
+    execute!(stdout(), SetTitle(title))
+}
+
+/// Leave the TUI to run a program that takes over the terminal, such as $EDITOR, and come back to
+/// it once the program is done.
+pub fn suspend<T>(terminal: &mut Tui, f: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+    restore()?;
+    let result = f();
+    write!(stdout(), "{}", PUSH_TITLE)?;
+    execute!(stdout(), EnterAlternateScreen)?;
+    enable_raw_mode()?;
+    terminal.clear()?;
+    result
 }
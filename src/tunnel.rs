@@ -0,0 +1,287 @@
+//! SSH tunnels declared in an environment block, for APIs that are only reachable from inside a
+//! private network. The tunnel is an `ssh -L` process that is only started when a request needs
+//! it.
+//!
+//! The http client reaches the tunnel through a local relay it uses as a proxy, so the url keeps
+//! the real host for the Host header, SNI and the certificate checks. Overriding the address the
+//! host resolves to is not enough, since the client always connects to the port of the url.
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use reqwest::Url;
+
+/// The environment key with the ssh destination, e.g. `user@bastion.example.com`.
+pub const HOST_KEY: &str = "ssh-host";
+/// The environment key with an optional jump host, passed to `ssh -J`.
+pub const JUMP_KEY: &str = "ssh-jump";
+/// The environment key with the `host:port` the tunnel forwards to, as seen from the ssh host.
+pub const FORWARD_KEY: &str = "ssh-forward";
+/// The environment key with an optional local port. A free port is picked when missing.
+pub const LOCAL_PORT_KEY: &str = "ssh-local-port";
+
+/// How long to wait for ssh to start listening on the local port.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// The longest request head the relay reads before giving up on the connection.
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+
+#[derive(Debug)]
+pub struct SshTunnel {
+    host: String,
+    jump: Option<String>,
+    remote_host: String,
+    remote_port: u16,
+    local_port: Option<u16>,
+    process: Option<Child>,
+    relay: Option<Relay>,
+}
+
+/// Where a request to the forwarded host goes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Route {
+    /// The local end of the ssh forward, for connections made by hand.
+    pub forward: SocketAddr,
+    /// The relay the http client uses as a proxy to reach the forward.
+    pub proxy: SocketAddr,
+}
+
+/// A local http proxy that hands every connection to the ssh forward. It answers `CONNECT` itself
+/// and passes any other request on as it came, since servers take urls in the absolute form.
+#[derive(Debug)]
+struct Relay {
+    address: SocketAddr,
+    closed: Arc<AtomicBool>,
+}
+
+impl SshTunnel {
+    /// Reads the tunnel from the reserved `ssh-*` keys of an environment. Returns None when the
+    /// environment does not declare a tunnel or the forward is not a valid `host:port`.
+    pub fn from_environment(environment: &HashMap<String, String>) -> Option<Self> {
+        let host = environment.get(HOST_KEY)?.clone();
+        let (remote_host, remote_port) = environment.get(FORWARD_KEY)?.rsplit_once(':')?;
+        Some(Self {
+            host,
+            jump: environment.get(JUMP_KEY).cloned(),
+            remote_host: remote_host.to_string(),
+            remote_port: remote_port.parse().ok()?,
+            local_port: environment
+                .get(LOCAL_PORT_KEY)
+                .and_then(|port| port.parse().ok()),
+            process: None,
+            relay: None,
+        })
+    }
+
+    /// Checks if the url points at the host the tunnel forwards to.
+    pub fn matches(&self, url: &Url) -> bool {
+        url.host_str() == Some(self.remote_host.as_str())
+            && url.port_or_known_default() == Some(self.remote_port)
+    }
+
+    /// Starts the ssh process if it is not running yet and returns the local port that forwards to
+    /// the remote host.
+    pub fn ensure_open(&mut self) -> io::Result<u16> {
+        if let Some(process) = self.process.as_mut() {
+            if process.try_wait()?.is_none() {
+                if let Some(port) = self.local_port {
+                    return Ok(port);
+                }
+            }
+        }
+        let local_port = match self.local_port {
+            Some(port) => port,
+            None => TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?
+                .local_addr()?
+                .port(),
+        };
+        let mut command = Command::new("ssh");
+        command
+            .arg("-N")
+            .arg("-o")
+            .arg("ExitOnForwardFailure=yes")
+            .arg("-L")
+            .arg(format!(
+                "{}:{}:{}",
+                local_port, self.remote_host, self.remote_port
+            ));
+        if let Some(jump) = self.jump.as_ref() {
+            command.arg("-J").arg(jump);
+        }
+        let mut process = command
+            .arg(&self.host)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let address = SocketAddr::from((Ipv4Addr::LOCALHOST, local_port));
+        let started = Instant::now();
+        while TcpStream::connect_timeout(&address, Duration::from_millis(200)).is_err() {
+            if let Some(status) = process.try_wait()? {
+                return Err(io::Error::other(format!("ssh exited with {}", status)));
+            }
+            if started.elapsed() > CONNECT_TIMEOUT {
+                process.kill()?;
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "timed out waiting for the ssh tunnel",
+                ));
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        self.process = Some(process);
+        self.local_port = Some(local_port);
+        Ok(local_port)
+    }
+
+    /// Opens the tunnel if the url points at the forwarded host, and returns where to send the
+    /// request. Other urls get None and are sent as usual.
+    pub fn route(&mut self, url: &Url) -> io::Result<Option<Route>> {
+        if !self.matches(url) {
+            return Ok(None);
+        }
+        let port = self.ensure_open()?;
+        let forward = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let proxy = match self.relay.as_ref() {
+            Some(relay) => relay.address,
+            None => {
+                let relay = Relay::start(forward)?;
+                let address = relay.address;
+                self.relay = Some(relay);
+                address
+            }
+        };
+        Ok(Some(Route { forward, proxy }))
+    }
+}
+
+impl Relay {
+    /// Listens on a free local port and relays every connection to forward in its own thread.
+    fn start(forward: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let address = listener.local_addr()?;
+        let closed = Arc::new(AtomicBool::new(false));
+        let stop = Arc::clone(&closed);
+        thread::spawn(move || {
+            for client in listener.incoming() {
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                if let Ok(client) = client {
+                    thread::spawn(move || relay(client, forward));
+                }
+            }
+        });
+        Ok(Self { address, closed })
+    }
+}
+
+impl Drop for Relay {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+        // wakes the thread blocked on accept so it sees it is closed
+        let _ = TcpStream::connect_timeout(&self.address, Duration::from_millis(200));
+    }
+}
+
+/// Reads the request head of the client, then copies bytes both ways between the client and the
+/// forward until either side is done.
+fn relay(mut client: TcpStream, forward: SocketAddr) -> io::Result<()> {
+    let mut head = Vec::new();
+    let mut byte = [0; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_HEAD_BYTES || client.read(&mut byte)? == 0 {
+            return Ok(());
+        }
+        head.push(byte[0]);
+    }
+    let mut server = TcpStream::connect_timeout(&forward, CONNECT_TIMEOUT)?;
+    if head.starts_with(b"CONNECT ") {
+        client.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")?;
+    } else {
+        server.write_all(&head)?;
+    }
+    let mut upstream = server.try_clone()?;
+    let mut downstream = client.try_clone()?;
+    let sending = thread::spawn(move || {
+        let _ = io::copy(&mut downstream, &mut upstream);
+        let _ = upstream.shutdown(Shutdown::Write);
+    });
+    let _ = io::copy(&mut server, &mut client);
+    let _ = client.shutdown(Shutdown::Write);
+    let _ = sending.join();
+    Ok(())
+}
+
+impl Drop for SshTunnel {
+    fn drop(&mut self) {
+        if let Some(process) = self.process.as_mut() {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_read_tunnel_from_environment() {
+        let mut environment = HashMap::new();
+        environment.insert(String::from(HOST_KEY), String::from("me@bastion"));
+        environment.insert(String::from(FORWARD_KEY), String::from("api.internal:8080"));
+        let tunnel = SshTunnel::from_environment(&environment).unwrap();
+        assert!(tunnel.matches(&Url::parse("http://api.internal:8080/users").unwrap()));
+        assert!(!tunnel.matches(&Url::parse("http://api.internal/users").unwrap()));
+
+        environment.insert(String::from(FORWARD_KEY), String::from("api.internal"));
+        assert!(SshTunnel::from_environment(&environment).is_none());
+    }
+
+    #[test]
+    fn should_relay_requests_to_the_forward_with_their_host() {
+        let server = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let relay = Relay::start(server.local_addr().unwrap()).unwrap();
+        let serving = thread::spawn(move || {
+            let (mut stream, _) = server.accept().unwrap();
+            let mut buffer = [0; 1024];
+            let read = stream.read(&mut buffer).unwrap();
+            let head = String::from_utf8_lossy(&buffer[..read]).to_string();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok")
+                .unwrap();
+
+            // a CONNECT is answered by the relay, only what follows reaches the forward
+            let (mut stream, _) = server.accept().unwrap();
+            let read = stream.read(&mut buffer).unwrap();
+            (head, buffer[..read].to_vec())
+        });
+
+        let client = reqwest::blocking::Client::builder()
+            .proxy(reqwest::Proxy::all(format!("http://{}", relay.address)).unwrap())
+            .build()
+            .unwrap();
+        let response = client.get("http://api.internal:8080/users").send().unwrap();
+        assert_eq!(response.text().unwrap(), "ok");
+
+        let mut stream = TcpStream::connect(relay.address).unwrap();
+        stream
+            .write_all(b"CONNECT api.internal:443 HTTP/1.1\r\nHost: api.internal:443\r\n\r\n")
+            .unwrap();
+        let mut buffer = [0; 64];
+        let read = stream.read(&mut buffer).unwrap();
+        assert!(buffer[..read].starts_with(b"HTTP/1.1 200"));
+        stream.write_all(b"hello").unwrap();
+
+        let (head, tunneled) = serving.join().unwrap();
+        assert!(head.to_lowercase().contains("host: api.internal:8080"));
+        assert_eq!(tunneled, b"hello");
+    }
+}
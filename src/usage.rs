@@ -0,0 +1,209 @@
+//! How often every request is sent from the TUI and when it last succeeded, to find the requests
+//! nobody uses anymore in large shared collections. Unlike the history, nothing is dropped. The
+//! usage is kept in `usage.json` in the data directory, shown in the usage popup and exported as
+//! JSON by `hermes usage`.
+
+use std::{collections::HashMap, fs, io, path::PathBuf};
+
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::{history, storage};
+
+/// The file in the data directory where the usage is saved.
+const USAGE_FILE: &str = "usage.json";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RequestUsage {
+    /// How many times the request was sent.
+    sent: u64,
+    /// When the request was last sent, in seconds since epoch.
+    last_sent: Option<i64>,
+    /// When the request last got a response with a status below 400, in seconds since epoch.
+    last_succeeded: Option<i64>,
+}
+
+impl RequestUsage {
+    pub fn sent(&self) -> u64 {
+        self.sent
+    }
+
+    pub fn last_sent(&self) -> Option<i64> {
+        self.last_sent
+    }
+
+    pub fn last_succeeded(&self) -> Option<i64> {
+        self.last_succeeded
+    }
+}
+
+/// The report of a collection as it is exported.
+#[derive(Debug, Serialize)]
+struct Exported<'a> {
+    collection: &'a str,
+    requests: Vec<ExportedRequest<'a>>,
+}
+
+/// The usage of a request as it is exported, with the times in RFC 3339.
+#[derive(Debug, Serialize)]
+struct ExportedRequest<'a> {
+    request: &'a str,
+    sent: u64,
+    last_sent: Option<String>,
+    last_succeeded: Option<String>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Usage {
+    /// The usage by collection, then by request.
+    requests: HashMap<String, HashMap<String, RequestUsage>>,
+    /// Where the usage is saved. None keeps it in memory only.
+    path: Option<PathBuf>,
+}
+
+impl Usage {
+    /// Loads the usage saved in the data directory, starting over when the file is missing or
+    /// broken.
+    pub fn load() -> Self {
+        let path = history::data_dir().map(|dir| dir.join(USAGE_FILE));
+        let requests = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        Self { requests, path }
+    }
+
+    /// Counts a send of the request, which succeeded when it got a status below 400.
+    pub fn record(
+        &mut self,
+        collection: &str,
+        request: &str,
+        sent_at: i64,
+        status: Option<u16>,
+    ) -> io::Result<()> {
+        let usage = self
+            .requests
+            .entry(collection.to_string())
+            .or_default()
+            .entry(request.to_string())
+            .or_default();
+        usage.sent += 1;
+        usage.last_sent = Some(sent_at);
+        if status.is_some_and(|status| status < 400) {
+            usage.last_succeeded = Some(sent_at);
+        }
+        self.save()
+    }
+
+    /// Moves the usage of a request that was renamed to its new name.
+    pub fn rename(&mut self, collection: &str, from: &str, to: &str) -> io::Result<()> {
+        let Some(requests) = self.requests.get_mut(collection) else {
+            return Ok(());
+        };
+        let Some(usage) = requests.remove(from) else {
+            return Ok(());
+        };
+        requests.insert(to.to_string(), usage);
+        self.save()
+    }
+
+    /// Gets the usage of the requests of the collection, the ones never sent included, the least
+    /// used first: never succeeded, then succeeded the longest ago, then sent the fewest times.
+    pub fn report<'a>(
+        &self,
+        collection: &str,
+        requests: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<(&'a str, RequestUsage)> {
+        let kept = self.requests.get(collection);
+        let mut report: Vec<_> = requests
+            .into_iter()
+            .map(|request| {
+                let usage = kept
+                    .and_then(|kept| kept.get(request))
+                    .copied()
+                    .unwrap_or_default();
+                (request, usage)
+            })
+            .collect();
+        report.sort_by_key(|(request, usage)| (usage.last_succeeded, usage.sent, *request));
+        report
+    }
+
+    /// Exports the report of the collection as JSON.
+    pub fn export<'a>(
+        &self,
+        collection: &str,
+        requests: impl IntoIterator<Item = &'a str>,
+    ) -> serde_json::Result<String> {
+        let rfc3339 =
+            |time: Option<i64>| DateTime::from_timestamp(time?, 0).map(|time| time.to_rfc3339());
+        let requests = self
+            .report(collection, requests)
+            .into_iter()
+            .map(|(request, usage)| ExportedRequest {
+                request,
+                sent: usage.sent,
+                last_sent: rfc3339(usage.last_sent),
+                last_succeeded: rfc3339(usage.last_succeeded),
+            })
+            .collect();
+        serde_json::to_string_pretty(&Exported {
+            collection,
+            requests,
+        })
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = self.path.as_deref() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = serde_json::to_string_pretty(&self.requests)?;
+        storage::overwrite(path, &contents)
+            .map(|_| ())
+            .map_err(|err| io::Error::other(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_report_the_least_used_requests_first() {
+        let mut usage = Usage::default();
+        usage.record("users", "login", 100, Some(200)).unwrap();
+        usage.record("users", "login", 200, Some(500)).unwrap();
+        usage.record("users", "old search", 50, Some(200)).unwrap();
+        usage.record("users", "broken", 300, None).unwrap();
+        usage.record("teams", "login", 400, Some(200)).unwrap();
+        usage.rename("users", "old search", "search").unwrap();
+
+        let report = usage.report("users", ["login", "search", "broken", "export"]);
+        let names: Vec<_> = report.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["export", "broken", "search", "login"]);
+        let login = report[3].1;
+        assert_eq!(
+            (login.sent(), login.last_sent(), login.last_succeeded()),
+            (2, Some(200), Some(100))
+        );
+
+        let exported: serde_json::Value =
+            serde_json::from_str(&usage.export("users", ["login"]).unwrap()).unwrap();
+        assert_eq!(
+            exported,
+            serde_json::json!({
+                "collection": "users",
+                "requests": [{
+                    "request": "login",
+                    "sent": 2,
+                    "last_sent": "1970-01-01T00:03:20+00:00",
+                    "last_succeeded": "1970-01-01T00:01:40+00:00",
+                }],
+            })
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Small encoding, hashing and generator utilities that can be used while editing request fields.
+
+use std::fmt;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, SecondsFormat};
+use md5::Md5;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+/// Utility is a single transformation from the utilities popup.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Utility {
+    #[default]
+    Base64Encode,
+    Base64Decode,
+    UrlEncode,
+    UrlDecode,
+    Md5,
+    Sha1,
+    Sha256,
+    Uuid,
+    EpochToIso,
+    IsoToEpoch,
+}
+
+impl Utility {
+    /// All the utilities in the order they are shown in the TUI.
+    pub const ALL: [Utility; 10] = [
+        Utility::Base64Encode,
+        Utility::Base64Decode,
+        Utility::UrlEncode,
+        Utility::UrlDecode,
+        Utility::Md5,
+        Utility::Sha1,
+        Utility::Sha256,
+        Utility::Uuid,
+        Utility::EpochToIso,
+        Utility::IsoToEpoch,
+    ];
+
+    /// Runs the utility on the input. Generators such as Uuid ignore the input.
+    pub fn apply(self, input: &str) -> Result<String, String> {
+        match self {
+            Utility::Base64Encode => Ok(STANDARD.encode(input)),
+            Utility::Base64Decode => {
+                let bytes = STANDARD
+                    .decode(input.trim())
+                    .map_err(|err| err.to_string())?;
+                String::from_utf8(bytes).map_err(|_| String::from("decoded value is not utf-8"))
+            }
+            Utility::UrlEncode => Ok(utf8_percent_encode(input, NON_ALPHANUMERIC).to_string()),
+            Utility::UrlDecode => percent_decode_str(input)
+                .decode_utf8()
+                .map(|s| s.to_string())
+                .map_err(|_| String::from("decoded value is not utf-8")),
+            Utility::Md5 => Ok(to_hex(&Md5::digest(input))),
+            Utility::Sha1 => Ok(to_hex(&Sha1::digest(input))),
+            Utility::Sha256 => Ok(to_hex(&Sha256::digest(input))),
+            Utility::Uuid => Ok(uuid::Uuid::new_v4().to_string()),
+            Utility::EpochToIso => {
+                let seconds: i64 = input
+                    .trim()
+                    .parse()
+                    .map_err(|_| String::from("expected the number of seconds since epoch"))?;
+                DateTime::from_timestamp(seconds, 0)
+                    .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+                    .ok_or_else(|| String::from("timestamp is out of range"))
+            }
+            Utility::IsoToEpoch => DateTime::parse_from_rfc3339(input.trim())
+                .map(|time| time.timestamp().to_string())
+                .map_err(|err| err.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Utility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let utility = match self {
+            Utility::Base64Encode => "Base64 encode",
+            Utility::Base64Decode => "Base64 decode",
+            Utility::UrlEncode => "URL encode",
+            Utility::UrlDecode => "URL decode",
+            Utility::Md5 => "MD5",
+            Utility::Sha1 => "SHA-1",
+            Utility::Sha256 => "SHA-256",
+            Utility::Uuid => "UUID v4",
+            Utility::EpochToIso => "Epoch to ISO",
+            Utility::IsoToEpoch => "ISO to epoch",
+        };
+        write!(f, "{}", utility)
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_apply_utilities() {
+        let test_cases = vec![
+            (Utility::Base64Encode, "hermes", "aGVybWVz"),
+            (Utility::Base64Decode, "aGVybWVz", "hermes"),
+            (Utility::UrlEncode, "a b&c", "a%20b%26c"),
+            (Utility::UrlDecode, "a%20b%26c", "a b&c"),
+            (Utility::Md5, "abc", "900150983cd24fb0d6963f7d28e17f72"),
+            (
+                Utility::Sha1,
+                "abc",
+                "a9993e364706816aba3e25717850c26c9cd0d89d",
+            ),
+            (
+                Utility::Sha256,
+                "abc",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (Utility::EpochToIso, "1700000000", "2023-11-14T22:13:20Z"),
+            (Utility::IsoToEpoch, "2023-11-14T22:13:20Z", "1700000000"),
+        ];
+        for (utility, input, expected) in test_cases {
+            assert_eq!(Ok(String::from(expected)), utility.apply(input));
+        }
+        assert_eq!(Utility::Uuid.apply("").unwrap().len(), 36);
+        assert!(Utility::EpochToIso.apply("soon").is_err());
+    }
+}
@@ -0,0 +1,368 @@
+//! Resolution of `{{NAME}}` variables in request fields. A variable value written as
+//! `$(command)` is the output of running the command in a shell, e.g. `$(pass show api/token)`.
+//! Commands only run when they are in the allowlist from the settings or the user confirmed them,
+//! and their output is cached for the rest of the session.
+//!
+//! A name is looked up through the scopes in order: the values prompted for when sending a request,
+//! runtime captures, the active environment, the collection variables and finally the built-ins
+//! such as `$uuid` or `$faker.name`.
+
+use std::{collections::HashMap, fmt, process::Command};
+
+use chrono::{SecondsFormat, Utc};
+
+use crate::faker;
+
+/// The built-in variables, generated every time they are used. `$faker.*` stands for every faker
+/// generator.
+pub const BUILT_INS: [&str; 4] = ["$uuid", "$timestamp", "$isoTimestamp", "$faker.*"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolveError {
+    /// The command is not allowed yet, the user has to confirm it first.
+    NeedsConfirmation(String),
+    CommandFailed {
+        command: String,
+        message: String,
+    },
+    /// A built-in variable such as `$faker.lorem` with a bad argument or an unknown generator.
+    BuiltInFailed(String),
+    /// The body could not be signed or encrypted with the key, see signing.
+    SigningFailed(String),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::NeedsConfirmation(command) => {
+                write!(f, "command `{}` needs confirmation", command)
+            }
+            ResolveError::CommandFailed { command, message } => {
+                write!(f, "command `{}` failed: {}", command, message)
+            }
+            ResolveError::BuiltInFailed(message) => write!(f, "{}", message),
+            ResolveError::SigningFailed(message) => write!(f, "signing the body: {}", message),
+        }
+    }
+}
+
+/// Gets the command of a variable value written as `$(command)`.
+pub fn command_of(value: &str) -> Option<&str> {
+    let command = value.trim().strip_prefix("$(")?.strip_suffix(')')?.trim();
+    if command.is_empty() {
+        None
+    } else {
+        Some(command)
+    }
+}
+
+/// Gets the names of the `{{NAME}}` variables used in the text, in order.
+pub fn names_in(text: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        names.push(rest[start + 2..start + end].trim());
+        rest = &rest[start + end + 2..];
+    }
+    names
+}
+
+/// Where the value of a variable comes from, in the order scopes are looked up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Source {
+    /// A value typed in when sending a request, only used for that one send.
+    Prompt,
+    Capture,
+    Environment,
+    Collection,
+    BuiltIn,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source = match self {
+            Source::Prompt => "prompt",
+            Source::Capture => "capture",
+            Source::Environment => "environment",
+            Source::Collection => "collection",
+            Source::BuiltIn => "built-in",
+        };
+        write!(f, "{}", source)
+    }
+}
+
+/// A variable as seen through the scopes, with the scopes whose value it hides.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopedVariable {
+    pub name: String,
+    pub value: String,
+    pub source: Source,
+    pub shadowed: Vec<Source>,
+}
+
+/// The variables of every scope but the built-ins, which are generated on use.
+#[derive(Debug, Default, Clone)]
+pub struct Scopes {
+    prompts: HashMap<String, String>,
+    captures: HashMap<String, String>,
+    environment: HashMap<String, String>,
+    collection: HashMap<String, String>,
+}
+
+impl Scopes {
+    pub fn new(
+        captures: HashMap<String, String>,
+        environment: HashMap<String, String>,
+        collection: HashMap<String, String>,
+    ) -> Self {
+        Self {
+            prompts: HashMap::new(),
+            captures,
+            environment,
+            collection,
+        }
+    }
+
+    /// Adds the values prompted for when sending a request, they win over every other scope.
+    pub fn with_prompts(mut self, prompts: HashMap<String, String>) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    fn layers(&self) -> [(Source, &HashMap<String, String>); 4] {
+        [
+            (Source::Prompt, &self.prompts),
+            (Source::Capture, &self.captures),
+            (Source::Environment, &self.environment),
+            (Source::Collection, &self.collection),
+        ]
+    }
+
+    /// Gets the raw value of the variable from the first scope that has it. Built-ins are not
+    /// looked up here.
+    pub fn lookup(&self, name: &str) -> Option<(Source, &str)> {
+        self.layers()
+            .into_iter()
+            .find_map(|(source, variables)| Some((source, variables.get(name)?.as_str())))
+    }
+
+    /// Gets every variable with the scope its value comes from, sorted by name.
+    pub fn entries(&self) -> Vec<ScopedVariable> {
+        let mut names: Vec<&String> = self
+            .layers()
+            .into_iter()
+            .flat_map(|(_, variables)| variables.keys())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let (source, value) = self.lookup(name)?;
+                let shadowed = self
+                    .layers()
+                    .into_iter()
+                    .filter(|(s, variables)| *s > source && variables.contains_key(name))
+                    .map(|(s, _)| s)
+                    .collect();
+                Some(ScopedVariable {
+                    name: name.clone(),
+                    value: value.to_string(),
+                    source,
+                    shadowed,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Generates the value of a built-in variable. Returns None when the name is not a built-in.
+pub fn built_in(name: &str) -> Option<Result<String, String>> {
+    match name {
+        "$uuid" => Some(Ok(uuid::Uuid::new_v4().to_string())),
+        "$timestamp" => Some(Ok(Utc::now().timestamp().to_string())),
+        "$isoTimestamp" => Some(Ok(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true))),
+        _ => faker::generate(name),
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Resolver {
+    /// Commands from the settings that can run without asking.
+    allowed_commands: Vec<String>,
+    /// Commands the user confirmed during this session.
+    approved_commands: Vec<String>,
+    /// The output of every command that ran, by command.
+    cache: HashMap<String, String>,
+}
+
+impl Resolver {
+    pub fn new(allowed_commands: Vec<String>) -> Self {
+        Self {
+            allowed_commands,
+            ..Self::default()
+        }
+    }
+
+    /// Allows the command to run for the rest of the session.
+    pub fn approve(&mut self, command: &str) {
+        if !self.is_allowed(command) {
+            self.approved_commands.push(command.to_string());
+        }
+    }
+
+    pub fn is_allowed(&self, command: &str) -> bool {
+        self.allowed_commands
+            .iter()
+            .chain(self.approved_commands.iter())
+            .any(|c| c == command)
+    }
+
+    /// Gets the cached output of the command if it already ran.
+    pub fn cached(&self, command: &str) -> Option<&str> {
+        self.cache.get(command).map(String::as_str)
+    }
+
+    /// Resolves the value of a variable, running its command if it has one.
+    pub fn resolve_value(&mut self, value: &str) -> Result<String, ResolveError> {
+        let Some(command) = command_of(value) else {
+            return Ok(value.to_string());
+        };
+        if let Some(output) = self.cache.get(command) {
+            return Ok(output.clone());
+        }
+        if !self.is_allowed(command) {
+            return Err(ResolveError::NeedsConfirmation(command.to_string()));
+        }
+        let output = run_command(command)?;
+        self.cache.insert(command.to_string(), output.clone());
+        Ok(output)
+    }
+
+    /// Replaces every `{{NAME}}` in the text with the value of the variable from the first scope
+    /// that has it. Unknown variables are left untouched so they are easy to spot.
+    pub fn interpolate(&mut self, text: &str, scopes: &Scopes) -> Result<String, ResolveError> {
+        let mut result = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start..].find("}}") else {
+                break;
+            };
+            result.push_str(&rest[..start]);
+            let name = rest[start + 2..start + end].trim();
+            match (scopes.lookup(name), built_in(name)) {
+                (Some((_, value)), _) => result.push_str(&self.resolve_value(value)?),
+                (None, Some(generated)) => {
+                    result.push_str(&generated.map_err(ResolveError::BuiltInFailed)?)
+                }
+                (None, None) => result.push_str(&rest[start..start + end + 2]),
+            }
+            rest = &rest[start + end + 2..];
+        }
+        result.push_str(rest);
+        Ok(result)
+    }
+}
+
+fn run_command(command: &str) -> Result<String, ResolveError> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output()
+        .map_err(|err| ResolveError::CommandFailed {
+            command: command.to_string(),
+            message: err.to_string(),
+        })?;
+    if !output.status.success() {
+        return Err(ResolveError::CommandFailed {
+            command: command.to_string(),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches(['\n', '\r'])
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_interpolate_variables() {
+        let mut variables = HashMap::new();
+        variables.insert(String::from("HOST"), String::from("localhost"));
+        variables.insert(String::from("TOKEN"), String::from("$(echo secret)"));
+        let variables = Scopes::new(HashMap::new(), variables, HashMap::new());
+        let mut resolver = Resolver::default();
+
+        assert_eq!(
+            resolver.interpolate("http://{{HOST}}/{{ MISSING }}", &variables),
+            Ok(String::from("http://localhost/{{ MISSING }}"))
+        );
+        assert_eq!(
+            resolver.interpolate("Bearer {{TOKEN}}", &variables),
+            Err(ResolveError::NeedsConfirmation(String::from("echo secret")))
+        );
+        resolver.approve("echo secret");
+        assert_eq!(
+            resolver.interpolate("Bearer {{TOKEN}}", &variables),
+            Ok(String::from("Bearer secret"))
+        );
+        assert_eq!(resolver.cached("echo secret"), Some("secret"));
+        assert!(resolver
+            .interpolate("{{$faker.unicorn}}", &variables)
+            .is_err());
+    }
+
+    #[test]
+    fn should_look_up_variables_through_scopes() {
+        let variables = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<String, String>>()
+        };
+        let scopes = Scopes::new(
+            variables(&[("TOKEN", "captured")]),
+            variables(&[("TOKEN", "from env"), ("HOST", "localhost")]),
+            variables(&[
+                ("TOKEN", "default"),
+                ("HOST", "example.com"),
+                ("$uuid", "fixed"),
+            ]),
+        );
+        assert_eq!(scopes.lookup("TOKEN"), Some((Source::Capture, "captured")));
+        assert_eq!(
+            scopes.lookup("HOST"),
+            Some((Source::Environment, "localhost"))
+        );
+        assert_eq!(scopes.lookup("$uuid"), Some((Source::Collection, "fixed")));
+        assert_eq!(
+            scopes.entries()[2],
+            ScopedVariable {
+                name: String::from("TOKEN"),
+                value: String::from("captured"),
+                source: Source::Capture,
+                shadowed: vec![Source::Environment, Source::Collection],
+            }
+        );
+        assert_eq!(built_in("$timestamp").unwrap().unwrap().len(), 10);
+
+        let scopes = scopes.with_prompts(variables(&[("TOKEN", "typed in")]));
+        assert_eq!(scopes.lookup("TOKEN"), Some((Source::Prompt, "typed in")));
+    }
+
+    #[test]
+    fn should_get_command_of_value() {
+        assert_eq!(
+            command_of("$(pass show api/token)"),
+            Some("pass show api/token")
+        );
+        assert_eq!(command_of("$()"), None);
+        assert_eq!(command_of("plain"), None);
+    }
+}
@@ -0,0 +1,198 @@
+//! Long operations such as big imports and directory scans run on a worker thread so the TUI keeps
+//! drawing. The worker reports its progress and checks if it was cancelled as it goes.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    widgets::{Block, Clear, Gauge, Paragraph, Widget},
+};
+
+use crate::instructions;
+
+/// The progress of a task, shared between the worker and the TUI.
+#[derive(Debug, Default)]
+pub struct Progress {
+    done: AtomicUsize,
+    /// 0 while the total amount of work is not known yet.
+    total: AtomicUsize,
+    message: Mutex<String>,
+    cancelled: AtomicBool,
+}
+
+impl Progress {
+    pub fn set_total(&self, total: usize) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, amount: usize) {
+        self.done.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    /// Sets what the worker is busy with, e.g. the file being read.
+    pub fn set_message(&self, message: impl Into<String>) {
+        if let Ok(mut current) = self.message.lock() {
+            *current = message.into();
+        }
+    }
+
+    /// Checks if the user cancelled the task. Workers should stop as soon as possible when it is.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn done(&self) -> usize {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    pub fn total(&self) -> usize {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    pub fn message(&self) -> String {
+        self.message
+            .lock()
+            .map(|message| message.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// A task running on a worker thread.
+#[derive(Debug)]
+pub struct Task<T> {
+    name: String,
+    progress: Arc<Progress>,
+    handle: Option<JoinHandle<T>>,
+}
+
+impl<T: Send + 'static> Task<T> {
+    /// Starts the work on a new thread. The work gets the progress to report to.
+    pub fn spawn(name: &str, work: impl FnOnce(&Progress) -> T + Send + 'static) -> Self {
+        let progress = Arc::new(Progress::default());
+        let worker_progress = Arc::clone(&progress);
+        Self {
+            name: name.to_string(),
+            progress,
+            handle: Some(thread::spawn(move || work(&worker_progress))),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    /// Asks the worker to stop. The task still has to be waited for with try_take.
+    pub fn cancel(&self) {
+        self.progress.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Takes the result of the work once it is done, without blocking. Returns None while the
+    /// worker is still running or after the result was taken. A panic in the worker is an Err.
+    pub fn try_take(&mut self) -> Option<Result<T, String>> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        let handle = self.handle.take()?;
+        Some(
+            handle
+                .join()
+                .map_err(|_| format!("{} stopped unexpectedly", self.name)),
+        )
+    }
+}
+
+/// A dialog with the progress of a task and a hint on how to cancel it.
+pub struct ProgressDialog<'a> {
+    title: &'a str,
+    progress: &'a Progress,
+}
+
+impl<'a> ProgressDialog<'a> {
+    pub fn new<T: Send + 'static>(task: &'a Task<T>) -> Self {
+        Self {
+            title: task.name(),
+            progress: task.progress(),
+        }
+    }
+}
+
+impl Widget for ProgressDialog<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let block = Block::bordered().title(self.title);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ])
+            .split(inner);
+
+        let done = self.progress.done();
+        let total = self.progress.total();
+        let (ratio, label) = if total == 0 {
+            (0.0, format!("{} done", done))
+        } else {
+            (
+                (done as f64 / total as f64).min(1.0),
+                format!("{} of {}", done, total),
+            )
+        };
+        Gauge::default()
+            .gauge_style(Style::new().fg(Color::LightYellow))
+            .ratio(ratio)
+            .label(label)
+            .render(chunks[0], buf);
+        Paragraph::new(self.progress.message()).render(chunks[1], buf);
+        let hint = if self.progress.is_cancelled() {
+            instructions!("Cancelling...")
+        } else {
+            instructions!("<esc> to cancel.")
+        };
+        hint.right_aligned().render(chunks[2], buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn should_stop_cancelled_tasks() {
+        let mut task = Task::spawn("Counting", |progress| {
+            while !progress.is_cancelled() {
+                progress.advance(1);
+                thread::sleep(Duration::from_millis(1));
+            }
+            progress.done()
+        });
+        assert!(task.try_take().is_none());
+        task.cancel();
+        let result = loop {
+            if let Some(result) = task.try_take() {
+                break result;
+            }
+            thread::sleep(Duration::from_millis(1));
+        };
+        assert!(result.is_ok());
+        assert!(task.try_take().is_none());
+    }
+}